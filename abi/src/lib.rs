@@ -0,0 +1,322 @@
+//! Intent Kernel System Call ABI
+//!
+//! The single source of truth for syscall numbers. The kernel dispatcher
+//! and every program under `user/` import these constants, so the two
+//! sides cannot drift apart.
+//!
+//! Calling convention (AArch64): number in `x8`, arguments in `x0`-`x3`
+//! (`x4` too for `SYS_MMAP`), `svc #0`, result in `x0`. Errors are returned
+//! as `u64::MAX`.
+
+#![no_std]
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// PROCESS
+// ═══════════════════════════════════════════════════════════════════════════════
+
+pub const SYS_EXIT: u64 = 0;
+pub const SYS_YIELD: u64 = 1;
+pub const SYS_PRINT: u64 = 2;
+pub const SYS_SLEEP: u64 = 3;
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// FILES
+// ═══════════════════════════════════════════════════════════════════════════════
+
+pub const SYS_OPEN: u64 = 4;
+pub const SYS_CLOSE: u64 = 5;
+pub const SYS_READ: u64 = 6;
+pub const SYS_WRITE: u64 = 7;
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// SIGNALS, PIPES, MEMORY
+// ═══════════════════════════════════════════════════════════════════════════════
+
+pub const SYS_KILL: u64 = 8;
+pub const SYS_SIGACTION: u64 = 9;
+pub const SYS_PIPE: u64 = 10;
+pub const SYS_DUP2: u64 = 11;
+pub const SYS_MMAP: u64 = 12;
+pub const SYS_MUNMAP: u64 = 13;
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// SOCKETS
+// ═══════════════════════════════════════════════════════════════════════════════
+
+pub const SYS_SOCKET: u64 = 14;
+pub const SYS_BIND: u64 = 15;
+pub const SYS_CONNECT: u64 = 16;
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// PROCESS LIFECYCLE
+// ═══════════════════════════════════════════════════════════════════════════════
+
+pub const SYS_GETPID: u64 = 17;
+pub const SYS_FORK: u64 = 18;
+pub const SYS_WAIT: u64 = 19;
+/// exec(path, argv, envp) - replace the calling program
+///
+/// `argv` and `envp` are NULL-terminated arrays of C strings, at most
+/// `ARG_MAX` bytes together (`argv` 0 means `[path]`, `envp` 0 none). The new
+/// program finds them on its initial stack (see PROGRAM STARTUP).
+pub const SYS_EXEC: u64 = 20;
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// NETWORK / INTENT / DIRECTORIES
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// recvfrom(port, buf, len) - legacy, receives by UDP port number
+pub const SYS_RECVFROM: u64 = 21;
+pub const SYS_PARSE_INTENT: u64 = 22;
+pub const SYS_GETDENTS64: u64 = 23;
+
+/// bind_udp(port) -> fd
+pub const SYS_BIND_UDP: u64 = 24;
+/// recvfrom_fd(fd, buf, len, src_addr) - `src_addr` is 8 bytes (IPv4, port (BE), pad)
+/// on AF_INET sockets and 20 bytes (IPv6, port (BE), pad) on AF_INET6 sockets
+pub const SYS_RECVFROM_FD: u64 = 25;
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// IPC
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// ipc_send(pid, msg_ptr) - `msg_ptr` points to `IPC_MSG_SIZE` bytes
+pub const SYS_IPC_SEND: u64 = 26;
+/// ipc_recv(buf_ptr) -> sender pid, blocks until a message arrives
+pub const SYS_IPC_RECV: u64 = 27;
+/// announce(concept_id) - route intents for a concept to this process
+pub const SYS_ANNOUNCE: u64 = 28;
+
+/// Fixed IPC message payload size
+pub const IPC_MSG_SIZE: usize = 64;
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// SOCKETS (CONNECTION-ORIENTED)
+// ═══════════════════════════════════════════════════════════════════════════════
+
+pub const SYS_LISTEN: u64 = 29;
+pub const SYS_ACCEPT: u64 = 30;
+pub const SYS_SEND: u64 = 31;
+pub const SYS_RECV: u64 = 32;
+pub const SYS_SHUTDOWN: u64 = 33;
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// READINESS MULTIPLEXING
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// poll(fds_ptr, nfds, timeout_ms) -> number of ready entries
+///
+/// `fds_ptr` is an array of `PollFd`; `timeout_ms` < 0 waits forever.
+pub const SYS_POLL: u64 = 34;
+
+/// Entry of the `SYS_POLL` array (layout of `struct pollfd`)
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PollFd {
+    pub fd: i32,
+    pub events: u16,
+    pub revents: u16,
+}
+
+/// Data (or EOF) can be read without blocking
+pub const POLLIN: u16 = 0x001;
+/// Data can be written without blocking
+pub const POLLOUT: u16 = 0x004;
+/// Error condition (always reported)
+pub const POLLERR: u16 = 0x008;
+/// Peer closed its end (always reported)
+pub const POLLHUP: u16 = 0x010;
+/// `fd` is not open (always reported)
+pub const POLLNVAL: u16 = 0x020;
+
+/// Pseudo-fd: readable when the caller's IPC mailbox holds a message
+pub const POLL_FD_MAILBOX: i32 = -2;
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// PATHS AND WORKING DIRECTORY
+// ═══════════════════════════════════════════════════════════════════════════════
+//
+// Relative paths are resolved against the caller's working directory.
+// Paths are NUL-terminated strings of at most `PATH_MAX` bytes.
+
+/// chdir(path)
+pub const SYS_CHDIR: u64 = 35;
+/// getcwd(buf, len) -> length written (excluding NUL)
+pub const SYS_GETCWD: u64 = 36;
+/// mkdir(path)
+pub const SYS_MKDIR: u64 = 37;
+/// unlink(path) - removes a file or an empty directory
+pub const SYS_UNLINK: u64 = 38;
+/// rename(old_path, new_path) - both on the same mount
+pub const SYS_RENAME: u64 = 39;
+/// stat(path, stat_ptr) - fills a `Stat`
+pub const SYS_STAT: u64 = 40;
+/// truncate(path, size)
+pub const SYS_TRUNCATE: u64 = 41;
+
+/// Longest path accepted by path syscalls (including NUL)
+pub const PATH_MAX: usize = 256;
+
+/// Result of `SYS_STAT`
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Stat {
+    pub size: u64,
+    pub mode: u32,
+    pub _pad: u32,
+    pub inode: u64,
+}
+
+/// `Stat::mode` file type mask and values
+pub const S_IFMT: u32 = 0o170000;
+pub const S_IFDIR: u32 = 0o040000;
+pub const S_IFREG: u32 = 0o100000;
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// NAME RESOLUTION
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// getaddrinfo(name, addrs_ptr, max) -> number of addresses written
+///
+/// Resolves a NUL-terminated host name (or dotted quad) through the
+/// configured name servers, blocking until an answer or failure. Each
+/// address is 4 bytes (IPv4, network order).
+pub const SYS_GETADDRINFO: u64 = 42;
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// SOCKET OPTIONS
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// setsockopt(fd, level, name, value) - `value` is passed directly, not by pointer
+pub const SYS_SETSOCKOPT: u64 = 43;
+
+/// Socket-level options
+pub const SOL_SOCKET: u64 = 1;
+/// TCP: probe the peer after two idle hours and drop it if it stays silent
+pub const SO_KEEPALIVE: u64 = 9;
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// SIGNAL DELIVERY
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// sigreturn() - resume the context saved when a signal handler was entered
+///
+/// Handlers return through a kernel-provided trampoline that issues this
+/// call, so programs never need to invoke it themselves.
+pub const SYS_SIGRETURN: u64 = 44;
+
+/// sigprocmask(how, set) -> previous mask
+///
+/// `set` is a bit mask (bit N = signal N) passed by value. SIGKILL and
+/// SIGSTOP are never blocked.
+pub const SYS_SIGPROCMASK: u64 = 45;
+
+/// `how` values for `SYS_SIGPROCMASK`
+pub const SIG_BLOCK: u64 = 0;
+pub const SIG_UNBLOCK: u64 = 1;
+pub const SIG_SETMASK: u64 = 2;
+
+/// `SigAction::flags`: don't block the signal while its handler runs
+pub const SA_NODEFER: u64 = 0x4000_0000;
+/// `SigAction::flags`: restore the default action once the handler is entered
+pub const SA_RESETHAND: u64 = 0x8000_0000;
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// MEMORY MAPPING
+// ═══════════════════════════════════════════════════════════════════════════════
+
+// mmap(len, prot, flags, fd, offset) -> addr
+//
+// `fd` and `offset` (page aligned, passed in x4) are only read without
+// MAP_ANONYMOUS. The fd may be a regular file or a shared memory object.
+
+/// `prot` bits for `SYS_MMAP`
+pub const PROT_READ: u64 = 1;
+pub const PROT_WRITE: u64 = 2;
+pub const PROT_EXEC: u64 = 4;
+
+/// `flags` bits for `SYS_MMAP`
+pub const MAP_PRIVATE: u64 = 1;
+pub const MAP_ANONYMOUS: u64 = 2;
+pub const MAP_FIXED: u64 = 4;
+/// Writes are seen by every mapping of the object and reach the file on
+/// `SYS_MSYNC` or `SYS_MUNMAP` (without it a mapping is private)
+pub const MAP_SHARED: u64 = 8;
+
+/// msync(addr, len) - write a `MAP_SHARED` file mapping back to its file
+pub const SYS_MSYNC: u64 = 46;
+
+/// shm_open(name, size, flags) -> fd
+///
+/// Opens (with `O_CREAT` = 64: creates `size` bytes of) the named shared memory
+/// object, for mapping with `SYS_MMAP`. Needs the `Memory` capability.
+pub const SYS_SHM_OPEN: u64 = 47;
+
+/// shm_unlink(name) - remove the name; existing mappings stay valid
+pub const SYS_SHM_UNLINK: u64 = 48;
+
+/// Longest shared memory object name, including the NUL
+pub const SHM_NAME_MAX: usize = 64;
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// THREADS
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// thread_create(entry, arg, stack_top, tls) -> tid
+///
+/// Starts `entry(arg)` in a new thread sharing the caller's memory, files,
+/// capabilities and signal handlers. `tls` becomes its TPIDR_EL0. With
+/// `stack_top` 0 the kernel reserves a 64KB stack, released on join. The
+/// entry function must end with `SYS_THREAD_EXIT`, never return.
+pub const SYS_THREAD_CREATE: u64 = 49;
+
+/// thread_exit(value) - end the calling thread; from the main thread this
+/// is `SYS_EXIT` and ends every thread
+pub const SYS_THREAD_EXIT: u64 = 50;
+
+/// join(tid, value_ptr) - wait for a thread of this agent to exit and store
+/// the value it passed to `SYS_THREAD_EXIT` (`value_ptr` may be 0)
+pub const SYS_JOIN: u64 = 51;
+
+/// futex(addr, op, val, timeout_ms)
+///
+/// `FUTEX_WAIT` sleeps while the aligned u32 at `addr` equals `val` (fails
+/// at once if it doesn't, or after `timeout_ms` if non-zero); `FUTEX_WAKE`
+/// wakes up to `val` waiters and returns how many it woke. Waiters on a
+/// `MAP_SHARED` mapping are found from any agent mapping the same page.
+pub const SYS_FUTEX: u64 = 52;
+
+/// `op` values for `SYS_FUTEX`
+pub const FUTEX_WAIT: u64 = 0;
+pub const FUTEX_WAKE: u64 = 1;
+
+/// gettid() -> id of the calling thread (`SYS_GETPID` for the main thread)
+pub const SYS_GETTID: u64 = 53;
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// PROGRAM STARTUP
+// ═══════════════════════════════════════════════════════════════════════════════
+
+// A program starts with the System V initial stack at `sp`: argc, the argv
+// pointers and a NULL, the envp pointers and a NULL, then auxv pairs of
+// (AT_* key, value) ending in AT_NULL. `x0` is 0 and TPIDR_EL0 points at the
+// main thread's TLS block if the image has PT_TLS.
+
+/// Most bytes of argument and environment strings (plus 8 per pointer) `SYS_EXEC` takes
+pub const ARG_MAX: usize = 32 * 1024;
+
+/// Auxiliary vector keys
+pub const AT_NULL: u64 = 0;
+pub const AT_PHDR: u64 = 3;
+pub const AT_PHENT: u64 = 4;
+pub const AT_PHNUM: u64 = 5;
+pub const AT_PAGESZ: u64 = 6;
+pub const AT_BASE: u64 = 7;
+pub const AT_ENTRY: u64 = 9;
+/// Address of 16 random bytes (stack protector / hash seeds)
+pub const AT_RANDOM: u64 = 25;
+/// Address of the executed path
+pub const AT_EXECFN: u64 = 31;
+
+/// Returned by every syscall on failure
+pub const SYSCALL_ERROR: u64 = u64::MAX;
@@ -81,9 +81,8 @@ impl AppManager {
             // 2. Resolve Inputs
             // For now, take the first input variable or raw string
             let input_val = if let Some(first_input) = step.inputs.first() {
-                if first_input.starts_with('$') {
+                if let Some(var_name) = first_input.strip_prefix('$') {
                     // Variable lookup
-                    let var_name = &first_input[1..];
                     variables.get(var_name).map(|s| s.as_str()).unwrap_or("")
                 } else {
                     // Literal
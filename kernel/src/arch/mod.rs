@@ -511,6 +511,21 @@ pub unsafe fn tlb_invalidate_all() {
 #[cfg(feature = "test_mocks")]
 pub unsafe fn tlb_invalidate_all() {}
 
+/// Invalidate the TLB entry for one page of one address space
+#[inline]
+#[cfg(not(feature = "test_mocks"))]
+pub unsafe fn tlb_invalidate_page(virt: u64, asid: u16) {
+    let arg = ((asid as u64) << 48) | ((virt >> 12) & 0xFFF_FFFF_FFFF);
+    dsb();
+    core::arch::asm!("tlbi vae1is, {}", in(reg) arg, options(nostack));
+    dsb();
+    isb();
+}
+
+#[inline]
+#[cfg(feature = "test_mocks")]
+pub unsafe fn tlb_invalidate_page(_virt: u64, _asid: u16) {}
+
 // ═══════════════════════════════════════════════════════════════════════════════
 // CONTEXT SWITCHING
 // ═══════════════════════════════════════════════════════════════════════════════
@@ -537,6 +552,12 @@ core::arch::global_asm!(
     "mrs x9, ttbr0_el1",
     "str x9, [x0, #104]",
 
+    // Save the user stack pointer and thread pointer (per thread, even
+    // when threads share TTBR0)
+    "mrs x9, sp_el0",
+    "mrs x10, tpidr_el0",
+    "stp x9, x10, [x0, #112]",
+
     // -----------------------------------------------------------------------
 
     // Restore callee-saved registers from next_ctx
@@ -555,6 +576,11 @@ core::arch::global_asm!(
     "ldr x9, [x1, #104]",
     "msr ttbr0_el1, x9",
     // "tlbi vmalle1", // Optimization: Don't flush TLB on switch (ASID handles it)
+
+    // Restore SP_EL0 and TPIDR_EL0
+    "ldp x9, x10, [x1, #112]",
+    "msr sp_el0, x9",
+    "msr tpidr_el0, x10",
     "dsb nsh",
     "isb",
 
@@ -648,9 +674,9 @@ core::arch::global_asm!(
     "mov sp, x0",
     
     // Restore SPSR, ELR
-    "ldr x2, [sp, #264]", // spsr
+    "ldr x2, [sp, #256]", // spsr
     "msr spsr_el1, x2",
-    "ldr x2, [sp, #256]", // elr
+    "ldr x2, [sp, #248]", // elr
     "msr elr_el1, x2",
     
     // Restore registers
@@ -149,7 +149,7 @@ fn bench_context_switch() {
     let total_switches = end_switches - start_switches;
     let total_cycles = end_cycles - start_cycles;
     
-    let avg_cycles = if total_switches > 0 { total_cycles / total_switches } else { 0 };
+    let avg_cycles = total_cycles.checked_div(total_switches).unwrap_or(0);
     
     kprintln!("  -> Total Switches: {}", total_switches);
     kprintln!("  -> Total Cycles:   {}", total_cycles);
@@ -1068,11 +1068,8 @@ fn bench_context_switch_full() {
     let end_switches = PROFILER.context_switches.load(Ordering::Relaxed);
     
     let switches = end_switches.saturating_sub(start_switches);
-    let avg_cycles = if switches > 0 {
-        end.wrapping_sub(start) / switches
-    } else {
-        end.wrapping_sub(start) / iterations
-    };
+    let avg_cycles = end.wrapping_sub(start).checked_div(switches)
+        .unwrap_or_else(|| end.wrapping_sub(start) / iterations);
     
     kprintln!("  -> Avg Context Switch: {} cycles", avg_cycles);
 }
@@ -455,6 +455,11 @@ pub fn recv_frame(buffer: &mut [u8]) -> Result<usize, &'static str> {
     ETHERNET.lock().recv_frame(buffer)
 }
 
+/// Is the RP1 MAC up?
+pub fn is_present() -> bool {
+    ETHERNET.lock().initialized
+}
+
 /// Get MAC address
 pub fn get_mac_address() -> MacAddr {
     if crate::dtb::machine_type() == crate::dtb::MachineType::QemuVirt {
@@ -17,3 +17,12 @@ pub const RAMDISK_SIZE: usize = 64 * 1024 * 1024;
 pub unsafe fn get_slice() -> &'static [u8] {
     core::slice::from_raw_parts(RAMDISK_BASE as *const u8, RAMDISK_SIZE)
 }
+
+/// The initrd as a TAR archive, if the bootloader loaded one.
+///
+/// Only meaningful on the Raspberry Pi, where `ramfsaddr` places the
+/// archive in RAM; on QEMU `virt` this address is not backed by memory.
+pub fn archive() -> Option<crate::fs::tar::TarArchive> {
+    let archive = crate::fs::tar::TarArchive::new(unsafe { get_slice() });
+    if archive.is_valid() { Some(archive) } else { None }
+}
@@ -451,6 +451,11 @@ pub fn recv_frame(buffer: &mut [u8]) -> Result<usize, &'static str> {
     DRIVER.lock().recv_frame(buffer)
 }
 
+/// Has a VirtIO-Net device been found and initialized?
+pub fn is_present() -> bool {
+    DRIVER.lock().initialized
+}
+
 pub fn get_mac() -> [u8; 6] {
     DRIVER.lock().mac
 }
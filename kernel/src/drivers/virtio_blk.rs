@@ -342,6 +342,11 @@ pub fn init() -> Result<(), &'static str> {
     DRIVER.lock().init()
 }
 
+/// Was a block device found by `init()`?
+pub fn is_present() -> bool {
+    DRIVER.lock().initialized
+}
+
 pub fn read_sector(sector: u64, buf: &mut [u8]) -> Result<(), &'static str> {
     DRIVER.lock().read_sector(sector, buf)
 }
@@ -157,6 +157,10 @@ impl EnglishParser {
                 // Priority 11: File Operations
                 "ls" | "list" | "dir" => Some((11, concepts::LIST_FILES, "LIST_FILES", 0.9)),
                 "cat" | "read" | "open" => Some((11, concepts::READ_FILE, "READ_FILE", 0.9)),
+
+                // Priority 12: Network
+                "resolve" | "lookup" | "nslookup" => Some((12, concepts::RESOLVE_HOST, "RESOLVE_HOST", 0.9)),
+                "capture" | "pcap" | "sniff" => Some((12, concepts::CAPTURE_TRAFFIC, "CAPTURE_TRAFFIC", 0.9)),
                 
                 _ => None
             };
@@ -177,13 +181,33 @@ impl EnglishParser {
             intent.name = name; // This is a static string, careful. Intent struct has static str? Yes.
 
             // Argument Handling
-            // If the intent is READ_FILE, we need to find the filename.
-            // Simplified logic: The first word that is NOT the command keyword is the filename.
-            if concept_id == concepts::READ_FILE {
+            // READ_FILE takes a filename, RESOLVE_HOST a host name.
+            // Simplified logic: The first word that is NOT the command keyword is the argument.
+            // CAPTURE_TRAFFIC takes everything after the command (filter and destination).
+            if concept_id == concepts::CAPTURE_TRAFFIC {
+                let rest: Vec<&str> = input
+                    .split_whitespace()
+                    .skip_while(|w| !matches!(*w, "capture" | "pcap" | "sniff"))
+                    .skip(1)
+                    .collect();
+                if !rest.is_empty() {
+                    intent.data = IntentData::String(rest.join(" "));
+                }
+                return intent;
+            }
+
+            let commands: &[&str] = if concept_id == concepts::READ_FILE {
+                &["cat", "read", "open"]
+            } else if concept_id == concepts::RESOLVE_HOST {
+                &["resolve", "lookup", "nslookup"]
+            } else {
+                &[]
+            };
+            if !commands.is_empty() {
                 for token in input.split_whitespace() {
                      let word = token.trim_matches(|c| c == '?' || c == '!' || c == '.' || c == ',');
                      // Is this the command?
-                     if word == "cat" || word == "read" || word == "open" {
+                     if commands.contains(&word) {
                          continue;
                      }
                      // If it's not the command, it's likely the argument
@@ -221,6 +245,8 @@ impl EnglishParser {
         else if concept == concepts::GET_COUNT { "GET_COUNT" }
         else if concept == concepts::LIST_FILES { "LIST_FILES" }
         else if concept == concepts::READ_FILE { "READ_FILE" }
+        else if concept == concepts::RESOLVE_HOST { "RESOLVE_HOST" }
+        else if concept == concepts::CAPTURE_TRAFFIC { "CAPTURE_TRAFFIC" }
         else { "UNKNOWN" }
     }
 }
@@ -331,6 +357,26 @@ mod tests {
         assert!(intent.confidence >= 0.85 && intent.confidence <= 1.0);
     }
 
+    #[test]
+    fn test_parse_resolve_host() {
+        let parser = EnglishParser::new();
+        let intent = parser.parse("resolve example.com").unwrap();
+        assert_eq!(intent.concept_id, concepts::RESOLVE_HOST);
+        assert!(matches!(intent.data, IntentData::String(ref host) if host == "example.com"));
+    }
+
+    #[test]
+    fn test_parse_capture() {
+        let parser = EnglishParser::new();
+        let intent = parser.parse("capture tcp port 80 to /tmp/web.pcap").unwrap();
+        assert_eq!(intent.concept_id, concepts::CAPTURE_TRAFFIC);
+        assert!(matches!(intent.data, IntentData::String(ref args) if args == "tcp port 80 to /tmp/web.pcap"));
+
+        let intent = parser.parse("capture").unwrap();
+        assert_eq!(intent.concept_id, concepts::CAPTURE_TRAFFIC);
+        assert!(matches!(intent.data, IntentData::None));
+    }
+
     #[test]
     fn test_normalize() {
         assert_eq!(EnglishParser::normalize("  HELP  "), "help");
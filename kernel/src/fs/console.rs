@@ -1,6 +1,6 @@
 use alloc::sync::Arc;
 use core::any::Any;
-use crate::fs::vfs::{self, FileOps, SeekFrom, FileStat};
+use crate::fs::vfs::{self, FileOps, SeekFrom, FileStat, POLLIN, POLLOUT};
 use crate::kernel::sync::SpinLock;
 use crate::drivers::uart;
 
@@ -45,6 +45,10 @@ impl FileOps for ConsoleFile {
     fn stat(&self) -> Result<FileStat, &'static str> {
          Ok(FileStat { size: 0, mode: 0, inode: 0 })
     }
+
+    fn poll_ready(&self) -> u16 {
+        if uart::has_data() { POLLIN | POLLOUT } else { POLLOUT }
+    }
     
     fn as_any(&mut self) -> &mut dyn Any {
         self
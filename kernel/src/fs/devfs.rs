@@ -0,0 +1,422 @@
+//! Device Filesystem (devfs)
+//!
+//! Publishes hardware as files under `/dev` so user services can use
+//! open/read/write instead of device-specific syscalls:
+//!
+//! | Node      | Backing                         | Capability |
+//! |-----------|---------------------------------|------------|
+//! | `console` | UART                            | -          |
+//! | `null`    | discards writes, reads EOF      | -          |
+//! | `zero`    | endless zero bytes              | -          |
+//! | `random`  | `drivers::rng`                  | -          |
+//! | `vda`     | raw `virtio_blk` sectors        | Storage    |
+//! | `fb0`     | framebuffer memory              | Display    |
+//! | `steno`   | raw strokes, 4 bytes LE each    | Input      |
+//!
+//! Capabilities are enforced when a descriptor is installed (`sys_open`).
+
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::any::Any;
+use crate::kernel::sync::{SpinLock, WaitQueue, EWOULDBLOCK};
+use crate::kernel::capability::CapabilityType;
+use crate::fs::console::ConsoleFile;
+use crate::fs::vfs::{FileOps, Filesystem, DirEntry, FileStat, SeekFrom, S_IFDIR, POLLIN, POLLOUT};
+use crate::drivers;
+
+/// Character device (`S_IFCHR`)
+const S_IFCHR: u32 = 0o020000;
+/// Block device (`S_IFBLK`)
+const S_IFBLK: u32 = 0o060000;
+
+const SECTOR_SIZE: usize = 512;
+
+/// Every node devfs can publish
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Device {
+    Console,
+    Null,
+    Zero,
+    Random,
+    Vda,
+    Fb0,
+    Steno,
+}
+
+const DEVICES: [(&str, Device); 7] = [
+    ("console", Device::Console),
+    ("null", Device::Null),
+    ("zero", Device::Zero),
+    ("random", Device::Random),
+    ("vda", Device::Vda),
+    ("fb0", Device::Fb0),
+    ("steno", Device::Steno),
+];
+
+impl Device {
+    fn lookup(name: &str) -> Option<Device> {
+        DEVICES.iter().find(|(n, _)| *n == name).map(|&(_, dev)| dev)
+    }
+
+    /// Hardware-backed nodes only appear once their driver is up
+    fn is_present(self) -> bool {
+        match self {
+            Device::Vda => drivers::virtio_blk::is_present(),
+            Device::Fb0 => drivers::framebuffer::with(|_| ()).is_some(),
+            _ => true,
+        }
+    }
+
+    fn capability(self) -> Option<CapabilityType> {
+        match self {
+            Device::Vda => Some(CapabilityType::Storage),
+            Device::Fb0 => Some(CapabilityType::Display),
+            Device::Steno => Some(CapabilityType::Input),
+            _ => None,
+        }
+    }
+
+    fn stat(self) -> FileStat {
+        let size = match self {
+            Device::Fb0 => fb_size() as u64,
+            _ => 0,
+        };
+        let kind = if self == Device::Vda { S_IFBLK } else { S_IFCHR };
+        FileStat { size, mode: kind | 0o660, inode: self as u64 + 1 }
+    }
+
+    fn open(self) -> Arc<SpinLock<dyn FileOps>> {
+        match self {
+            Device::Console => ConsoleFile::new(),
+            _ => Arc::new(SpinLock::new(DevFile { device: self, offset: 0, nonblocking: false })),
+        }
+    }
+}
+
+/// Size of the mapped framebuffer in bytes (0 if none)
+fn fb_size() -> usize {
+    drivers::framebuffer::with(|fb| fb.info().size as usize).unwrap_or(0)
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// FILESYSTEM
+// ═══════════════════════════════════════════════════════════════════════════════
+
+pub struct DevFs;
+
+impl DevFs {
+    pub fn new() -> Arc<Self> {
+        Arc::new(DevFs)
+    }
+}
+
+impl Filesystem for DevFs {
+    fn open(&self, path: &str, _flags: usize) -> Result<Arc<SpinLock<dyn FileOps>>, &'static str> {
+        let name = path.trim_matches('/');
+        if name.is_empty() {
+            return Ok(Arc::new(SpinLock::new(DevDir { index: 0 })));
+        }
+        match Device::lookup(name) {
+            Some(dev) if dev.is_present() => Ok(dev.open()),
+            _ => Err("No such device"),
+        }
+    }
+
+    fn create(&self, path: &str) -> Result<Arc<SpinLock<dyn FileOps>>, &'static str> {
+        // Opening an existing node for writing is fine (e.g. `> /dev/null`)
+        self.open(path, 0).map_err(|_| "Read-only filesystem")
+    }
+
+    fn mkdir(&self, _path: &str) -> Result<(), &'static str> {
+        Err("Read-only filesystem")
+    }
+
+    fn remove(&self, _path: &str) -> Result<(), &'static str> {
+        Err("Read-only filesystem")
+    }
+
+    fn read_dir(&self, path: &str) -> Result<Vec<DirEntry>, &'static str> {
+        if !path.trim_matches('/').is_empty() {
+            return Err("Not a directory");
+        }
+        Ok(present_entries().collect())
+    }
+
+    fn stat(&self, path: &str) -> Result<FileStat, &'static str> {
+        let name = path.trim_matches('/');
+        if name.is_empty() {
+            return Ok(FileStat { size: 0, mode: S_IFDIR | 0o755, inode: 0 });
+        }
+        match Device::lookup(name) {
+            Some(dev) if dev.is_present() => Ok(dev.stat()),
+            _ => Err("No such device"),
+        }
+    }
+}
+
+fn present_entries() -> impl Iterator<Item = DirEntry> {
+    DEVICES.iter()
+        .filter(|(_, dev)| dev.is_present())
+        .map(|&(name, dev)| DirEntry { name: String::from(name), is_dir: false, size: dev.stat().size })
+}
+
+/// `/dev` itself
+struct DevDir {
+    index: usize,
+}
+
+impl FileOps for DevDir {
+    fn read(&mut self, _buf: &mut [u8]) -> Result<usize, &'static str> {
+        Err("Is a directory")
+    }
+
+    fn write(&mut self, _buf: &[u8]) -> Result<usize, &'static str> {
+        Err("Is a directory")
+    }
+
+    fn seek(&mut self, _pos: SeekFrom) -> Result<u64, &'static str> {
+        self.index = 0;
+        Ok(0)
+    }
+
+    fn close(&mut self) -> Result<(), &'static str> {
+        Ok(())
+    }
+
+    fn stat(&self) -> Result<FileStat, &'static str> {
+        Ok(FileStat { size: 0, mode: S_IFDIR | 0o755, inode: 0 })
+    }
+
+    fn readdir(&mut self) -> Result<Option<DirEntry>, &'static str> {
+        let entry = present_entries().nth(self.index);
+        if entry.is_some() {
+            self.index += 1;
+        }
+        Ok(entry)
+    }
+
+    fn as_any(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// DEVICE FILES
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Open handle on any device except the console
+struct DevFile {
+    device: Device,
+    /// Byte offset (vda, fb0)
+    offset: u64,
+    nonblocking: bool,
+}
+
+impl DevFile {
+    /// Sector-granular access to the VirtIO disk at the current offset
+    fn block_io(&mut self, len: usize, mut op: impl FnMut(&mut [u8; SECTOR_SIZE], usize, usize) -> bool) -> Result<usize, &'static str> {
+        let mut done = 0;
+        let mut sector_buf = [0u8; SECTOR_SIZE];
+        while done < len {
+            let sector = self.offset / SECTOR_SIZE as u64;
+            let in_sector = (self.offset % SECTOR_SIZE as u64) as usize;
+            let n = core::cmp::min(len - done, SECTOR_SIZE - in_sector);
+
+            drivers::virtio_blk::read_sector(sector, &mut sector_buf)?;
+            if op(&mut sector_buf, in_sector, done) {
+                drivers::virtio_blk::write_sector(sector, &sector_buf)?;
+            }
+            done += n;
+            self.offset += n as u64;
+        }
+        Ok(done)
+    }
+
+    /// Copy between `buf` and framebuffer memory at the current offset
+    fn fb_io(&mut self, len: usize, copy: impl FnOnce(*mut u8, usize)) -> Result<usize, &'static str> {
+        let offset = self.offset as usize;
+        let n = drivers::framebuffer::with(|fb| {
+            let info = fb.info();
+            let size = info.size as usize;
+            if offset >= size {
+                return 0;
+            }
+            let n = core::cmp::min(len, size - offset);
+            copy(unsafe { (info.buffer as *mut u8).add(offset) }, n);
+            n
+        }).ok_or("No framebuffer")?;
+        self.offset += n as u64;
+        Ok(n)
+    }
+}
+
+impl FileOps for DevFile {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, &'static str> {
+        match self.device {
+            Device::Console => unreachable!("console uses ConsoleFile"),
+            Device::Null => Ok(0),
+            Device::Zero => {
+                buf.fill(0);
+                Ok(buf.len())
+            }
+            Device::Random => {
+                for chunk in buf.chunks_mut(8) {
+                    let bytes = drivers::rng::next_u64().to_le_bytes();
+                    chunk.copy_from_slice(&bytes[..chunk.len()]);
+                }
+                Ok(buf.len())
+            }
+            Device::Vda => {
+                self.block_io(buf.len(), |sector, in_sector, done| {
+                    let n = core::cmp::min(buf.len() - done, SECTOR_SIZE - in_sector);
+                    buf[done..done + n].copy_from_slice(&sector[in_sector..in_sector + n]);
+                    false
+                })
+            }
+            Device::Fb0 => {
+                self.fb_io(buf.len(), |src, n| unsafe {
+                    core::ptr::copy_nonoverlapping(src as *const u8, buf.as_mut_ptr(), n);
+                })
+            }
+            Device::Steno => {
+                // Whole strokes only
+                let max = buf.len() / 4;
+                if max == 0 {
+                    return Err("Buffer too small");
+                }
+                let first = if self.nonblocking {
+                    crate::steno::read_raw_stroke().ok_or(EWOULDBLOCK)?
+                } else {
+                    crate::steno::STROKE_WAIT.wait_until(crate::steno::read_raw_stroke)?
+                };
+                buf[0..4].copy_from_slice(&first.raw().to_le_bytes());
+                let mut count = 1;
+                while count < max {
+                    match crate::steno::read_raw_stroke() {
+                        Some(stroke) => {
+                            buf[count * 4..count * 4 + 4].copy_from_slice(&stroke.raw().to_le_bytes());
+                            count += 1;
+                        }
+                        None => break,
+                    }
+                }
+                Ok(count * 4)
+            }
+        }
+    }
+
+    fn write(&mut self, buf: &[u8]) -> Result<usize, &'static str> {
+        match self.device {
+            Device::Console => unreachable!("console uses ConsoleFile"),
+            Device::Null | Device::Zero => Ok(buf.len()),
+            Device::Random => Err("Read-only device"),
+            Device::Vda => {
+                self.block_io(buf.len(), |sector, in_sector, done| {
+                    let n = core::cmp::min(buf.len() - done, SECTOR_SIZE - in_sector);
+                    sector[in_sector..in_sector + n].copy_from_slice(&buf[done..done + n]);
+                    true
+                })
+            }
+            Device::Fb0 => {
+                self.fb_io(buf.len(), |dst, n| unsafe {
+                    core::ptr::copy_nonoverlapping(buf.as_ptr(), dst, n);
+                })
+            }
+            Device::Steno => {
+                // Inject strokes as if typed on the machine
+                let (strokes, _) = buf.as_chunks::<4>();
+                for &chunk in strokes {
+                    crate::steno::process_raw(u32::from_le_bytes(chunk));
+                }
+                Ok(strokes.len() * 4)
+            }
+        }
+    }
+
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64, &'static str> {
+        let size = match self.device {
+            Device::Vda => None, // Capacity unknown
+            Device::Fb0 => Some(fb_size() as u64),
+            _ => return Ok(0),   // Stream devices ignore seeks
+        };
+        let new_pos = match pos {
+            SeekFrom::Start(off) => off as i64,
+            SeekFrom::Current(off) => self.offset as i64 + off,
+            SeekFrom::End(off) => size.ok_or("Seek from end unsupported")? as i64 + off,
+        };
+        if new_pos < 0 {
+            return Err("Invalid seek");
+        }
+        self.offset = new_pos as u64;
+        Ok(self.offset)
+    }
+
+    fn close(&mut self) -> Result<(), &'static str> {
+        Ok(())
+    }
+
+    fn stat(&self) -> Result<FileStat, &'static str> {
+        Ok(self.device.stat())
+    }
+
+    fn set_nonblocking(&mut self, nonblocking: bool) {
+        self.nonblocking = nonblocking;
+    }
+
+    fn read_queue(&self) -> Option<&'static WaitQueue> {
+        match self.device {
+            Device::Steno if !self.nonblocking => Some(&crate::steno::STROKE_WAIT),
+            _ => None,
+        }
+    }
+
+    fn try_read(&mut self, buf: &mut [u8]) -> Result<usize, &'static str> {
+        let nonblocking = core::mem::replace(&mut self.nonblocking, true);
+        let result = self.read(buf);
+        self.nonblocking = nonblocking;
+        result
+    }
+
+    fn poll_ready(&self) -> u16 {
+        match self.device {
+            Device::Steno if !crate::steno::has_raw_strokes() => POLLOUT,
+            _ => POLLIN | POLLOUT,
+        }
+    }
+
+    fn required_capability(&self) -> Option<CapabilityType> {
+        self.device.capability()
+    }
+
+    fn as_any(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lookup_and_capabilities() {
+        assert_eq!(Device::lookup("null"), Some(Device::Null));
+        assert_eq!(Device::lookup("sda"), None);
+        assert_eq!(Device::Null.capability(), None);
+        assert_eq!(Device::Vda.capability(), Some(CapabilityType::Storage));
+        assert_eq!(Device::Steno.capability(), Some(CapabilityType::Input));
+    }
+
+    #[test]
+    fn test_null_and_zero() {
+        let fs = DevFs::new();
+        let null = fs.open("/null", 0).unwrap();
+        let mut buf = [0xAAu8; 8];
+        assert_eq!(null.lock().read(&mut buf).unwrap(), 0);
+        assert_eq!(null.lock().write(b"gone").unwrap(), 4);
+
+        let zero = fs.open("/zero", 0).unwrap();
+        assert_eq!(zero.lock().read(&mut buf).unwrap(), 8);
+        assert_eq!(buf, [0u8; 8]);
+    }
+}
@@ -5,10 +5,11 @@
 use alloc::vec::Vec;
 use alloc::vec;
 use alloc::string::String;
-use alloc::sync::Arc;
+use alloc::sync::{Arc, Weak};
 use crate::kernel::sync::SpinLock;
 use crate::fs::vfs::{FileOps, Filesystem, DirEntry, BlockDevice, FileStat, SeekFrom};
 use crate::kprintln;
+use core::sync::atomic::{AtomicU64, Ordering};
 
 // ═══════════════════════════════════════════════════════════════════════════════
 // DATA STRUCTURES
@@ -67,6 +68,22 @@ pub struct FatDirEntry {
     pub size: u32,
 }
 
+impl FatDirEntry {
+    /// First cluster of the file/directory data (0 = none)
+    pub fn first_cluster(&self) -> u32 {
+        ((self.cluster_high as u32) << 16) | (self.cluster_low as u32)
+    }
+
+    fn set_first_cluster(&mut self, cluster: u32) {
+        self.cluster_high = (cluster >> 16) as u16;
+        self.cluster_low = (cluster & 0xFFFF) as u16;
+    }
+
+    fn is_dot(&self) -> bool {
+        &self.name == b".          " || &self.name == b"..         "
+    }
+}
+
 // Attributes
 pub const ATTR_READ_ONLY: u8 = 0x01;
 pub const ATTR_HIDDEN:    u8 = 0x02;
@@ -76,6 +93,147 @@ pub const ATTR_DIRECTORY: u8 = 0x10;
 pub const ATTR_ARCHIVE:   u8 = 0x20;
 pub const ATTR_LONG_NAME: u8 = 0x0F;
 
+// NT reserved byte: short name stored in lowercase (no LFN needed)
+const NT_LOWER_BASE: u8 = 0x08;
+const NT_LOWER_EXT:  u8 = 0x10;
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// VFAT LONG FILE NAMES
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Sequence number flag on the first (highest) LFN entry of a chain
+const LFN_LAST_ENTRY: u8 = 0x40;
+/// UTF-16 units stored per LFN entry
+const LFN_CHARS_PER_ENTRY: usize = 13;
+/// Longest name VFAT allows (UTF-16 units)
+const LFN_MAX_CHARS: usize = 255;
+/// Byte offsets of the 13 UTF-16 units inside an LFN entry
+const LFN_CHAR_OFFSETS: [usize; 13] = [1, 3, 5, 7, 9, 14, 16, 18, 20, 22, 24, 28, 30];
+
+/// Checksum of an 8.3 name, stored in every LFN entry that belongs to it
+fn lfn_checksum(short_name: &[u8; 11]) -> u8 {
+    short_name.iter().fold(0u8, |sum, &c| {
+        ((sum & 1) << 7).wrapping_add(sum >> 1).wrapping_add(c)
+    })
+}
+
+/// Assembles a long name from the LFN entries preceding a short entry
+///
+/// Entries are stored last-part-first: `0x40 | N`, `N-1`, ..., `1`.
+/// Any break in the sequence or checksum mismatch discards the name and
+/// the short name is used instead (what DOS-era tools left behind).
+struct LfnBuilder {
+    units: Vec<u16>,
+    /// Sequence number expected next (0 = chain complete)
+    expected: u8,
+    checksum: u8,
+    active: bool,
+    /// Positions (cluster, offset) of the LFN entries, for rewriting
+    slots: Vec<(u32, usize)>,
+}
+
+impl LfnBuilder {
+    fn new() -> Self {
+        Self { units: Vec::new(), expected: 0, checksum: 0, active: false, slots: Vec::new() }
+    }
+
+    fn reset(&mut self) {
+        self.active = false;
+        self.units.clear();
+        self.slots.clear();
+    }
+
+    /// Feed one LFN entry
+    fn push(&mut self, raw: &[u8], slot: (u32, usize)) {
+        let ord = raw[0];
+        let seq = ord & 0x1F;
+
+        if ord & LFN_LAST_ENTRY != 0 {
+            // Start of a new chain
+            self.reset();
+            if seq == 0 || seq as usize * LFN_CHARS_PER_ENTRY > LFN_MAX_CHARS + LFN_CHARS_PER_ENTRY {
+                return;
+            }
+            self.units = vec![0xFFFF; seq as usize * LFN_CHARS_PER_ENTRY];
+            self.expected = seq;
+            self.checksum = raw[13];
+            self.active = true;
+        } else if !self.active || seq == 0 || seq != self.expected || raw[13] != self.checksum {
+            // seq 0 would match a finished chain (expected == 0) and index below it
+            self.reset();
+            return;
+        }
+
+        let base = (seq as usize - 1) * LFN_CHARS_PER_ENTRY;
+        for (i, &off) in LFN_CHAR_OFFSETS.iter().enumerate() {
+            self.units[base + i] = u16::from_le_bytes([raw[off], raw[off + 1]]);
+        }
+        self.slots.push(slot);
+        self.expected -= 1;
+    }
+
+    /// Finish the chain at its short entry; returns the long name if valid
+    fn take(&mut self, short_name: &[u8; 11]) -> Option<String> {
+        let complete = self.active && self.expected == 0 && self.checksum == lfn_checksum(short_name);
+        let name = if complete {
+            let len = self.units.iter().position(|&u| u == 0x0000 || u == 0xFFFF).unwrap_or(self.units.len());
+            let name: String = core::char::decode_utf16(self.units[..len].iter().copied())
+                .map(|c| c.unwrap_or(core::char::REPLACEMENT_CHARACTER))
+                .collect();
+            if name.is_empty() { None } else { Some(name) }
+        } else {
+            None
+        };
+        self.active = false;
+        self.units.clear();
+        name
+    }
+}
+
+/// Build the LFN entries for `name`, in on-disk order (highest sequence first)
+fn lfn_entries(name: &str, short_name: &[u8; 11]) -> Result<Vec<[u8; 32]>, &'static str> {
+    let units: Vec<u16> = name.encode_utf16().collect();
+    if units.is_empty() || units.len() > LFN_MAX_CHARS {
+        return Err("Invalid file name length");
+    }
+    let count = units.len().div_ceil(LFN_CHARS_PER_ENTRY);
+    let checksum = lfn_checksum(short_name);
+
+    let mut entries = Vec::with_capacity(count);
+    for seq in (1..=count).rev() {
+        let mut raw = [0u8; 32];
+        raw[0] = seq as u8 | if seq == count { LFN_LAST_ENTRY } else { 0 };
+        raw[11] = ATTR_LONG_NAME;
+        raw[13] = checksum;
+        // raw[12] (type) and raw[26..28] (first cluster) stay zero
+
+        let base = (seq - 1) * LFN_CHARS_PER_ENTRY;
+        for (i, &off) in LFN_CHAR_OFFSETS.iter().enumerate() {
+            // NUL terminator right after the name, 0xFFFF padding after that
+            let unit = match units.get(base + i) {
+                Some(&u) => u,
+                None if base + i == units.len() => 0x0000,
+                None => 0xFFFF,
+            };
+            raw[off..off + 2].copy_from_slice(&unit.to_le_bytes());
+        }
+        entries.push(raw);
+    }
+    Ok(entries)
+}
+
+/// A directory entry with its resolved (long or short) name and location
+#[derive(Clone)]
+struct DirRecord {
+    entry: FatDirEntry,
+    name: String,
+    /// Cluster and byte offset of the short entry
+    cluster: u32,
+    offset: usize,
+    /// Cluster and byte offset of each LFN entry (may be empty)
+    lfn_slots: Vec<(u32, usize)>,
+}
+
 // ═══════════════════════════════════════════════════════════════════════════════
 // FAT32 FILESYSTEM IMPLEMENTATION
 // ═══════════════════════════════════════════════════════════════════════════════
@@ -86,6 +244,8 @@ pub struct Fat32FileSystem {
     bpb: Fat32BootSector,
     fat_start_sector: u32,
     data_start_sector: u32,
+    /// Open files, so renames, truncates and unlinks reach every handle
+    nodes: Arc<SpinLock<Vec<Weak<SpinLock<FatNode>>>>>,
 }
 
 impl Fat32FileSystem {
@@ -114,6 +274,7 @@ impl Fat32FileSystem {
             bpb,
             fat_start_sector,
             data_start_sector,
+            nodes: Arc::new(SpinLock::new(Vec::new())),
         }))
     }
 
@@ -260,49 +421,76 @@ impl Fat32FileSystem {
         Ok(next)
     }
 
-    /// Read directory entries from a cluster chain
-    fn read_directory_entries(&self, start_cluster: u32) -> Result<Vec<FatDirEntry>, &'static str> {
-        let mut entries = Vec::new();
-        let mut current_cluster = start_cluster;
-        let cluster_size = self.bpb.sectors_per_cluster as usize * 512;
-        let mut buf = vec![0; cluster_size];
+    /// Walk a directory's entries in order, resolving long names
+    ///
+    /// `visit` returns true to stop the walk early.
+    fn walk_directory(&self, dir_cluster: u32, mut visit: impl FnMut(&DirRecord) -> bool) -> Result<(), &'static str> {
+        let mut current_cluster = dir_cluster;
+        let mut lfn = LfnBuilder::new();
 
         loop {
-            // Read cluster
-            self.read_cluster(current_cluster, &mut buf)?;
-            
-            // Parse entries
-            for i in 0..(cluster_size / 32) {
-                let offset = i * 32;
-                let entry_raw = &buf[offset..offset+32];
-                
-                // Check for end of directory
-                if entry_raw[0] == 0x00 {
-                    return Ok(entries);
-                }
-                
-                // Check for deleted entry
-                if entry_raw[0] == 0xE5 {
-                    continue;
-                }
-                
-                let entry = unsafe { core::ptr::read(entry_raw.as_ptr() as *const FatDirEntry) };
-                
-                // Skip Long File Name (LFN) entries for now
-                if entry.attr == ATTR_LONG_NAME {
-                    continue;
+            // Iterate sectors in cluster
+            let start_sec = self.cluster_to_sector(current_cluster);
+            for i in 0..self.bpb.sectors_per_cluster {
+                let mut buf = [0u8; 512];
+                self.device.read_sector(start_sec + i as u32, &mut buf)?;
+
+                for j in 0..16 { // 16 entries per sector
+                    let offset = j * 32;
+                    let entry_raw = &buf[offset..offset+32];
+                    let cluster_offset = (i as usize * 512) + offset;
+
+                    // End of directory
+                    if entry_raw[0] == 0x00 { return Ok(()); }
+
+                    // Deleted entry breaks any pending LFN chain
+                    if entry_raw[0] == 0xE5 {
+                        lfn.reset();
+                        continue;
+                    }
+
+                    if entry_raw[11] == ATTR_LONG_NAME {
+                        lfn.push(entry_raw, (current_cluster, cluster_offset));
+                        continue;
+                    }
+
+                    let entry = unsafe { core::ptr::read_unaligned(entry_raw.as_ptr() as *const FatDirEntry) };
+                    if (entry.attr & ATTR_VOLUME_ID) != 0 {
+                        lfn.reset();
+                        continue;
+                    }
+
+                    let lfn_slots = core::mem::take(&mut lfn.slots);
+                    let (name, lfn_slots) = match lfn.take(&entry.name) {
+                        Some(long) => (long, lfn_slots),
+                        None => (parse_fat_name(&entry), Vec::new()),
+                    };
+
+                    let record = DirRecord {
+                        entry,
+                        name,
+                        cluster: current_cluster,
+                        offset: cluster_offset,
+                        lfn_slots,
+                    };
+                    if visit(&record) { return Ok(()); }
                 }
-                
-                entries.push(entry);
             }
-            
-            // Next cluster
+
             match self.get_next_cluster(current_cluster)? {
                 Some(next) => current_cluster = next,
-                None => break,
+                None => return Ok(()),
             }
         }
-        
+    }
+
+    /// Read directory entries from a cluster chain
+    fn read_directory_entries(&self, start_cluster: u32) -> Result<Vec<DirRecord>, &'static str> {
+        let mut entries = Vec::new();
+        self.walk_directory(start_cluster, |record| {
+            entries.push(record.clone());
+            false
+        })?;
         Ok(entries)
     }
 
@@ -319,68 +507,101 @@ impl Fat32FileSystem {
         Ok(unsafe { core::ptr::read_unaligned(entry_ptr) })
     }
 
+    /// Find `count` consecutive free entry slots, extending the directory if needed
+    fn find_free_slots(&self, dir_cluster: u32, count: usize) -> Result<Vec<(u32, usize)>, &'static str> {
+        let mut run: Vec<(u32, usize)> = Vec::with_capacity(count);
+        let mut current_cluster = dir_cluster;
+        let cluster_bytes = self.bpb.sectors_per_cluster as usize * 512;
+
+        loop {
+            let start_sec = self.cluster_to_sector(current_cluster);
+            for i in 0..self.bpb.sectors_per_cluster {
+                let mut buf = [0u8; 512];
+                self.device.read_sector(start_sec + i as u32, &mut buf)?;
+
+                for j in 0..16 {
+                    let offset = j * 32;
+                    if buf[offset] == 0x00 || buf[offset] == 0xE5 {
+                        run.push((current_cluster, (i as usize * 512) + offset));
+                        if run.len() == count { return Ok(run); }
+                    } else {
+                        run.clear();
+                    }
+                }
+            }
+
+            match self.get_next_cluster(current_cluster)? {
+                Some(next) => current_cluster = next,
+                None => {
+                    // Extend (new clusters are zeroed, so every slot is free)
+                    while run.len() < count {
+                        current_cluster = self.alloc_cluster(Some(current_cluster))?;
+                        for off in (0..cluster_bytes).step_by(32) {
+                            run.push((current_cluster, off));
+                            if run.len() == count { break; }
+                        }
+                    }
+                    return Ok(run);
+                }
+            }
+        }
+    }
+
+    /// Write a directory entry for `name` (LFN chain + short entry)
+    ///
+    /// Returns the location of the short entry.
+    fn add_dir_entry(&self, dir_cluster: u32, name: &str, mut entry: FatDirEntry) -> Result<(u32, usize), &'static str> {
+        if name.is_empty() || name == "." || name == ".." || name.contains('/') {
+            return Err("Invalid file name");
+        }
+
+        let lfn = match short_name_for(name) {
+            Some((short, nt_res)) => {
+                entry.name = short;
+                entry.nt_res = nt_res;
+                Vec::new()
+            }
+            None => {
+                // Pick a unique NAME~N.EXT alias
+                let mut existing = Vec::new();
+                self.walk_directory(dir_cluster, |r| {
+                    existing.push(r.entry.name);
+                    false
+                })?;
+                let short = (1..1_000_000)
+                    .map(|n| make_short_name(name, n))
+                    .find(|c| !existing.contains(c))
+                    .ok_or("Directory full")?;
+                entry.name = short;
+                entry.nt_res = 0;
+                lfn_entries(name, &short)?
+            }
+        };
+
+        let slots = self.find_free_slots(dir_cluster, lfn.len() + 1)?;
+        for (raw, &(cluster, offset)) in lfn.iter().zip(slots.iter()) {
+            let lfn_entry = unsafe { core::ptr::read_unaligned(raw.as_ptr() as *const FatDirEntry) };
+            self.write_entry(cluster, offset, lfn_entry)?;
+        }
+        let (cluster, offset) = slots[lfn.len()];
+        self.write_entry(cluster, offset, entry)?;
+        Ok((cluster, offset))
+    }
+
     /// Create a new file entry in a directory
     fn create_file(&self, dir_cluster: u32, name: &str) -> Result<Arc<SpinLock<dyn FileOps>>, &'static str> {
-         // 1. Iterate dir to find free slot
-         let mut current_cluster = dir_cluster;
-         let mut target_cluster = dir_cluster;
-         let mut target_offset = 0;
-         let mut found = false;
-         
-         loop {
-             let start_sec = self.cluster_to_sector(current_cluster);
-             for i in 0..self.bpb.sectors_per_cluster {
-                 let mut buf = [0u8; 512];
-                 self.device.read_sector(start_sec + i as u32, &mut buf)?;
-                 
-                 for j in 0..16 {
-                     let offset = j * 32;
-                     if buf[offset] == 0 || buf[offset] == 0xE5 {
-                         target_cluster = current_cluster;
-                         target_offset = (i as usize * 512) + offset;
-                         found = true;
-                         break;
-                     }
-                 }
-                 if found { break; }
-             }
-             if found { break; }
-             
-             match self.get_next_cluster(current_cluster)? {
-                 Some(next) => current_cluster = next,
-                 None => {
-                     // Extend
-                     let next = self.alloc_cluster(Some(current_cluster))?;
-                     target_cluster = next;
-                     target_offset = 0;
-                     found = true;
-                     break;
-                 }
-             }
-         }
-         
-         // 2. Prepare entry
+         // 1. Prepare entry
          let mut entry: FatDirEntry = unsafe { core::mem::zeroed() };
-         let short_name = make_short_name(name);
-         entry.name = short_name;
-         entry.attr = 0x20; // Archive
+         entry.attr = ATTR_ARCHIVE;
          entry.size = 0;
          entry.cluster_high = 0;
          entry.cluster_low = 0;
          
-         // 3. Write entry
-         self.write_entry(target_cluster, target_offset, entry)?;
+         // 2. Write LFN chain + short entry
+         let location = self.add_dir_entry(dir_cluster, name, entry)?;
          
-         Ok(Arc::new(SpinLock::new(Fat32File {
-             fs: Arc::new(self.clone()),
-             first_cluster: 0,
-             current_cluster: 0,
-             current_offset: 0,
-             size: 0,
-             is_dir: false,
-             entry_cluster: target_cluster,
-             entry_offset: target_offset,
-         })))
+         let node = self.node_for(location, &entry);
+         Ok(Arc::new(SpinLock::new(Fat32File::new(self, node, false))))
     }
 
     /// Write a directory entry
@@ -399,40 +620,204 @@ impl Fat32FileSystem {
          Ok(())
     }
 
-    /// Find an entry in a directory by name (Returns Entry + Cluster + Offset)
-    fn find_entry(&self, dir_cluster: u32, name: &str) -> Result<Option<(FatDirEntry, u32, usize)>, &'static str> {
-        let mut current_cluster = dir_cluster;
-        loop {
-            // Iterate sectors in cluster
-            let start_sec = self.cluster_to_sector(current_cluster);
-            for i in 0..self.bpb.sectors_per_cluster {
-                 let mut buf = [0u8; 512];
-                 self.device.read_sector(start_sec + i as u32, &mut buf)?;
-                 
-                 for j in 0..16 { // 16 entries per sector
-                     let offset = j * 32;
-                     let entry_raw = &buf[offset..offset+32];
-                     
-                     if entry_raw[0] == 0 { return Ok(None); }
-                     if entry_raw[0] == 0xE5 { continue; }
-                     
-                     let entry = unsafe { core::ptr::read(entry_raw.as_ptr() as *const FatDirEntry) };
-                     if entry.attr == ATTR_LONG_NAME || (entry.attr & ATTR_VOLUME_ID) != 0 { continue; }
-                     
-                     let entry_name = parse_fat_name(&entry.name);
-                     if entry_name.eq_ignore_ascii_case(name) {
-                         let cluster_offset = (i as usize * 512) + offset;
-                         return Ok(Some((entry, current_cluster, cluster_offset)));
-                     }
-                 }
+    /// Find an entry in a directory by long or short name (case-insensitive)
+    fn find_entry(&self, dir_cluster: u32, name: &str) -> Result<Option<DirRecord>, &'static str> {
+        let mut found = None;
+        self.walk_directory(dir_cluster, |record| {
+            if record.name.eq_ignore_ascii_case(name)
+                || parse_fat_name(&record.entry).eq_ignore_ascii_case(name)
+            {
+                found = Some(record.clone());
+                return true;
             }
-            
-            match self.get_next_cluster(current_cluster)? {
-                Some(next) => current_cluster = next,
+            false
+        })?;
+        Ok(found)
+    }
+
+    /// Data cluster of an entry; `..` pointing at the root stores 0
+    fn dir_cluster_of(&self, entry: &FatDirEntry) -> u32 {
+        match entry.first_cluster() {
+            0 if (entry.attr & ATTR_DIRECTORY) != 0 => self.bpb.root_cluster,
+            cluster => cluster,
+        }
+    }
+
+    /// Walk `path` to a directory and return its first cluster
+    fn resolve_dir(&self, path: &str) -> Result<u32, &'static str> {
+        let mut current_cluster = self.bpb.root_cluster;
+        for name in path.split('/').filter(|s| !s.is_empty()) {
+            let record = self.find_entry(current_cluster, name)?.ok_or("Directory not found")?;
+            if (record.entry.attr & ATTR_DIRECTORY) == 0 { return Err("Not a directory"); }
+            current_cluster = self.dir_cluster_of(&record.entry);
+        }
+        Ok(current_cluster)
+    }
+
+    /// Resolve the parent directory of `path`; returns (parent cluster, final name)
+    fn resolve_parent<'a>(&self, path: &'a str) -> Result<(u32, &'a str), &'static str> {
+        let path = path.trim_matches('/');
+        let (parent, name) = match path.rfind('/') {
+            Some(idx) => (&path[..idx], &path[idx+1..]),
+            None => ("", path),
+        };
+        if name.is_empty() { return Err("Invalid path"); }
+        Ok((self.resolve_dir(parent)?, name))
+    }
+
+    /// Return a cluster chain to the free pool
+    fn free_chain(&self, start_cluster: u32) -> Result<(), &'static str> {
+        let mut cluster = start_cluster;
+        while cluster >= 2 {
+            let next = self.get_next_cluster(cluster)?;
+            self.write_fat_entry(cluster, 0)?;
+            match next {
+                Some(n) => cluster = n,
                 None => break,
             }
         }
-        Ok(None)
+        Ok(())
+    }
+
+    /// Mark a directory entry (and its LFN entries) as deleted
+    fn delete_record(&self, record: &DirRecord) -> Result<(), &'static str> {
+        for &(cluster, offset) in record.lfn_slots.iter().chain(core::iter::once(&(record.cluster, record.offset))) {
+            let mut entry = self.read_entry(cluster, offset)?;
+            entry.name[0] = 0xE5;
+            self.write_entry(cluster, offset, entry)?;
+        }
+        Ok(())
+    }
+
+    /// Resize the file behind `record`; returns the updated entry
+    ///
+    /// Shrinking frees the clusters past the new end. Growing allocates
+    /// clusters and zeroes everything between the old and new end.
+    fn truncate_record(&self, record: &DirRecord, size: u64) -> Result<FatDirEntry, &'static str> {
+        let mut entry = record.entry;
+        if (entry.attr & ATTR_DIRECTORY) != 0 { return Err("Is a directory"); }
+        if size > u32::MAX as u64 { return Err("File too large"); }
+        
+        let cluster_size = self.bpb.sectors_per_cluster as u64 * 512;
+        let old_size = entry.size as u64;
+        
+        if size == 0 {
+            self.free_chain(entry.first_cluster())?;
+            entry.set_first_cluster(0);
+        } else {
+            let needed = size.div_ceil(cluster_size);
+            let mut cluster = entry.first_cluster();
+            if cluster == 0 {
+                cluster = self.alloc_cluster(None)?;
+                entry.set_first_cluster(cluster);
+            }
+            
+            for idx in 0..needed {
+                if idx > 0 {
+                    cluster = match self.get_next_cluster(cluster)? {
+                        Some(next) => next,
+                        None => self.alloc_cluster(Some(cluster))?, // Zeroed
+                    };
+                }
+                
+                // Zero stale bytes past the old end of file
+                let start = idx * cluster_size;
+                if size > old_size && start + cluster_size > old_size {
+                    let from = old_size.saturating_sub(start) as usize;
+                    let mut buf = vec![0u8; cluster_size as usize];
+                    self.read_cluster(cluster, &mut buf)?;
+                    if buf[from..].iter().any(|&b| b != 0) {
+                        buf[from..].fill(0);
+                        self.write_cluster(cluster, &buf)?;
+                    }
+                }
+            }
+            
+            // Cut the chain after the last needed cluster
+            if let Some(rest) = self.get_next_cluster(cluster)? {
+                self.write_fat_entry(cluster, 0x0FFFFFFF)?;
+                self.free_chain(rest)?;
+            }
+        }
+        
+        entry.size = size as u32;
+        self.write_entry(record.cluster, record.offset, entry)?;
+        self.update_node((record.cluster, record.offset), |node| {
+            node.first_cluster = entry.first_cluster();
+            node.size = size;
+            node.generation += 1;
+        });
+        Ok(entry)
+    }
+
+    /// Does the directory contain anything besides `.` and `..`?
+    fn dir_is_empty(&self, dir_cluster: u32) -> Result<bool, &'static str> {
+        let mut empty = true;
+        self.walk_directory(dir_cluster, |record| {
+            if !record.entry.is_dot() {
+                empty = false;
+                return true;
+            }
+            false
+        })?;
+        Ok(empty)
+    }
+
+    /// Is directory `dir` the directory `ancestor` or somewhere below it?
+    fn is_within(&self, mut dir: u32, ancestor: u32) -> Result<bool, &'static str> {
+        // Bounded by the cluster count, so a corrupt `..` loop can't hang us
+        for _ in 0..self.bpb.fat_size_32 as u64 * 128 {
+            if dir == ancestor {
+                return Ok(true);
+            }
+            if dir == self.bpb.root_cluster {
+                return Ok(false);
+            }
+            let dotdot = self.read_entry(dir, 32)?;
+            if &dotdot.name != b"..         " {
+                return Err("Corrupt directory");
+            }
+            dir = self.dir_cluster_of(&dotdot);
+        }
+        Err("Corrupt directory")
+    }
+
+    /// Shared state of the file whose short entry is at `location`, made
+    /// from `entry` if the file isn't open yet
+    fn node_for(&self, location: (u32, usize), entry: &FatDirEntry) -> Arc<SpinLock<FatNode>> {
+        let mut nodes = self.nodes.lock();
+        nodes.retain(|weak| weak.strong_count() > 0);
+        if let Some(node) = nodes.iter().filter_map(Weak::upgrade).find(|n| n.lock().entry == Some(location)) {
+            return node;
+        }
+        let node = Arc::new(SpinLock::new(FatNode {
+            entry: Some(location),
+            inode: NEXT_INODE.fetch_add(1, Ordering::Relaxed),
+            first_cluster: self.dir_cluster_of(entry),
+            size: entry.size as u64,
+            generation: 0,
+        }));
+        nodes.push(Arc::downgrade(&node));
+        node
+    }
+
+    /// Update the open file, if any, whose short entry is at `location`
+    fn update_node(&self, location: (u32, usize), update: impl FnOnce(&mut FatNode)) {
+        let nodes = self.nodes.lock();
+        if let Some(node) = nodes.iter().filter_map(Weak::upgrade).find(|n| n.lock().entry == Some(location)) {
+            update(&mut node.lock());
+        }
+    }
+
+    /// Cut the open file at `location` off its entry and clusters, which
+    /// are about to be freed
+    fn detach_node(&self, location: (u32, usize)) {
+        self.update_node(location, |node| {
+            node.entry = None;
+            node.first_cluster = 0;
+            node.size = 0;
+            node.generation += 1;
+        });
     }
 }
 
@@ -442,46 +827,30 @@ impl Filesystem for Fat32FileSystem {
         let mut path_parts = path.split('/').filter(|s| !s.is_empty()).peekable();
         
         if path_parts.peek().is_none() {
-             // Root directory
-             return Ok(Arc::new(SpinLock::new(Fat32File {
-                 fs: Arc::new(self.clone()),
-                 first_cluster: self.bpb.root_cluster,
-                 current_cluster: self.bpb.root_cluster,
-                 current_offset: 0,
-                 size: 0,
-                 is_dir: true,
-                 entry_cluster: 0,
-                 entry_offset: 0,
-             })));
+             // Root directory (it has no entry)
+             let node = FatNode { entry: None, inode: ROOT_INODE, first_cluster: self.bpb.root_cluster, size: 0, generation: 0 };
+             return Ok(Arc::new(SpinLock::new(Fat32File::new(self, Arc::new(SpinLock::new(node)), true))));
         }
         
         while let Some(name) = path_parts.next() {
             match self.find_entry(current_cluster, name)? {
-                Some((entry, entry_cluster, entry_offset)) => {
+                Some(record) => {
+                    let mut entry = record.entry;
                     if path_parts.peek().is_none() {
                         // Found file/dir
                         let is_dir = (entry.attr & ATTR_DIRECTORY) != 0;
-                        let cluster = ((entry.cluster_high as u32) << 16) | (entry.cluster_low as u32);
                         
                         // Check flags
-                        if (flags & crate::fs::vfs::O_TRUNC) != 0 && !is_dir {
-                             // Truncate logic (not implemented yet, but we allow write)
+                        if (flags & crate::fs::vfs::O_TRUNC) != 0 && !is_dir && entry.size > 0 {
+                            entry = self.truncate_record(&record, 0)?;
                         }
 
-                        return Ok(Arc::new(SpinLock::new(Fat32File {
-                            fs: Arc::new(self.clone()),
-                            first_cluster: cluster,
-                            current_cluster: cluster,
-                            current_offset: 0,
-                            size: entry.size as u64,
-                            is_dir,
-                            entry_cluster,
-                            entry_offset,
-                        })));
+                        let node = self.node_for((record.cluster, record.offset), &entry);
+                        return Ok(Arc::new(SpinLock::new(Fat32File::new(self, node, is_dir))));
                     } else {
                         // Directory
                         if (entry.attr & ATTR_DIRECTORY) == 0 { return Err("Not a directory"); }
-                        current_cluster = ((entry.cluster_high as u32) << 16) | (entry.cluster_low as u32);
+                        current_cluster = self.dir_cluster_of(&entry);
                     }
                 },
                 None => {
@@ -498,49 +867,162 @@ impl Filesystem for Fat32FileSystem {
     
     fn create(&self, path: &str) -> Result<Arc<SpinLock<dyn FileOps>>, &'static str> {
         // Find parent
-        let path = path.trim_matches('/');
-        if path.contains('/') { return Err("Subdirs not supported yet"); }
-        self.create_file(self.bpb.root_cluster, path)
+        let (parent_cluster, name) = self.resolve_parent(path)?;
+        if self.find_entry(parent_cluster, name)?.is_some() {
+            return Err("File exists");
+        }
+        self.create_file(parent_cluster, name)
     }
     
-    fn mkdir(&self, _path: &str) -> Result<(), &'static str> {
-        Err("Read-only filesystem")
+    fn mkdir(&self, path: &str) -> Result<(), &'static str> {
+        let (parent_cluster, name) = self.resolve_parent(path)?;
+        if self.find_entry(parent_cluster, name)?.is_some() {
+            return Err("File exists");
+        }
+        
+        // 1. Allocate (zeroed) data cluster
+        let cluster = self.alloc_cluster(None)?;
+        
+        // 2. "." and ".." ("..", when it is the root, is stored as 0)
+        let mut dot: FatDirEntry = unsafe { core::mem::zeroed() };
+        dot.name = *b".          ";
+        dot.attr = ATTR_DIRECTORY;
+        dot.set_first_cluster(cluster);
+        
+        let mut dotdot = dot;
+        dotdot.name = *b"..         ";
+        dotdot.set_first_cluster(if parent_cluster == self.bpb.root_cluster { 0 } else { parent_cluster });
+        
+        self.write_entry(cluster, 0, dot)?;
+        self.write_entry(cluster, 32, dotdot)?;
+        
+        // 3. Link into parent
+        let mut entry: FatDirEntry = unsafe { core::mem::zeroed() };
+        entry.attr = ATTR_DIRECTORY;
+        entry.set_first_cluster(cluster);
+        if let Err(e) = self.add_dir_entry(parent_cluster, name, entry) {
+            self.free_chain(cluster)?;
+            return Err(e);
+        }
+        Ok(())
     }
     
-    fn remove(&self, _path: &str) -> Result<(), &'static str> {
-        Err("Read-only filesystem")
+    fn remove(&self, path: &str) -> Result<(), &'static str> {
+        let (parent_cluster, name) = self.resolve_parent(path)?;
+        let record = self.find_entry(parent_cluster, name)?.ok_or("File not found")?;
+        if record.entry.is_dot() {
+            return Err("Invalid path");
+        }
+        
+        if (record.entry.attr & ATTR_DIRECTORY) != 0 {
+            let cluster = self.dir_cluster_of(&record.entry);
+            if !self.dir_is_empty(cluster)? {
+                return Err("Directory not empty");
+            }
+        }
+        
+        // Unlink first so a failure part-way never leaves an entry pointing at freed clusters
+        self.delete_record(&record)?;
+        self.detach_node((record.cluster, record.offset));
+        self.free_chain(record.entry.first_cluster())
     }
     
     fn read_dir(&self, path: &str) -> Result<Vec<DirEntry>, &'static str> {
-        // Assume root for now
-        if path == "/" || path.is_empty() {
-            let raw_entries = self.read_directory_entries(self.bpb.root_cluster)?;
-            let mut entries = Vec::new();
-            
-            for raw in raw_entries {
-                // Convert 8.3 name to string
-                let name = parse_fat_name(&raw.name);
-                
-                entries.push(DirEntry {
-                    name,
-                    is_dir: (raw.attr & ATTR_DIRECTORY) != 0,
-                    size: raw.size as u64,
-                });
+        let dir_cluster = self.resolve_dir(path)?;
+        let raw_entries = self.read_directory_entries(dir_cluster)?;
+        let mut entries = Vec::new();
+        
+        for raw in raw_entries.into_iter().filter(|r| !r.entry.is_dot()) {
+            entries.push(DirEntry {
+                name: raw.name,
+                is_dir: (raw.entry.attr & ATTR_DIRECTORY) != 0,
+                size: raw.entry.size as u64,
+            });
+        }
+        Ok(entries)
+    }
+    
+    fn rename(&self, from: &str, to: &str) -> Result<(), &'static str> {
+        let (from_parent, from_name) = self.resolve_parent(from)?;
+        let record = self.find_entry(from_parent, from_name)?.ok_or("File not found")?;
+        if record.entry.is_dot() {
+            return Err("Invalid path");
+        }
+        let is_dir = (record.entry.attr & ATTR_DIRECTORY) != 0;
+        
+        let (to_parent, to_name) = self.resolve_parent(to)?;
+        if is_dir && self.is_within(to_parent, self.dir_cluster_of(&record.entry))? {
+            return Err("Cannot move a directory into itself");
+        }
+
+        // Same entry under another spelling (case-only rename) is not a replacement
+        let replaced = self.find_entry(to_parent, to_name)?
+            .filter(|existing| (existing.cluster, existing.offset) != (record.cluster, record.offset));
+        let location = match &replaced {
+            Some(existing) => {
+                let existing_dir = (existing.entry.attr & ATTR_DIRECTORY) != 0;
+                match (is_dir, existing_dir) {
+                    (false, true) => return Err("Is a directory"),
+                    (true, false) => return Err("Not a directory"),
+                    (true, true) if !self.dir_is_empty(self.dir_cluster_of(&existing.entry))? => {
+                        return Err("Directory not empty");
+                    }
+                    _ => {}
+                }
+                // Overwrite the target's short entry in place (its name and
+                // LFN chain stay valid), so the name never goes missing
+                let mut entry = record.entry;
+                entry.name = existing.entry.name;
+                entry.nt_res = existing.entry.nt_res;
+                self.write_entry(existing.cluster, existing.offset, entry)?;
+                self.detach_node((existing.cluster, existing.offset));
+                (existing.cluster, existing.offset)
             }
-            Ok(entries)
-        } else {
-            Err("Subdirectories not supported yet")
+            // Link under the new name
+            None => self.add_dir_entry(to_parent, to_name, record.entry)?,
+        };
+
+        // Then drop the old entry and point open handles at the new one
+        self.delete_record(&record)?;
+        self.update_node((record.cluster, record.offset), |node| node.entry = Some(location));
+        
+        // A moved directory's ".." must follow it
+        if is_dir && to_parent != from_parent {
+            let cluster = self.dir_cluster_of(&record.entry);
+            let mut dotdot = self.read_entry(cluster, 32)?;
+            if &dotdot.name == b"..         " {
+                dotdot.set_first_cluster(if to_parent == self.bpb.root_cluster { 0 } else { to_parent });
+                self.write_entry(cluster, 32, dotdot)?;
+            }
+        }
+
+        // The replaced file's clusters go only once nothing points at them
+        match replaced {
+            Some(existing) => self.free_chain(existing.entry.first_cluster()),
+            None => Ok(()),
         }
     }
+    
+    fn truncate(&self, path: &str, size: u64) -> Result<(), &'static str> {
+        let (parent_cluster, name) = self.resolve_parent(path)?;
+        let record = self.find_entry(parent_cluster, name)?.ok_or("File not found")?;
+        self.truncate_record(&record, size)?;
+        Ok(())
+    }
 }
 
-/// Helper to parse 8.3 name
-fn parse_fat_name(raw: &[u8; 11]) -> String {
+/// Helper to parse 8.3 name (honours the NT lowercase flags)
+fn parse_fat_name(entry: &FatDirEntry) -> String {
+    let raw = entry.name;
+    let lower_base = entry.nt_res & NT_LOWER_BASE != 0;
+    let lower_ext = entry.nt_res & NT_LOWER_EXT != 0;
     let mut name = String::new();
     // Filename (8 chars)
-    for &c in raw.iter().take(8) {
+    for (i, &c) in raw.iter().take(8).enumerate() {
         if c != 0x20 {
-            name.push(c as char);
+            // 0x05 stands for a leading 0xE5 byte
+            let c = if i == 0 && c == 0x05 { 0xE5 } else { c };
+            name.push(if lower_base { c.to_ascii_lowercase() } else { c } as char);
         }
     }
     // Extension (3 chars)
@@ -548,32 +1030,104 @@ fn parse_fat_name(raw: &[u8; 11]) -> String {
         name.push('.');
         for &c in raw.iter().skip(8).take(3) {
             if c != 0x20 {
-                name.push(c as char);
+                name.push(if lower_ext { c.to_ascii_lowercase() } else { c } as char);
             }
         }
     }
     name
 }
 
-fn make_short_name(name: &str) -> [u8; 11] {
-    let mut res = [0x20u8; 11];
+/// Characters allowed in an 8.3 name besides letters and digits
+fn is_short_name_char(c: u8) -> bool {
+    c.is_ascii_alphanumeric() || b"$%'-_@~`!(){}^#&".contains(&c)
+}
+
+/// Store `name` as a plain 8.3 entry if possible (no LFN needed)
+///
+/// Returns the short name and NT case flags. Mixed-case parts need an LFN.
+fn short_name_for(name: &str) -> Option<([u8; 11], u8)> {
     let bytes = name.as_bytes();
+    let (base, ext) = match bytes.iter().position(|&c| c == b'.') {
+        Some(idx) => (&bytes[..idx], &bytes[idx+1..]),
+        None => (bytes, &[][..]),
+    };
+    if base.is_empty() || base.len() > 8 || ext.len() > 3 || (ext.is_empty() && bytes.contains(&b'.')) {
+        return None;
+    }
+    if !base.iter().chain(ext.iter()).all(|&c| is_short_name_char(c)) {
+        return None;
+    }
+
+    // Each part must be single-case to be representable with the NT flags
+    let case_flag = |part: &[u8], flag: u8| -> Option<u8> {
+        let lower = part.iter().any(|c| c.is_ascii_lowercase());
+        let upper = part.iter().any(|c| c.is_ascii_uppercase());
+        match (lower, upper) {
+            (true, true) => None,
+            (true, false) => Some(flag),
+            _ => Some(0),
+        }
+    };
+    let nt_res = case_flag(base, NT_LOWER_BASE)? | case_flag(ext, NT_LOWER_EXT)?;
+
+    let mut res = [0x20u8; 11];
+    for (i, &b) in base.iter().enumerate() {
+        res[i] = b.to_ascii_uppercase();
+    }
+    for (i, &b) in ext.iter().enumerate() {
+        res[8+i] = b.to_ascii_uppercase();
+    }
+    Some((res, nt_res))
+}
+
+/// Generate the `BASENA~N.EXT` alias for a long name
+fn make_short_name(name: &str, n: u32) -> [u8; 11] {
+    let mut res = [0x20u8; 11];
+    let bytes = name.trim_start_matches('.').as_bytes();
     
-    // Split extension
+    // Split extension at the last dot
     let (base, ext) = if let Some(idx) = bytes.iter().rposition(|&c| c == b'.') {
         (&bytes[0..idx], &bytes[idx+1..])
     } else {
         (bytes, &[][..])
     };
     
-    // Copy base (up to 8, uppercase)
-    for (i, &b) in base.iter().take(8).enumerate() {
-        res[i] = b.to_ascii_uppercase();
+    let clean = |c: u8| -> Option<u8> {
+        match c {
+            b' ' | b'.' => None,                       // Dropped
+            c if is_short_name_char(c) => Some(c.to_ascii_uppercase()),
+            _ => Some(b'_'),                           // Invalid or non-ASCII
+        }
+    };
+    
+    // Tail "~N" replaces the end of the base
+    let mut tail = [0u8; 8];
+    let mut tail_len = 0;
+    let mut v = n;
+    while v > 0 {
+        tail[tail_len] = b'0' + (v % 10) as u8;
+        tail_len += 1;
+        v /= 10;
+    }
+    let keep = 8 - (tail_len + 1);
+    
+    let mut i = 0;
+    for c in base.iter().filter_map(|&c| clean(c)).take(keep) {
+        res[i] = c;
+        i += 1;
+    }
+    if i == 0 {
+        res[0] = b'_';
+        i = 1;
+    }
+    res[i] = b'~';
+    for k in 0..tail_len {
+        res[i + 1 + k] = tail[tail_len - 1 - k];
     }
     
     // Copy ext (up to 3, uppercase)
-    for (i, &b) in ext.iter().take(3).enumerate() {
-        res[8+i] = b.to_ascii_uppercase();
+    for (i, c) in ext.iter().filter_map(|&c| clean(c)).take(3).enumerate() {
+        res[8+i] = c;
     }
     res
 }
@@ -582,60 +1136,131 @@ fn make_short_name(name: &str) -> [u8; 11] {
 // FILE HANDLE
 // ═══════════════════════════════════════════════════════════════════════════════
 
+/// FAT has no inode numbers: the root gets a fixed one, and every other
+/// file a fresh one when it is first opened, kept while any handle (or a
+/// mapping) holds it. First clusters won't do, since empty files have none
+/// and freed clusters are reused.
+const ROOT_INODE: u64 = 1;
+static NEXT_INODE: AtomicU64 = AtomicU64::new(ROOT_INODE + 1);
+
+/// State shared by every open handle of one file
+struct FatNode {
+    /// Location of the short entry; `None` for the root and unlinked files
+    entry: Option<(u32, usize)>,
+    inode: u64,
+    first_cluster: u32,
+    size: u64,
+    /// Bumped whenever the cluster chain is cut, so handles walk it afresh
+    generation: u64,
+}
+
 pub struct Fat32File {
     fs: Arc<Fat32FileSystem>,
-    first_cluster: u32,
-    current_cluster: u32,
+    node: Arc<SpinLock<FatNode>>,
+    /// `cluster` is cluster number `index` of the chain as of node
+    /// `generation`; 0 until the first access
+    cluster: u32,
+    index: u64,
+    generation: u64,
     current_offset: u64,
-    size: u64,
     is_dir: bool,
-    entry_cluster: u32,
-    entry_offset: usize,
+    /// Directory read reached the end of its cluster chain
+    dir_end: bool,
+}
+
+impl Fat32File {
+    fn new(fs: &Fat32FileSystem, node: Arc<SpinLock<FatNode>>, is_dir: bool) -> Self {
+        Self {
+            fs: Arc::new(fs.clone()),
+            node,
+            cluster: 0,
+            index: 0,
+            generation: 0,
+            current_offset: 0,
+            is_dir,
+            dir_end: false,
+        }
+    }
+
+    /// Cluster holding byte `offset`, following the chain from the last
+    /// position; `grow` allocates clusters past the end instead of
+    /// returning `None`
+    fn cluster_at(&mut self, offset: u64, grow: bool) -> Result<Option<u32>, &'static str> {
+        let cluster_size = self.fs.bpb.sectors_per_cluster as u64 * 512;
+        let target = offset / cluster_size;
+
+        let mut node = self.node.lock();
+        if node.first_cluster == 0 {
+            if !grow {
+                return Ok(None);
+            }
+            let first = self.fs.alloc_cluster(None)?;
+            node.first_cluster = first;
+            if let Some((cluster, offset)) = node.entry {
+                let mut entry = self.fs.read_entry(cluster, offset)?;
+                entry.set_first_cluster(first);
+                self.fs.write_entry(cluster, offset, entry)?;
+            }
+        }
+        if self.cluster == 0 || self.generation != node.generation || target < self.index {
+            self.cluster = node.first_cluster;
+            self.index = 0;
+            self.generation = node.generation;
+        }
+        drop(node);
+
+        while self.index < target {
+            self.cluster = match self.fs.get_next_cluster(self.cluster)? {
+                Some(next) => next,
+                None if grow => self.fs.alloc_cluster(Some(self.cluster))?,
+                None => return Ok(None),
+            };
+            self.index += 1;
+        }
+        Ok(Some(self.cluster))
+    }
 }
 
 impl FileOps for Fat32File {
     fn read(&mut self, buf: &mut [u8]) -> Result<usize, &'static str> {
-        if !self.is_dir && self.current_offset >= self.size {
+        let size = self.node.lock().size;
+        if !self.is_dir && self.current_offset >= size {
             return Ok(0); // EOF
         }
+        if self.is_dir && self.dir_end {
+            return Ok(0);
+        }
         
         let cluster_size = self.fs.bpb.sectors_per_cluster as u64 * 512;
         let mut bytes_read = 0;
-        let mut buf_offset = 0;
         
-        while buf_offset < buf.len() && (self.is_dir || self.current_offset < self.size) {
+        while bytes_read < buf.len() && (self.is_dir || self.current_offset < size) {
+            // Directories have no size: the end of the chain ends them
+            let Some(cluster) = self.cluster_at(self.current_offset, false)? else {
+                self.dir_end = self.is_dir;
+                break;
+            };
+
             // Calculate offset within current cluster
             let cluster_offset = (self.current_offset % cluster_size) as usize;
-            let bytes_to_read = core::cmp::min(
-                buf.len() - buf_offset,
+            let mut bytes_to_read = core::cmp::min(
+                buf.len() - bytes_read,
                 (cluster_size as usize) - cluster_offset,
             );
-            
-            let bytes_to_read = if self.is_dir {
-                bytes_to_read
-            } else {
-                core::cmp::min(bytes_to_read, (self.size - self.current_offset) as usize)
-            };
+            if !self.is_dir {
+                bytes_to_read = core::cmp::min(bytes_to_read, (size - self.current_offset) as usize);
+            }
             
             // Read cluster
             let mut cluster_buf = vec![0; cluster_size as usize];
-            self.fs.read_cluster(self.current_cluster, &mut cluster_buf)?;
+            self.fs.read_cluster(cluster, &mut cluster_buf)?;
             
             // Copy data
-            buf[buf_offset..buf_offset+bytes_to_read].copy_from_slice(&cluster_buf[cluster_offset..cluster_offset+bytes_to_read]);
+            buf[bytes_read..bytes_read+bytes_to_read].copy_from_slice(&cluster_buf[cluster_offset..cluster_offset+bytes_to_read]);
             
             // Update state
             bytes_read += bytes_to_read;
-            buf_offset += bytes_to_read;
             self.current_offset += bytes_to_read as u64;
-            
-            // Move to next cluster if needed
-            if self.current_offset.is_multiple_of(cluster_size) && self.current_offset < self.size {
-                match self.fs.get_next_cluster(self.current_cluster)? {
-                    Some(next) => self.current_cluster = next,
-                    None => break, // Should not happen if size is correct
-                }
-            }
         }
         
         Ok(bytes_read)
@@ -643,69 +1268,40 @@ impl FileOps for Fat32File {
     
     fn write(&mut self, buf: &[u8]) -> Result<usize, &'static str> {
         if self.is_dir { return Err("Cannot write to dir"); }
-        if self.first_cluster == 0 {
-            // Alloc first cluster
-            let c = self.fs.alloc_cluster(None)?;
-            self.first_cluster = c;
-            self.current_cluster = c;
-            
-            // Update directory entry with first cluster
-            let mut entry = self.fs.read_entry(self.entry_cluster, self.entry_offset)?;
-            entry.cluster_high = (c >> 16) as u16;
-            entry.cluster_low = (c & 0xFFFF) as u16;
-            self.fs.write_entry(self.entry_cluster, self.entry_offset, entry)?;
-        }
+        if self.node.lock().entry.is_none() { return Err("File was deleted"); }
         
         let mut total_written = 0;
         let cluster_size = self.fs.bpb.sectors_per_cluster as u64 * 512;
         
         while total_written < buf.len() {
-             // Calculate local offset
+             // Extends the chain when writing past its end
+             let cluster = self.cluster_at(self.current_offset, true)?.ok_or("Disk full")?;
              let offset_in_cluster = (self.current_offset % cluster_size) as usize;
              let space_in_cluster = (cluster_size as usize) - offset_in_cluster;
              let to_write = core::cmp::min(buf.len() - total_written, space_in_cluster);
              
-             // Check if we need to extend
-             if offset_in_cluster == 0 && self.current_offset > 0 {
-                 // Boundary reached, check if we need new cluster
-                 // Actually we can just check if current_cluster is EOC?
-                 // Simple logic: if offset matches size limit of chain so far, append.
-                 // Better: check FAT for current_cluster.
-                 // For now, assume if we write past end, we alloc.
-             }
-             
              // Write data to cluster
              let mut cluster_buf = vec![0u8; cluster_size as usize];
              // Read current content if partial write
-             self.fs.read_cluster(self.current_cluster, &mut cluster_buf)?;
+             self.fs.read_cluster(cluster, &mut cluster_buf)?;
              
              cluster_buf[offset_in_cluster..offset_in_cluster+to_write]
                  .copy_from_slice(&buf[total_written..total_written+to_write]);
                  
-             self.fs.write_cluster(self.current_cluster, &cluster_buf)?;
+             self.fs.write_cluster(cluster, &cluster_buf)?;
              
              total_written += to_write;
              self.current_offset += to_write as u64;
-             
-             // Move to next cluster
-             if self.current_offset % cluster_size == 0 && total_written < buf.len() {
-                 match self.fs.get_next_cluster(self.current_cluster)? {
-                     Some(next) => self.current_cluster = next,
-                     None => {
-                         let next = self.fs.alloc_cluster(Some(self.current_cluster))?;
-                         self.current_cluster = next;
-                     }
-                 }
-             }
         }
         
-        if self.current_offset > self.size {
-            self.size = self.current_offset;
+        let mut node = self.node.lock();
+        if self.current_offset > node.size {
+            node.size = self.current_offset;
             // Update directory entry size
-            if self.entry_cluster != 0 {
-                let mut entry = self.fs.read_entry(self.entry_cluster, self.entry_offset)?;
-                entry.size = self.size as u32;
-                self.fs.write_entry(self.entry_cluster, self.entry_offset, entry)?;
+            if let Some((cluster, offset)) = node.entry {
+                let mut entry = self.fs.read_entry(cluster, offset)?;
+                entry.size = node.size as u32;
+                self.fs.write_entry(cluster, offset, entry)?;
             }
         }
         
@@ -713,41 +1309,19 @@ impl FileOps for Fat32File {
     }
     
     fn seek(&mut self, pos: SeekFrom) -> Result<u64, &'static str> {
+        let size = self.node.lock().size;
         let new_pos = match pos {
             SeekFrom::Start(off) => off,
             SeekFrom::Current(off) => (self.current_offset as i64 + off) as u64,
-            SeekFrom::End(off) => (self.size as i64 + off) as u64,
+            SeekFrom::End(off) => (size as i64 + off) as u64,
         };
 
-        if new_pos > self.size {
+        if new_pos > size {
             return Err("Seek beyond EOF");
         }
 
-        // Optimize: If seeking forward within same cluster, just update offset
-        let cluster_size = self.fs.bpb.sectors_per_cluster as u64 * 512;
-        let current_cluster_idx = self.current_offset / cluster_size;
-        let new_cluster_idx = new_pos / cluster_size;
-
-        if new_cluster_idx == current_cluster_idx {
-            self.current_offset = new_pos;
-            return Ok(new_pos);
-        }
-
-        // If seeking backwards or to a different cluster, we might need to restart from first_cluster
-        // For simplicity, always restart from first_cluster if cluster changes
-        // (Optimization: could traverse from current if moving forward)
-        
-        let mut cluster = self.first_cluster;
-        for _ in 0..new_cluster_idx {
-            match self.fs.get_next_cluster(cluster)? {
-                Some(next) => cluster = next,
-                None => return Err("Seek error: broken cluster chain"),
-            }
-        }
-        
-        self.current_cluster = cluster;
+        // The cluster is found on the next access
         self.current_offset = new_pos;
-        
         Ok(new_pos)
     }
     
@@ -756,10 +1330,11 @@ impl FileOps for Fat32File {
     }
     
     fn stat(&self) -> Result<FileStat, &'static str> {
+        let node = self.node.lock();
         Ok(FileStat {
-            size: self.size,
+            size: node.size,
             mode: if self.is_dir { 0o040777 } else { 0o100777 },
-            inode: self.first_cluster as u64,
+            inode: node.inode,
         })
     }
 
@@ -772,6 +1347,7 @@ impl FileOps for Fat32File {
             return Err("Not a directory");
         }
         
+        let mut lfn = LfnBuilder::new();
         loop {
             let mut buf = [0u8; 32];
             match self.read(&mut buf) {
@@ -780,16 +1356,26 @@ impl FileOps for Fat32File {
                     if buf[0] == 0x00 { return Ok(None); }
                     
                     // Check for deleted entry
-                    if buf[0] == 0xE5 { continue; }
+                    if buf[0] == 0xE5 {
+                        lfn.reset();
+                        continue;
+                    }
+                    
+                    // Collect LFN parts until their short entry
+                    if buf[11] == ATTR_LONG_NAME {
+                        lfn.push(&buf, (0, 0));
+                        continue;
+                    }
                     
-                    let entry = unsafe { core::ptr::read(buf.as_ptr() as *const FatDirEntry) };
+                    let entry = unsafe { core::ptr::read_unaligned(buf.as_ptr() as *const FatDirEntry) };
                     
-                    // Skip LFN and Volume ID
-                    if entry.attr == ATTR_LONG_NAME || (entry.attr & ATTR_VOLUME_ID) != 0 {
+                    // Skip Volume ID
+                    if (entry.attr & ATTR_VOLUME_ID) != 0 {
+                        lfn.reset();
                         continue;
                     }
                     
-                    let name = parse_fat_name(&entry.name);
+                    let name = lfn.take(&entry.name).unwrap_or_else(|| parse_fat_name(&entry));
                     
                     return Ok(Some(DirEntry {
                         name,
@@ -803,3 +1389,267 @@ impl FileOps for Fat32File {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// In-memory disk holding a test image
+    struct MemDisk(SpinLock<Vec<u8>>);
+
+    impl BlockDevice for MemDisk {
+        fn read_sector(&self, sector: u32, buf: &mut [u8]) -> Result<(), &'static str> {
+            let disk = self.0.lock();
+            let start = sector as usize * 512;
+            buf[..512].copy_from_slice(disk.get(start..start + 512).ok_or("Sector out of range")?);
+            Ok(())
+        }
+
+        fn write_sector(&self, sector: u32, buf: &[u8]) -> Result<(), &'static str> {
+            let mut disk = self.0.lock();
+            let start = sector as usize * 512;
+            disk.get_mut(start..start + 512).ok_or("Sector out of range")?.copy_from_slice(&buf[..512]);
+            Ok(())
+        }
+    }
+
+    /// Freshly formatted volume: one FAT sector (128 clusters) of 512-byte
+    /// clusters, root directory in cluster 2
+    fn test_fs() -> Arc<Fat32FileSystem> {
+        const RESERVED: usize = 32;
+        let mut image = vec![0u8; (RESERVED + 1 + 127) * 512];
+
+        let mut bpb: Fat32BootSector = unsafe { core::mem::zeroed() };
+        bpb.bytes_per_sector = 512;
+        bpb.sectors_per_cluster = 1;
+        bpb.reserved_sectors = RESERVED as u16;
+        bpb.num_fats = 1;
+        bpb.total_sectors_32 = (image.len() / 512) as u32;
+        bpb.fat_size_32 = 1;
+        bpb.root_cluster = 2;
+        unsafe { core::ptr::write_unaligned(image.as_mut_ptr() as *mut Fat32BootSector, bpb) };
+
+        // Media and reserved entries, then the root directory's single cluster
+        let fat = RESERVED * 512;
+        for (i, value) in [0x0FFF_FFF8u32, 0x0FFF_FFFF, 0x0FFF_FFFF].iter().enumerate() {
+            image[fat + i * 4..fat + i * 4 + 4].copy_from_slice(&value.to_le_bytes());
+        }
+        Fat32FileSystem::new(Arc::new(MemDisk(SpinLock::new(image)))).unwrap()
+    }
+
+    /// Clusters of the chain starting at `start`
+    fn chain(fs: &Fat32FileSystem, start: u32) -> Vec<u32> {
+        let mut clusters = vec![start];
+        while let Some(next) = fs.get_next_cluster(*clusters.last().unwrap()).unwrap() {
+            clusters.push(next);
+        }
+        clusters
+    }
+
+    fn short_entry(name: [u8; 11], nt_res: u8) -> FatDirEntry {
+        let mut entry: FatDirEntry = unsafe { core::mem::zeroed() };
+        entry.name = name;
+        entry.nt_res = nt_res;
+        entry
+    }
+
+    #[test]
+    fn test_mkdir_creates_dot_entries() {
+        let fs = test_fs();
+        let root = fs.bpb.root_cluster;
+        fs.mkdir("/docs").unwrap();
+        assert_eq!(fs.mkdir("/docs"), Err("File exists"));
+
+        let docs = fs.find_entry(root, "docs").unwrap().unwrap().entry;
+        assert_ne!(docs.attr & ATTR_DIRECTORY, 0);
+        let cluster = docs.first_cluster();
+        assert_ne!(cluster, root);
+        // Allocated: a one-cluster chain rather than a free (0) FAT entry
+        assert_eq!(chain(&fs, cluster), vec![cluster]);
+
+        let dot = fs.read_entry(cluster, 0).unwrap();
+        assert_eq!(&dot.name, b".          ");
+        assert_eq!(dot.first_cluster(), cluster);
+        let dotdot = fs.read_entry(cluster, 32).unwrap();
+        assert_eq!(&dotdot.name, b"..         ");
+        assert_eq!(dotdot.first_cluster(), 0); // The root is stored as 0
+
+        fs.mkdir("/docs/sub").unwrap();
+        let sub = fs.resolve_dir("/docs/sub").unwrap();
+        assert_eq!(fs.read_entry(sub, 32).unwrap().first_cluster(), cluster);
+        assert!(fs.dir_is_empty(sub).unwrap());
+    }
+
+    #[test]
+    fn test_remove_frees_whole_chain() {
+        let fs = test_fs();
+        let root = fs.bpb.root_cluster;
+        fs.create("/data.bin").unwrap();
+        fs.truncate("/data.bin", 3 * 512).unwrap();
+        let first = fs.find_entry(root, "data.bin").unwrap().unwrap().entry.first_cluster();
+        let clusters = chain(&fs, first);
+        assert_eq!(clusters.len(), 3);
+
+        fs.remove("/data.bin").unwrap();
+        assert!(fs.find_entry(root, "data.bin").unwrap().is_none());
+        for cluster in clusters {
+            assert_eq!(fs.get_next_cluster(cluster), Ok(Some(0)));
+        }
+    }
+
+    #[test]
+    fn test_remove_non_empty_directory_fails() {
+        let fs = test_fs();
+        fs.mkdir("/d").unwrap();
+        fs.create("/d/f").unwrap();
+        assert_eq!(fs.remove("/d"), Err("Directory not empty"));
+        let cluster = fs.resolve_dir("/d").unwrap();
+
+        fs.remove("/d/f").unwrap();
+        fs.remove("/d").unwrap();
+        assert_eq!(fs.resolve_dir("/d"), Err("Directory not found"));
+        assert_eq!(fs.get_next_cluster(cluster), Ok(Some(0)));
+    }
+
+    #[test]
+    fn test_rename_replaces_target_and_moves_handles() {
+        let fs = test_fs();
+        let root = fs.bpb.root_cluster;
+        let moved = fs.create("/a").unwrap();
+        moved.lock().write(b"new").unwrap();
+        let target = fs.create("/b").unwrap();
+        target.lock().write(&[7u8; 3 * 512]).unwrap();
+        let old_chain = chain(&fs, fs.find_entry(root, "b").unwrap().unwrap().entry.first_cluster());
+
+        fs.rename("/a", "/b").unwrap();
+        assert!(fs.find_entry(root, "a").unwrap().is_none());
+        for cluster in old_chain {
+            assert_eq!(fs.get_next_cluster(cluster), Ok(Some(0)));
+        }
+        // The replaced file's handle is cut off; the moved one follows its entry
+        assert_eq!(target.lock().write(b"x"), Err("File was deleted"));
+        moved.lock().write(b"er").unwrap();
+
+        let file = fs.open("/b", 0).unwrap();
+        let mut buf = [0u8; 16];
+        let n = file.lock().read(&mut buf).unwrap();
+        assert_eq!(&buf[..n], b"newer");
+        assert_eq!({ fs.find_entry(root, "b").unwrap().unwrap().entry.size }, 5);
+    }
+
+    #[test]
+    fn test_rename_into_own_subtree_fails() {
+        let fs = test_fs();
+        fs.mkdir("/a").unwrap();
+        fs.mkdir("/a/b").unwrap();
+        assert_eq!(fs.rename("/a", "/a/b/c"), Err("Cannot move a directory into itself"));
+        assert_eq!(fs.rename("/a", "/a/c"), Err("Cannot move a directory into itself"));
+        fs.rename("/a/b", "/c").unwrap();
+        assert_eq!(fs.read_entry(fs.resolve_dir("/c").unwrap(), 32).unwrap().first_cluster(), 0);
+    }
+
+    #[test]
+    fn test_truncate_refreshes_open_handles() {
+        let fs = test_fs();
+        let file = fs.create("/f").unwrap();
+        file.lock().write(&[1u8; 2 * 512]).unwrap();
+        fs.truncate("/f", 0).unwrap();
+        assert_eq!(file.lock().stat().unwrap().size, 0);
+
+        file.lock().seek(SeekFrom::Start(0)).unwrap();
+        file.lock().write(b"hi").unwrap();
+        assert_eq!(fs.stat("/f").unwrap().size, 2);
+    }
+
+    #[test]
+    fn test_inodes_identify_open_files() {
+        let fs = test_fs();
+        let a = fs.create("/a").unwrap();
+        let b = fs.create("/b").unwrap();
+        let inode = a.lock().stat().unwrap().inode;
+        assert_ne!(inode, b.lock().stat().unwrap().inode); // Both empty
+        assert_eq!(fs.open("/a", 0).unwrap().lock().stat().unwrap().inode, inode);
+
+        fs.remove("/a").unwrap();
+        let again = fs.create("/a").unwrap();
+        assert_ne!(again.lock().stat().unwrap().inode, inode);
+    }
+
+    #[test]
+    fn test_lfn_checksum() {
+        // Rotate-right-and-add over all 11 bytes
+        assert_eq!(lfn_checksum(b"FOO     BAR"), 0x53);
+        assert_ne!(lfn_checksum(b"FOO     BAR"), lfn_checksum(b"FOO     BAZ"));
+    }
+
+    #[test]
+    fn test_lfn_round_trip() {
+        let name = "A rather long file name.txt";
+        let short = make_short_name(name, 1);
+        let entries = lfn_entries(name, &short).unwrap();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0][0], LFN_LAST_ENTRY | 3);
+
+        let mut lfn = LfnBuilder::new();
+        for (i, raw) in entries.iter().enumerate() {
+            lfn.push(raw, (2, i * 32));
+        }
+        assert_eq!(lfn.slots.len(), 3);
+        assert_eq!(lfn.take(&short).as_deref(), Some(name));
+    }
+
+    #[test]
+    fn test_lfn_bad_checksum_ignored() {
+        let short = make_short_name("some long name", 1);
+        let entries = lfn_entries("some long name", &short).unwrap();
+        let mut lfn = LfnBuilder::new();
+        for raw in &entries {
+            lfn.push(raw, (2, 0));
+        }
+        assert_eq!(lfn.take(b"OTHER   TXT"), None);
+    }
+
+    #[test]
+    fn test_lfn_seq_zero_after_chain_ignored() {
+        let name = "some long name";
+        let short = make_short_name(name, 1);
+        let entries = lfn_entries(name, &short).unwrap();
+        let mut lfn = LfnBuilder::new();
+        for raw in &entries {
+            lfn.push(raw, (2, 0));
+        }
+        // A stray continuation entry with sequence 0 once the chain is complete
+        let mut stray = *entries.last().unwrap();
+        stray[0] = 0;
+        lfn.push(&stray, (2, 0));
+        assert_eq!(lfn.take(&short), None);
+    }
+
+    #[test]
+    fn test_short_name_generation() {
+        assert_eq!(&make_short_name("Long Document.text", 1), b"LONGDO~1TEX");
+        assert_eq!(&make_short_name("x", 12), b"X~12       ");
+        assert_eq!(short_name_for("README.TXT"), Some((*b"README  TXT", 0)));
+        assert_eq!(short_name_for("hello.c"), Some((*b"HELLO   C  ", NT_LOWER_BASE | NT_LOWER_EXT)));
+        assert_eq!(short_name_for("Hello.c"), None);
+        assert_eq!(short_name_for("verylongname.c"), None);
+    }
+
+    #[test]
+    fn test_parse_fat_name_case_flags() {
+        assert_eq!(parse_fat_name(&short_entry(*b"HELLO   C  ", NT_LOWER_BASE | NT_LOWER_EXT)), "hello.c");
+        assert_eq!(parse_fat_name(&short_entry(*b"HELLO   C  ", NT_LOWER_EXT)), "HELLO.c");
+        assert_eq!(parse_fat_name(&short_entry(*b"NOEXT      ", 0)), "NOEXT");
+    }
+
+    #[test]
+    fn test_dir_entry_cluster_and_dots() {
+        let mut entry = short_entry(*b"..         ", 0);
+        entry.set_first_cluster(0x0012_3456);
+        assert_eq!({ entry.cluster_high }, 0x0012);
+        assert_eq!({ entry.cluster_low }, 0x3456);
+        assert_eq!(entry.first_cluster(), 0x0012_3456);
+        assert!(entry.is_dot());
+        assert!(!short_entry(*b"...        ", 0).is_dot());
+    }
+}
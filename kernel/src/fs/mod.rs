@@ -9,6 +9,12 @@ pub mod fat32;
 pub mod pipe;
 pub mod cache;
 pub mod console;
+pub mod tar;
+pub mod tmpfs;
+pub mod devfs;
+pub mod procfs;
+pub mod page_cache;
+pub mod shm;
 
 pub use vfs::{VFS, FileOps, Filesystem, SeekFrom, O_RDONLY, O_WRONLY, O_RDWR, O_CREAT};
 
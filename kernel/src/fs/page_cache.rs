@@ -0,0 +1,166 @@
+//! Page Cache
+//!
+//! File pages for `mmap`. Every mapping of the same file shares one
+//! `MemObject`, so `MAP_SHARED` writers see each other's stores at once; the
+//! file itself only changes when a mapping is written back (`msync`,
+//! `munmap`, exit), and then only in the pages written through a mapping.
+//! Plain `read`/`write` on the descriptor bypass the cache.
+//!
+//! Files are identified by their concrete `FileOps` type and inode, which the
+//! filesystem must keep unique for as long as the file is open (a mapping
+//! keeps it open). A file reporting inode 0 has no such identity and gets an
+//! object of its own. The cache only holds weak references: an object lives
+//! as long as some VMA maps it.
+
+use alloc::collections::BTreeMap;
+use alloc::sync::{Arc, Weak};
+use core::any::TypeId;
+use crate::kernel::memory::object::MemObject;
+use crate::kernel::memory::vma::VMA;
+use crate::kernel::sync::SpinLock;
+use super::vfs::{FileOps, SeekFrom, S_IFMT, S_IFREG};
+
+type CacheKey = (TypeId, u64);
+
+static PAGE_CACHE: SpinLock<BTreeMap<CacheKey, Weak<SpinLock<MemObject>>>> = SpinLock::new(BTreeMap::new());
+
+/// The object holding `file`'s pages, shared with every other mapping of it
+pub fn object_for(file: &Arc<SpinLock<dyn FileOps>>) -> Result<Arc<SpinLock<MemObject>>, &'static str> {
+    let key = {
+        let mut guard = file.lock();
+        let stat = guard.stat()?;
+        if stat.mode & S_IFMT != S_IFREG {
+            return Err("File can't be mapped");
+        }
+        (guard.as_any().type_id(), stat.inode)
+    };
+
+    // Mapped pages past EOF read as zero and are never written back, so the
+    // object itself is unbounded
+    let object = Arc::new(SpinLock::new(MemObject::new(u64::MAX)));
+    if key.1 == 0 {
+        return Ok(object);
+    }
+    let mut cache = PAGE_CACHE.lock();
+    if let Some(object) = cache.get(&key).and_then(Weak::upgrade) {
+        return Ok(object);
+    }
+    cache.retain(|_, weak| weak.strong_count() > 0);
+    cache.insert(key, Arc::downgrade(&object));
+    Ok(object)
+}
+
+/// Read the file's pages `[first, first + count)` into the object, skipping
+/// pages that are already resident (they may hold newer, unsynced data)
+pub fn load(object: &SpinLock<MemObject>, file: &SpinLock<dyn FileOps>, first: u64, count: u64) -> Result<(), &'static str> {
+    let mut file = file.lock();
+    let saved = file.seek(SeekFrom::Current(0))?;
+    let size = file.stat()?.size;
+    let mut object = object.lock();
+
+    let mut result = Ok(());
+    for index in first..first.saturating_add(count) {
+        let offset = index * 4096;
+        if offset >= size {
+            break;
+        }
+        if object.resident(index).is_some() {
+            continue;
+        }
+        let phys = match object.page(index) {
+            Ok(phys) => phys,
+            Err(e) => {
+                result = Err(e);
+                break;
+            }
+        };
+        let page = unsafe { core::slice::from_raw_parts_mut(phys as *mut u8, 4096) };
+        if let Err(e) = read_at(&mut *file, offset, page) {
+            result = Err(e);
+            break;
+        }
+    }
+
+    file.seek(SeekFrom::Start(saved))?;
+    result
+}
+
+/// Write the dirty pages in `[first, first + count)` back to the file
+///
+/// Mappings never grow a file: the last page is cut at the current size.
+pub fn write_back(object: &SpinLock<MemObject>, file: &SpinLock<dyn FileOps>, first: u64, count: u64) -> Result<(), &'static str> {
+    let mut file = file.lock();
+    let saved = file.seek(SeekFrom::Current(0))?;
+    let size = file.stat()?.size;
+    let mut object = object.lock();
+
+    let mut result = Ok(());
+    let dirty = object.take_dirty(first, count);
+    for (i, &(index, phys)) in dirty.iter().enumerate() {
+        let offset = index * 4096;
+        if offset >= size {
+            break;
+        }
+        let len = core::cmp::min(4096, size - offset) as usize;
+        let page = unsafe { core::slice::from_raw_parts(phys as *const u8, len) };
+        if let Err(e) = write_at(&mut *file, offset, page) {
+            // Whatever didn't make it to the file is still dirty
+            for &(index, _) in &dirty[i..] {
+                object.mark_dirty(index);
+            }
+            result = Err(e);
+            break;
+        }
+    }
+
+    file.seek(SeekFrom::Start(saved))?;
+    result
+}
+
+/// Write back the part of a `MAP_SHARED` file mapping in `[start, end)`
+///
+/// Private, anonymous and shared memory mappings have nothing to write.
+pub fn sync_vma(vma: &VMA, start: u64, end: u64) -> Result<(), &'static str> {
+    let backing = match &vma.backing {
+        Some(backing) if !vma.flags.private => backing,
+        _ => return Ok(()),
+    };
+    let file = match &backing.file {
+        Some(file) => file,
+        None => return Ok(()),
+    };
+    let start = start.max(vma.start) & !0xFFF;
+    let end = end.min(vma.end);
+    if start >= end {
+        return Ok(());
+    }
+    let first = (backing.offset + (start - vma.start)) / 4096;
+    let count = (end - start).div_ceil(4096);
+    write_back(&backing.object, &**file, first, count)
+}
+
+fn read_at(file: &mut dyn FileOps, offset: u64, buf: &mut [u8]) -> Result<(), &'static str> {
+    file.seek(SeekFrom::Start(offset))?;
+    let mut done = 0;
+    while done < buf.len() {
+        let n = file.read(&mut buf[done..])?;
+        if n == 0 {
+            break; // EOF: the rest of the page stays zero
+        }
+        done += n;
+    }
+    Ok(())
+}
+
+fn write_at(file: &mut dyn FileOps, offset: u64, buf: &[u8]) -> Result<(), &'static str> {
+    file.seek(SeekFrom::Start(offset))?;
+    let mut done = 0;
+    while done < buf.len() {
+        let n = file.write(&buf[done..])?;
+        if n == 0 {
+            return Err("Short write");
+        }
+        done += n;
+    }
+    Ok(())
+}
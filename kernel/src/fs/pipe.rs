@@ -1,85 +1,99 @@
 //! Pipe Implementation
 //!
 //! Unidirectional data channel.
+//!
+//! Blocked readers of every pipe sleep on `PIPE_WAIT`, so `sys_read` can
+//! wait with the file unlocked; a write wakes them all and each re-checks
+//! its own pipe.
 
 use alloc::collections::vec_deque::VecDeque;
 use alloc::sync::Arc;
-use crate::kernel::sync::SpinLock;
-use crate::fs::vfs::{FileOps, SeekFrom, FileStat};
-
-use alloc::vec::Vec;
-use crate::kernel::scheduler::SCHEDULER;
-use crate::kernel::process::AgentState;
+use crate::kernel::sync::{SpinLock, WaitQueue, EWOULDBLOCK};
+use crate::fs::vfs::{FileOps, SeekFrom, FileStat, POLLIN, POLLOUT, POLLERR, POLLHUP};
 
 struct PipeState {
     buffer: VecDeque<u8>,
     closed_read: bool,
     closed_write: bool,
-    waiting_readers: Vec<u64>, // Agent IDs
+}
+
+/// Readers sleeping on an empty pipe
+static PIPE_WAIT: WaitQueue = WaitQueue::new();
+
+/// Shared between both ends
+struct Pipe {
+    state: SpinLock<PipeState>,
 }
 
 pub struct PipeReader {
-    state: Arc<SpinLock<PipeState>>,
+    pipe: Arc<Pipe>,
+    nonblocking: bool,
 }
 
 pub struct PipeWriter {
-    state: Arc<SpinLock<PipeState>>,
+    pipe: Arc<Pipe>,
 }
 
 pub fn create_pipe() -> (PipeReader, PipeWriter) {
-    let state = Arc::new(SpinLock::new(PipeState {
-        buffer: VecDeque::new(),
-        closed_read: false,
-        closed_write: false,
-        waiting_readers: Vec::new(),
-    }));
+    let pipe = Arc::new(Pipe {
+        state: SpinLock::new(PipeState {
+            buffer: VecDeque::new(),
+            closed_read: false,
+            closed_write: false,
+        }),
+    });
 
     (
-        PipeReader { state: state.clone() },
-        PipeWriter { state },
+        PipeReader { pipe: pipe.clone(), nonblocking: false },
+        PipeWriter { pipe },
     )
 }
 
-impl FileOps for PipeReader {
-    fn read(&mut self, buf: &mut [u8]) -> Result<usize, &'static str> {
-        loop {
-            let mut state = self.state.lock();
-            
-            if !state.buffer.is_empty() {
-                let mut read = 0;
-                for b in buf.iter_mut() {
-                    if let Some(byte) = state.buffer.pop_front() {
-                        *b = byte;
-                        read += 1;
-                    } else {
-                        break;
-                    }
+impl PipeReader {
+    /// Take buffered bytes; `None` means empty but the writer is still open
+    fn take(&self, buf: &mut [u8]) -> Option<usize> {
+        let mut state = self.pipe.state.lock();
+
+        if !state.buffer.is_empty() {
+            let mut read = 0;
+            for b in buf.iter_mut() {
+                if let Some(byte) = state.buffer.pop_front() {
+                    *b = byte;
+                    read += 1;
+                } else {
+                    break;
                 }
-                return Ok(read);
-            }
-            
-            if state.closed_write {
-                return Ok(0); // EOF
-            }
-            
-            // Block
-            let mut scheduler = SCHEDULER.lock();
-            let current_id = scheduler.with_current_agent(|agent| {
-                agent.state = AgentState::Blocked;
-                agent.id.0
-            });
-            
-            if let Some(id) = current_id {
-                state.waiting_readers.push(id);
-                drop(state); // Drop pipe lock before yielding
-                drop(scheduler); // Drop scheduler lock
-                
-                crate::kernel::scheduler::yield_task();
-            } else {
-                // No current agent (kernel thread?), just return 0
-                return Ok(0);
             }
+            return Some(read);
+        }
+
+        if state.closed_write {
+            return Some(0); // EOF
+        }
+        None
+    }
+}
+
+impl FileOps for PipeReader {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, &'static str> {
+        if self.nonblocking {
+            return self.try_read(buf);
         }
+        // Sleep until a writer pushes data or closes.
+        // No current agent (kernel thread?): just return 0
+        match PIPE_WAIT.wait_until(|| self.take(buf)) {
+            Ok(n) => Ok(n),
+            Err(EWOULDBLOCK) => Ok(0),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn try_read(&mut self, buf: &mut [u8]) -> Result<usize, &'static str> {
+        self.take(buf).ok_or(EWOULDBLOCK)
+    }
+
+    fn read_queue(&self) -> Option<&'static WaitQueue> {
+        if self.nonblocking { None } else { Some(&PIPE_WAIT) }
     }
 
     fn write(&mut self, _buf: &[u8]) -> Result<usize, &'static str> {
@@ -91,7 +105,7 @@ impl FileOps for PipeReader {
     }
 
     fn close(&mut self) -> Result<(), &'static str> {
-        let mut state = self.state.lock();
+        let mut state = self.pipe.state.lock();
         state.closed_read = true;
         Ok(())
     }
@@ -104,18 +118,41 @@ impl FileOps for PipeReader {
         })
     }
 
+    fn set_nonblocking(&mut self, nonblocking: bool) {
+        self.nonblocking = nonblocking;
+    }
+
+    fn poll_ready(&self) -> u16 {
+        let state = self.pipe.state.lock();
+        let mut ready = 0;
+        if !state.buffer.is_empty() {
+            ready |= POLLIN;
+        }
+        if state.closed_write {
+            ready |= POLLIN | POLLHUP; // read() returns EOF
+        }
+        ready
+    }
+
     fn as_any(&mut self) -> &mut dyn core::any::Any {
         self
     }
 }
 
+impl Drop for PipeReader {
+    fn drop(&mut self) {
+        // Last read end gone: writers get "Broken pipe"
+        let _ = self.close();
+    }
+}
+
 impl FileOps for PipeWriter {
     fn read(&mut self, _buf: &mut [u8]) -> Result<usize, &'static str> {
         Err("Bad file descriptor")
     }
 
     fn write(&mut self, buf: &[u8]) -> Result<usize, &'static str> {
-        let mut state = self.state.lock();
+        let mut state = self.pipe.state.lock();
         
         if state.closed_read {
             return Err("Broken pipe");
@@ -124,18 +161,10 @@ impl FileOps for PipeWriter {
         for &b in buf {
             state.buffer.push_back(b);
         }
+        drop(state);
         
         // Wake up readers
-        if !state.waiting_readers.is_empty() {
-            let mut scheduler = SCHEDULER.lock();
-            for id in state.waiting_readers.drain(..) {
-                if let Some(agent) = scheduler.get_agent_mut(id) {
-                    if agent.state == AgentState::Blocked {
-                        agent.state = AgentState::Ready;
-                    }
-                }
-            }
-        }
+        PIPE_WAIT.wake_all();
         
         Ok(buf.len())
     }
@@ -145,20 +174,12 @@ impl FileOps for PipeWriter {
     }
 
     fn close(&mut self) -> Result<(), &'static str> {
-        let mut state = self.state.lock();
+        let mut state = self.pipe.state.lock();
         state.closed_write = true;
+        drop(state);
         
         // Wake up readers so they see EOF
-        if !state.waiting_readers.is_empty() {
-            let mut scheduler = SCHEDULER.lock();
-            for id in state.waiting_readers.drain(..) {
-                if let Some(agent) = scheduler.get_agent_mut(id) {
-                    if agent.state == AgentState::Blocked {
-                        agent.state = AgentState::Ready;
-                    }
-                }
-            }
-        }
+        PIPE_WAIT.wake_all();
         
         Ok(())
     }
@@ -171,11 +192,27 @@ impl FileOps for PipeWriter {
         })
     }
 
+    fn poll_ready(&self) -> u16 {
+        // Unbounded buffer: writable until the read end goes away
+        if self.pipe.state.lock().closed_read {
+            POLLERR
+        } else {
+            POLLOUT
+        }
+    }
+
     fn as_any(&mut self) -> &mut dyn core::any::Any {
         self
     }
 }
 
+impl Drop for PipeWriter {
+    fn drop(&mut self) {
+        // Last write end gone: readers see EOF
+        let _ = self.close();
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -201,4 +238,41 @@ mod tests {
         // Read from empty pipe (should return 0 or block, currently 0)
         assert_eq!(reader.read(&mut buf).unwrap(), 0);
     }
+
+    #[test]
+    fn test_pipe_nonblocking_empty() {
+        let (mut reader, _writer) = create_pipe();
+        let mut buf = [0u8; 10];
+
+        reader.set_nonblocking(true);
+        assert_eq!(reader.read(&mut buf), Err(EWOULDBLOCK));
+    }
+
+    #[test]
+    fn test_pipe_eof_after_writer_dropped() {
+        let (mut reader, mut writer) = create_pipe();
+        let mut buf = [0u8; 10];
+
+        writer.write(b"hi").unwrap();
+        drop(writer);
+
+        // Buffered data first, then EOF
+        assert_eq!(reader.read(&mut buf).unwrap(), 2);
+        reader.set_nonblocking(true);
+        assert_eq!(reader.read(&mut buf).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_pipe_poll_ready() {
+        let (reader, mut writer) = create_pipe();
+
+        assert_eq!(reader.poll_ready(), 0);
+        assert_eq!(writer.poll_ready(), POLLOUT);
+
+        writer.write(b"x").unwrap();
+        assert_eq!(reader.poll_ready(), POLLIN);
+
+        drop(writer);
+        assert_eq!(reader.poll_ready(), POLLIN | POLLHUP);
+    }
 }
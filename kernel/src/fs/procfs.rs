@@ -0,0 +1,586 @@
+//! Process Filesystem (procfs)
+//!
+//! Read-only text views of kernel state, mounted at `/proc`:
+//!
+//! | Path                     | Contents                                  |
+//! |--------------------------|-------------------------------------------|
+//! | `<pid>/status`           | state, parent, cpu cycles, cwd            |
+//! | `<pid>/caps`             | held capabilities (private)               |
+//! | `<pid>/maps`             | virtual memory areas (private)            |
+//! | `<pid>/fds`              | open file descriptors (private)           |
+//! | `self/...`               | the reading agent                         |
+//! | `meminfo`                | heap allocator statistics                 |
+//! | `stat`                   | profiler counters, per-core cycles        |
+//! | `health`                 | watchdog `SystemHealth`                   |
+//! | `neural`                 | most activated neural allocator concepts  |
+//! | `intent/handlers`        | registered intent handlers                |
+//! | `intent/broadcast`       | latest handler broadcast                  |
+//! | `intent/feedback`        | predictive feedback statistics            |
+//! | `intent/hierarchy`       | hierarchical processing statistics        |
+//! | `intent/token`           | remote intent server boot token (`System`)|
+//! | `net/tcp`                | TCP connection table                      |
+//! | `net/route`              | IPv4 routing table                        |
+//! | `net/if_inet6`           | IPv6 addresses and default router         |
+//!
+//! Private files can only be read by the agent's own process, or by an
+//! agent holding the `System` capability.
+//!
+//! Files are rendered on first read, so a single open sees a consistent
+//! snapshot; seek to 0 to refresh. Rendering never happens in `open`,
+//! which runs under the VFS lock and must not take the scheduler lock.
+
+use alloc::format;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::any::Any;
+use core::fmt::Write;
+use crate::kernel::sync::SpinLock;
+use crate::kernel::capability::CapabilityType;
+use crate::kernel::process::Agent;
+use crate::kernel::scheduler::{IntentScheduler, SCHEDULER};
+use crate::fs::vfs::{FileOps, Filesystem, DirEntry, FileStat, SeekFrom, S_IFDIR, S_IFREG};
+
+/// Concepts listed by `/proc/neural`
+const NEURAL_LIMIT: usize = 32;
+/// Minimum activation for a concept to be listed
+const NEURAL_THRESHOLD: f32 = 0.01;
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// NODES
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Agent named by a path component
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Pid {
+    /// `self`: resolved when the file is read
+    Current,
+    Id(u64),
+}
+
+impl Pid {
+    fn parse(name: &str) -> Option<Pid> {
+        if name == "self" {
+            return Some(Pid::Current);
+        }
+        name.parse().ok().map(Pid::Id)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Global {
+    Meminfo,
+    Stat,
+    Health,
+    Neural,
+    Handlers,
+    Broadcast,
+    Feedback,
+    Hierarchy,
+    Token,
+    Tcp,
+    Route,
+    Inet6,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum AgentFile {
+    Status,
+    Caps,
+    Maps,
+    Fds,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Node {
+    Root,
+    IntentDir,
+    NetDir,
+    AgentDir(Pid),
+    Global(Global),
+    Agent(Pid, AgentFile),
+}
+
+const ROOT_FILES: [(&str, Global); 4] = [
+    ("meminfo", Global::Meminfo),
+    ("stat", Global::Stat),
+    ("health", Global::Health),
+    ("neural", Global::Neural),
+];
+
+const INTENT_FILES: [(&str, Global); 5] = [
+    ("handlers", Global::Handlers),
+    ("broadcast", Global::Broadcast),
+    ("feedback", Global::Feedback),
+    ("hierarchy", Global::Hierarchy),
+    ("token", Global::Token),
+];
+
+const NET_FILES: [(&str, Global); 3] = [
+    ("tcp", Global::Tcp),
+    ("route", Global::Route),
+    ("if_inet6", Global::Inet6),
+];
+
+const AGENT_FILES: [(&str, AgentFile); 4] = [
+    ("status", AgentFile::Status),
+    ("caps", AgentFile::Caps),
+    ("maps", AgentFile::Maps),
+    ("fds", AgentFile::Fds),
+];
+
+impl AgentFile {
+    /// Whether only the agent's own process (or `System`) may read it
+    fn is_private(self) -> bool {
+        !matches!(self, AgentFile::Status)
+    }
+}
+
+fn find<T: Copy>(table: &[(&str, T)], name: &str) -> Option<T> {
+    table.iter().find(|(n, _)| *n == name).map(|&(_, v)| v)
+}
+
+impl Node {
+    /// Map a path (relative to the mount) to a node; does not check pids
+    fn parse(path: &str) -> Option<Node> {
+        let parts: Vec<&str> = path.split('/').filter(|p| !p.is_empty()).collect();
+        match parts.as_slice() {
+            [] => Some(Node::Root),
+            ["intent"] => Some(Node::IntentDir),
+            ["intent", name] => find(&INTENT_FILES, name).map(Node::Global),
+            ["net"] => Some(Node::NetDir),
+            ["net", name] => find(&NET_FILES, name).map(Node::Global),
+            [name] => find(&ROOT_FILES, name).map(Node::Global)
+                .or_else(|| Pid::parse(name).map(Node::AgentDir)),
+            [pid, name] => Some(Node::Agent(Pid::parse(pid)?, find(&AGENT_FILES, name)?)),
+            _ => None,
+        }
+    }
+
+    fn is_dir(self) -> bool {
+        matches!(self, Node::Root | Node::IntentDir | Node::NetDir | Node::AgentDir(_))
+    }
+
+    fn stat(self) -> FileStat {
+        // Sizes are unknown until rendered, as on Linux
+        let mode = match self {
+            _ if self.is_dir() => S_IFDIR | 0o555,
+            Node::Agent(_, file) if file.is_private() => S_IFREG | 0o400,
+            Node::Global(Global::Token) => S_IFREG | 0o400,
+            _ => S_IFREG | 0o444,
+        };
+        FileStat { size: 0, mode, inode: 0 }
+    }
+
+    /// Directory listing (takes the scheduler lock for the root)
+    fn children(self) -> Result<Vec<DirEntry>, &'static str> {
+        fn file(name: &str) -> DirEntry {
+            DirEntry { name: String::from(name), is_dir: false, size: 0 }
+        }
+        fn dir(name: String) -> DirEntry {
+            DirEntry { name, is_dir: true, size: 0 }
+        }
+
+        Ok(match self {
+            Node::Root => {
+                let mut entries: Vec<DirEntry> = ROOT_FILES.iter().map(|(n, _)| file(n)).collect();
+                entries.push(dir(String::from("intent")));
+                entries.push(dir(String::from("net")));
+                entries.push(dir(String::from("self")));
+                let scheduler = SCHEDULER.lock();
+                entries.extend(scheduler.agents().map(|agent| dir(format!("{}", agent.id.0))));
+                entries
+            }
+            Node::IntentDir => INTENT_FILES.iter().map(|(n, _)| file(n)).collect(),
+            Node::NetDir => NET_FILES.iter().map(|(n, _)| file(n)).collect(),
+            Node::AgentDir(pid) => {
+                with_agent(pid, false, |_| ())?;
+                AGENT_FILES.iter().map(|(n, _)| file(n)).collect()
+            }
+            _ => return Err("Not a directory"),
+        })
+    }
+
+    /// Produce the text of a file node
+    fn render(self) -> Result<String, &'static str> {
+        let mut out = String::new();
+        match self {
+            Node::Global(Global::Token) => {
+                if !reader_is_system() {
+                    return Err("Permission denied");
+                }
+                render_global(Global::Token, &mut out)
+            }
+            Node::Global(global) => render_global(global, &mut out),
+            Node::Agent(pid, file) => {
+                with_agent(pid, file.is_private(), |agent| render_agent(agent, file, &mut out))?
+            }
+            _ => return Err("Is a directory"),
+        }
+        Ok(out)
+    }
+}
+
+/// Run `f` on the agent named by `pid` under the scheduler lock
+///
+/// For a `private` file the reader must belong to the agent's process or
+/// hold the `System` capability.
+fn with_agent<R>(pid: Pid, private: bool, f: impl FnOnce(&Agent) -> R) -> Result<R, &'static str> {
+    let scheduler = SCHEDULER.lock();
+    let reader = scheduler.current_process_id();
+    let id = match pid {
+        Pid::Current => reader.ok_or("No such process")?,
+        Pid::Id(id) => id,
+    };
+    let agent = scheduler.get_agent(id).ok_or("No such process")?;
+    if private && reader != Some(scheduler.process_of(id)) && !holds_system(&scheduler, reader) {
+        return Err("Permission denied");
+    }
+    Ok(f(agent))
+}
+
+/// Does the agent `reader` hold the `System` capability?
+fn holds_system(scheduler: &IntentScheduler, reader: Option<u64>) -> bool {
+    reader.and_then(|reader| scheduler.get_agent(reader))
+        .is_some_and(|reader| reader.has_capability(CapabilityType::System))
+}
+
+/// Does the reading agent hold the `System` capability?
+fn reader_is_system() -> bool {
+    let scheduler = SCHEDULER.lock();
+    holds_system(&scheduler, scheduler.current_process_id())
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// RENDERING
+// ═══════════════════════════════════════════════════════════════════════════════
+
+fn render_agent(agent: &Agent, file: AgentFile, out: &mut String) {
+    match file {
+        AgentFile::Status => {
+            let _ = writeln!(out, "pid:\t{}", agent.id.0);
+            let _ = match agent.parent_id {
+                Some(ppid) => writeln!(out, "ppid:\t{}", ppid),
+                None => writeln!(out, "ppid:\t-"),
+            };
+            let _ = writeln!(out, "state:\t{:?}", agent.state);
+            let _ = writeln!(out, "kind:\t{}", if agent.vmm.is_some() { "user" } else { "kernel" });
+            let _ = writeln!(out, "cpu_cycles:\t{}", agent.cpu_cycles);
+            let _ = writeln!(out, "cwd:\t{}", agent.cwd);
+            let _ = writeln!(out, "sig_pending:\t{:#010x}", agent.pending_signals);
+            let _ = writeln!(out, "sig_blocked:\t{:#010x}", agent.blocked_signals);
+            let _ = writeln!(out, "mailbox:\t{}", agent.mailbox.lock().len());
+        }
+        AgentFile::Caps => {
+            for cap in &agent.capabilities {
+                let _ = writeln!(out, "{:?}", cap.cap_type());
+            }
+        }
+        AgentFile::Maps => {
+            for vma in &agent.vma_manager.vmas {
+                let _ = writeln!(out, "{:016x}-{:016x} {}{}{}{} {}",
+                    vma.start, vma.end,
+                    if vma.perms.read { 'r' } else { '-' },
+                    if vma.perms.write { 'w' } else { '-' },
+                    if vma.perms.execute { 'x' } else { '-' },
+                    if vma.flags.private { 'p' } else { 's' },
+                    if vma.flags.anonymous { "[anon]" } else { "" });
+            }
+        }
+        AgentFile::Fds => {
+            for (fd, desc) in agent.file_table.fds.iter().enumerate() {
+                let Some(desc) = desc else { continue };
+                // The file being read (this one) is locked by our caller
+                let kind = match desc.file.try_lock().map(|file| file.stat()) {
+                    Some(Ok(stat)) => match stat.mode & crate::fs::vfs::S_IFMT {
+                        S_IFDIR => "dir",
+                        S_IFREG => "file",
+                        0 => "stream",
+                        _ => "device",
+                    },
+                    Some(Err(_)) => "stream",
+                    None => "busy",
+                };
+                let _ = writeln!(out, "{}\t{:#x}\t{}", fd, desc.flags, kind);
+            }
+        }
+    }
+}
+
+fn render_global(global: Global, out: &mut String) {
+    match global {
+        Global::Meminfo => {
+            let stats = crate::kernel::memory::stats();
+            let _ = writeln!(out, "heap_allocated:\t{}", stats.allocated);
+            let _ = writeln!(out, "heap_available:\t{}", crate::kernel::memory::heap_available());
+            let _ = writeln!(out, "slab_allocated:\t{}", stats.slab_allocated);
+            let _ = writeln!(out, "allocations:\t{}", stats.total_allocations);
+            let _ = writeln!(out, "user_frames:\t{}", crate::kernel::memory::frames::tracked());
+            let _ = writeln!(out, "shm_objects:\t{}", crate::fs::shm::count());
+        }
+        Global::Stat => {
+            use core::sync::atomic::Ordering;
+            let p = &crate::profiling::PROFILER;
+            let _ = writeln!(out, "context_switches:\t{}", p.context_switches.load(Ordering::Relaxed));
+            let _ = writeln!(out, "syscalls:\t{}", p.syscalls.load(Ordering::Relaxed));
+            let _ = writeln!(out, "syscall_cycles:\t{}", p.total_syscall_cycles.load(Ordering::Relaxed));
+            let _ = writeln!(out, "page_faults:\t{}", p.page_faults.load(Ordering::Relaxed));
+            let _ = writeln!(out, "interrupts:\t{}", p.interrupts.load(Ordering::Relaxed));
+            for core in 0..4 {
+                let stats = crate::kernel::scheduler::get_core_stats(core);
+                let _ = writeln!(out, "core{}:\ttotal={} idle={} queue={}",
+                    core, stats.total_cycles, stats.idle_cycles, stats.queue_length);
+            }
+        }
+        Global::Health => {
+            let health = crate::kernel::watchdog::health::measure_health();
+            let _ = writeln!(out, "cpu_usage:\t{:?}", health.cpu_usage);
+            let _ = writeln!(out, "memory_used:\t{}", health.memory_used);
+            let _ = writeln!(out, "memory_free:\t{}", health.memory_free);
+            let _ = writeln!(out, "task_queue_depth:\t{:?}", health.task_queue_depth);
+            let _ = writeln!(out, "interrupt_latency_us:\t{}", health.interrupt_latency_us);
+        }
+        Global::Neural => {
+            let allocator = crate::kernel::memory::neural::NEURAL_ALLOCATOR.lock();
+            let _ = writeln!(out, "concepts:\t{}", allocator.count());
+            for (concept, activation) in allocator.get_active_concepts(NEURAL_THRESHOLD, NEURAL_LIMIT) {
+                let _ = writeln!(out, "{:#018x}\t{:.3}", concept.0, activation);
+            }
+        }
+        Global::Handlers => crate::intent::with_handlers(|registry| {
+            let _ = writeln!(out, "concept\tprio\tcap\tname");
+            for entry in registry.list() {
+                let _ = writeln!(out, "{:#018x}\t{}\t{:?}\t{}",
+                    entry.concept_id.0, entry.priority, entry.required_cap, entry.name);
+            }
+        }),
+        Global::Broadcast => crate::intent::with_handlers(|registry| {
+            let (count, last) = registry.broadcast_summary();
+            let _ = writeln!(out, "broadcasts:\t{}", count);
+            let _ = writeln!(out, "last_handlers:\t{}", last.total_handlers);
+            let _ = writeln!(out, "last_handled:\t{}", last.handled);
+            let _ = writeln!(out, "last_errors:\t{}", last.errors);
+            let _ = writeln!(out, "last_inhibited:\t{}", last.inhibited);
+            let _ = writeln!(out, "last_modulation:\t{:.3}", last.modulation);
+            let _ = writeln!(out, "last_stopped:\t{}", last.stopped);
+        }),
+        Global::Feedback => {
+            let stats = crate::intent::feedback::FEEDBACK_PROCESSOR.lock().stats();
+            let _ = writeln!(out, "processed:\t{}", stats.total_processed);
+            let _ = writeln!(out, "predictions:\t{}", stats.total_predictions);
+            let _ = writeln!(out, "successful:\t{}", stats.successful_predictions);
+            let _ = writeln!(out, "active_predictions:\t{}", stats.active_predictions);
+            let _ = writeln!(out, "active_expectations:\t{}", stats.active_expectations);
+            let _ = writeln!(out, "surprise:\t{:.3}", stats.surprise_level);
+            let _ = writeln!(out, "accuracy:\t{:.3}", stats.prediction_accuracy);
+        }
+        Global::Hierarchy => {
+            let stats = crate::intent::hierarchy::HIERARCHICAL_PROCESSOR.lock().stats();
+            let _ = writeln!(out, "layer_counts:\t{:?}", stats.layer_counts);
+            let _ = writeln!(out, "attended:\t{}", stats.attended_count);
+            let _ = writeln!(out, "goals:\t{}", stats.goal_count);
+            let _ = writeln!(out, "propagated:\t{}", stats.total_propagated);
+        }
+        Global::Token => {
+            if let Some(token) = crate::intent::remote::boot_token() {
+                let _ = writeln!(out, "{}", token);
+            }
+        }
+        Global::Tcp => {
+            let table = crate::net::tcp::TCB_TABLE.lock();
+            let _ = writeln!(out, "local\tremote\tstate\taccepted");
+            for conn in table.iter() {
+                let _ = writeln!(out, "{}:{}\t{}:{}\t{:?}\t{}",
+                    conn.local_addr, conn.local_port,
+                    conn.remote_addr, conn.remote_port,
+                    conn.state, conn.accepted);
+            }
+        }
+        Global::Route => {
+            let routes: Vec<_> = crate::net::route::ROUTES.lock().iter().copied().collect();
+            let _ = writeln!(out, "destination\tgateway\tiface\tmtu");
+            for route in routes {
+                let gateway = route.gateway.unwrap_or(crate::net::Ipv4Addr::ANY);
+                let mtu = route.mtu.or_else(|| crate::net::interface::mtu(route.iface)).unwrap_or(0);
+                let _ = writeln!(out, "{}/{}\t{}\t{}\t{}",
+                    route.dest, route.prefix_len, gateway, route.iface, mtu);
+            }
+        }
+        Global::Inet6 => {
+            let _ = writeln!(out, "address\tscope\tstate");
+            for info in crate::net::ipv6::addresses() {
+                let scope = if info.addr.is_link_local() { "link" } else { "global" };
+                let state = if info.is_tentative() { "tentative" } else { "preferred" };
+                let _ = writeln!(out, "{}/{}\t{}\t{}", info.addr, info.prefix_len, scope, state);
+            }
+            if let Some(router) = crate::net::ipv6::router() {
+                let _ = writeln!(out, "router\t{}", router.addr);
+            }
+        }
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// FILESYSTEM
+// ═══════════════════════════════════════════════════════════════════════════════
+
+pub struct ProcFs;
+
+impl ProcFs {
+    pub fn new() -> Arc<Self> {
+        Arc::new(ProcFs)
+    }
+}
+
+impl Filesystem for ProcFs {
+    fn open(&self, path: &str, _flags: usize) -> Result<Arc<SpinLock<dyn FileOps>>, &'static str> {
+        let node = Node::parse(path).ok_or("File not found")?;
+        Ok(Arc::new(SpinLock::new(ProcFile { node, data: None, offset: 0, entries: None })))
+    }
+
+    fn create(&self, _path: &str) -> Result<Arc<SpinLock<dyn FileOps>>, &'static str> {
+        Err("Read-only filesystem")
+    }
+
+    fn mkdir(&self, _path: &str) -> Result<(), &'static str> {
+        Err("Read-only filesystem")
+    }
+
+    fn remove(&self, _path: &str) -> Result<(), &'static str> {
+        Err("Read-only filesystem")
+    }
+
+    fn read_dir(&self, path: &str) -> Result<Vec<DirEntry>, &'static str> {
+        Node::parse(path).ok_or("File not found")?.children()
+    }
+
+    fn stat(&self, path: &str) -> Result<FileStat, &'static str> {
+        Ok(Node::parse(path).ok_or("File not found")?.stat())
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// FILE HANDLE
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Open procfs file or directory
+struct ProcFile {
+    node: Node,
+    /// Rendered text, produced on first read
+    data: Option<Vec<u8>>,
+    offset: usize,
+    /// Directory listing snapshot and position
+    entries: Option<(Vec<DirEntry>, usize)>,
+}
+
+impl FileOps for ProcFile {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, &'static str> {
+        if self.data.is_none() {
+            self.data = Some(self.node.render()?.into_bytes());
+        }
+        let data = self.data.as_deref().unwrap_or(&[]);
+        if self.offset >= data.len() {
+            return Ok(0); // EOF
+        }
+        let n = core::cmp::min(buf.len(), data.len() - self.offset);
+        buf[..n].copy_from_slice(&data[self.offset..self.offset + n]);
+        self.offset += n;
+        Ok(n)
+    }
+
+    fn write(&mut self, _buf: &[u8]) -> Result<usize, &'static str> {
+        Err("Read-only filesystem")
+    }
+
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64, &'static str> {
+        match pos {
+            SeekFrom::Start(0) => {
+                // Rewind re-renders on the next read / readdir
+                self.data = None;
+                self.entries = None;
+                self.offset = 0;
+                Ok(0)
+            }
+            SeekFrom::Start(off) => {
+                self.offset = off as usize;
+                Ok(off)
+            }
+            SeekFrom::Current(off) => {
+                let new_pos = self.offset as i64 + off;
+                if new_pos < 0 {
+                    return Err("Invalid seek");
+                }
+                self.offset = new_pos as usize;
+                Ok(new_pos as u64)
+            }
+            SeekFrom::End(_) => Err("Invalid seek"),
+        }
+    }
+
+    fn close(&mut self) -> Result<(), &'static str> {
+        Ok(())
+    }
+
+    fn stat(&self) -> Result<FileStat, &'static str> {
+        let mut stat = self.node.stat();
+        if let Some(data) = &self.data {
+            stat.size = data.len() as u64;
+        }
+        Ok(stat)
+    }
+
+    fn readdir(&mut self) -> Result<Option<DirEntry>, &'static str> {
+        if self.entries.is_none() {
+            self.entries = Some((self.node.children()?, 0));
+        }
+        let Some((entries, index)) = self.entries.as_mut() else { return Ok(None) };
+        let entry = entries.get(*index).cloned();
+        if entry.is_some() {
+            *index += 1;
+        }
+        Ok(entry)
+    }
+
+    fn as_any(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_paths() {
+        assert_eq!(Node::parse("/"), Some(Node::Root));
+        assert_eq!(Node::parse("/meminfo"), Some(Node::Global(Global::Meminfo)));
+        assert_eq!(Node::parse("/intent/handlers"), Some(Node::Global(Global::Handlers)));
+        assert_eq!(Node::parse("/net/tcp"), Some(Node::Global(Global::Tcp)));
+        assert_eq!(Node::parse("/net/route"), Some(Node::Global(Global::Route)));
+        assert_eq!(Node::parse("/net/if_inet6"), Some(Node::Global(Global::Inet6)));
+        assert_eq!(Node::parse("/7"), Some(Node::AgentDir(Pid::Id(7))));
+        assert_eq!(Node::parse("/self/maps"), Some(Node::Agent(Pid::Current, AgentFile::Maps)));
+        assert_eq!(Node::parse("/7/bogus"), None);
+        assert_eq!(Node::parse("/bogus"), None);
+        assert_eq!(Node::parse("/intent/handlers/x"), None);
+    }
+
+    #[test]
+    fn test_read_only() {
+        let fs = ProcFs::new();
+        assert_eq!(fs.create("/meminfo").err(), Some("Read-only filesystem"));
+        let file = fs.open("/meminfo", 0).unwrap();
+        assert_eq!(file.lock().write(b"x"), Err("Read-only filesystem"));
+        assert_eq!(fs.stat("/intent").unwrap().mode & crate::fs::vfs::S_IFMT, S_IFDIR);
+    }
+
+    #[test]
+    fn test_private_files() {
+        assert_eq!(Node::Agent(Pid::Id(7), AgentFile::Status).stat().mode, S_IFREG | 0o444);
+        for file in [AgentFile::Caps, AgentFile::Maps, AgentFile::Fds] {
+            assert_eq!(Node::Agent(Pid::Id(7), file).stat().mode, S_IFREG | 0o400);
+        }
+        assert_eq!(Node::parse("/intent/token"), Some(Node::Global(Global::Token)));
+        assert_eq!(Node::Global(Global::Token).stat().mode, S_IFREG | 0o400);
+    }
+}
@@ -0,0 +1,165 @@
+//! Shared Memory Objects
+//!
+//! Named regions of memory (`shm_open`) that several agents map with
+//! `MAP_SHARED` to exchange data without copying: camera frames, tensors.
+//! A name keeps its object alive until `shm_unlink`; after that the object
+//! lives on until the last descriptor and mapping are gone.
+//!
+//! Objects are memory only, never files in the VFS, and creating, opening
+//! or unlinking one needs the `Memory` capability.
+
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::sync::Arc;
+use core::any::Any;
+use crate::kernel::capability::CapabilityType;
+use crate::kernel::memory::object::MemObject;
+use crate::kernel::sync::SpinLock;
+use super::vfs::{FileOps, FileStat, SeekFrom, S_IFREG};
+
+/// Largest object `shm_open` creates (64MB)
+pub const MAX_SIZE: u64 = 64 * 1024 * 1024;
+
+static OBJECTS: SpinLock<BTreeMap<String, Arc<SpinLock<MemObject>>>> = SpinLock::new(BTreeMap::new());
+
+/// Look up `name`, creating a `size`-byte object if `create` is set and
+/// it doesn't exist yet
+pub fn open(name: &str, size: u64, create: bool) -> Result<Arc<SpinLock<MemObject>>, &'static str> {
+    if name.is_empty() || name.len() >= intent_abi::SHM_NAME_MAX {
+        return Err("Invalid name");
+    }
+    let mut objects = OBJECTS.lock();
+    if let Some(object) = objects.get(name) {
+        return Ok(object.clone());
+    }
+    if !create {
+        return Err("No such object");
+    }
+    if size == 0 || size > MAX_SIZE {
+        return Err("Invalid size");
+    }
+    let object = Arc::new(SpinLock::new(MemObject::new(size)));
+    objects.insert(String::from(name), object.clone());
+    Ok(object)
+}
+
+/// Remove a name; agents that opened or mapped the object keep it
+pub fn unlink(name: &str) -> Result<(), &'static str> {
+    OBJECTS.lock().remove(name).map(|_| ()).ok_or("No such object")
+}
+
+/// Number of named objects (for /proc/meminfo)
+pub fn count() -> usize {
+    OBJECTS.lock().len()
+}
+
+/// Open shared memory object
+///
+/// Besides `mmap`, read/write/seek work on the object's bytes, which is
+/// handy for initialising a region before publishing it.
+pub struct ShmFile {
+    object: Arc<SpinLock<MemObject>>,
+    offset: u64,
+}
+
+impl ShmFile {
+    pub fn new(object: Arc<SpinLock<MemObject>>) -> Self {
+        Self { object, offset: 0 }
+    }
+
+    /// Frame holding the byte at `offset` and the bytes left in its page
+    ///
+    /// The object lock is dropped before the caller copies, so a user buffer
+    /// that is itself a mapping of this object can still be faulted in.
+    fn page_at(&self, offset: u64) -> Result<(u64, usize), &'static str> {
+        let phys = self.object.lock().page(offset / 4096)?;
+        let in_page = offset % 4096;
+        Ok((phys + in_page, (4096 - in_page) as usize))
+    }
+
+    fn remaining(&self) -> usize {
+        self.object.lock().size().saturating_sub(self.offset) as usize
+    }
+}
+
+impl FileOps for ShmFile {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, &'static str> {
+        let total = core::cmp::min(buf.len(), self.remaining());
+        let mut done = 0;
+        while done < total {
+            let (src, avail) = self.page_at(self.offset)?;
+            let n = core::cmp::min(avail, total - done);
+            unsafe { core::ptr::copy_nonoverlapping(src as *const u8, buf[done..].as_mut_ptr(), n) };
+            done += n;
+            self.offset += n as u64;
+        }
+        Ok(done)
+    }
+
+    fn write(&mut self, buf: &[u8]) -> Result<usize, &'static str> {
+        let total = core::cmp::min(buf.len(), self.remaining());
+        if total == 0 && !buf.is_empty() {
+            return Err("No space left on object");
+        }
+        let mut done = 0;
+        while done < total {
+            let (dest, avail) = self.page_at(self.offset)?;
+            let n = core::cmp::min(avail, total - done);
+            unsafe { core::ptr::copy_nonoverlapping(buf[done..].as_ptr(), dest as *mut u8, n) };
+            done += n;
+            self.offset += n as u64;
+        }
+        Ok(done)
+    }
+
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64, &'static str> {
+        let size = self.object.lock().size() as i64;
+        let new_pos = match pos {
+            SeekFrom::Start(off) => off as i64,
+            SeekFrom::Current(off) => self.offset as i64 + off,
+            SeekFrom::End(off) => size + off,
+        };
+        if new_pos < 0 {
+            return Err("Invalid seek");
+        }
+        self.offset = new_pos as u64;
+        Ok(self.offset)
+    }
+
+    fn close(&mut self) -> Result<(), &'static str> {
+        Ok(())
+    }
+
+    fn stat(&self) -> Result<FileStat, &'static str> {
+        Ok(FileStat { size: self.object.lock().size(), mode: S_IFREG | 0o600, inode: 0 })
+    }
+
+    fn required_capability(&self) -> Option<CapabilityType> {
+        Some(CapabilityType::Memory)
+    }
+
+    fn memory_object(&self) -> Option<Arc<SpinLock<MemObject>>> {
+        Some(self.object.clone())
+    }
+
+    fn as_any(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_open_requires_create_for_new_names() {
+        assert!(open("/shm-test-missing", 4096, false).is_err());
+    }
+
+    #[test]
+    fn test_open_rejects_bad_arguments() {
+        assert!(open("", 4096, true).is_err());
+        assert!(open("/shm-test-empty", 0, true).is_err());
+        assert!(open("/shm-test-huge", MAX_SIZE + 1, true).is_err());
+    }
+}
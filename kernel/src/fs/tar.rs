@@ -1,187 +1,123 @@
-//! TAR Filesystem Implementation
+//! TAR Archive Reader
 //!
-//! A simple read-only filesystem that parses a TAR archive in memory.
-//! Supports standard ustar format.
+//! Parses a ustar archive in memory (e.g. the initrd loaded by the
+//! bootloader). Read-only; used as the lower layer of `tmpfs`.
 
-use super::{FileSystem, FileEntry};
-use heapless::{String, Vec};
+use alloc::string::String;
 use core::str;
 
-pub struct TarFileSystem {
-    base_address: usize,
-    size: usize,
+/// Size of a TAR header / data block
+const BLOCK_SIZE: usize = 512;
+
+/// A ustar archive in memory
+#[derive(Clone, Copy)]
+pub struct TarArchive {
+    data: &'static [u8],
+}
+
+/// One member of the archive
+#[derive(Debug, Clone)]
+pub struct TarEntry {
+    /// Path inside the archive (prefix joined, no leading "./" or slashes)
+    pub path: String,
+    pub data: &'static [u8],
+    pub is_dir: bool,
 }
 
-impl TarFileSystem {
-    /// Create a new TAR filesystem from a memory region.
-    pub fn new(base_address: usize, size: usize) -> Self {
-        Self { base_address, size }
+impl TarArchive {
+    /// Wrap a memory region holding a TAR archive
+    pub fn new(data: &'static [u8]) -> Self {
+        Self { data }
+    }
+
+    /// Does the region start with a ustar header?
+    pub fn is_valid(&self) -> bool {
+        self.data.len() >= BLOCK_SIZE && &self.data[257..262] == b"ustar"
+    }
+
+    /// Iterate over regular files and directories
+    pub fn entries(&self) -> TarEntries {
+        TarEntries { data: self.data, offset: 0 }
     }
 
     /// Helper to parse an octal number from a byte slice.
     fn parse_octal(bytes: &[u8]) -> usize {
-        let mut result = 0;
-        for &b in bytes {
-            if b < b'0' || b > b'7' {
+        let mut result = 0usize;
+        for &b in bytes.iter().skip_while(|&&b| b == b' ') {
+            if !(b'0'..=b'7').contains(&b) {
                 break;
             }
-            result = result * 8 + (b - b'0') as usize;
+            result = result.saturating_mul(8).saturating_add((b - b'0') as usize);
         }
         result
     }
 
-    /// Helper to get the file content slice.
-    fn get_file_content(&self, offset: usize, size: usize) -> &[u8] {
-        unsafe {
-            core::slice::from_raw_parts((self.base_address + offset) as *const u8, size)
-        }
+    /// NUL-terminated string field
+    fn field(bytes: &'static [u8]) -> &'static str {
+        let len = bytes.iter().position(|&c| c == 0).unwrap_or(bytes.len());
+        str::from_utf8(&bytes[..len]).unwrap_or("")
     }
 }
 
-impl FileSystem for TarFileSystem {
-    fn init(&mut self) -> Result<(), &'static str> {
-        // Basic validation: Check if memory is readable (trivial in bare metal)
-        Ok(())
-    }
+/// Iterator over archive members
+pub struct TarEntries {
+    data: &'static [u8],
+    offset: usize,
+}
 
-    fn list_files(&self) -> Result<Vec<FileEntry, 32>, &'static str> {
-        let mut files = Vec::new();
-        let mut offset = 0;
+impl Iterator for TarEntries {
+    type Item = TarEntry;
 
-        // Iterate through TAR blocks
-        while offset + 512 <= self.size {
-            let header = unsafe {
-                core::slice::from_raw_parts((self.base_address + offset) as *const u8, 512)
-            };
+    fn next(&mut self) -> Option<TarEntry> {
+        let data = self.data;
+        while self.offset + BLOCK_SIZE <= data.len() {
+            let header = &data[self.offset..self.offset + BLOCK_SIZE];
 
-            // Check for empty block (end of archive)
+            // Empty block: end of archive
             if header[0] == 0 {
-                break;
+                return None;
             }
 
-            // Parse filename (0..100)
-            let name_bytes = &header[0..100];
-            let name_len = name_bytes.iter().position(|&c| c == 0).unwrap_or(100);
-            let name_str = str::from_utf8(&name_bytes[0..name_len]).map_err(|_| "Invalid UTF-8 filename")?;
-
-            // Parse size (124..136)
-            let size = Self::parse_octal(&header[124..136]);
-
-            // Parse type flag (156)
+            let size = TarArchive::parse_octal(&header[124..136]);
             let type_flag = header[156];
-            let is_dir = type_flag == b'5';
-
-            // Add to list if it's a regular file or directory
-            if type_flag == b'0' || type_flag == 0 || is_dir {
-                let name: String<64> = String::from(name_str);
-                let _ = files.push(FileEntry {
-                    name,
-                    size,
-                    is_dir,
-                });
-            }
 
-            // Move to next header: 512 header + size rounded up to 512
             // Move to next header: 512 header + size rounded up to 512
             // Use checked arithmetic to prevent overflow attacks
-            let padded_size = (size.checked_add(511).unwrap_or(0)) / 512 * 512;
-            let next_offset = offset.checked_add(512).and_then(|o| o.checked_add(padded_size)).unwrap_or(self.size + 1);
-            
-            if next_offset > self.size {
-                break; // Corrupt archive or end of buffer
+            let data_start = self.offset + BLOCK_SIZE;
+            let padded_size = size.checked_add(BLOCK_SIZE - 1)? / BLOCK_SIZE * BLOCK_SIZE;
+            let data_end = data_start.checked_add(size)?;
+            if data_end > data.len() {
+                return None; // Corrupt archive or end of buffer
             }
+            self.offset = data_start.checked_add(padded_size)?;
 
-            offset = next_offset;
-        }
-
-        Ok(files)
-    }
-
-    fn read_file(&self, name: &str, buffer: &mut [u8]) -> Result<usize, &'static str> {
-        let mut offset = 0;
-
-        while offset + 512 <= self.size {
-            let header = unsafe {
-                core::slice::from_raw_parts((self.base_address + offset) as *const u8, 512)
-            };
-
-            if header[0] == 0 {
-                break;
+            let is_dir = type_flag == b'5';
+            if !(type_flag == b'0' || type_flag == 0 || is_dir) {
+                continue; // Links, devices, pax headers...
             }
 
-            let name_bytes = &header[0..100];
-            let name_len = name_bytes.iter().position(|&c| c == 0).unwrap_or(100);
-            let current_name = str::from_utf8(&name_bytes[0..name_len]).unwrap_or("");
-            let size = Self::parse_octal(&header[124..136]);
-
-            if current_name == name {
-                if buffer.len() < size {
-                    return Err("Buffer too small");
+            // ustar splits long names into prefix (345..500) + name (0..100)
+            let mut path = String::new();
+            if &header[257..262] == b"ustar" {
+                let prefix = TarArchive::field(&header[345..500]);
+                if !prefix.is_empty() {
+                    path.push_str(prefix);
+                    path.push('/');
                 }
-                
-                let content = self.get_file_content(offset + 512, size);
-                buffer[0..size].copy_from_slice(content);
-                return Ok(size);
             }
+            path.push_str(TarArchive::field(&header[0..100]));
 
-            // Move to next header: 512 header + size rounded up to 512
-            let padded_size = (size.checked_add(511).unwrap_or(0)) / 512 * 512;
-            let next_offset = offset.checked_add(512).and_then(|o| o.checked_add(padded_size)).unwrap_or(self.size + 1);
-            
-            if next_offset > self.size {
-                break;
-            }
-
-            offset = next_offset;
-        }
-
-        Err("File not found")
-    }
-
-    fn file_size(&self, name: &str) -> Result<usize, &'static str> {
-        let mut offset = 0;
-
-        while offset + 512 <= self.size {
-            let header = unsafe {
-                core::slice::from_raw_parts((self.base_address + offset) as *const u8, 512)
-            };
-
-            if header[0] == 0 {
-                break;
-            }
-
-            let name_bytes = &header[0..100];
-            let name_len = name_bytes.iter().position(|&c| c == 0).unwrap_or(100);
-            let current_name = str::from_utf8(&name_bytes[0..name_len]).unwrap_or("");
-            let size = Self::parse_octal(&header[124..136]);
-
-            if current_name == name {
-                return Ok(size);
+            let path = path.trim_start_matches("./").trim_matches('/');
+            if path.is_empty() || path == "." {
+                continue;
             }
 
-            // Move to next header: 512 header + size rounded up to 512
-            let padded_size = (size.checked_add(511).unwrap_or(0)) / 512 * 512;
-            let next_offset = offset.checked_add(512).and_then(|o| o.checked_add(padded_size)).unwrap_or(self.size + 1);
-            
-            if next_offset > self.size {
-                break;
-            }
-
-            offset = next_offset;
+            return Some(TarEntry {
+                path: String::from(path),
+                data: &data[data_start..data_end],
+                is_dir,
+            });
         }
-
-        Err("File not found")
-    }
-
-    fn create_file(&mut self, _name: &str) -> Result<(), &'static str> {
-        Err("Read-only filesystem")
-    }
-
-    fn write_file(&mut self, _name: &str, _data: &[u8]) -> Result<(), &'static str> {
-        Err("Read-only filesystem")
-    }
-
-    fn delete_file(&mut self, _name: &str) -> Result<(), &'static str> {
-        Err("Read-only filesystem")
+        None
     }
 }
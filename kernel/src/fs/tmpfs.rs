@@ -0,0 +1,526 @@
+//! Temporary Filesystem (tmpfs)
+//!
+//! Hierarchical in-memory filesystem mounted at `/tmp` (and at `/` when no
+//! disk is available). It can be seeded from a read-only TAR archive such
+//! as the initrd: archive files are served in place and only copied into
+//! RAM the first time they are modified, so the archive is never written.
+
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::any::Any;
+use core::sync::atomic::{AtomicU64, Ordering};
+use crate::kernel::sync::SpinLock;
+use crate::fs::tar::TarArchive;
+use crate::fs::vfs::{self, FileOps, Filesystem, DirEntry, FileStat, SeekFrom, S_IFDIR, S_IFREG};
+
+/// Inode numbers are unique across all tmpfs instances
+static NEXT_INODE: AtomicU64 = AtomicU64::new(1);
+
+fn next_inode() -> u64 {
+    NEXT_INODE.fetch_add(1, Ordering::Relaxed)
+}
+
+/// Largest file tmpfs will hold (EFBIG beyond it), so one write or
+/// truncate can't take all of kernel memory
+const MAX_FILE_SIZE: usize = 64 * 1024 * 1024;
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// NODES
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// File data: borrowed from the lower (TAR) layer until first written
+enum Contents {
+    Lower(&'static [u8]),
+    Owned(Vec<u8>),
+}
+
+impl Contents {
+    fn as_slice(&self) -> &[u8] {
+        match self {
+            Contents::Lower(data) => data,
+            Contents::Owned(data) => data,
+        }
+    }
+
+    /// Copy-up on first modification
+    fn make_mut(&mut self) -> &mut Vec<u8> {
+        if let Contents::Lower(data) = *self {
+            *self = Contents::Owned(data.to_vec());
+        }
+        match self {
+            Contents::Owned(data) => data,
+            Contents::Lower(_) => unreachable!("copied up above"),
+        }
+    }
+
+    /// Grow (zero-filled) or shrink to `len`, failing rather than aborting
+    /// when the file would be too large or memory runs out
+    fn resize(&mut self, len: usize) -> Result<(), &'static str> {
+        if len > MAX_FILE_SIZE {
+            return Err("File too large");
+        }
+        let data = self.make_mut();
+        data.try_reserve(len.saturating_sub(data.len())).map_err(|_| "Out of memory")?;
+        data.resize(len, 0);
+        Ok(())
+    }
+}
+
+struct FileNode {
+    inode: u64,
+    contents: Contents,
+}
+
+struct DirNode {
+    inode: u64,
+    entries: BTreeMap<String, Node>,
+}
+
+impl DirNode {
+    fn new() -> Self {
+        Self { inode: next_inode(), entries: BTreeMap::new() }
+    }
+}
+
+#[derive(Clone)]
+enum Node {
+    File(Arc<SpinLock<FileNode>>),
+    Dir(Arc<SpinLock<DirNode>>),
+}
+
+impl Node {
+    fn new_file(contents: Contents) -> Self {
+        Node::File(Arc::new(SpinLock::new(FileNode { inode: next_inode(), contents })))
+    }
+
+    fn new_dir() -> Self {
+        Node::Dir(Arc::new(SpinLock::new(DirNode::new())))
+    }
+
+    fn is_dir(&self) -> bool {
+        matches!(self, Node::Dir(_))
+    }
+
+    fn stat(&self) -> FileStat {
+        match self {
+            Node::File(file) => {
+                let file = file.lock();
+                FileStat { size: file.contents.as_slice().len() as u64, mode: S_IFREG | 0o644, inode: file.inode }
+            }
+            Node::Dir(dir) => {
+                let dir = dir.lock();
+                FileStat { size: dir.entries.len() as u64, mode: S_IFDIR | 0o755, inode: dir.inode }
+            }
+        }
+    }
+
+    /// Open a handle on this node
+    fn open(&self) -> Arc<SpinLock<dyn FileOps>> {
+        match self {
+            Node::File(file) => Arc::new(SpinLock::new(TmpFile { node: file.clone(), offset: 0 })),
+            Node::Dir(dir) => Arc::new(SpinLock::new(TmpDir { node: dir.clone(), index: 0 })),
+        }
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// FILESYSTEM
+// ═══════════════════════════════════════════════════════════════════════════════
+
+pub struct TmpFs {
+    root: Arc<SpinLock<DirNode>>,
+}
+
+impl TmpFs {
+    /// Empty filesystem
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self { root: Arc::new(SpinLock::new(DirNode::new())) })
+    }
+
+    /// Filesystem whose initial contents come from a TAR archive
+    pub fn with_lower(archive: TarArchive) -> Arc<Self> {
+        let fs = Self { root: Arc::new(SpinLock::new(DirNode::new())) };
+        let mut count = 0;
+        for entry in archive.entries() {
+            let node = if entry.is_dir { Node::new_dir() } else { Node::new_file(Contents::Lower(entry.data)) };
+            if fs.insert_lower(&entry.path, node).is_ok() {
+                count += 1;
+            }
+        }
+        crate::kprintln!("[TMPFS] Loaded {} entries from archive", count);
+        Arc::new(fs)
+    }
+
+    /// Add an archive member, creating missing parent directories
+    fn insert_lower(&self, path: &str, node: Node) -> Result<(), &'static str> {
+        let mut dir = self.root.clone();
+        let mut parts = path.split('/').filter(|p| !p.is_empty() && *p != ".").peekable();
+        while let Some(name) = parts.next() {
+            if parts.peek().is_none() {
+                // Explicit directory entries may follow their children
+                let mut guard = dir.lock();
+                if !(node.is_dir() && guard.entries.get(name).is_some_and(Node::is_dir)) {
+                    guard.entries.insert(String::from(name), node);
+                }
+                return Ok(());
+            }
+            let next = {
+                let mut guard = dir.lock();
+                match guard.entries.entry(String::from(name)).or_insert_with(Node::new_dir) {
+                    Node::Dir(d) => d.clone(),
+                    Node::File(_) => return Err("Not a directory"),
+                }
+            };
+            dir = next;
+        }
+        Err("Invalid path")
+    }
+
+    /// Walk to the node at `path`
+    fn lookup(&self, path: &str) -> Result<Node, &'static str> {
+        let mut node = Node::Dir(self.root.clone());
+        for name in path.split('/').filter(|p| !p.is_empty()) {
+            let dir = match &node {
+                Node::Dir(dir) => dir.clone(),
+                Node::File(_) => return Err("Not a directory"),
+            };
+            let next = dir.lock().entries.get(name).cloned().ok_or("File not found")?;
+            node = next;
+        }
+        Ok(node)
+    }
+
+    /// Resolve the parent directory of `path`; returns (parent, final name)
+    fn lookup_parent<'a>(&self, path: &'a str) -> Result<(Arc<SpinLock<DirNode>>, &'a str), &'static str> {
+        let path = path.trim_matches('/');
+        let (parent, name) = match path.rfind('/') {
+            Some(idx) => (&path[..idx], &path[idx+1..]),
+            None => ("", path),
+        };
+        if name.is_empty() || name == "." || name == ".." { return Err("Invalid path"); }
+        match self.lookup(parent)? {
+            Node::Dir(dir) => Ok((dir, name)),
+            Node::File(_) => Err("Not a directory"),
+        }
+    }
+}
+
+impl Filesystem for TmpFs {
+    fn open(&self, path: &str, flags: usize) -> Result<Arc<SpinLock<dyn FileOps>>, &'static str> {
+        match self.lookup(path) {
+            Ok(node) => {
+                if let Node::File(file) = &node {
+                    if flags & vfs::O_TRUNC != 0 {
+                        file.lock().contents = Contents::Owned(Vec::new());
+                    }
+                }
+                Ok(node.open())
+            }
+            Err("File not found") if flags & vfs::O_CREAT != 0 => self.create(path),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn create(&self, path: &str) -> Result<Arc<SpinLock<dyn FileOps>>, &'static str> {
+        let (parent, name) = self.lookup_parent(path)?;
+        let mut dir = parent.lock();
+        let node = match dir.entries.get(name) {
+            // Existing file is truncated, like creat()
+            Some(Node::File(file)) => {
+                file.lock().contents = Contents::Owned(Vec::new());
+                Node::File(file.clone())
+            }
+            Some(Node::Dir(_)) => return Err("Is a directory"),
+            None => {
+                let node = Node::new_file(Contents::Owned(Vec::new()));
+                dir.entries.insert(String::from(name), node.clone());
+                node
+            }
+        };
+        Ok(node.open())
+    }
+
+    fn mkdir(&self, path: &str) -> Result<(), &'static str> {
+        let (parent, name) = self.lookup_parent(path)?;
+        let mut dir = parent.lock();
+        if dir.entries.contains_key(name) {
+            return Err("File exists");
+        }
+        dir.entries.insert(String::from(name), Node::new_dir());
+        Ok(())
+    }
+
+    fn remove(&self, path: &str) -> Result<(), &'static str> {
+        let (parent, name) = self.lookup_parent(path)?;
+        let mut dir = parent.lock();
+        match dir.entries.get(name) {
+            None => return Err("File not found"),
+            Some(Node::Dir(child)) if !child.lock().entries.is_empty() => {
+                return Err("Directory not empty");
+            }
+            Some(_) => {}
+        }
+        // Open handles keep their node alive until closed
+        dir.entries.remove(name);
+        Ok(())
+    }
+
+    fn read_dir(&self, path: &str) -> Result<Vec<DirEntry>, &'static str> {
+        let dir = match self.lookup(path)? {
+            Node::Dir(dir) => dir,
+            Node::File(_) => return Err("Not a directory"),
+        };
+        let entries: Vec<(String, Node)> = dir.lock().entries.iter()
+            .map(|(name, node)| (name.clone(), node.clone()))
+            .collect();
+        Ok(entries.into_iter().map(|(name, node)| DirEntry {
+            is_dir: node.is_dir(),
+            size: node.stat().size,
+            name,
+        }).collect())
+    }
+
+    fn rename(&self, from: &str, to: &str) -> Result<(), &'static str> {
+        let (from_parent, from_name) = self.lookup_parent(from)?;
+        let (to_parent, to_name) = self.lookup_parent(to)?;
+        let node = from_parent.lock().entries.get(from_name).cloned().ok_or("File not found")?;
+
+        let existing = to_parent.lock().entries.get(to_name).cloned();
+        if let Some(existing) = existing {
+            match (&node, &existing) {
+                (Node::File(a), Node::File(b)) if Arc::ptr_eq(a, b) => return Ok(()),
+                (Node::File(_), Node::Dir(_)) => return Err("Is a directory"),
+                (Node::Dir(_), Node::File(_)) => return Err("Not a directory"),
+                (Node::Dir(a), Node::Dir(b)) => {
+                    if Arc::ptr_eq(a, b) { return Ok(()); }
+                    if !b.lock().entries.is_empty() { return Err("Directory not empty"); }
+                }
+                _ => {}
+            }
+        }
+
+        // The two parents may be the same directory: never hold both locks
+        from_parent.lock().entries.remove(from_name);
+        to_parent.lock().entries.insert(String::from(to_name), node);
+        Ok(())
+    }
+
+    fn truncate(&self, path: &str, size: u64) -> Result<(), &'static str> {
+        match self.lookup(path)? {
+            Node::File(file) => {
+                let size = usize::try_from(size).map_err(|_| "File too large")?;
+                file.lock().contents.resize(size)
+            }
+            Node::Dir(_) => Err("Is a directory"),
+        }
+    }
+
+    fn stat(&self, path: &str) -> Result<FileStat, &'static str> {
+        Ok(self.lookup(path)?.stat())
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// FILE HANDLES
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Open regular file
+pub struct TmpFile {
+    node: Arc<SpinLock<FileNode>>,
+    offset: usize,
+}
+
+impl FileOps for TmpFile {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, &'static str> {
+        let file = self.node.lock();
+        let data = file.contents.as_slice();
+        if self.offset >= data.len() {
+            return Ok(0); // EOF
+        }
+        let n = core::cmp::min(buf.len(), data.len() - self.offset);
+        buf[..n].copy_from_slice(&data[self.offset..self.offset + n]);
+        self.offset += n;
+        Ok(n)
+    }
+
+    fn write(&mut self, buf: &[u8]) -> Result<usize, &'static str> {
+        let mut file = self.node.lock();
+        let end = self.offset.checked_add(buf.len()).ok_or("File too large")?;
+        if end > file.contents.as_slice().len() {
+            // Writing past EOF leaves a zero-filled gap
+            file.contents.resize(end)?;
+        }
+        file.contents.make_mut()[self.offset..end].copy_from_slice(buf);
+        self.offset = end;
+        Ok(buf.len())
+    }
+
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64, &'static str> {
+        let size = self.node.lock().contents.as_slice().len() as i64;
+        let new_pos = match pos {
+            SeekFrom::Start(off) => off as i64,
+            SeekFrom::Current(off) => self.offset as i64 + off,
+            SeekFrom::End(off) => size + off,
+        };
+        if new_pos < 0 {
+            return Err("Invalid seek");
+        }
+        self.offset = new_pos as usize;
+        Ok(new_pos as u64)
+    }
+
+    fn close(&mut self) -> Result<(), &'static str> {
+        Ok(())
+    }
+
+    fn stat(&self) -> Result<FileStat, &'static str> {
+        Ok(Node::File(self.node.clone()).stat())
+    }
+
+    fn as_any(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+/// Open directory
+pub struct TmpDir {
+    node: Arc<SpinLock<DirNode>>,
+    /// Entries already returned by readdir
+    index: usize,
+}
+
+impl FileOps for TmpDir {
+    fn read(&mut self, _buf: &mut [u8]) -> Result<usize, &'static str> {
+        Err("Is a directory")
+    }
+
+    fn write(&mut self, _buf: &[u8]) -> Result<usize, &'static str> {
+        Err("Is a directory")
+    }
+
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64, &'static str> {
+        match pos {
+            SeekFrom::Start(0) => {
+                self.index = 0; // rewinddir
+                Ok(0)
+            }
+            _ => Err("Invalid seek"),
+        }
+    }
+
+    fn close(&mut self) -> Result<(), &'static str> {
+        Ok(())
+    }
+
+    fn stat(&self) -> Result<FileStat, &'static str> {
+        Ok(Node::Dir(self.node.clone()).stat())
+    }
+
+    fn readdir(&mut self) -> Result<Option<DirEntry>, &'static str> {
+        let entry = self.node.lock().entries.iter().nth(self.index)
+            .map(|(name, node)| (name.clone(), node.clone()));
+        match entry {
+            Some((name, node)) => {
+                self.index += 1;
+                Ok(Some(DirEntry { is_dir: node.is_dir(), size: node.stat().size, name }))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn as_any(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::boxed::Box;
+    use alloc::vec;
+
+    fn read_all(fs: &TmpFs, path: &str) -> Vec<u8> {
+        let file = fs.open(path, vfs::O_RDONLY).unwrap();
+        let mut buf = vec![0u8; 64];
+        let n = file.lock().read(&mut buf).unwrap();
+        buf.truncate(n);
+        buf
+    }
+
+    /// Minimal ustar archive with one file at `path`
+    fn tar_with(path: &str, data: &[u8]) -> &'static [u8] {
+        let mut tar = vec![0u8; 512 * 4];
+        tar[..path.len()].copy_from_slice(path.as_bytes());
+        let size = alloc::format!("{:011o}", data.len());
+        tar[124..135].copy_from_slice(size.as_bytes());
+        tar[156] = b'0';
+        tar[257..262].copy_from_slice(b"ustar");
+        tar[512..512 + data.len()].copy_from_slice(data);
+        Box::leak(tar.into_boxed_slice())
+    }
+
+    #[test]
+    fn test_create_write_read() {
+        let fs = TmpFs::new();
+        fs.mkdir("/logs").unwrap();
+        let file = fs.create("/logs/boot.txt").unwrap();
+        assert_eq!(file.lock().write(b"hello").unwrap(), 5);
+        assert_eq!(read_all(&fs, "/logs/boot.txt"), b"hello");
+        assert_eq!(fs.stat("/logs/boot.txt").unwrap().size, 5);
+        assert_eq!(fs.stat("/logs").unwrap().mode & vfs::S_IFMT, S_IFDIR);
+    }
+
+    #[test]
+    fn test_rename_and_remove() {
+        let fs = TmpFs::new();
+        fs.mkdir("/a").unwrap();
+        fs.mkdir("/b").unwrap();
+        fs.create("/a/f").unwrap().lock().write(b"x").unwrap();
+        assert_eq!(fs.remove("/a"), Err("Directory not empty"));
+
+        fs.rename("/a/f", "/b/g").unwrap();
+        assert!(fs.stat("/a/f").is_err());
+        assert_eq!(read_all(&fs, "/b/g"), b"x");
+
+        fs.remove("/a").unwrap();
+        let names: Vec<String> = fs.read_dir("/").unwrap().into_iter().map(|e| e.name).collect();
+        assert_eq!(names, vec![String::from("b")]);
+    }
+
+    #[test]
+    fn test_truncate_zero_fills() {
+        let fs = TmpFs::new();
+        fs.create("/f").unwrap().lock().write(b"abc").unwrap();
+        fs.truncate("/f", 5).unwrap();
+        assert_eq!(read_all(&fs, "/f"), b"abc\0\0");
+        fs.truncate("/f", 1).unwrap();
+        assert_eq!(read_all(&fs, "/f"), b"a");
+    }
+
+    #[test]
+    fn test_size_limit() {
+        let fs = TmpFs::new();
+        let file = fs.create("/f").unwrap();
+        assert_eq!(fs.truncate("/f", u64::MAX), Err("File too large"));
+        assert_eq!(fs.truncate("/f", MAX_FILE_SIZE as u64 + 1), Err("File too large"));
+
+        file.lock().seek(SeekFrom::Start(MAX_FILE_SIZE as u64)).unwrap();
+        assert_eq!(file.lock().write(b"x"), Err("File too large"));
+        assert_eq!(fs.stat("/f").unwrap().size, 0);
+    }
+
+    #[test]
+    fn test_lower_layer_copy_on_write() {
+        let archive = tar_with("etc/motd", b"welcome");
+        let fs = TmpFs::with_lower(TarArchive::new(archive));
+        assert_eq!(read_all(&fs, "/etc/motd"), b"welcome");
+
+        let file = fs.open("/etc/motd", vfs::O_RDWR).unwrap();
+        file.lock().write(b"W").unwrap();
+        assert_eq!(read_all(&fs, "/etc/motd"), b"Welcome");
+        // The archive itself is untouched
+        assert_eq!(&archive[512..519], b"welcome");
+    }
+}
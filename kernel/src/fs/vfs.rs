@@ -6,7 +6,9 @@
 use alloc::vec::Vec;
 use alloc::string::String;
 use alloc::sync::Arc;
-use crate::kernel::sync::SpinLock;
+use crate::kernel::sync::{SpinLock, WaitQueue};
+use crate::kernel::capability::CapabilityType;
+use crate::kernel::memory::object::MemObject;
 use crate::kprintln;
 
 /// File Open Flags
@@ -16,6 +18,14 @@ pub const O_RDWR:   usize = 2;
 pub const O_CREAT:  usize = 64;
 pub const O_TRUNC:  usize = 512;
 pub const O_APPEND: usize = 1024;
+/// Reads that would sleep fail with `EWOULDBLOCK` instead
+pub const O_NONBLOCK: usize = 2048;
+
+/// poll() readiness bits (shared with user programs)
+pub use intent_abi::{POLLIN, POLLOUT, POLLERR, POLLHUP, POLLNVAL};
+
+/// File type bits of `FileStat::mode` (shared with user programs)
+pub use intent_abi::{S_IFMT, S_IFDIR, S_IFREG};
 
 /// Seek Whence
 pub enum SeekFrom {
@@ -53,6 +63,24 @@ pub trait FileOps: Send + Sync + Any {
     fn close(&mut self) -> Result<(), &'static str>;
     fn stat(&self) -> Result<FileStat, &'static str>;
     fn readdir(&mut self) -> Result<Option<DirEntry>, &'static str> { Err("Not a directory") }
+    /// Switch blocking reads to non-blocking (O_NONBLOCK). Regular files never block.
+    fn set_nonblocking(&mut self, _nonblocking: bool) {}
+    /// Queue a blocking `read` would sleep on; `None` if reads never block
+    /// (regular files, or O_NONBLOCK set)
+    ///
+    /// `sys_read` sleeps on it itself and retries `try_read` with the file
+    /// unlocked, so a blocked reader never locks other threads, forked
+    /// children or `poll` out of a shared file.
+    fn read_queue(&self) -> Option<&'static WaitQueue> { None }
+    /// `read` that fails with EWOULDBLOCK instead of sleeping
+    fn try_read(&mut self, buf: &mut [u8]) -> Result<usize, &'static str> { self.read(buf) }
+    /// Current readiness as POLLIN/POLLOUT/POLLHUP/POLLERR bits. Regular files are always ready.
+    fn poll_ready(&self) -> u16 { POLLIN | POLLOUT }
+    /// Capability a process must hold to get a descriptor for this file (checked by sys_open)
+    fn required_capability(&self) -> Option<CapabilityType> { None }
+    /// Pages `mmap` maps directly, for files that are memory (shared memory
+    /// objects). Regular files are mapped through the page cache instead.
+    fn memory_object(&self) -> Option<Arc<SpinLock<MemObject>>> { None }
     fn as_any(&mut self) -> &mut dyn Any;
 }
 
@@ -64,6 +92,14 @@ pub trait Filesystem: Send + Sync {
     fn mkdir(&self, path: &str) -> Result<(), &'static str>;
     fn remove(&self, path: &str) -> Result<(), &'static str>;
     fn read_dir(&self, path: &str) -> Result<Vec<DirEntry>, &'static str>;
+    /// Move `from` to `to` (both on this filesystem), replacing a file at `to`
+    fn rename(&self, _from: &str, _to: &str) -> Result<(), &'static str> { Err("Operation not supported") }
+    /// Set the size of a regular file, zero-filling when it grows
+    fn truncate(&self, _path: &str, _size: u64) -> Result<(), &'static str> { Err("Operation not supported") }
+    /// Metadata without keeping the file open
+    fn stat(&self, path: &str) -> Result<FileStat, &'static str> {
+        self.open(path, O_RDONLY)?.lock().stat()
+    }
 }
 
 /// Abstract Block Device (e.g., SD Card partition)
@@ -95,6 +131,10 @@ impl ProcessFileTable {
 
     /// Allocate a new file descriptor
     pub fn alloc_fd(&mut self, file: Arc<SpinLock<dyn FileOps>>, flags: usize) -> Result<usize, &'static str> {
+        if flags & O_NONBLOCK != 0 {
+            file.lock().set_nonblocking(true);
+        }
+        
         // Find first free slot
         for (i, slot) in self.fds.iter_mut().enumerate() {
             if slot.is_none() {
@@ -176,6 +216,44 @@ impl Default for ProcessFileTable {
     }
 }
 
+// ═══════════════════════════════════════════════════════════════════════════════
+// PATHS
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Canonicalise `path` against `cwd`
+///
+/// Returns an absolute path with no `.`/`..` components, repeated or
+/// trailing slashes. `..` at the root stays at the root.
+pub fn normalize_path(cwd: &str, path: &str) -> String {
+    let mut parts: Vec<&str> = Vec::new();
+    let base = if path.starts_with('/') { "" } else { cwd };
+
+    for part in base.split('/').chain(path.split('/')) {
+        match part {
+            "" | "." => {}
+            ".." => { parts.pop(); }
+            name => parts.push(name),
+        }
+    }
+
+    if parts.is_empty() {
+        return String::from("/");
+    }
+    let mut out = String::new();
+    for part in parts {
+        out.push('/');
+        out.push_str(part);
+    }
+    out
+}
+
+/// Does `mount_point` cover `path`? (Both canonical; matches whole components only)
+fn mount_covers(mount_point: &str, path: &str) -> bool {
+    mount_point == "/"
+        || path == mount_point
+        || (path.starts_with(mount_point) && path.as_bytes()[mount_point.len()] == b'/')
+}
+
 /// Global VFS Manager
 pub struct VfsManager {
     mounts: Vec<(String, Arc<dyn Filesystem>)>, // (Mount Point, FS)
@@ -190,57 +268,90 @@ impl VfsManager {
 
     /// Mount a filesystem at a path
     pub fn mount(&mut self, path: &str, fs: Arc<dyn Filesystem>) -> Result<(), &'static str> {
-        self.mounts.push((String::from(path), fs));
+        self.mounts.push((normalize_path("/", path), fs));
         Ok(())
     }
 
     /// Find the filesystem responsible for a path
-    /// Returns (FS, Relative Path)
-    fn resolve_path<'a>(&self, path: &'a str) -> Result<(Arc<dyn Filesystem>, &'a str), &'static str> {
-        // Simple longest prefix match
-        let mut best_match_len = 0;
-        let mut best_fs = None;
-        let mut relative_path = path;
-
-        for (mount_point, fs) in &self.mounts {
-            if path.starts_with(mount_point) && mount_point.len() > best_match_len {
-                best_match_len = mount_point.len();
-                best_fs = Some(fs.clone());
-                
-                // Strip mount point from path
-                if path.len() == mount_point.len() {
-                    relative_path = "/";
-                } else {
-                    relative_path = &path[mount_point.len()..];
-                }
-            }
-        }
+    /// Returns (FS, Mount Point, Relative Path)
+    ///
+    /// `path` is canonicalised first, so callers may pass any absolute path.
+    fn resolve_path(&self, path: &str) -> Result<(Arc<dyn Filesystem>, String, String), &'static str> {
+        let path = normalize_path("/", path);
+        
+        // Longest mount point that covers whole components
+        let (mount_point, fs) = self.mounts.iter()
+            .filter(|(mount_point, _)| mount_covers(mount_point, &path))
+            .max_by_key(|(mount_point, _)| mount_point.len())
+            .ok_or("No filesystem mounted at path")?;
 
-        if let Some(fs) = best_fs {
-            Ok((fs, relative_path))
+        // Strip mount point from path
+        let relative_path = if mount_point == "/" {
+            path.clone()
+        } else if path.len() == mount_point.len() {
+            String::from("/")
         } else {
-            Err("No filesystem mounted at path")
-        }
+            String::from(&path[mount_point.len()..])
+        };
+        Ok((fs.clone(), mount_point.clone(), relative_path))
     }
 
     pub fn open(&self, path: &str, flags: usize) -> Result<Arc<SpinLock<dyn FileOps>>, &'static str> {
-        let (fs, rel_path) = self.resolve_path(path)?;
-        fs.open(rel_path, flags)
+        let (fs, _, rel_path) = self.resolve_path(path)?;
+        fs.open(&rel_path, flags)
     }
 
     pub fn create(&self, path: &str) -> Result<Arc<SpinLock<dyn FileOps>>, &'static str> {
-        let (fs, rel_path) = self.resolve_path(path)?;
-        fs.create(rel_path)
+        let (fs, _, rel_path) = self.resolve_path(path)?;
+        fs.create(&rel_path)
     }
     
     pub fn mkdir(&self, path: &str) -> Result<(), &'static str> {
-        let (fs, rel_path) = self.resolve_path(path)?;
-        fs.mkdir(rel_path)
+        let (fs, _, rel_path) = self.resolve_path(path)?;
+        fs.mkdir(&rel_path)
     }
     
     pub fn read_dir(&self, path: &str) -> Result<Vec<DirEntry>, &'static str> {
-        let (fs, rel_path) = self.resolve_path(path)?;
-        fs.read_dir(rel_path)
+        let (fs, _, rel_path) = self.resolve_path(path)?;
+        fs.read_dir(&rel_path)
+    }
+
+    /// Remove a file or empty directory
+    pub fn unlink(&self, path: &str) -> Result<(), &'static str> {
+        let (fs, _, rel_path) = self.resolve_path(path)?;
+        if rel_path == "/" {
+            return Err("Device or resource busy"); // Mount point
+        }
+        fs.remove(&rel_path)
+    }
+
+    pub fn rename(&self, from: &str, to: &str) -> Result<(), &'static str> {
+        let (fs, from_mount, from_rel) = self.resolve_path(from)?;
+        let (_, to_mount, to_rel) = self.resolve_path(to)?;
+        if from_mount != to_mount {
+            return Err("Cross-device link");
+        }
+        if from_rel == "/" || to_rel == "/" {
+            return Err("Device or resource busy");
+        }
+        if from_rel == to_rel {
+            return Ok(());
+        }
+        // A directory cannot move below itself
+        if to_rel.starts_with(from_rel.as_str()) && to_rel.as_bytes()[from_rel.len()] == b'/' {
+            return Err("Invalid argument");
+        }
+        fs.rename(&from_rel, &to_rel)
+    }
+
+    pub fn stat(&self, path: &str) -> Result<FileStat, &'static str> {
+        let (fs, _, rel_path) = self.resolve_path(path)?;
+        fs.stat(&rel_path)
+    }
+
+    pub fn truncate(&self, path: &str, size: u64) -> Result<(), &'static str> {
+        let (fs, _, rel_path) = self.resolve_path(path)?;
+        fs.truncate(&rel_path, size)
     }
 }
 
@@ -258,3 +369,27 @@ pub fn init() {
     // In the future, we will mount the root FS here.
     kprintln!("[VFS] Initialized.");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_path() {
+        assert_eq!(normalize_path("/", "/"), "/");
+        assert_eq!(normalize_path("/", "//a///b/"), "/a/b");
+        assert_eq!(normalize_path("/home", "docs/./x.txt"), "/home/docs/x.txt");
+        assert_eq!(normalize_path("/home/user", "../other"), "/home/other");
+        assert_eq!(normalize_path("/", "../../.."), "/");
+        assert_eq!(normalize_path("/cwd", "/abs/../path"), "/path");
+    }
+
+    #[test]
+    fn test_mount_covers_whole_components() {
+        assert!(mount_covers("/", "/anything"));
+        assert!(mount_covers("/mnt", "/mnt"));
+        assert!(mount_covers("/mnt", "/mnt/file"));
+        assert!(!mount_covers("/mnt", "/mntfoo"));
+        assert!(!mount_covers("/mnt", "/"));
+    }
+}
@@ -389,6 +389,10 @@ pub struct HandlerRegistry {
     count: usize,
     /// Whether handlers are sorted by priority
     sorted: bool,
+    /// Broadcasts performed since boot
+    broadcast_count: u64,
+    /// Outcome of the most recent broadcast
+    last_broadcast: BroadcastStats,
 }
 
 impl HandlerRegistry {
@@ -398,6 +402,15 @@ impl HandlerRegistry {
             handlers: [HandlerEntry::EMPTY; MAX_HANDLERS],
             count: 0,
             sorted: true,
+            broadcast_count: 0,
+            last_broadcast: BroadcastStats {
+                total_handlers: 0,
+                handled: 0,
+                errors: 0,
+                inhibited: 0,
+                modulation: 1.0,
+                stopped: false,
+            },
         }
     }
     
@@ -577,6 +590,19 @@ impl HandlerRegistry {
         has_cap: impl Fn(CapabilityType) -> bool,
         timestamp: u64,
         scope: BroadcastScope,
+    ) -> BroadcastResult {
+        let result = self.run_broadcast(intent, has_cap, timestamp, scope);
+        self.broadcast_count += 1;
+        self.last_broadcast = result.stats();
+        result
+    }
+
+    fn run_broadcast(
+        &mut self,
+        intent: &Intent,
+        has_cap: impl Fn(CapabilityType) -> bool,
+        timestamp: u64,
+        scope: BroadcastScope,
     ) -> BroadcastResult {
         self.sort_by_priority();
         
@@ -733,6 +759,11 @@ impl HandlerRegistry {
     pub fn list(&self) -> &[HandlerEntry] {
         &self.handlers[..self.count]
     }
+
+    /// Number of broadcasts and the statistics of the latest one
+    pub fn broadcast_summary(&self) -> (u64, BroadcastStats) {
+        (self.broadcast_count, self.last_broadcast)
+    }
     
     /// Get handlers that would match a given ConceptID under a scope
     pub fn matching_handlers(&self, concept_id: ConceptID, scope: BroadcastScope) -> usize {
@@ -942,6 +973,10 @@ mod tests {
         assert_eq!(stats.handled, 2);
         assert_eq!(stats.errors, 0);
         assert!(!stats.stopped);
+
+        let (count, last) = registry.broadcast_summary();
+        assert_eq!(count, 1);
+        assert_eq!(last.handled, 2);
     }
     
     fn modulating_handler(_: &Intent) -> HandlerResult {
@@ -94,6 +94,86 @@ pub fn handle_read_file(intent: &Intent) -> HandlerResult {
     HandlerResult::Handled
 }
 
+/// Handle host name lookup (RESOLVE_HOST)
+///
+/// Runs under the executor lock, so it never waits on the network: a cold
+/// name starts a query and the resolver logs the answer when it arrives.
+pub fn handle_resolve(intent: &Intent) -> HandlerResult {
+    let host = match &intent.data {
+            IntentData::String(s) => s.as_str(),
+            _ => {
+                kprintln!("[INTENT] RESOLVE_HOST requires a host name (IntentData::String)");
+                return HandlerResult::Error(1);
+            }
+    };
+
+    match crate::net::dns::lookup(host, crate::net::dns::RecordType::A) {
+        Some(result) => kprintln!("[DNS] {}: {}", host, crate::net::dns::describe(&result)),
+        None => kprintln!("[DNS] Resolving {}...", host),
+    }
+    HandlerResult::Handled
+}
+
+/// Handle packet capture control (CAPTURE_TRAFFIC)
+///
+/// `capture <filter> [to <path> | uart]` starts a capture, `capture stop`
+/// ends it and a bare `capture` reports progress.
+pub fn handle_capture(intent: &Intent) -> HandlerResult {
+    use crate::net::capture;
+
+    let args = match &intent.data {
+        IntentData::String(s) => s.as_str(),
+        _ => "",
+    };
+
+    if args.is_empty() {
+        match capture::status() {
+            Some((filter, stats)) => kprintln!(
+                "[PCAP] Capturing '{}': {} frames, {} dropped, {} bytes written",
+                filter.describe(), stats.captured, stats.dropped, stats.written
+            ),
+            None => kprintln!("[PCAP] No capture running"),
+        }
+        return HandlerResult::Handled;
+    }
+
+    if args == "stop" {
+        return match capture::stop() {
+            Ok(stats) => {
+                kprintln!("[PCAP] Stopped: {} frames, {} dropped, {} bytes written",
+                    stats.captured, stats.dropped, stats.written);
+                HandlerResult::Handled
+            }
+            Err(e) => {
+                kprintln!("[PCAP] {}", e);
+                HandlerResult::Error(1)
+            }
+        };
+    }
+
+    let started = capture::parse_request(args).and_then(|(filter, target)| {
+        match &target {
+            capture::Target::File(path) => capture::start_file(filter, path)?,
+            capture::Target::Uart => capture::start(filter, capture::CaptureSink::Uart)?,
+        }
+        Ok((filter, target))
+    });
+    match started {
+        Ok((filter, capture::Target::File(path))) => {
+            kprintln!("[PCAP] Capturing '{}' to {}", filter.describe(), path);
+        }
+        Ok((filter, capture::Target::Uart)) => {
+            kprintln!("[PCAP] Capturing '{}' to UART (lines prefixed '{}')",
+                filter.describe(), capture::UART_PREFIX.trim_end());
+        }
+        Err(e) => {
+            kprintln!("[PCAP] {}", e);
+            return HandlerResult::Error(1);
+        }
+    }
+    HandlerResult::Handled
+}
+
 pub fn handle_help(_intent: &Intent) -> HandlerResult {
     kprintln!("╔═══════════════════════════════════════╗");
     kprintln!("║     INTENT KERNEL - STENO HELP        ║");
@@ -104,6 +184,8 @@ pub fn handle_help(_intent: &Intent) -> HandlerResult {
     kprintln!("║ Display: SHRO (show), HEU (hide)      ║");
     kprintln!("║ Memory:  STOR, RAOE/KAUL (recall)     ║");
     kprintln!("║ Files:   LIST_FILES, READ_FILE        ║");
+    kprintln!("║ Network: RESOLVE_HOST (resolve host)  ║");
+    kprintln!("║          CAPTURE (capture tcp to f)   ║");
     kprintln!("╚═══════════════════════════════════════╝");
     HandlerResult::Handled
 }
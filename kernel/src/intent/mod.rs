@@ -23,6 +23,7 @@ pub mod temporal;
 pub mod hierarchy;
 pub mod feedback;
 pub mod scheduling;
+pub mod remote;
 
 pub use handlers::{
     HandlerRegistry, HandlerResult, HandlerFn, HandlerEntry, 
@@ -31,6 +32,7 @@ pub use handlers::{
 };
 pub use queue::{IntentQueue, QueuedIntent, Priority};
 pub use security::{IntentSecurity, SecurityViolation, PrivilegeLevel};
+use crate::english::IntentResult;
 pub use temporal::{
     TemporalDynamics, TemporalStats, TEMPORAL_DYNAMICS,
     decay_tick, process_intent_activation, summate, is_primed,
@@ -275,6 +277,14 @@ pub struct IntentExecutor {
     security: security::IntentSecurity,
 }
 
+/// Capabilities the executor holds and handlers may require
+pub const ALL_CAPS: [CapabilityType; 4] = [
+    CapabilityType::Display,
+    CapabilityType::Memory,
+    CapabilityType::System,
+    CapabilityType::Compute,
+];
+
 impl IntentExecutor {
     pub const fn new() -> Self {
         Self {
@@ -331,6 +341,10 @@ impl IntentExecutor {
         // Files
         self.handlers.register(concepts::LIST_FILES, system::handle_list_files, "ls");
         self.handlers.register(concepts::READ_FILE, system::handle_read_file, "cat");
+
+        // Network
+        self.handlers.register(concepts::RESOLVE_HOST, system::handle_resolve, "resolve");
+        self.handlers.register_with_options(concepts::CAPTURE_TRAFFIC, system::handle_capture, "capture", 100, Some(CapabilityType::System));
     }
     
     /// Check if we have a capability
@@ -345,23 +359,35 @@ impl IntentExecutor {
     }
     
     /// Execute an intent
-    pub fn execute(&mut self, intent: &Intent) {
-        // ═══════════════════════════════════════════════════════════════════════════
-        // SECURITY CHECKS (HDC-BASED)
-        // ═══════════════════════════════════════════════════════════════════════════
-        
+    pub fn execute(&mut self, intent: &Intent) -> IntentResult {
         // Get current process/source ID for rate limiting
         let source_id = {
             let scheduler = crate::kernel::scheduler::SCHEDULER.lock();
-            scheduler.current_pid().unwrap_or(0)
+            scheduler.current_process_id().unwrap_or(0)
         };
         
-        // Get current timestamp
-        let timestamp = crate::drivers::timer::uptime_ms();
-        
         // Determine privilege level (kernel privilege assumed for now)
         // In real implementation, this would check the current execution level
-        let privilege = security::PrivilegeLevel::Kernel;
+        self.execute_as(intent, source_id, security::PrivilegeLevel::Kernel, &ALL_CAPS)
+    }
+
+    /// Execute an intent for an explicit source (e.g. a remote session)
+    ///
+    /// Handlers only see the capabilities in `granted` that the executor
+    /// itself still holds.
+    pub fn execute_as(
+        &mut self,
+        intent: &Intent,
+        source_id: u64,
+        privilege: security::PrivilegeLevel,
+        granted: &[CapabilityType],
+    ) -> IntentResult {
+        // ═══════════════════════════════════════════════════════════════════════════
+        // SECURITY CHECKS (HDC-BASED)
+        // ═══════════════════════════════════════════════════════════════════════════
+        
+        // Get current timestamp
+        let timestamp = crate::drivers::timer::uptime_ms();
         
         // CHECK SECURITY
         if let Err(violation) = self.security.check_intent(
@@ -371,7 +397,7 @@ impl IntentExecutor {
             timestamp,
         ) {
             crate::kprintln!("[SECURITY] Intent rejected: {:?}", violation);
-            return;
+            return IntentResult::error("Rejected by security policy");
         }
         
         // ═══════════════════════════════════════════════════════════════════════════
@@ -379,20 +405,19 @@ impl IntentExecutor {
         // ═══════════════════════════════════════════════════════════════════════════
         
         // Pre-compute capability checks to avoid borrow issues
-        let caps = [
-            (CapabilityType::Display, self.has_capability(CapabilityType::Display)),
-            (CapabilityType::Memory, self.has_capability(CapabilityType::Memory)),
-            (CapabilityType::System, self.has_capability(CapabilityType::System)),
-            (CapabilityType::Compute, self.has_capability(CapabilityType::Compute)),
-        ];
+        let caps = ALL_CAPS.map(|cap| (cap, granted.contains(&cap) && self.has_capability(cap)));
         
         let has_cap = |cap: CapabilityType| {
             caps.iter().find(|(c, _)| *c == cap).map(|(_, v)| *v).unwrap_or(false)
         };
         
         // First, try user-defined handlers (which now include system handlers)
-        if self.handlers.dispatch(intent, has_cap) {
-            return; // Handled
+        let broadcast = self.handlers.broadcast(intent, has_cap, 0);
+        if broadcast.was_handled() {
+            return IntentResult::success();
+        }
+        if broadcast.error_count() > 0 {
+            return IntentResult::error("Handler failed");
         }
         
         // Fall back to Skill Registry
@@ -405,13 +430,16 @@ impl IntentExecutor {
             match skill.execute(intent.name, &ctx) {
                 Ok(result) => {
                     crate::kprintln!("[SKILL] {}: {}", skill.name(), result);
+                    IntentResult::success()
                 }
                 Err(e) => {
                     crate::kprintln!("[SKILL] {} failed: {:?}", skill.name(), e);
+                    IntentResult::error("Skill failed")
                 }
             }
         } else {
             crate::kprintln!("[INTENT] Unknown concept: {:?}", id);
+            IntentResult::error("No handler for this intent")
         }
     }
     
@@ -513,9 +541,21 @@ pub fn init() {
 }
 
 /// Execute an intent
-pub fn execute(intent: &Intent) {
+pub fn execute(intent: &Intent) -> IntentResult {
     let mut executor = EXECUTOR.lock();
-    executor.execute(intent);
+    executor.execute(intent)
+}
+
+/// Execute an intent for an explicit source and privilege level, with only
+/// the `granted` capabilities
+pub fn execute_as(
+    intent: &Intent,
+    source_id: u64,
+    privilege: PrivilegeLevel,
+    granted: &[CapabilityType],
+) -> IntentResult {
+    let mut executor = EXECUTOR.lock();
+    executor.execute_as(intent, source_id, privilege, granted)
 }
 
 /// Check if we have a capability
@@ -544,6 +584,12 @@ pub fn register_wildcard(
     executor.register_wildcard(handler, name, priority)
 }
 
+/// Inspect the handler registry (e.g. for `/proc/intent/handlers`)
+pub fn with_handlers<R>(f: impl FnOnce(&HandlerRegistry) -> R) -> R {
+    let executor = EXECUTOR.lock();
+    f(&executor.handlers)
+}
+
 /// Unregister a handler by name
 pub fn unregister_handler(name: &'static str) -> bool {
     let mut executor = EXECUTOR.lock();
@@ -0,0 +1,470 @@
+//! Remote Intent Server
+//!
+//! A line-based intent shell on TCP port `DEFAULT_PORT`, so the kernel can
+//! be scripted from other machines (`nc <host> 7000`).
+//!
+//! A session must authenticate first with `AUTH <token>`. A token is a
+//! random secret bound to a derived `Intent` capability and to the executor
+//! capabilities its sessions may use. Revoking that capability ends every
+//! session that used the token, and it is checked again before each line.
+//! The token minted at boot is never logged; a `System` holder reads it
+//! from `/proc/intent/token`.
+//!
+//! After that, every line is one input:
+//! - Lines written in RTFCRE (`STPH`, `KAT/-S`) go to `steno::process_steno`.
+//! - Anything else goes to `english::parse`.
+//!
+//! The resulting intent runs through the executor at user privilege, rate
+//! limited per token, so reconnecting does not reset the limit. The `ResponseGenerator` text comes back with an
+//! SMTP-style status code. Every line but the last of a reply uses `-`
+//! after the code, so clients read until `<code> `:
+//!
+//! ```text
+//! 220 Intent Kernel ready, AUTH <token> first
+//! AUTH 3f9c...
+//! 200 Authenticated
+//! help
+//! 200-Welcome to Intent Kernel!
+//! 200-...
+//! 200 ...
+//! ```
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicU64, Ordering};
+use crate::english::{self, IntentResult};
+use crate::intent::{Intent, PrivilegeLevel};
+use crate::kernel::capability::{self, Capability, CapabilityType, Permissions};
+use crate::kernel::sync::SpinLock;
+use crate::net::ip::{IpAddr, Ipv6Addr};
+use crate::net::socket::TcpSocket;
+use crate::steno;
+
+pub const DEFAULT_PORT: u16 = 7000;
+
+const MAX_SESSIONS: usize = 8;
+const MAX_LINE: usize = 512;
+/// Time allowed between connecting and a successful AUTH
+const AUTH_TIMEOUT_MS: u64 = 10_000;
+/// Secret bytes per token (hex encoded on the wire)
+const TOKEN_BYTES: usize = 16;
+const MAX_TOKENS: usize = 16;
+/// Rate-limiter sources for tokens, above any process ID
+const SOURCE_BASE: u64 = 0x5245_4D00_0000_0000;
+/// What the boot token grants: everything but `System`
+const BOOT_GRANTS: [CapabilityType; 3] =
+    [CapabilityType::Display, CapabilityType::Memory, CapabilityType::Compute];
+
+const CODE_READY: u16 = 220;
+const CODE_BYE: u16 = 221;
+const CODE_OK: u16 = 200;
+const CODE_BAD_REQUEST: u16 = 400;
+const CODE_UNAUTHORIZED: u16 = 401;
+const CODE_NOT_UNDERSTOOD: u16 = 404;
+const CODE_FAILED: u16 = 500;
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// PROTOCOL
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// One request line
+#[derive(Debug, PartialEq, Eq)]
+enum Command<'a> {
+    Auth(&'a str),
+    Quit,
+    Input(&'a str),
+}
+
+impl<'a> Command<'a> {
+    fn parse(line: &'a str) -> Self {
+        let (word, rest) = line.split_once(' ').unwrap_or((line, ""));
+        if word.eq_ignore_ascii_case("AUTH") {
+            Command::Auth(rest.trim())
+        } else if line.eq_ignore_ascii_case("QUIT") {
+            Command::Quit
+        } else {
+            Command::Input(line)
+        }
+    }
+}
+
+/// Format a reply, marking every line but the last as a continuation
+fn reply(code: u16, text: &str) -> String {
+    let lines: Vec<&str> = text.lines().filter(|l| !l.trim().is_empty()).collect();
+    let mut out = String::new();
+    if lines.is_empty() {
+        out.push_str(&alloc::format!("{}\r\n", code));
+    }
+    for (i, line) in lines.iter().enumerate() {
+        let sep = if i + 1 == lines.len() { ' ' } else { '-' };
+        out.push_str(&alloc::format!("{}{}{}\r\n", code, sep, line.trim_end()));
+    }
+    out
+}
+
+/// Is the line RTFCRE steno rather than English?
+///
+/// Steno is upper case keys, `-`, `*`, `#` and digits, with `/` between
+/// strokes. English input is matched case-insensitively, so it never needs
+/// capitals.
+fn is_steno(line: &str) -> bool {
+    line.bytes().any(|b| b.is_ascii_uppercase())
+        && line.bytes().all(|b| {
+            matches!(b, b'S' | b'T' | b'K' | b'P' | b'W' | b'H' | b'R' | b'A' | b'O'
+                | b'E' | b'U' | b'F' | b'B' | b'L' | b'G' | b'D' | b'Z'
+                | b'-' | b'*' | b'#' | b'/' | b'0'..=b'9')
+        })
+}
+
+/// Turn one input line into intents
+///
+/// Multi-stroke steno yields an intent once a stroke completes an entry.
+fn interpret(line: &str) -> Vec<Intent> {
+    if is_steno(line) {
+        line.split('/').filter_map(steno::process_steno).collect()
+    } else {
+        english::parse(line).into_iter().collect()
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// TOKENS
+// ═══════════════════════════════════════════════════════════════════════════════
+
+struct Token {
+    secret: [u8; TOKEN_BYTES],
+    grant: Grant,
+}
+
+/// What a token lets its sessions do
+#[derive(Clone)]
+struct Grant {
+    cap: Capability,
+    /// Executor capabilities handlers may use for this token
+    caps: Vec<CapabilityType>,
+    /// Rate-limiter source shared by every session of this token
+    source_id: u64,
+}
+
+/// Root `Intent` capability the session capabilities derive from
+static ROOT: SpinLock<Option<Capability>> = SpinLock::new(None);
+static TOKENS: SpinLock<Vec<Token>> = SpinLock::new(Vec::new());
+static NEXT_SOURCE: AtomicU64 = AtomicU64::new(1);
+/// Token minted when the server starts, for `/proc/intent/token`
+static BOOT_TOKEN: SpinLock<Option<String>> = SpinLock::new(None);
+
+fn encode_hex(bytes: &[u8]) -> String {
+    const DIGITS: &[u8; 16] = b"0123456789abcdef";
+    let mut out = String::with_capacity(bytes.len() * 2);
+    for byte in bytes {
+        out.push(DIGITS[usize::from(byte >> 4)] as char);
+        out.push(DIGITS[usize::from(byte & 0x0F)] as char);
+    }
+    out
+}
+
+fn decode_token(text: &str) -> Option<[u8; TOKEN_BYTES]> {
+    if text.len() != TOKEN_BYTES * 2 {
+        return None;
+    }
+    let mut secret = [0u8; TOKEN_BYTES];
+    for (byte, pair) in secret.iter_mut().zip(text.as_bytes().chunks(2)) {
+        let pair = core::str::from_utf8(pair).ok()?;
+        *byte = u8::from_str_radix(pair, 16).ok()?;
+    }
+    Some(secret)
+}
+
+/// Compare secrets without an early exit, so timing reveals nothing
+fn secrets_equal(a: &[u8; TOKEN_BYTES], b: &[u8; TOKEN_BYTES]) -> bool {
+    a.iter().zip(b).fold(0u8, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+/// Mint a new session token allowed to use the `grants` capabilities
+///
+/// Returns the hex secret to hand to the remote client. Its capability can
+/// execute intents and be revoked with `revoke_token`.
+pub fn issue_token(grants: &[CapabilityType]) -> Result<String, &'static str> {
+    let root = {
+        let mut root = ROOT.lock();
+        if root.is_none() {
+            // SAFETY: kernel-internal root for the remote server only
+            *root = unsafe { capability::mint_root(CapabilityType::Intent, 0, 0, Permissions::ALL) };
+        }
+        (*root).ok_or("Capability table full")?
+    };
+    let cap = capability::derive(&root, Permissions::EXECUTE.or(Permissions::REVOKE))
+        .ok_or("Capability table full")?;
+
+    let mut secret = [0u8; TOKEN_BYTES];
+    for chunk in secret.chunks_mut(8) {
+        chunk.copy_from_slice(&crate::drivers::rng::next_u64().to_le_bytes());
+    }
+
+    let mut tokens = TOKENS.lock();
+    // Forget tokens whose capability is already gone
+    tokens.retain(|t| capability::validate(&t.grant.cap));
+    if tokens.len() >= MAX_TOKENS {
+        capability::revoke(&cap);
+        return Err("Too many tokens");
+    }
+    let source_id = SOURCE_BASE | NEXT_SOURCE.fetch_add(1, Ordering::Relaxed);
+    tokens.push(Token { secret, grant: Grant { cap, caps: grants.to_vec(), source_id } });
+    Ok(encode_hex(&secret))
+}
+
+/// The token minted at boot, if the server is up
+pub fn boot_token() -> Option<String> {
+    BOOT_TOKEN.lock().clone()
+}
+
+/// Revoke a token; sessions using it are closed before their next line
+pub fn revoke_token(token: &str) -> bool {
+    let Some(secret) = decode_token(token) else { return false };
+    let mut tokens = TOKENS.lock();
+    let Some(pos) = tokens.iter().position(|t| secrets_equal(&t.secret, &secret)) else { return false };
+    let entry = tokens.remove(pos);
+    let mut boot = BOOT_TOKEN.lock();
+    if boot.as_deref().is_some_and(|t| t.eq_ignore_ascii_case(token)) {
+        *boot = None;
+    }
+    capability::revoke(&entry.grant.cap)
+}
+
+/// The grant behind a valid token
+fn authenticate(token: &str) -> Option<Grant> {
+    let secret = decode_token(token)?;
+    let tokens = TOKENS.lock();
+    let mut found = None;
+    for t in tokens.iter() {
+        if secrets_equal(&t.secret, &secret) {
+            found = Some(&t.grant);
+        }
+    }
+    found.filter(|grant| capability::validate(&grant.cap)).cloned()
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// SESSIONS
+// ═══════════════════════════════════════════════════════════════════════════════
+
+struct Session {
+    socket: TcpSocket,
+    /// Bytes of the line being received
+    line: Vec<u8>,
+    /// Set once AUTH succeeds
+    grant: Option<Grant>,
+    opened_ms: u64,
+    /// Discarding an over-long line up to its newline
+    overflow: bool,
+}
+
+impl Session {
+    fn new(socket: TcpSocket, now: u64) -> Self {
+        let mut session = Self {
+            socket,
+            line: Vec::new(),
+            grant: None,
+            opened_ms: now,
+            overflow: false,
+        };
+        session.send(&reply(CODE_READY, "Intent Kernel ready, AUTH <token> first"));
+        session
+    }
+
+    fn send(&mut self, text: &str) {
+        // A failed send means the peer is gone; recv reports that next
+        let _ = self.socket.send(text.as_bytes());
+    }
+
+    /// Read and answer whatever has arrived; false once the session is over
+    fn poll(&mut self, now: u64) -> bool {
+        if self.grant.is_none() && now.saturating_sub(self.opened_ms) > AUTH_TIMEOUT_MS {
+            self.send(&reply(CODE_UNAUTHORIZED, "Authentication timeout"));
+            return false;
+        }
+
+        let mut buf = [0u8; 256];
+        loop {
+            let n = match self.socket.recv(&mut buf) {
+                Ok(Some(0)) | Err(_) => return false,
+                Ok(Some(n)) => n,
+                Ok(None) => return true,
+            };
+            for &byte in &buf[..n] {
+                if byte == b'\n' {
+                    let overflow = core::mem::replace(&mut self.overflow, false);
+                    let line = core::mem::take(&mut self.line);
+                    if overflow {
+                        self.send(&reply(CODE_BAD_REQUEST, "Line too long"));
+                    } else if !self.handle_line(&line) {
+                        return false;
+                    }
+                } else if self.line.len() < MAX_LINE {
+                    self.line.push(byte);
+                } else {
+                    self.overflow = true;
+                }
+            }
+        }
+    }
+
+    /// Answer one line; false to close the session
+    fn handle_line(&mut self, raw: &[u8]) -> bool {
+        let Ok(line) = core::str::from_utf8(raw) else {
+            self.send(&reply(CODE_BAD_REQUEST, "Input must be UTF-8"));
+            return true;
+        };
+        let line = line.trim();
+        if line.is_empty() {
+            return true;
+        }
+
+        match Command::parse(line) {
+            Command::Quit => {
+                self.send(&reply(CODE_BYE, "Bye"));
+                false
+            }
+            Command::Auth(token) => match authenticate(token) {
+                Some(grant) => {
+                    self.grant = Some(grant);
+                    self.send(&reply(CODE_OK, "Authenticated"));
+                    true
+                }
+                None => {
+                    crate::kprintln!("[REMOTE] Bad token from {}", self.socket.remote_addr);
+                    self.send(&reply(CODE_UNAUTHORIZED, "Invalid token"));
+                    false
+                }
+            },
+            Command::Input(input) => {
+                let grant = match &self.grant {
+                    None => {
+                        self.send(&reply(CODE_UNAUTHORIZED, "AUTH required"));
+                        return true;
+                    }
+                    Some(grant) if !capability::validate(&grant.cap) => {
+                        self.send(&reply(CODE_UNAUTHORIZED, "Token revoked"));
+                        return false;
+                    }
+                    Some(grant) => grant.clone(),
+                };
+                self.run(input, &grant);
+                true
+            }
+        }
+    }
+
+    /// Execute an input line and send back the generated responses
+    fn run(&mut self, input: &str, grant: &Grant) {
+        let intents = interpret(input);
+        if intents.is_empty() {
+            self.send(&reply(CODE_NOT_UNDERSTOOD, "Not understood"));
+            return;
+        }
+        for intent in intents {
+            crate::kprintln!("[REMOTE] {} -> {}", self.socket.remote_addr, intent.name);
+            let result = crate::intent::execute_as(
+                &intent,
+                grant.source_id,
+                PrivilegeLevel::User,
+                &grant.caps,
+            );
+            let text = english::generate_response(&intent, &result);
+            self.send(&reply(status_code(&result), &text));
+        }
+    }
+}
+
+fn status_code(result: &IntentResult) -> u16 {
+    if result.success { CODE_OK } else { CODE_FAILED }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// SERVER
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Accept and serve sessions forever (an async-executor task)
+///
+/// Mints a first token for `/proc/intent/token`, so the operator can hand
+/// it out without it ever reaching the console.
+pub async fn serve(port: u16) {
+    let mut listener = TcpSocket::new();
+    listener.family = crate::net::socket::AF_INET6;
+    // The IPv6 wildcard is dual-stack
+    if let Err(e) = listener
+        .bind(IpAddr::V6(Ipv6Addr::UNSPECIFIED), port)
+        .and_then(|()| listener.listen(MAX_SESSIONS))
+    {
+        crate::kprintln!("[REMOTE] Cannot listen on port {}: {}", port, e);
+        return;
+    }
+    match issue_token(&BOOT_GRANTS) {
+        Ok(token) => {
+            *BOOT_TOKEN.lock() = Some(token);
+            crate::kprintln!("[REMOTE] Intent server on port {}, token in /proc/intent/token", port);
+        }
+        Err(e) => crate::kprintln!("[REMOTE] Intent server on port {}, no token: {}", port, e),
+    }
+
+    let mut sessions: Vec<Session> = Vec::new();
+    loop {
+        let now = crate::drivers::timer::uptime_ms();
+        while let Ok(Some(socket)) = listener.try_accept() {
+            if sessions.len() >= MAX_SESSIONS {
+                // Dropping the socket closes it
+                continue;
+            }
+            crate::kprintln!("[REMOTE] Connection from {}", socket.remote_addr);
+            sessions.push(Session::new(socket, now));
+        }
+        // Dropped sessions close their sockets
+        sessions.retain_mut(|session| session.poll(now));
+        crate::kernel::async_core::yield_now().await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_command_parse() {
+        assert_eq!(Command::parse("AUTH abc"), Command::Auth("abc"));
+        assert_eq!(Command::parse("auth  abc "), Command::Auth("abc"));
+        assert_eq!(Command::parse("quit"), Command::Quit);
+        assert_eq!(Command::parse("show status"), Command::Input("show status"));
+    }
+
+    #[test]
+    fn test_reply_continuation() {
+        assert_eq!(reply(200, "Authenticated"), "200 Authenticated\r\n");
+        assert_eq!(reply(200, "a\n\nb\nc\n"), "200-a\r\n200-b\r\n200 c\r\n");
+        assert_eq!(reply(200, ""), "200\r\n");
+    }
+
+    #[test]
+    fn test_steno_detection() {
+        assert!(is_steno("STPH"));
+        assert!(is_steno("KAT/-S"));
+        assert!(is_steno("PH-FPL"));
+        assert!(!is_steno("help"));
+        assert!(!is_steno("Show me"));
+        assert!(!is_steno("-/"));
+    }
+
+    #[test]
+    fn test_token_encoding() {
+        let secret = [0xABu8; TOKEN_BYTES];
+        let text = encode_hex(&secret);
+        assert_eq!(text.len(), TOKEN_BYTES * 2);
+        assert_eq!(decode_token(&text), Some(secret));
+        assert_eq!(decode_token("abcd"), None);
+        assert_eq!(decode_token(&"zz".repeat(TOKEN_BYTES)), None);
+
+        let mut other = secret;
+        other[TOKEN_BYTES - 1] ^= 1;
+        assert!(secrets_equal(&secret, &secret));
+        assert!(!secrets_equal(&secret, &other));
+    }
+}
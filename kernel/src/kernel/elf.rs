@@ -1,9 +1,26 @@
 //! ELF64 Loader
 //!
 //! Parses ELF64 binaries and loads them into a UserAddressSpace.
-
-use crate::kernel::memory::paging::{UserAddressSpace, EntryFlags};
+//!
+//! Both fixed-address (`ET_EXEC`) and position-independent (`ET_DYN`)
+//! executables are supported; the latter are placed at a randomised base and
+//! their `R_AARCH64_RELATIVE` relocations applied. There is no dynamic linker,
+//! so images with `PT_INTERP` are rejected. `PT_TLS` gets the main thread a
+//! TLS block and `PT_GNU_STACK` decides whether the stack is executable.
+//! `setup_stack` then builds the System V initial stack (argc, argv, envp,
+//! auxv) that `_start` finds at `sp`.
+//!
+//! The image is written through the kernel's identity map, so the address
+//! space being loaded needn't be the current one.
+
+use alloc::vec::Vec;
+use core::mem::{size_of, MaybeUninit};
+use crate::kernel::memory::paging::UserAddressSpace;
+use crate::kernel::memory::vma::{VmaManager, VmaFlags, VmaPerms, VMA};
+use crate::kernel::memory::fault::{self, Access};
+use crate::kernel::memory::frames;
 use crate::kprintln;
+use intent_abi as abi;
 
 // ═══════════════════════════════════════════════════════════════════════════════
 // ELF STRUCTURES
@@ -49,6 +66,27 @@ pub struct Elf64ProgramHeader {
     pub align: u64,
 }
 
+/// Dynamic Section Entry
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct Elf64Dyn {
+    pub tag: u64,
+    pub val: u64,
+}
+
+/// Relocation with Addend
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct Elf64Rela {
+    pub offset: u64,
+    pub info: u64,   // Symbol index << 32 | type
+    pub addend: i64,
+}
+
+// ELF Types
+pub const ET_EXEC: u16 = 2;
+pub const ET_DYN: u16 = 3;
+
 // Program Header Types
 pub const PT_NULL: u32 = 0;
 pub const PT_LOAD: u32 = 1;
@@ -57,12 +95,64 @@ pub const PT_INTERP: u32 = 3;
 pub const PT_NOTE: u32 = 4;
 pub const PT_SHLIB: u32 = 5;
 pub const PT_PHDR: u32 = 6;
+pub const PT_TLS: u32 = 7;
+pub const PT_GNU_STACK: u32 = 0x6474_E551;
 
 // Program Header Flags
 pub const PF_X: u32 = 1; // Execute
 pub const PF_W: u32 = 2; // Write
 pub const PF_R: u32 = 4; // Read
 
+// Dynamic Section Tags
+pub const DT_NULL: u64 = 0;
+pub const DT_RELA: u64 = 7;
+pub const DT_RELASZ: u64 = 8;
+pub const DT_RELAENT: u64 = 9;
+
+// Relocation Types
+pub const R_AARCH64_NONE: u32 = 0;
+pub const R_AARCH64_RELATIVE: u32 = 1027;
+
+/// Size of the thread control block TPIDR_EL0 points at (AArch64 TLS variant I)
+const TCB_SIZE: u64 = 16;
+
+/// Where a loaded image ended up, for the initial stack and auxv
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LoadedImage {
+    /// Entry point, relocated
+    pub entry: u64,
+    /// Added to every link-time address (0 for `ET_EXEC`)
+    pub bias: u64,
+    /// Program headers in user memory (`AT_PHDR`), 0 if not loaded
+    pub phdr: u64,
+    /// Number of program headers (`AT_PHNUM`)
+    pub phnum: u64,
+    /// `PT_GNU_STACK` asked for an executable stack
+    pub exec_stack: bool,
+    /// Initial TPIDR_EL0: the main thread's TLS block (0 without `PT_TLS`)
+    pub tp: u64,
+}
+
+impl LoadedImage {
+    /// Permissions for the initial stack
+    pub fn stack_perms(&self) -> VmaPerms {
+        if self.exec_stack { VmaPerms::RWX } else { VmaPerms::RW }
+    }
+
+    /// Auxiliary vector entries describing the image (`AT_RANDOM`,
+    /// `AT_EXECFN` and `AT_NULL` are added by `setup_stack`)
+    pub fn auxv(&self) -> [(u64, u64); 6] {
+        [
+            (abi::AT_PHDR, self.phdr),
+            (abi::AT_PHENT, size_of::<Elf64ProgramHeader>() as u64),
+            (abi::AT_PHNUM, self.phnum),
+            (abi::AT_PAGESZ, 4096),
+            (abi::AT_BASE, 0), // No interpreter
+            (abi::AT_ENTRY, self.entry),
+        ]
+    }
+}
+
 // ═══════════════════════════════════════════════════════════════════════════════
 // ELF LOADER
 // ═══════════════════════════════════════════════════════════════════════════════
@@ -101,47 +191,114 @@ impl<'a> ElfLoader<'a> {
             return Err("Not AArch64");
         }
 
-        // Validate Type (Executable)
-        if header.type_ != 2 {
+        // Validate Type (Executable or Position-Independent Executable)
+        if header.type_ != ET_EXEC && header.type_ != ET_DYN {
             return Err("Not an Executable");
         }
 
-        Ok(Self { data, header })
+        // Reject a truncated program header table up front
+        let loader = Self { data, header };
+        loader.program_headers()?.for_each(drop);
+        Ok(loader)
     }
 
-    /// Get the entry point address
+    /// Get the entry point address (link-time; see `LoadedImage::entry`)
     pub fn entry_point(&self) -> u64 {
         self.header.entry
     }
 
-    /// Load segments into a UserAddressSpace
-    pub fn load(&self, vmm: &mut UserAddressSpace) -> Result<(), &'static str> {
+    /// Position-independent executable, loaded at a chosen base
+    pub fn is_pie(&self) -> bool {
+        self.header.type_ == ET_DYN
+    }
+
+    /// The program header table, bounds-checked
+    fn program_headers(&self) -> Result<impl Iterator<Item = &'a Elf64ProgramHeader>, &'static str> {
+        let data = self.data;
         let ph_off = self.header.ph_off as usize;
         let ph_num = self.header.ph_num as usize;
         let ph_ent_size = self.header.ph_ent_size as usize;
 
-        if ph_off + ph_num * ph_ent_size > self.data.len() {
+        if ph_num > 0 && ph_ent_size < size_of::<Elf64ProgramHeader>() {
+            return Err("Program Header entries too small");
+        }
+        let end = ph_num.checked_mul(ph_ent_size).and_then(|len| len.checked_add(ph_off));
+        if end.is_none_or(|end| end > data.len()) {
             return Err("Program Headers out of bounds");
         }
 
-        for i in 0..ph_num {
+        Ok((0..ph_num).map(move |i| {
             let offset = ph_off + i * ph_ent_size;
-            let ph = unsafe { &*(self.data.as_ptr().add(offset) as *const Elf64ProgramHeader) };
+            unsafe { &*(data.as_ptr().add(offset) as *const Elf64ProgramHeader) }
+        }))
+    }
+
+    /// Load segments into a UserAddressSpace, recording a VMA for each
+    ///
+    /// A PIE is placed with its lowest segment at `load_base` and relocated;
+    /// fixed-address images ignore it. Anything else the image needs (its TLS
+    /// block) comes from `vmas.mmap`.
+    pub fn load(&self, vmm: &mut UserAddressSpace, vmas: &mut VmaManager, load_base: u64) -> Result<LoadedImage, &'static str> {
+        let mut image = LoadedImage { phnum: self.header.ph_num as u64, ..LoadedImage::default() };
+
+        if self.is_pie() {
+            let lowest = self.program_headers()?
+                .filter(|ph| ph.type_ == PT_LOAD)
+                .map(|ph| ph.vaddr & !0xFFF)
+                .min()
+                .ok_or("No loadable segments")?;
+            image.bias = load_base.wrapping_sub(lowest);
+        }
+        let bias = image.bias;
+
+        let mut dynamic = None;
+        let mut tls = None;
+        for ph in self.program_headers()? {
+            match ph.type_ {
+                PT_INTERP => return Err("Dynamically linked executables are not supported"),
+                PT_DYNAMIC => dynamic = Some(*ph),
+                PT_TLS => tls = Some(*ph),
+                PT_PHDR => image.phdr = ph.vaddr.wrapping_add(bias),
+                PT_GNU_STACK => image.exec_stack = ph.flags & PF_X != 0,
+                _ => {}
+            }
+        }
 
+        for ph in self.program_headers()? {
             if ph.type_ == PT_LOAD {
-                self.load_segment(ph, vmm)?;
+                self.load_segment(ph, bias, vmm, vmas)?;
             }
         }
 
-        Ok(())
+        // Without PT_PHDR, find the headers in whichever segment holds them
+        if image.phdr == 0 {
+            let ph_off = self.header.ph_off;
+            for ph in self.program_headers()?.filter(|ph| ph.type_ == PT_LOAD) {
+                let end = ph.offset.checked_add(ph.file_size).ok_or("Bad ELF")?;
+                if ph.offset <= ph_off && ph_off < end {
+                    image.phdr = ph.vaddr.checked_add(ph_off - ph.offset).ok_or("Bad ELF")?.wrapping_add(bias);
+                    break;
+                }
+            }
+        }
+
+        if let (true, Some(dynamic)) = (self.is_pie(), dynamic) {
+            relocate(vmm, vmas, dynamic.vaddr.wrapping_add(bias), dynamic.mem_size, bias)?;
+        }
+        if let Some(tls) = tls {
+            image.tp = setup_tls(vmm, vmas, &tls, bias)?;
+        }
+
+        image.entry = self.header.entry.wrapping_add(bias);
+        Ok(image)
     }
 
-    fn load_segment(&self, ph: &Elf64ProgramHeader, vmm: &mut UserAddressSpace) -> Result<(), &'static str> {
+    fn load_segment(&self, ph: &Elf64ProgramHeader, bias: u64, vmm: &mut UserAddressSpace, vmas: &mut VmaManager) -> Result<(), &'static str> {
         if ph.mem_size == 0 {
             return Ok(());
         }
 
-        let vaddr = ph.vaddr;
+        let vaddr = ph.vaddr.wrapping_add(bias);
         let file_size = ph.file_size;
         let mem_size = ph.mem_size;
         let flags = ph.flags;
@@ -156,80 +313,314 @@ impl<'a> ElfLoader<'a> {
         kprintln!("[ELF] Loading Segment: VAddr={:#x}, FileSize={:#x}, MemSize={:#x}, Flags={:#x} ({})",
             vaddr, file_size, mem_size, flags, flag_str);
 
-        // Calculate pages needed
-        // We need to map from vaddr to vaddr + mem_size
-        // We allocate physical pages and copy data.
-
-        let start_addr = ph.vaddr;
-        let end_addr = start_addr + ph.mem_size;
-        
+        let start_addr = vaddr;
+        let end_addr = start_addr.checked_add(ph.mem_size).ok_or("Segment wraps address space")?;
+        if end_addr > USER_LIMIT {
+            return Err("Segment outside user space");
+        }
         let start_page = start_addr & !0xFFF;
         let end_page = (end_addr + 0xFFF) & !0xFFF;
-        let page_count = ((end_page - start_page) / 4096) as usize;
 
-        // Allocate pages
-        // We use specific user page allocation for better accounting/separation
-        let pages_ptr = unsafe { crate::kernel::memory::alloc_user_pages(page_count) }
-            .ok_or("Out of memory for segment")?;
-        
-        let phys_base = pages_ptr.as_ptr() as u64;
-
-        // Zero the memory first (for BSS)
-        unsafe {
-            core::ptr::write_bytes(pages_ptr.as_ptr(), 0, page_count * 4096);
+        let file_offset = ph.offset as usize;
+        if ph.file_size > ph.mem_size || file_offset.saturating_add(ph.file_size as usize) > self.data.len() {
+            return Err("Segment file data out of bounds");
         }
-
-        // Copy file data
-        if ph.file_size > 0 {
-            let file_offset = ph.offset as usize;
-            if file_offset + ph.file_size as usize > self.data.len() {
-                return Err("Segment file data out of bounds");
+        let file_data = &self.data[file_offset..file_offset + ph.file_size as usize];
+
+        let perms = VmaPerms::new(flags & PF_R != 0, flags & PF_W != 0, flags & PF_X != 0);
+
+        // Recorded before mapping so a partially mapped segment is still
+        // released. Segments are page aligned by our linker scripts; an
+        // overlapping VMA keeps the first segment's permissions
+        let _ = vmas.add_vma(VMA::new(
+            start_page,
+            end_page - start_page,
+            perms,
+            VmaFlags { private: true, anonymous: false, fixed: true },
+        ));
+
+        // One tracked frame per page, so fork can share them instead of copying.
+        // We write through the kernel's identity map, so read-only segments
+        // can be mapped read-only for the user straight away.
+        let mut virt = start_page;
+        while virt < end_page {
+            let phys = frames::alloc_zeroed().ok_or("Out of memory for segment")?;
+
+            // Part of the file image that lands in this page (the rest stays zero: BSS)
+            let copy_start = virt.max(start_addr);
+            let copy_end = (virt + 4096).min(start_addr + ph.file_size);
+            if copy_start < copy_end {
+                let src = &file_data[(copy_start - start_addr) as usize..(copy_end - start_addr) as usize];
+                unsafe {
+                    let dest = (phys + (copy_start - virt)) as *mut u8;
+                    core::ptr::copy_nonoverlapping(src.as_ptr(), dest, src.len());
+                }
             }
-            
-            let src = &self.data[file_offset..file_offset + ph.file_size as usize];
-            
-            // Calculate offset within the first page
-            let page_offset = (start_addr & 0xFFF) as usize;
-            
-            unsafe {
-                let dest = pages_ptr.as_ptr().add(page_offset);
-                core::ptr::copy_nonoverlapping(src.as_ptr(), dest, src.len());
+
+            if let Err(e) = vmm.map_user_page(virt, phys, fault::page_flags(perms)) {
+                frames::release(phys);
+                return Err(e);
             }
+            virt += 4096;
         }
 
-        // Map pages
-        // Determine flags
-        let mut flags = EntryFlags::ATTR_NORMAL | EntryFlags::AP_RW_USER | EntryFlags::SH_INNER;
-        if (ph.flags & PF_X) == 0 {
-            flags |= EntryFlags::UXN; // Not executable
-        }
-        if (ph.flags & PF_W) == 0 {
-            // Read-only?
-            // AP_RO_USER = 3 << 6
-            // AP_RW_USER = 1 << 6
-            // We default to RW for now because we just wrote to it!
-            // Ideally we map RW, write data, then remap RO.
-            // But for simplicity, let's keep it RW for now or handle it carefully.
-            // If we map RO now, we can't write to it via the direct map?
-            // Wait, we are writing to the PHYSICAL address via the linear map (identity map in kernel).
-            // The user mapping is separate.
-            // So we CAN map it RO for the user.
-            flags = EntryFlags::ATTR_NORMAL | EntryFlags::AP_RO_USER | EntryFlags::SH_INNER;
-            if (ph.flags & PF_X) == 0 {
-                flags |= EntryFlags::UXN;
-            }
+        Ok(())
+    }
+}
+
+/// End of the user half of the address space (48-bit)
+const USER_LIMIT: u64 = 0x0001_0000_0000_0000;
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// RELOCATION & TLS
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Apply the `DT_RELA` relocations of a PIE loaded with `bias`
+///
+/// A static PIE only needs `R_AARCH64_RELATIVE` (base + addend); anything
+/// that refers to a symbol would need a dynamic linker.
+fn relocate(vmm: &mut UserAddressSpace, vmas: &VmaManager, dynamic: u64, size: u64, bias: u64) -> Result<(), &'static str> {
+    let (mut rela, mut rela_size, mut rela_ent) = (0, 0, size_of::<Elf64Rela>() as u64);
+    for i in 0..size / size_of::<Elf64Dyn>() as u64 {
+        let addr = i.checked_mul(size_of::<Elf64Dyn>() as u64)
+            .and_then(|off| dynamic.checked_add(off))
+            .ok_or("Bad ELF")?;
+        let entry: Elf64Dyn = read_struct(vmm, vmas, addr)?;
+        match entry.tag {
+            DT_NULL => break,
+            DT_RELA => rela = entry.val,
+            DT_RELASZ => rela_size = entry.val,
+            DT_RELAENT => rela_ent = entry.val,
+            _ => {}
         }
+    }
+    if rela == 0 || rela_size == 0 {
+        return Ok(());
+    }
+    if rela_ent < size_of::<Elf64Rela>() as u64 {
+        return Err("Bad relocation entry size");
+    }
 
-        // Map into User VMM
-        let mut curr_virt = start_page;
-        let mut curr_phys = phys_base;
-        
-        for _ in 0..page_count {
-            vmm.map_user(curr_virt, curr_phys, 4096)?;
-            curr_virt += 4096;
-            curr_phys += 4096;
+    let table = rela.wrapping_add(bias);
+    for i in 0..rela_size / rela_ent {
+        let addr = i.checked_mul(rela_ent)
+            .and_then(|off| table.checked_add(off))
+            .ok_or("Bad ELF")?;
+        let reloc: Elf64Rela = read_struct(vmm, vmas, addr)?;
+        match reloc.info as u32 {
+            R_AARCH64_NONE => {}
+            R_AARCH64_RELATIVE => {
+                let value = bias.wrapping_add(reloc.addend as u64);
+                write_mem(vmm, vmas, reloc.offset.wrapping_add(bias), &value.to_le_bytes())?;
+            }
+            _ => return Err("Unsupported relocation type"),
         }
+    }
+    Ok(())
+}
 
-        Ok(())
+/// Give the main thread a TLS block and return its thread pointer
+///
+/// AArch64 uses TLS variant I: TPIDR_EL0 points at a 16-byte TCB, and the
+/// `PT_TLS` image follows at the first offset that suits its alignment.
+/// `.tdata` is copied from the loaded (relocated) image; `.tbss` stays zero.
+fn setup_tls(vmm: &mut UserAddressSpace, vmas: &mut VmaManager, tls: &Elf64ProgramHeader, bias: u64) -> Result<u64, &'static str> {
+    let align = tls.align.max(TCB_SIZE);
+    if !align.is_power_of_two() || align > 4096 || tls.file_size > tls.mem_size {
+        return Err("Bad TLS segment");
+    }
+    let offset = TCB_SIZE.next_multiple_of(align);
+    let size = offset.checked_add(tls.mem_size).ok_or("Bad TLS segment")?;
+
+    let block = vmas.mmap(size, VmaPerms::RW, VmaFlags::DEFAULT).ok_or("No room for TLS")?;
+    fault::populate(vmm, vmas, block, size as usize, Access::Write)?;
+
+    let mut tdata = Vec::new();
+    tdata.try_reserve_exact(tls.file_size as usize).map_err(|_| "TLS image too large")?;
+    tdata.resize(tls.file_size as usize, 0);
+    read_mem(vmm, vmas, tls.vaddr.wrapping_add(bias), &mut tdata)?;
+    write_mem(vmm, vmas, block + offset, &tdata)?;
+    Ok(block)
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// INITIAL STACK
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Build the System V initial stack below `top` and return the stack pointer
+///
+/// The stack VMA must already cover the range; its pages are populated here.
+pub fn setup_stack(
+    vmm: &mut UserAddressSpace,
+    vmas: &VmaManager,
+    top: u64,
+    image: &LoadedImage,
+    argv: &[&str],
+    envp: &[&str],
+    execfn: &str,
+) -> Result<u64, &'static str> {
+    let mut random = [0u8; 16];
+    random[..8].copy_from_slice(&crate::drivers::rng::next_u64().to_le_bytes());
+    random[8..].copy_from_slice(&crate::drivers::rng::next_u64().to_le_bytes());
+
+    let (sp, stack) = initial_stack(top, argv, envp, execfn, random, &image.auxv());
+    if !vmas.find_vma(sp).is_some_and(|vma| vma.end >= top) {
+        return Err("Arguments don't fit on the stack");
+    }
+    fault::populate(vmm, vmas, sp, stack.len(), Access::Write)?;
+    write_mem(vmm, vmas, sp, &stack)?;
+    Ok(sp)
+}
+
+/// Lay out the initial stack that ends at `top`: the returned bytes go at
+/// the returned (16-byte aligned) stack pointer
+///
+/// From `sp` up: argc, the argv pointers and a NULL, the envp pointers and a
+/// NULL, the auxv pairs ending in `AT_NULL`, then the 16 `AT_RANDOM` bytes
+/// and the `AT_EXECFN`, argument and environment strings.
+fn initial_stack(top: u64, argv: &[&str], envp: &[&str], execfn: &str, random: [u8; 16], auxv: &[(u64, u64)]) -> (u64, Vec<u8>) {
+    let mut strings = Vec::new();
+    strings.extend_from_slice(&random);
+    let mut offsets = Vec::new();
+    for s in core::iter::once(&execfn).chain(argv).chain(envp) {
+        offsets.push(strings.len() as u64);
+        strings.extend_from_slice(s.as_bytes());
+        strings.push(0);
+    }
+    let strings_at = (top - strings.len() as u64) & !0xF;
+    let string = |i: usize| strings_at + offsets[i];
+
+    let mut words = Vec::new();
+    words.push(argv.len() as u64);
+    words.extend((0..argv.len()).map(|i| string(1 + i)));
+    words.push(0);
+    words.extend((0..envp.len()).map(|i| string(1 + argv.len() + i)));
+    words.push(0);
+    for &(key, value) in auxv {
+        words.extend([key, value]);
+    }
+    words.extend([abi::AT_RANDOM, strings_at, abi::AT_EXECFN, string(0), abi::AT_NULL, 0]);
+
+    let sp = (strings_at - words.len() as u64 * 8) & !0xF;
+    let mut stack = alloc::vec![0u8; (top - sp) as usize];
+    for (i, word) in words.iter().enumerate() {
+        stack[i * 8..i * 8 + 8].copy_from_slice(&word.to_le_bytes());
+    }
+    let at = (strings_at - sp) as usize;
+    stack[at..at + strings.len()].copy_from_slice(&strings);
+    (sp, stack)
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// IMAGE ACCESS
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Physical address of a byte of the image being built
+///
+/// Only addresses inside a VMA count: the kernel's identity map lives in the
+/// same tables, and a bad relocation must not reach it.
+fn image_phys(vmm: &UserAddressSpace, vmas: &VmaManager, addr: u64) -> Result<u64, &'static str> {
+    if vmas.find_vma(addr).is_none() {
+        return Err("Address outside the image");
+    }
+    vmm.translate(addr).ok_or("Image page not mapped")
+}
+
+/// Copy out of the image at `addr`, page by page
+fn read_mem(vmm: &UserAddressSpace, vmas: &VmaManager, addr: u64, buf: &mut [u8]) -> Result<(), &'static str> {
+    let mut done = 0;
+    while done < buf.len() {
+        let virt = addr.checked_add(done as u64).ok_or("Address outside the image")?;
+        let phys = image_phys(vmm, vmas, virt)?;
+        let n = core::cmp::min(buf.len() - done, (4096 - (virt & 0xFFF)) as usize);
+        unsafe { core::ptr::copy_nonoverlapping(phys as *const u8, buf[done..].as_mut_ptr(), n) };
+        done += n;
+    }
+    Ok(())
+}
+
+/// Copy into the image at `addr`, page by page (pages must be populated)
+fn write_mem(vmm: &UserAddressSpace, vmas: &VmaManager, addr: u64, data: &[u8]) -> Result<(), &'static str> {
+    let mut done = 0;
+    while done < data.len() {
+        let virt = addr.checked_add(done as u64).ok_or("Address outside the image")?;
+        let phys = image_phys(vmm, vmas, virt)?;
+        let n = core::cmp::min(data.len() - done, (4096 - (virt & 0xFFF)) as usize);
+        unsafe { core::ptr::copy_nonoverlapping(data[done..].as_ptr(), phys as *mut u8, n) };
+        done += n;
+    }
+    Ok(())
+}
+
+/// Read a plain-data structure (`Elf64Dyn`, `Elf64Rela`) from the image
+fn read_struct<T: Copy>(vmm: &UserAddressSpace, vmas: &VmaManager, addr: u64) -> Result<T, &'static str> {
+    let mut value = MaybeUninit::<T>::uninit();
+    let bytes = unsafe { core::slice::from_raw_parts_mut(value.as_mut_ptr() as *mut u8, size_of::<T>()) };
+    read_mem(vmm, vmas, addr, bytes)?;
+    // Only used with structs of integers, for which any bytes are valid
+    Ok(unsafe { value.assume_init() })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn word(stack: &[u8], sp: u64, addr: u64) -> u64 {
+        let at = (addr - sp) as usize;
+        u64::from_le_bytes(stack[at..at + 8].try_into().unwrap())
+    }
+
+    fn c_str(stack: &[u8], sp: u64, addr: u64) -> &str {
+        let at = (addr - sp) as usize;
+        let len = stack[at..].iter().position(|&b| b == 0).unwrap();
+        core::str::from_utf8(&stack[at..at + len]).unwrap()
+    }
+
+    #[test]
+    fn test_initial_stack_layout() {
+        let top = 0x7F_0000_0000;
+        let auxv = [(abi::AT_PAGESZ, 4096)];
+        let (sp, stack) = initial_stack(top, &["cat", "file.txt"], &["HOME=/"], "/bin/cat", [7; 16], &auxv);
+
+        assert_eq!(sp & 0xF, 0);
+        assert_eq!(sp + stack.len() as u64, top);
+        assert_eq!(word(&stack, sp, sp), 2); // argc
+        assert_eq!(c_str(&stack, sp, word(&stack, sp, sp + 8)), "cat");
+        assert_eq!(c_str(&stack, sp, word(&stack, sp, sp + 16)), "file.txt");
+        assert_eq!(word(&stack, sp, sp + 24), 0);
+        assert_eq!(c_str(&stack, sp, word(&stack, sp, sp + 32)), "HOME=/");
+        assert_eq!(word(&stack, sp, sp + 40), 0);
+
+        // auxv: ours, then AT_RANDOM, AT_EXECFN, AT_NULL
+        assert_eq!(word(&stack, sp, sp + 48), abi::AT_PAGESZ);
+        assert_eq!(word(&stack, sp, sp + 56), 4096);
+        assert_eq!(word(&stack, sp, sp + 64), abi::AT_RANDOM);
+        let random = (word(&stack, sp, sp + 72) - sp) as usize;
+        assert_eq!(&stack[random..random + 16], &[7; 16]);
+        assert_eq!(word(&stack, sp, sp + 80), abi::AT_EXECFN);
+        assert_eq!(c_str(&stack, sp, word(&stack, sp, sp + 88)), "/bin/cat");
+        assert_eq!(word(&stack, sp, sp + 96), abi::AT_NULL);
+    }
+
+    #[test]
+    fn test_initial_stack_without_arguments() {
+        let (sp, stack) = initial_stack(0x1_0000, &[], &[], "/init", [0; 16], &[]);
+        assert_eq!(sp & 0xF, 0);
+        assert_eq!(word(&stack, sp, sp), 0);
+        assert_eq!(word(&stack, sp, sp + 8), 0);
+        assert_eq!(word(&stack, sp, sp + 16), 0);
+    }
+
+    #[test]
+    fn test_rejects_non_executables() {
+        let mut header = [0u8; size_of::<Elf64Header>()];
+        header[..4].copy_from_slice(&[0x7F, b'E', b'L', b'F']);
+        header[4] = 2; // 64-bit
+        header[5] = 1; // Little endian
+        header[18] = 183; // AArch64
+        header[16] = 1; // ET_REL
+        assert!(ElfLoader::new(&header).is_err());
+        header[16] = ET_DYN as u8;
+        assert!(ElfLoader::new(&header).unwrap().is_pie());
     }
 }
@@ -6,12 +6,14 @@
 
 use crate::arch;
 use crate::drivers;
+use crate::kernel::signal::{self, Signal};
 
 // ═══════════════════════════════════════════════════════════════════════════════
 // EXCEPTION FRAME
 // ═══════════════════════════════════════════════════════════════════════════════
 
 /// Exception frame pushed by the assembly stubs in boot.s
+#[derive(Clone, Copy)]
 #[repr(C)]
 pub struct ExceptionFrame {
     pub x: [u64; 30],       // x0-x29
@@ -161,6 +163,8 @@ pub unsafe extern "C" fn handle_exception(frame: *mut ExceptionFrame) {
     let ec = ExceptionClass::from(frame_ref.esr);
     
     match ec {
+        ExceptionClass::DataAbortSame if handle_page_fault(frame_ref, ec) => return,
+        ExceptionClass::DataAbortSame if unsafe { fixup_user_copy(frame) } => return,
         ExceptionClass::DataAbortLower | ExceptionClass::DataAbortSame => {
             crate::kprintln!();
             crate::kprintln!("╔═══════════════════════════════════════════════════════════╗");
@@ -212,15 +216,16 @@ pub unsafe extern "C" fn handle_exception(frame: *mut ExceptionFrame) {
             let arg2 = frame_mut.x[2];
             let arg3 = frame_mut.x[3];
             
+            // Advance PC to next instruction (SVC is 4 bytes) before dispatch:
+            // exec and sigreturn replace ELR outright, and fork copies the
+            // frame so the child must already point past the SVC
+            frame_mut.elr += 4;
+            
             let ret = crate::kernel::syscall::dispatcher(syscall_num, arg0, arg1, arg2, arg3, frame_mut);
 
-            
             // Set return value
             frame_mut.x[0] = ret;
             
-            // Advance PC to next instruction (SVC is 4 bytes)
-            frame_mut.elr += 4;
-            
             // Return immediately (don't halt)
             return;
         }
@@ -293,17 +298,123 @@ pub unsafe extern "C" fn handle_serror(frame: *mut ExceptionFrame) {
     handle_exception(frame);
 }
 
+/// Signal raised by a synchronous exception taken from EL0, if it is a user fault
+fn fault_signal(ec: ExceptionClass) -> Option<Signal> {
+    match ec {
+        ExceptionClass::DataAbortLower | ExceptionClass::InstrAbortLower => Some(Signal::SIGSEGV),
+        ExceptionClass::PCAlignment | ExceptionClass::SPAlignment => Some(Signal::SIGBUS),
+        ExceptionClass::BRK
+        | ExceptionClass::BreakpointLower
+        | ExceptionClass::SoftwareStepLower
+        | ExceptionClass::WatchpointLower => Some(Signal::SIGTRAP),
+        ExceptionClass::Unknown | ExceptionClass::IllegalState => Some(Signal::SIGILL),
+        _ => None,
+    }
+}
+
+/// Try to resolve a fault on a user address from the agent's VMAs
+///
+/// Covers demand-zero and copy-on-write faults, whether user code or the
+/// kernel (copying to or from user memory in a syscall) took them. Returns
+/// false if the access really is invalid.
+fn handle_page_fault(frame: &ExceptionFrame, ec: ExceptionClass) -> bool {
+    use crate::kernel::memory::fault::{self, Access};
+
+    // Only TTBR0 (user) addresses are demand paged
+    if frame.far >> 48 != 0 {
+        return false;
+    }
+    let iss = frame.esr & 0x1FFFFFF;
+    match DataFaultStatusCode::from(iss) {
+        DataFaultStatusCode::TranslationLevel0
+        | DataFaultStatusCode::TranslationLevel1
+        | DataFaultStatusCode::TranslationLevel2
+        | DataFaultStatusCode::TranslationLevel3
+        | DataFaultStatusCode::PermissionLevel1
+        | DataFaultStatusCode::PermissionLevel2
+        | DataFaultStatusCode::PermissionLevel3 => {}
+        _ => return false,
+    }
+    let access = match ec {
+        ExceptionClass::InstrAbortLower => Access::Execute,
+        _ if (iss >> 6) & 1 == 1 => Access::Write,
+        _ => Access::Read,
+    };
+
+    // A fault taken from EL1 may have interrupted code holding the scheduler
+    // lock; such code must not touch unpopulated user memory, so give up
+    let mut scheduler = match ec {
+        ExceptionClass::DataAbortSame => match crate::kernel::scheduler::SCHEDULER.try_lock() {
+            Some(guard) => guard,
+            None => return false,
+        },
+        _ => crate::kernel::scheduler::SCHEDULER.lock(),
+    };
+    let resolved = scheduler.with_current_process(|agent| {
+        let space = match agent.vmm.as_mut() {
+            Some(space) => space,
+            None => return false,
+        };
+        fault::resolve(space, &agent.vma_manager, frame.far, access).is_ok()
+    }).unwrap_or(false);
+
+    if resolved {
+        crate::profiling::PROFILER.page_faults.fetch_add(1, core::sync::atomic::Ordering::Relaxed);
+    }
+    resolved
+}
+
+/// Fail a syscall's copy to or from a bad user address with EFAULT
+///
+/// Resumes at the copy routine's fixup if the fault was taken inside it;
+/// any other kernel fault is left to panic.
+unsafe fn fixup_user_copy(frame: *mut ExceptionFrame) -> bool {
+    match crate::kernel::memory::uaccess::fixup(unsafe { (*frame).elr }) {
+        Some(pc) => {
+            unsafe { (*frame).elr = pc };
+            true
+        }
+        None => false,
+    }
+}
+
+/// Turn a user fault into a signal on the current agent instead of halting
+fn raise_user_fault(frame: &ExceptionFrame, ec: ExceptionClass, sig: Signal) {
+    let pid = crate::kernel::scheduler::SCHEDULER.lock().current_pid().unwrap_or(0);
+    if ec == ExceptionClass::DataAbortLower {
+        crate::profiling::PROFILER.page_faults.fetch_add(1, core::sync::atomic::Ordering::Relaxed);
+        let iss = frame.esr & 0x1FFFFFF;
+        crate::kprintln!("[SIGNAL] pid {}: {:?} on {} at {:#x} (PC {:#x}, {:?})",
+            pid, sig, if (iss >> 6) & 1 == 1 { "WRITE" } else { "READ" },
+            frame.far, frame.elr, DataFaultStatusCode::from(iss));
+    } else {
+        crate::kprintln!("[SIGNAL] pid {}: {:?} from {:?} at PC {:#x} (FAR {:#x})",
+            pid, sig, ec, frame.elr, frame.far);
+    }
+    signal::force(sig);
+}
+
 /// # Safety
 /// Called from assembly with raw frame pointer.
 #[no_mangle]
 pub unsafe extern "C" fn handle_sync_lower(frame: *mut ExceptionFrame) {
-    handle_exception(frame);
+    let ec = ExceptionClass::from(unsafe { (*frame).esr });
+    match fault_signal(ec) {
+        Some(_) if matches!(ec, ExceptionClass::DataAbortLower | ExceptionClass::InstrAbortLower)
+            && handle_page_fault(unsafe { &*frame }, ec) => {}
+        Some(sig) => raise_user_fault(unsafe { &*frame }, ec, sig),
+        None => handle_exception(frame),
+    }
+    signal::deliver_pending(unsafe { &mut *frame });
 }
 
+/// # Safety
+/// Called from assembly with raw frame pointer.
 #[no_mangle]
-pub extern "C" fn handle_irq_lower(_frame: *mut ExceptionFrame) {
+pub unsafe extern "C" fn handle_irq_lower(frame: *mut ExceptionFrame) {
     // Handle IRQ from Lower EL (EL0) same as EL1
-    handle_irq(_frame);
+    handle_irq(frame);
+    signal::deliver_pending(unsafe { &mut *frame });
 }
 
 // ═══════════════════════════════════════════════════════════════════════════════
@@ -0,0 +1,166 @@
+//! Futexes
+//!
+//! Kernel wait queues named by a user address, for mutexes and condition
+//! variables built in user space: the uncontended path is an atomic on user
+//! memory, and only a thread that has to sleep (or wake a sleeper) enters
+//! the kernel.
+//!
+//! A word in private memory is keyed by its agent and virtual address, so
+//! the threads of one agent meet on it. A word in a `MAP_SHARED` mapping is
+//! keyed by its physical address instead, so agents that map the same
+//! object meet on it wherever they mapped it.
+//!
+//! Waiters sleep with `AgentState::Sleeping`: a timeout or a signal wakes
+//! them just like `sys_sleep`, and they find themselves still queued.
+
+use alloc::collections::{BTreeMap, VecDeque};
+use crate::kernel::memory::fault::{self, Access};
+use crate::kernel::process::{Agent, AgentState};
+use crate::kernel::scheduler::{self, IntentScheduler, SCHEDULER};
+use crate::kernel::sync::SpinLock;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum FutexKey {
+    Private { process: u64, addr: u64 },
+    Shared { phys: u64 },
+}
+
+/// Sleeping thread ids per futex, in arrival order
+///
+/// Lock order: `SCHEDULER`, then `FUTEXES`.
+static FUTEXES: SpinLock<BTreeMap<FutexKey, VecDeque<u64>>> = SpinLock::new(BTreeMap::new());
+
+/// Locate the futex word at `addr` in `agent`'s memory: its key and the
+/// physical address to read it through
+fn lookup(agent: &mut Agent, addr: u64) -> Result<(FutexKey, u64), &'static str> {
+    if addr & 3 != 0 {
+        return Err("Unaligned futex");
+    }
+    let vma = agent.vma_manager.find_vma(addr).ok_or("Futex not mapped")?;
+    if !vma.perms.read {
+        return Err("Futex not readable");
+    }
+    let shared = !vma.flags.private;
+    let space = agent.vmm.as_mut().ok_or("Agent has no user address space")?;
+    fault::populate(space, &agent.vma_manager, addr, 4, Access::Read)?;
+    let phys = space.translate(addr).ok_or("Futex not mapped")?;
+
+    let key = if shared {
+        FutexKey::Shared { phys }
+    } else {
+        FutexKey::Private { process: agent.id.0, addr }
+    };
+    Ok((key, phys))
+}
+
+/// Sleep while the u32 at `addr` holds `expected`
+///
+/// Fails at once if it doesn't, and after `timeout_ms` (0 = never) or on a
+/// signal if no `wake` came first.
+pub fn wait(addr: u64, expected: u32, timeout_ms: u64) -> Result<(), &'static str> {
+    let mut sched = SCHEDULER.lock();
+    let tid = sched.current_pid().ok_or("No current agent")?;
+    let (key, phys) = sched.with_current_process(|agent| lookup(agent, addr))
+        .unwrap_or(Err("No current agent"))?;
+
+    // Checked and queued under the scheduler lock, which `wake` takes
+    // first: a store followed by a wake can't slip in between
+    let value = unsafe { core::ptr::read_volatile(phys as *const u32) };
+    if value != expected {
+        return Err("Futex value changed");
+    }
+    FUTEXES.lock().entry(key).or_default().push_back(tid);
+    sched.with_current_agent(|agent| {
+        agent.state = AgentState::Sleeping;
+        agent.wake_time = match timeout_ms {
+            0 => u64::MAX,
+            ms => crate::drivers::timer::uptime_ms().saturating_add(ms),
+        };
+    });
+    drop(sched);
+
+    // With nothing else Ready, yield comes straight back: keep yielding
+    // until a wake, the timeout or a signal makes this thread runnable
+    loop {
+        scheduler::yield_task();
+        let sleeping = SCHEDULER.lock()
+            .with_current_agent(|agent| agent.state == AgentState::Sleeping)
+            .unwrap_or(false);
+        if !sleeping {
+            break;
+        }
+    }
+
+    // `wake` dequeues whoever it wakes: still queued means timeout or signal
+    let mut futexes = FUTEXES.lock();
+    let queue = match futexes.get_mut(&key) {
+        Some(queue) => queue,
+        None => return Ok(()),
+    };
+    let pos = match queue.iter().position(|&id| id == tid) {
+        Some(pos) => pos,
+        None => return Ok(()),
+    };
+    queue.remove(pos);
+    if queue.is_empty() {
+        futexes.remove(&key);
+    }
+    Err("Futex wait interrupted")
+}
+
+/// Wake up to `count` threads waiting on the futex at `addr`, returning
+/// how many were woken
+pub fn wake(addr: u64, count: u64) -> Result<u64, &'static str> {
+    let mut sched = SCHEDULER.lock();
+    let (key, _) = sched.with_current_process(|agent| lookup(agent, addr))
+        .unwrap_or(Err("No current agent"))?;
+    Ok(wake_key(&mut sched, key, count))
+}
+
+fn wake_key(sched: &mut IntentScheduler, key: FutexKey, count: u64) -> u64 {
+    let mut futexes = FUTEXES.lock();
+    let queue = match futexes.get_mut(&key) {
+        Some(queue) => queue,
+        None => return 0,
+    };
+
+    let mut woken = 0;
+    while woken < count {
+        let tid = match queue.pop_front() {
+            Some(tid) => tid,
+            None => break,
+        };
+        // Threads that died while queued are simply dropped; one already
+        // woken by a signal still sees the wake when it checks the queue
+        if let Some(agent) = sched.get_agent_mut(tid) {
+            if agent.state == AgentState::Sleeping {
+                agent.state = AgentState::Ready;
+                agent.wake_time = 0;
+            }
+            woken += 1;
+        }
+    }
+    if queue.is_empty() {
+        futexes.remove(&key);
+    }
+    woken
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keys_separate_agents_and_addresses() {
+        let a = FutexKey::Private { process: 1, addr: 0x1000 };
+        assert_ne!(a, FutexKey::Private { process: 2, addr: 0x1000 });
+        assert_ne!(a, FutexKey::Private { process: 1, addr: 0x1004 });
+        assert_ne!(a, FutexKey::Shared { phys: 0x1000 });
+    }
+
+    #[test]
+    fn test_wake_without_waiters_wakes_nobody() {
+        let mut sched = IntentScheduler::new();
+        assert_eq!(wake_key(&mut sched, FutexKey::Shared { phys: 0xdead_0000 }, u64::MAX), 0);
+    }
+}
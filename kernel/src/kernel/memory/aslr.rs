@@ -0,0 +1,70 @@
+//! Address Space Layout Randomisation
+//!
+//! Every program image gets its own random bases for a position-independent
+//! executable, the `mmap` area and the stack, so addresses learned from one
+//! agent are useless against another. Each base is chosen a page at a time
+//! inside a fixed window; the windows sit above the kernel's identity map
+//! and its peripherals and never overlap.
+//!
+//! Fixed-address (`ET_EXEC`) images still load at their link address.
+
+use crate::drivers::rng;
+
+/// `ET_DYN` images load somewhere in the 4GB above this
+pub const LOAD_WINDOW: (u64, u64) = (0x20_0000_0000, 1 << 20);
+/// `mmap` searches start somewhere in the 64GB above this
+pub const MMAP_WINDOW: (u64, u64) = (0x40_0000_0000, 1 << 24);
+/// The stack top lies somewhere in the 1GB below this
+pub const STACK_WINDOW: (u64, u64) = (0x80_0000_0000, 1 << 18);
+
+/// Randomised bases for one address space
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Layout {
+    /// Load bias for a position-independent executable
+    pub load_base: u64,
+    /// Where `mmap` starts looking for free space
+    pub mmap_base: u64,
+    /// Top of the initial stack (exclusive)
+    pub stack_top: u64,
+}
+
+impl Layout {
+    /// A fresh layout from the hardware RNG
+    pub fn random() -> Self {
+        Self::from_seeds(rng::next_u64(), rng::next_u64(), rng::next_u64())
+    }
+
+    /// The layout the three random values pick
+    pub fn from_seeds(load: u64, mmap: u64, stack: u64) -> Self {
+        let pick = |(base, pages): (u64, u64), seed: u64| (seed % pages) * 4096 + base;
+        Self {
+            load_base: pick(LOAD_WINDOW, load),
+            mmap_base: pick(MMAP_WINDOW, mmap),
+            stack_top: STACK_WINDOW.0 - (stack % STACK_WINDOW.1) * 4096,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bases_are_page_aligned_and_in_their_windows() {
+        for seed in [0, 1, 0x1234_5678_9abc_def0, u64::MAX] {
+            let layout = Layout::from_seeds(seed, seed, seed);
+            assert_eq!(layout.load_base & 0xFFF, 0);
+            assert_eq!(layout.mmap_base & 0xFFF, 0);
+            assert_eq!(layout.stack_top & 0xFFF, 0);
+            assert!(layout.load_base >= LOAD_WINDOW.0 && layout.load_base < LOAD_WINDOW.0 + LOAD_WINDOW.1 * 4096);
+            assert!(layout.mmap_base >= MMAP_WINDOW.0 && layout.mmap_base < MMAP_WINDOW.0 + MMAP_WINDOW.1 * 4096);
+            assert!(layout.stack_top <= STACK_WINDOW.0 && layout.stack_top > STACK_WINDOW.0 - STACK_WINDOW.1 * 4096);
+        }
+    }
+
+    #[test]
+    fn test_windows_do_not_overlap() {
+        assert!(LOAD_WINDOW.0 + LOAD_WINDOW.1 * 4096 <= MMAP_WINDOW.0);
+        assert!(MMAP_WINDOW.0 + MMAP_WINDOW.1 * 4096 <= STACK_WINDOW.0 - STACK_WINDOW.1 * 4096);
+    }
+}
@@ -0,0 +1,230 @@
+//! User Page Faults
+//!
+//! Resolves faults on user addresses that the agent's VMAs allow:
+//! - demand-zero: the first touch of an anonymous page maps a zeroed frame
+//! - copy-on-write: the first write to a page shared by `fork` gets a
+//!   private copy (or takes the frame back if no one else maps it)
+//! - object pages: a file or shared memory mapping maps the backing
+//!   `MemObject`'s frame (copy-on-write if the mapping is private)
+//! - dirty tracking: a shared file mapping maps pages read-only until they
+//!   are first written, and that write marks the page dirty in the object
+//!
+//! Anything else is a genuine access violation and becomes SIGSEGV. When
+//! the kernel itself takes such a fault while copying user memory, the
+//! copy fails with EFAULT (see `uaccess`).
+
+use super::frames;
+use super::paging::{EntryFlags, UserAddressSpace};
+use super::vma::{VmaManager, VmaPerms, VMA};
+
+/// Kind of access that faulted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    Read,
+    Write,
+    Execute,
+}
+
+/// Page table flags for a user page with the given permissions
+pub fn page_flags(perms: VmaPerms) -> EntryFlags {
+    let mut flags = EntryFlags::ATTR_NORMAL | EntryFlags::SH_INNER | EntryFlags::PXN;
+    flags |= if perms.write { EntryFlags::AP_RW_USER } else { EntryFlags::AP_RO_USER };
+    if !perms.execute {
+        flags |= EntryFlags::UXN;
+    }
+    flags
+}
+
+/// Flags for a page shared copy-on-write (read-only until written)
+fn cow_flags(perms: VmaPerms) -> EntryFlags {
+    page_flags(VmaPerms { write: false, ..perms }) | EntryFlags::COW
+}
+
+/// Resolve a fault at `addr`, mapping or copying the page as needed
+pub fn resolve(space: &mut UserAddressSpace, vmas: &VmaManager, addr: u64, access: Access) -> Result<(), &'static str> {
+    let vma = vmas.find_vma(addr).ok_or("Address not mapped")?;
+    if !vma.perms.allows(access) {
+        return Err("Access violates mapping permissions");
+    }
+
+    let page = addr & !0xFFF;
+    match space.page_entry(page) {
+        None if vma.backing.is_some() => map_object_page(space, vma, page, access),
+        None => {
+            if !vma.flags.anonymous {
+                return Err("Page not present");
+            }
+            let phys = frames::alloc_zeroed().ok_or("Out of memory")?;
+            if let Err(e) = space.map_user_page(page, phys, page_flags(vma.perms)) {
+                frames::release(phys);
+                return Err(e);
+            }
+            Ok(())
+        }
+        Some(entry) if access == Access::Write && entry.flags().contains(EntryFlags::COW) => {
+            break_cow(space, page, entry.address(), vma.perms)
+        }
+        Some(entry) if access == Access::Write && entry.flags().contains(EntryFlags::AP_RO_EL1) && tracks_dirty(vma) => {
+            map_dirty(space, vma, page, entry.address())
+        }
+        // AP[2] set: read-only in the page table although the VMA allows writes
+        Some(entry) if access == Access::Write && entry.flags().contains(EntryFlags::AP_RO_EL1) => {
+            Err("Write to read-only page")
+        }
+        Some(_) => {
+            // Already resolved; the fault came from a stale TLB entry
+            space.flush_page(page);
+            Ok(())
+        }
+    }
+}
+
+/// Map the backing object's frame for `page`
+///
+/// The object keeps its own reference, so a private mapping that writes
+/// always copies and the object's page stays as the file (or region) has it.
+fn map_object_page(space: &mut UserAddressSpace, vma: &VMA, page: u64, access: Access) -> Result<(), &'static str> {
+    let backing = vma.backing.as_ref().ok_or("Page not present")?;
+    let index = vma.object_page(page).ok_or("Page not present")?;
+    let dirty = tracks_dirty(vma) && access == Access::Write;
+    let phys = {
+        let mut object = backing.object.lock();
+        let phys = object.page(index)?;
+        if dirty {
+            object.mark_dirty(index);
+        }
+        phys
+    };
+    frames::share(phys);
+    let flags = if vma.flags.private && vma.perms.write {
+        cow_flags(vma.perms)
+    } else if tracks_dirty(vma) && !dirty {
+        page_flags(VmaPerms { write: false, ..vma.perms })
+    } else {
+        page_flags(vma.perms)
+    };
+    if let Err(e) = space.map_user_page(page, phys, flags) {
+        frames::release(phys);
+        return Err(e);
+    }
+    Ok(())
+}
+
+/// Whether `vma` is a shared, writable file mapping, whose pages are mapped
+/// read-only until written so the page cache knows which ones are dirty
+fn tracks_dirty(vma: &VMA) -> bool {
+    !vma.flags.private && vma.perms.write
+        && vma.backing.as_ref().is_some_and(|backing| backing.file.is_some())
+}
+
+/// Make a clean page of a shared file mapping writable and mark it dirty
+fn map_dirty(space: &mut UserAddressSpace, vma: &VMA, page: u64, phys: u64) -> Result<(), &'static str> {
+    let backing = vma.backing.as_ref().ok_or("Page not present")?;
+    let index = vma.object_page(page).ok_or("Page not present")?;
+    backing.object.lock().mark_dirty(index);
+    space.map_user_page(page, phys, page_flags(vma.perms))?;
+    space.flush_page(page);
+    Ok(())
+}
+
+/// Give the faulting address space a writable page of its own
+fn break_cow(space: &mut UserAddressSpace, page: u64, phys: u64, perms: VmaPerms) -> Result<(), &'static str> {
+    let flags = page_flags(perms);
+    if frames::refcount(phys) <= 1 {
+        // Every other sharer has gone: take the frame back
+        space.map_user_page(page, phys, flags)?;
+    } else {
+        let copy = frames::alloc_copy(phys).ok_or("Out of memory")?;
+        if let Err(e) = space.map_user_page(page, copy, flags) {
+            frames::release(copy);
+            return Err(e);
+        }
+        frames::release(phys);
+    }
+    space.flush_page(page);
+    Ok(())
+}
+
+/// Resolve every page of a user range up front
+///
+/// For kernel code that writes user memory while holding the scheduler lock,
+/// where a fault could not be serviced.
+pub fn populate(space: &mut UserAddressSpace, vmas: &VmaManager, addr: u64, len: usize, access: Access) -> Result<(), &'static str> {
+    let end = addr.checked_add(len as u64).ok_or("Range overflow")?;
+    let mut page = addr & !0xFFF;
+    while page < end {
+        resolve(space, vmas, page, access)?;
+        page += 4096;
+    }
+    Ok(())
+}
+
+/// Share a VMA's pages with a forked child
+///
+/// Writable private pages become read-only copy-on-write in both spaces;
+/// read-only pages and `MAP_SHARED` pages are simply shared. Frames the
+/// table doesn't track are copied eagerly, since their owner may free them.
+pub fn share_for_fork(parent: &mut UserAddressSpace, child: &mut UserAddressSpace, vma: &VMA) -> Result<(), &'static str> {
+    let mut page = vma.start & !0xFFF;
+    while page < vma.end {
+        if let Some(entry) = parent.page_entry(page) {
+            let phys = entry.address();
+            if !frames::share(phys) {
+                let copy = frames::alloc_copy(phys).ok_or("Out of memory for fork")?;
+                child.map_user_page(page, copy, page_flags(vma.perms))?;
+            } else if vma.perms.write && vma.flags.private {
+                parent.map_user_page(page, phys, cow_flags(vma.perms))?;
+                parent.flush_page(page);
+                child.map_user_page(page, phys, cow_flags(vma.perms))?;
+            } else {
+                // Keep a parent's unbroken COW entry read-only in the child too
+                child.map_user_page(page, phys, entry.flags())?;
+            }
+        }
+        page += 4096;
+    }
+    Ok(())
+}
+
+/// Unmap `[start, end)` and drop the frames' references
+pub fn release_range(space: &mut UserAddressSpace, start: u64, end: u64) {
+    let mut page = start & !0xFFF;
+    while page < end {
+        if let Ok(Some(phys)) = space.unmap_page(page) {
+            space.flush_page(page);
+            frames::release(phys);
+        }
+        page += 4096;
+    }
+}
+
+/// Release every user frame mapped by the given VMAs (exec, exit)
+pub fn release_all(space: &mut UserAddressSpace, vmas: &VmaManager) {
+    for vma in &vmas.vmas {
+        release_range(space, vma.start, vma.end);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_page_flags_follow_perms() {
+        let rw = page_flags(VmaPerms::RW);
+        assert!(rw.contains(EntryFlags::AP_RW_USER));
+        assert!(!rw.contains(EntryFlags::AP_RO_EL1));
+        assert!(rw.contains(EntryFlags::UXN));
+
+        let rx = page_flags(VmaPerms::RX);
+        assert!(rx.contains(EntryFlags::AP_RO_USER));
+        assert!(!rx.contains(EntryFlags::UXN));
+    }
+
+    #[test]
+    fn test_cow_flags_are_read_only() {
+        let flags = cow_flags(VmaPerms::RW);
+        assert!(flags.contains(EntryFlags::COW));
+        assert!(flags.contains(EntryFlags::AP_RO_USER));
+    }
+}
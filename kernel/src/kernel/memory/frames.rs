@@ -0,0 +1,143 @@
+//! Physical Frame Table
+//!
+//! Reference counts for user page frames. Every page handed to user space
+//! through `alloc_zeroed` is tracked here, so a frame can be mapped by several
+//! address spaces (copy-on-write after fork, read-only code) and is returned
+//! to the page allocator only when the last mapping goes away.
+//!
+//! Frames that were never registered (legacy stacks owned by a `Stack`) are
+//! ignored by `share`/`release`; their owner frees them.
+
+use alloc::collections::BTreeMap;
+use core::ptr::NonNull;
+use crate::kernel::sync::SpinLock;
+use super::PAGE_SIZE;
+
+/// Reference counts keyed by physical frame address
+pub struct FrameTable {
+    refs: BTreeMap<u64, u32>,
+}
+
+impl FrameTable {
+    pub const fn new() -> Self {
+        Self { refs: BTreeMap::new() }
+    }
+
+    /// Start tracking a freshly allocated frame with one reference
+    pub fn insert(&mut self, phys: u64) {
+        self.refs.insert(phys, 1);
+    }
+
+    /// Add a reference; returns false if the frame isn't tracked
+    pub fn share(&mut self, phys: u64) -> bool {
+        match self.refs.get_mut(&phys) {
+            Some(count) => {
+                *count += 1;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Drop a reference; returns true when it was the last one
+    pub fn release(&mut self, phys: u64) -> bool {
+        match self.refs.get_mut(&phys) {
+            Some(count) if *count > 1 => {
+                *count -= 1;
+                false
+            }
+            Some(_) => {
+                self.refs.remove(&phys);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Current reference count (0 for untracked frames)
+    pub fn refcount(&self, phys: u64) -> u32 {
+        self.refs.get(&phys).copied().unwrap_or(0)
+    }
+
+    /// Number of tracked frames
+    pub fn len(&self) -> usize {
+        self.refs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.refs.is_empty()
+    }
+}
+
+impl Default for FrameTable { fn default() -> Self { Self::new() } }
+
+static FRAMES: SpinLock<FrameTable> = SpinLock::new(FrameTable::new());
+
+/// Allocate a zeroed, tracked user frame
+pub fn alloc_zeroed() -> Option<u64> {
+    let page = unsafe { super::alloc_user_pages(1)? };
+    unsafe { core::ptr::write_bytes(page.as_ptr(), 0, PAGE_SIZE) };
+    let phys = page.as_ptr() as u64;
+    FRAMES.lock().insert(phys);
+    Some(phys)
+}
+
+/// Allocate a tracked user frame holding a copy of `src`
+pub fn alloc_copy(src: u64) -> Option<u64> {
+    let page = unsafe { super::alloc_user_pages(1)? };
+    unsafe { core::ptr::copy_nonoverlapping(src as *const u8, page.as_ptr(), PAGE_SIZE) };
+    let phys = page.as_ptr() as u64;
+    FRAMES.lock().insert(phys);
+    Some(phys)
+}
+
+/// Take another reference to a frame; false if the frame isn't tracked
+pub fn share(phys: u64) -> bool {
+    FRAMES.lock().share(phys)
+}
+
+/// Drop a reference, freeing the frame when it was the last one
+pub fn release(phys: u64) {
+    let last = FRAMES.lock().release(phys);
+    if last {
+        if let Some(ptr) = NonNull::new(phys as *mut u8) {
+            unsafe { super::free_pages(ptr, 1) };
+        }
+    }
+}
+
+/// Current reference count (0 for untracked frames)
+pub fn refcount(phys: u64) -> u32 {
+    FRAMES.lock().refcount(phys)
+}
+
+/// Number of tracked user frames (for /proc/meminfo)
+pub fn tracked() -> usize {
+    FRAMES.lock().len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_refcount_lifecycle() {
+        let mut table = FrameTable::new();
+        table.insert(0x1000);
+        assert_eq!(table.refcount(0x1000), 1);
+        assert!(table.share(0x1000));
+        assert_eq!(table.refcount(0x1000), 2);
+        assert!(!table.release(0x1000));
+        assert!(table.release(0x1000));
+        assert_eq!(table.refcount(0x1000), 0);
+        assert!(table.is_empty());
+    }
+
+    #[test]
+    fn test_untracked_frames_are_ignored() {
+        let mut table = FrameTable::new();
+        assert!(!table.share(0x2000));
+        assert!(!table.release(0x2000));
+        assert_eq!(table.refcount(0x2000), 0);
+    }
+}
@@ -15,6 +15,11 @@ use crate::kernel::scheduler::CoreStats;
 pub mod paging;
 pub mod vma;
 pub mod neural;
+pub mod frames;
+pub mod fault;
+pub mod uaccess;
+pub mod object;
+pub mod aslr;
 
 
 // ...
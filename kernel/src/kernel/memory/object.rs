@@ -0,0 +1,130 @@
+//! Memory Objects
+//!
+//! A set of pages that several VMAs can map at once: the page cache of a
+//! mapped file, a named shared memory region, or an anonymous `MAP_SHARED`
+//! mapping inherited across `fork`. The object holds one frame table
+//! reference per resident page and every mapping of the page takes its own,
+//! so a frame outlives whichever of them goes last.
+//!
+//! File pages also carry a dirty bit, set on the first write through a
+//! shared mapping, so only written pages go back to the file.
+
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::vec::Vec;
+use super::frames;
+
+/// Pages of a mappable object, indexed by page number within the object
+pub struct MemObject {
+    pages: BTreeMap<u64, u64>,
+    /// Resident pages written since they were last written back
+    dirty: BTreeSet<u64>,
+    size: u64,
+}
+
+impl MemObject {
+    /// An object of `size` bytes; pages are zero-filled on first use
+    pub fn new(size: u64) -> Self {
+        Self { pages: BTreeMap::new(), dirty: BTreeSet::new(), size }
+    }
+
+    /// Size in bytes (pages at or past it can't be mapped)
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// Number of pages that may be used
+    pub fn page_count(&self) -> u64 {
+        self.size.div_ceil(4096)
+    }
+
+    /// Physical frame of a page, if resident
+    pub fn resident(&self, index: u64) -> Option<u64> {
+        self.pages.get(&index).copied()
+    }
+
+    /// Physical frame of a page, allocating a zeroed one on first use
+    pub fn page(&mut self, index: u64) -> Result<u64, &'static str> {
+        if index >= self.page_count() {
+            return Err("Beyond end of object");
+        }
+        if let Some(phys) = self.resident(index) {
+            return Ok(phys);
+        }
+        let phys = frames::alloc_zeroed().ok_or("Out of memory")?;
+        self.pages.insert(index, phys);
+        Ok(phys)
+    }
+
+    /// Note that a resident page was written through a mapping
+    pub fn mark_dirty(&mut self, index: u64) {
+        if self.pages.contains_key(&index) {
+            self.dirty.insert(index);
+        }
+    }
+
+    /// Whether a page has been written since it was last written back
+    pub fn is_dirty(&self, index: u64) -> bool {
+        self.dirty.contains(&index)
+    }
+
+    /// Dirty pages in `[first, first + count)` as `(index, frame)`, in order
+    ///
+    /// A page no address space maps any more is marked clean; one that is
+    /// still mapped stays dirty, since its writable mappings can keep
+    /// writing without faulting again.
+    pub fn take_dirty(&mut self, first: u64, count: u64) -> Vec<(u64, u64)> {
+        let indices: Vec<u64> = self.dirty.range(first..first.saturating_add(count)).copied().collect();
+        let mut taken = Vec::with_capacity(indices.len());
+        for index in indices {
+            let phys = self.pages[&index];
+            if frames::refcount(phys) <= 1 {
+                self.dirty.remove(&index);
+            }
+            taken.push((index, phys));
+        }
+        taken
+    }
+
+    /// Number of resident pages
+    pub fn resident_count(&self) -> usize {
+        self.pages.len()
+    }
+}
+
+impl Drop for MemObject {
+    fn drop(&mut self) {
+        for &phys in self.pages.values() {
+            frames::release(phys);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_page_count_rounds_up() {
+        assert_eq!(MemObject::new(0).page_count(), 0);
+        assert_eq!(MemObject::new(1).page_count(), 1);
+        assert_eq!(MemObject::new(4096).page_count(), 1);
+        assert_eq!(MemObject::new(4097).page_count(), 2);
+    }
+
+    #[test]
+    fn test_only_resident_pages_get_dirty() {
+        let mut object = MemObject::new(8192);
+        object.mark_dirty(1);
+        assert!(!object.is_dirty(1));
+        object.page(1).unwrap();
+        object.mark_dirty(1);
+        assert!(object.is_dirty(1));
+    }
+
+    #[test]
+    fn test_page_past_end_is_rejected() {
+        let mut object = MemObject::new(4096);
+        assert!(object.page(1).is_err());
+        assert_eq!(object.resident_count(), 0);
+    }
+}
@@ -48,6 +48,11 @@ impl PageTableEntry {
         self.0 & EntryFlags::TABLE.bits() != 0
     }
 
+    /// Attribute bits (everything but the output address)
+    pub fn flags(&self) -> EntryFlags {
+        EntryFlags(self.0 & !0x0000_FFFF_FFFF_F000)
+    }
+
     /// Get the physical address this entry points to
     /// Mask out the attributes (lower 12 bits) and upper attributes (bits 48+)
     pub fn address(&self) -> u64 {
@@ -144,6 +149,10 @@ impl EntryFlags {
     /// Unprivileged Execute Never (EL0 cannot execute)
     pub const UXN: Self = Self(1 << 54);
 
+    // --- Software bits (ignored by the MMU) ---
+    /// Page is shared copy-on-write: mapped read-only, copied on the first write
+    pub const COW: Self = Self(1 << 55);
+
     pub const fn bits(&self) -> u64 {
         self.0
     }
@@ -338,6 +347,28 @@ impl VMM {
         }
     }
 
+    /// Get the Level 3 page entry for a virtual address
+    ///
+    /// Returns `None` if the page is unmapped or covered by a block mapping.
+    pub fn page_entry(&self, virt_addr: u64) -> Option<PageTableEntry> {
+        let indices = [
+            (virt_addr >> 39) & 0x1FF,
+            (virt_addr >> 30) & 0x1FF,
+            (virt_addr >> 21) & 0x1FF,
+        ];
+
+        unsafe {
+            let mut table = self.root_table.as_ref();
+            for idx in indices {
+                let entry = &table.entries[idx as usize];
+                if !entry.is_valid() || !entry.is_table() { return None; }
+                table = &*(entry.address() as *const PageTable);
+            }
+            let entry = table.entries[((virt_addr >> 12) & 0x1FF) as usize];
+            if entry.is_valid() { Some(entry) } else { None }
+        }
+    }
+
     /// Translate a virtual address to physical address
     pub fn translate(&self, virt_addr: u64) -> Option<u64> {
         let l0_idx = (virt_addr >> 39) & 0x1FF;
@@ -575,6 +606,11 @@ impl UserAddressSpace {
         Ok(())
     }
 
+    /// Map a single user page with explicit permissions (e.g. read-only or non-executable)
+    pub fn map_user_page(&mut self, virt: u64, phys: u64, flags: EntryFlags) -> Result<(), &'static str> {
+        unsafe { self.vmm.map_page(virt, phys, flags | EntryFlags::NG) }
+    }
+
     /// Check if a virtual address is mapped
     pub fn is_mapped(&self, virt_addr: u64) -> bool {
         self.vmm.is_mapped(virt_addr)
@@ -585,6 +621,16 @@ impl UserAddressSpace {
         self.vmm.translate(virt_addr)
     }
 
+    /// Get the page entry mapping a user page, if any
+    pub fn page_entry(&self, virt_addr: u64) -> Option<PageTableEntry> {
+        self.vmm.page_entry(virt_addr)
+    }
+
+    /// Drop any cached translation for a page after changing its entry
+    pub fn flush_page(&self, virt_addr: u64) {
+        unsafe { crate::arch::tlb_invalidate_page(virt_addr, self.asid) }
+    }
+
     /// Unmap a virtual page
     pub fn unmap_page(&mut self, virt_addr: u64) -> Result<Option<u64>, &'static str> {
         unsafe { self.vmm.unmap_page(virt_addr) }
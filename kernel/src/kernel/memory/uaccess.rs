@@ -0,0 +1,106 @@
+//! User Memory Access
+//!
+//! Syscalls copy to and from user memory only through `copy_from_user` and
+//! `copy_to_user`. Both run one small assembly routine; the exception table
+//! below covers exactly its instructions. When a load or store in it takes a
+//! fault that can't be resolved (a bad pointer, or a page unmapped by another
+//! thread after the syscall checked it), the exception handler resumes at the
+//! fixup label instead, and the copy fails with EFAULT. A fault anywhere else
+//! in the kernel still panics.
+
+use core::mem::{size_of, MaybeUninit};
+
+#[cfg(not(feature = "test_mocks"))]
+core::arch::global_asm!(
+    ".section .text.uaccess, \"ax\"",
+    ".global __uaccess_copy",
+    ".global __uaccess_copy_end",
+    ".global __uaccess_fixup",
+    // x0 = dst, x1 = src, x2 = len; returns 0, or 1 if an access faulted
+    "__uaccess_copy:",
+    "cmp x2, #8",
+    "b.lo 2f",
+    "1:",
+    "ldr x3, [x1], #8",
+    "str x3, [x0], #8",
+    "sub x2, x2, #8",
+    "cmp x2, #8",
+    "b.hs 1b",
+    "2:",
+    "cbz x2, 4f",
+    "3:",
+    "ldrb w3, [x1], #1",
+    "strb w3, [x0], #1",
+    "subs x2, x2, #1",
+    "b.ne 3b",
+    "4:",
+    "mov x0, #0",
+    "ret",
+    "__uaccess_copy_end:",
+    "__uaccess_fixup:",
+    "mov x0, #1",
+    "ret",
+    ".previous",
+);
+
+#[cfg(not(feature = "test_mocks"))]
+extern "C" {
+    fn __uaccess_copy(dst: *mut u8, src: *const u8, len: usize) -> u64;
+    static __uaccess_copy_end: u8;
+    static __uaccess_fixup: u8;
+}
+
+#[cfg(not(feature = "test_mocks"))]
+fn copy(dst: *mut u8, src: *const u8, len: usize) -> Result<(), &'static str> {
+    match unsafe { __uaccess_copy(dst, src, len) } {
+        0 => Ok(()),
+        _ => Err("Bad address"), // EFAULT
+    }
+}
+
+#[cfg(feature = "test_mocks")]
+fn copy(dst: *mut u8, src: *const u8, len: usize) -> Result<(), &'static str> {
+    unsafe { core::ptr::copy_nonoverlapping(src, dst, len) };
+    Ok(())
+}
+
+/// Where to resume after an unresolvable kernel fault at `pc`
+///
+/// Only the copy routine's own loads and stores have a fixup; for any
+/// other PC the fault is a kernel bug.
+#[cfg(not(feature = "test_mocks"))]
+pub fn fixup(pc: u64) -> Option<u64> {
+    let start = __uaccess_copy as *const () as u64;
+    let end = &raw const __uaccess_copy_end as u64;
+    (start..end).contains(&pc).then_some(&raw const __uaccess_fixup as u64)
+}
+
+#[cfg(feature = "test_mocks")]
+pub fn fixup(_pc: u64) -> Option<u64> {
+    None
+}
+
+/// Copy `dst.len()` bytes from user address `src`
+pub fn copy_from_user(dst: &mut [u8], src: u64) -> Result<(), &'static str> {
+    copy(dst.as_mut_ptr(), src as *const u8, dst.len())
+}
+
+/// Copy `src` to user address `dst`
+pub fn copy_to_user(dst: u64, src: &[u8]) -> Result<(), &'static str> {
+    copy(dst as *mut u8, src.as_ptr(), src.len())
+}
+
+/// Read a `T` from user address `src` (any alignment)
+///
+/// # Safety
+/// Every bit pattern must be a valid `T` (plain integers and structs of them).
+pub unsafe fn read_user<T: Copy>(src: u64) -> Result<T, &'static str> {
+    let mut value = MaybeUninit::<T>::uninit();
+    copy(value.as_mut_ptr() as *mut u8, src as *const u8, size_of::<T>())?;
+    Ok(unsafe { value.assume_init() })
+}
+
+/// Write `value` to user address `dst` (any alignment)
+pub fn write_user<T: Copy>(dst: u64, value: &T) -> Result<(), &'static str> {
+    copy(dst as *mut u8, value as *const T as *const u8, size_of::<T>())
+}
@@ -2,7 +2,12 @@
 //!
 //! Tracks memory regions for user processes to enforce permissions and security.
 
+use alloc::sync::Arc;
 use alloc::vec::Vec;
+use crate::fs::vfs::FileOps;
+use crate::kernel::sync::SpinLock;
+use super::fault::Access;
+use super::object::MemObject;
 
 /// Memory Permissions
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -21,6 +26,15 @@ impl VmaPerms {
     pub fn new(read: bool, write: bool, execute: bool) -> Self {
         Self { read, write, execute }
     }
+
+    /// Whether these permissions allow an access of the given kind
+    pub fn allows(&self, access: Access) -> bool {
+        match access {
+            Access::Read => self.read,
+            Access::Write => self.write,
+            Access::Execute => self.execute,
+        }
+    }
 }
 
 /// VMA Flags
@@ -35,6 +49,28 @@ impl VmaFlags {
     pub const DEFAULT: Self = Self { private: true, anonymous: true, fixed: false };
 }
 
+/// Pages behind a VMA that isn't plain anonymous private memory
+///
+/// Shared mappings map the object's frames directly; private ones map them
+/// copy-on-write.
+#[derive(Clone)]
+pub struct VmaBacking {
+    pub object: Arc<SpinLock<MemObject>>,
+    /// Byte offset of `VMA::start` within the object (page aligned)
+    pub offset: u64,
+    /// File that `MAP_SHARED` writes go back to (None for memory objects)
+    pub file: Option<Arc<SpinLock<dyn FileOps>>>,
+}
+
+impl core::fmt::Debug for VmaBacking {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("VmaBacking")
+            .field("offset", &self.offset)
+            .field("file", &self.file.is_some())
+            .finish()
+    }
+}
+
 /// Virtual Memory Area
 #[derive(Debug, Clone)]
 pub struct VMA {
@@ -42,6 +78,7 @@ pub struct VMA {
     pub end: u64,
     pub perms: VmaPerms,
     pub flags: VmaFlags,
+    pub backing: Option<VmaBacking>,
 }
 
 impl VMA {
@@ -51,8 +88,25 @@ impl VMA {
             end: start + size,
             perms,
             flags,
+            backing: None,
         }
     }
+
+    /// Map `backing` instead of anonymous memory
+    pub fn with_backing(mut self, backing: VmaBacking) -> Self {
+        self.backing = Some(backing);
+        self
+    }
+
+    /// Backing of the part of this VMA that starts at `addr`
+    fn backing_from(&self, addr: u64) -> Option<VmaBacking> {
+        self.backing.clone().map(|backing| VmaBacking { offset: backing.offset + (addr - self.start), ..backing })
+    }
+
+    /// Page of the backing object that `addr` falls in
+    pub fn object_page(&self, addr: u64) -> Option<u64> {
+        self.backing.as_ref().map(|backing| (backing.offset + (addr - self.start)) / 4096)
+    }
     
     pub fn contains(&self, addr: u64) -> bool {
         addr >= self.start && addr < self.end
@@ -67,12 +121,20 @@ impl VMA {
 #[derive(Debug, Clone)]
 pub struct VmaManager {
     pub vmas: Vec<VMA>,
+    /// Lowest address `mmap` picks on its own (randomised per image)
+    pub mmap_base: u64,
 }
 
 impl VmaManager {
     pub fn new() -> Self {
+        Self::with_mmap_base(0x100000) // Start at 1MB to avoid low memory conflicts
+    }
+
+    /// A manager whose `mmap` allocations start at `mmap_base`
+    pub fn with_mmap_base(mmap_base: u64) -> Self {
         Self {
             vmas: Vec::new(),
+            mmap_base,
         }
     }
     
@@ -121,11 +183,16 @@ impl VmaManager {
     /// Map memory (mmap)
     /// Finds a free region of size `len` and adds a VMA.
     pub fn mmap(&mut self, len: u64, perms: VmaPerms, flags: VmaFlags) -> Option<u64> {
+        self.mmap_backed(len, perms, flags, None)
+    }
+
+    /// Map memory backed by an object (file page cache, shared memory)
+    pub fn mmap_backed(&mut self, len: u64, perms: VmaPerms, flags: VmaFlags, backing: Option<VmaBacking>) -> Option<u64> {
         // Simple allocator: Find first gap
         // User space starts at 0x1000 (skip null page)
         // Ends at 0x0000_7FFF_FFFF_FFFF (User space limit)
         
-        let mut start = 0x100000; // Start at 1MB to avoid low memory conflicts
+        let mut start = self.mmap_base;
         let align = 4096;
         
         // Align length to page size
@@ -134,7 +201,7 @@ impl VmaManager {
         for vma in &self.vmas {
             if start + len <= vma.start {
                 // Found a gap before this VMA
-                let new_vma = VMA::new(start, len, perms, flags);
+                let new_vma = VMA { backing, ..VMA::new(start, len, perms, flags) };
                 if self.add_vma(new_vma) {
                     return Some(start);
                 } else {
@@ -142,13 +209,13 @@ impl VmaManager {
                     return None;
                 }
             }
-            // Move start to end of current VMA (aligned)
-            start = (vma.end + align - 1) & !(align - 1);
+            // Move start to end of current VMA (aligned), never below the base
+            start = start.max((vma.end + align - 1) & !(align - 1));
         }
         
         // Check if fits after last VMA
         if start + len < 0x0000_7FFF_FFFF_FFFF {
-             let new_vma = VMA::new(start, len, perms, flags);
+             let new_vma = VMA { backing, ..VMA::new(start, len, perms, flags) };
              if self.add_vma(new_vma) {
                  return Some(start);
              }
@@ -168,7 +235,11 @@ impl VmaManager {
         // We need to clone the VMA properties to create the return value and potentially the split part
         let original_perms = self.vmas[idx].perms;
         let original_flags = self.vmas[idx].flags;
-        let unmapped_vma = VMA::new(start, len, original_perms, original_flags);
+        let unmapped_vma = VMA {
+            backing: self.vmas[idx].backing_from(start),
+            ..VMA::new(start, len, original_perms, original_flags)
+        };
+        let right_backing = self.vmas[idx].backing_from(end);
         
         let vma = &mut self.vmas[idx];
         
@@ -178,6 +249,7 @@ impl VmaManager {
         } else if vma.start == start {
             // Case 2: Prefix removal - Shrink from start
             vma.start = end;
+            vma.backing = right_backing;
         } else if vma.end == end {
             // Case 3: Suffix removal - Shrink from end
             vma.end = start;
@@ -190,7 +262,10 @@ impl VmaManager {
             vma.end = start; // Shrink current to be left part
             
             // Create right part
-            let right_vma = VMA::new(right_start, right_len, original_perms, original_flags);
+            let right_vma = VMA {
+                backing: right_backing,
+                ..VMA::new(right_start, right_len, original_perms, original_flags)
+            };
             
             // Insert right part after the current one
             self.vmas.insert(idx + 1, right_vma);
@@ -201,3 +276,44 @@ impl VmaManager {
 }
 
 impl Default for VmaManager { fn default() -> Self { Self::new() } }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn backed(start: u64, pages: u64, offset: u64) -> VMA {
+        let object = Arc::new(SpinLock::new(MemObject::new(0)));
+        VMA::new(start, pages * 4096, VmaPerms::RW, VmaFlags { private: false, anonymous: false, fixed: false })
+            .with_backing(VmaBacking { object, offset, file: None })
+    }
+
+    #[test]
+    fn test_munmap_split_keeps_object_offsets() {
+        let mut vmas = VmaManager::new();
+        assert!(vmas.add_vma(backed(0x10_0000, 4, 0x2000)));
+
+        let hole = vmas.munmap(0x10_1000, 0x1000).unwrap();
+        assert_eq!(hole.object_page(0x10_1000), Some(3));
+
+        assert_eq!(vmas.vmas.len(), 2);
+        assert_eq!(vmas.vmas[0].object_page(0x10_0000), Some(2));
+        assert_eq!(vmas.vmas[1].start, 0x10_2000);
+        assert_eq!(vmas.vmas[1].object_page(0x10_2000), Some(4));
+    }
+
+    #[test]
+    fn test_munmap_prefix_advances_offset() {
+        let mut vmas = VmaManager::new();
+        assert!(vmas.add_vma(backed(0x10_0000, 2, 0)));
+        vmas.munmap(0x10_0000, 0x1000).unwrap();
+        assert_eq!(vmas.vmas[0].object_page(0x10_1000), Some(1));
+    }
+
+    #[test]
+    fn test_mmap_starts_at_base_past_lower_vmas() {
+        let mut vmas = VmaManager::with_mmap_base(0x40_0000_0000);
+        assert!(vmas.add_vma(VMA::new(0x40_0000, 0x1000, VmaPerms::RX, VmaFlags::DEFAULT)));
+        assert_eq!(vmas.mmap(0x1000, VmaPerms::RW, VmaFlags::DEFAULT), Some(0x40_0000_0000));
+        assert_eq!(vmas.mmap(0x1000, VmaPerms::RW, VmaFlags::DEFAULT), Some(0x40_0000_1000));
+    }
+}
@@ -17,6 +17,7 @@ pub mod scheduler;
 pub mod syscall;
 pub mod elf;
 pub mod signal;
+pub mod futex;
 pub mod recovery;
 pub mod watchdog;
 
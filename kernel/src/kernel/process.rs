@@ -5,9 +5,12 @@
 
 
 use alloc::vec::Vec;
+use alloc::string::String;
 use core::sync::atomic::{AtomicU64, Ordering};
 use crate::kernel::memory::paging::UserAddressSpace;
 use crate::kernel::memory::{Stack, alloc_stack};
+use crate::kernel::memory::fault::{self, Access};
+use crate::kernel::memory::vma::{VmaManager, VmaFlags, VmaPerms, VMA};
 use crate::kernel::capability::Capability;
 use crate::fs::vfs::ProcessFileTable;
 use crate::kernel::signal::SigAction;
@@ -39,13 +42,16 @@ pub enum AgentState {
     Running,
     Blocked,
     Sleeping,
+    /// Stopped by SIGSTOP/SIGTSTP until SIGCONT
+    Stopped,
     Terminated,
 }
 
 /// CPU Context (Callee-saved registers)
 /// This matches the layout expected by `switch_to` in assembly.
 /// CRITICAL: Field order must match assembly offsets exactly!
-/// Assembly stores: x19-x28 at 0-72, x29 at 80, x30/LR at 88, SP at 96, TTBR0 at 104
+/// Assembly stores: x19-x28 at 0-72, x29 at 80, x30/LR at 88, SP at 96, TTBR0 at 104,
+/// SP_EL0 at 112, TPIDR_EL0 at 120
 #[repr(C)]
 #[derive(Debug, Default)]
 pub struct Context {
@@ -63,6 +69,8 @@ pub struct Context {
     pub lr: u64,    // Link Register x30 (offset 88) - stored by "stp x29, x30, [x0, #80]"
     pub sp: u64,    // Stack Pointer (offset 96) - stored by "str x9, [x0, #96]"
     pub ttbr0: u64, // Page Table Base (offset 104)
+    pub sp_el0: u64,    // User Stack Pointer (offset 112)
+    pub tpidr_el0: u64, // User Thread Pointer / TLS (offset 120)
 }
 
 /// Agent Control Block
@@ -87,6 +95,15 @@ pub struct Agent {
     pub cpu_cycles: u64,
     pub last_scheduled: u64,
     pub mailbox: SpinLock<VecDeque<Message>>,
+    /// Current working directory (canonical absolute path)
+    pub cwd: String,
+    /// For a user thread, the agent whose address space, files, capabilities
+    /// and signal state it shares (None for an agent's main thread)
+    pub leader: Option<u64>,
+    /// Value passed to `sys_thread_exit`, collected by `sys_join`
+    pub exit_value: u64,
+    /// User stack the kernel reserved for this thread, as `(start, end)`
+    pub thread_stack: Option<(u64, u64)>,
 }
 
 impl Agent {
@@ -112,6 +129,10 @@ impl Agent {
             cpu_cycles: 0,
             last_scheduled: 0,
             mailbox: SpinLock::new(VecDeque::new()),
+            cwd: String::from("/"),
+            leader: None,
+            exit_value: 0,
+            thread_stack: None,
         };
 
         // GRANT DRIVER CAPABILITY TO KERNEL THREADS BY DEFAULT
@@ -205,6 +226,10 @@ impl Agent {
             cpu_cycles: 0,
             last_scheduled: 0,
             mailbox: SpinLock::new(VecDeque::new()),
+            cwd: String::from("/"),
+            leader: None,
+            exit_value: 0,
+            thread_stack: None,
         };
 
         // Kernel Stack Setup (for when we are in kernel mode handling this process)
@@ -243,46 +268,24 @@ impl Agent {
         Ok(agent)
     }
 
-    /// Create a new user agent from ELF binary
-    pub fn new_user_elf(elf_data: &[u8]) -> Result<Self, &'static str> {
-        // 1. Parse ELF
-        let loader = crate::kernel::elf::ElfLoader::new(elf_data)?;
-        
-        // 2. Create Address Space
-        let mut space = UserAddressSpace::new().ok_or("Failed to create user address space")?;
-        
-        // 3. Load Segments
-        loader.load(&mut space)?;
-        
-        // 4. Allocate Stacks
-        let kernel_stack = alloc_stack(4).ok_or("Failed to alloc kernel stack")?;
-        let user_stack = alloc_stack(4).ok_or("Failed to alloc user stack")?;
-        
-        // 5. Map User Stack
-        // We map it at a fixed high address for the user?
-        // Or just identity map the allocated pages?
-        // Let's map it at 0x0000_FFFF_FFFF_F000 (Top of user space - 4KB)
-        // Stack grows down.
-        // User Stack Size = 16KB (4 pages)
-        // Top = 0x0000_FFFF_FFFF_F000
-        // Bottom = Top - 16KB = 0x0000_FFFF_FFFB_F000
-        
-        // Wait, our VMM map_user takes virt, phys, size.
-        // user_stack.ptr points to the physical pages (identity mapped in kernel).
-        // The first page is the guard page.
-        // user_stack.bottom is the start of usable memory.
-        
-        let ustack_phys_start = user_stack.bottom; // Physical address of bottom of stack
-        let ustack_size = (user_stack.top - user_stack.bottom) as usize;
-        
-        // Let's pick a virtual address for the stack top.
-        // Move to lower memory to avoid potential 48-bit boundary issues
-        let ustack_virt_top = 0x2000_0000;
-        let ustack_virt_bottom = ustack_virt_top - ustack_size as u64;
-        
-        space.map_user(ustack_virt_bottom, ustack_phys_start, ustack_size)?;
+    /// Create a new user agent from ELF binary, started with `argv`
+    pub fn new_user_elf(elf_data: &[u8], argv: &[&str]) -> Result<Self, &'static str> {
+        // 1-3. Load the image and build its stack (randomised layout)
+        let execfn = argv.first().copied().unwrap_or("");
+        let program = load_program(elf_data, argv, &[], execfn)?;
+        let ProgramImage { space, vmas: vma_manager, entry, sp, tp } = program;
+        
+        // 4. Allocate Kernel Stack
+        let kernel_stack = match alloc_stack(4) {
+            Some(stack) => stack,
+            None => {
+                let mut space = space;
+                fault::release_all(&mut space, &vma_manager);
+                return Err("Failed to alloc kernel stack");
+            }
+        };
         
-        // 6. Create Agent
+        // 5. Create Agent
         let mut agent = Agent {
             id: AgentId::new(),
             state: AgentState::Ready,
@@ -290,59 +293,39 @@ impl Agent {
             capabilities: Vec::new(),
             vmm: Some(space),
             kernel_stack,
-            user_stack: Some(user_stack),
+            user_stack: None, // Frames are owned by the address space
             file_table: ProcessFileTable::new(),
             wake_time: 0,
             sig_actions: [SigAction::default(); 32],
-            vma_manager: crate::kernel::memory::vma::VmaManager::new(),
+            vma_manager,
             pending_signals: 0,
             blocked_signals: 0,
             parent_id: None,
             cpu_cycles: 0,
             last_scheduled: 0,
             mailbox: SpinLock::new(VecDeque::new()),
+            cwd: String::from("/"),
+            leader: None,
+            exit_value: 0,
+            thread_stack: None,
         };
 
         // Kernel Stack Setup
         let kstack_top = agent.kernel_stack.top & !0xF;
         agent.context.sp = kstack_top;
 
-        // User Stack Setup (Virtual Address)
-        let ustack_top_virt = ustack_virt_top & !0xF;
-
         // Trampoline Setup
         agent.context.lr = user_trampoline as *const () as u64;
-        agent.context.x19 = loader.entry_point(); // Entry point
-        agent.context.x20 = ustack_top_virt;      // User Stack (Virtual)
-        agent.context.x21 = 0;                    // Arg (argc/argv ptr?)
+        agent.context.x19 = entry;  // Entry point
+        agent.context.x20 = sp;     // User Stack: argc, argv, envp, auxv
+        agent.context.x21 = 0;      // x0 = 0, as for exec
+        agent.context.tpidr_el0 = tp;
 
         // Set TTBR0 (with ASID)
         let vmm = agent.vmm.as_ref().expect("VMM must exist for ELF process");
         agent.context.ttbr0 = vmm.table_base() | ((vmm.asid() as u64) << 48);
 
-        // 7. Initialize VMAs
-        use crate::kernel::memory::vma::{VMA, VmaPerms, VmaFlags};
-        
-        // Stack VMA (RW)
-        let stack_vma = VMA::new(
-            ustack_virt_bottom,
-            ustack_size as u64,
-            VmaPerms::RW,
-            VmaFlags { private: true, anonymous: true, fixed: true }
-        );
-        let _ = agent.vma_manager.add_vma(stack_vma);
-        
-        // Code VMA (RX) - For now, just map the entry page
-        let entry_page = loader.entry_point() & !0xFFF;
-        let code_vma = VMA::new(
-            entry_page,
-            4096,
-            VmaPerms::RX,
-            VmaFlags { private: true, anonymous: false, fixed: true }
-        );
-        let _ = agent.vma_manager.add_vma(code_vma);
-
-        // 8. Initialize Standard File Descriptors (0, 1, 2) to Console
+        // 7. Initialize Standard File Descriptors (0, 1, 2) to Console
         // This allows init to read/write to UART.
         use crate::fs::console::ConsoleFile;
         use crate::fs::vfs;
@@ -357,7 +340,7 @@ impl Agent {
         // FD 2: Stderr
         let _ = agent.file_table.alloc_fd(console.clone(), vfs::O_WRONLY);
 
-        // 9. Grant Driver Capability (Temporary for shell access)
+        // 8. Grant Driver Capability (Temporary for shell access)
         unsafe {
              if let Some(cap) = crate::kernel::capability::mint_root(
                  crate::kernel::capability::CapabilityType::Driver, 
@@ -369,48 +352,43 @@ impl Agent {
              }
         }
 
+        // 9. Grant Memory Capability (shared memory objects; inherited by
+        // the services init forks)
+        unsafe {
+             if let Some(cap) = crate::kernel::capability::mint_root(
+                 crate::kernel::capability::CapabilityType::Memory,
+                 0,
+                 0,
+                 crate::kernel::capability::Permissions::ALL
+             ) {
+                 agent.capabilities.push(cap);
+             }
+        }
+
         Ok(agent)
     }
 
 
-    pub fn fork(&self, frame: &crate::kernel::exception::ExceptionFrame, sp_el0: u64) -> Result<Self, &'static str> {
+    pub fn fork(&mut self, frame: &crate::kernel::exception::ExceptionFrame, sp_el0: u64) -> Result<Self, &'static str> {
         // 1. Create new Address Space
         let mut space = UserAddressSpace::new().ok_or("Failed to create user address space")?;
         
-        // 2. Deep Copy Memory (VMAs)
-        // Iterate over all VMAs and copy data
+        // 2. Share Memory Copy-on-Write (VMAs)
+        // Pages are copied only when parent or child first writes to them
+        let parent_space = self.vmm.as_mut().ok_or("VMM required for fork")?;
         for vma in &self.vma_manager.vmas {
-            // Check permissions (must be readable to copy)
-            if !vma.perms.read { continue; }
-            
-            let mut virt = vma.start;
-            while virt < vma.end {
-                // Get physical address of current page
-                if let Some(old_phys) = self.vmm.as_ref().expect("VMM required for fork").translate(virt) {
-                    // Allocate new page
-                    let new_page = unsafe { crate::kernel::memory::alloc_pages(1) }.ok_or("Out of memory for fork")?;
-                    let new_phys = new_page.as_ptr() as u64;
-                    
-                    // Copy data
-                    unsafe {
-                        core::ptr::copy_nonoverlapping(old_phys as *const u8, new_phys as *mut u8, 4096);
-                    }
-                    
-                    // Map new page in new VMM
-                    // Use same flags as VMA (approximate)
-                    // We need EntryFlags.
-                    // VMA perms -> EntryFlags
-                    use crate::kernel::memory::paging::EntryFlags;
-                    let mut flags = EntryFlags::ATTR_NORMAL | EntryFlags::SH_INNER | EntryFlags::AF;
-                    if vma.perms.write { flags |= EntryFlags::AP_RW_USER; } else { flags |= EntryFlags::AP_RO_USER; }
-                    if !vma.perms.execute { flags |= EntryFlags::UXN; }
-                    
-                    space.map_user(virt, new_phys, 4096).map_err(|_| "Failed to map forked page")?;
-                }
-                virt += 4096;
+            if let Err(e) = fault::share_for_fork(parent_space, &mut space, vma) {
+                fault::release_all(&mut space, &self.vma_manager);
+                return Err(e);
             }
         }
-        
+
+        // The sigreturn trampoline isn't a VMA; the child may be forked from
+        // inside a signal handler and still needs it to return
+        if parent_space.is_mapped(crate::kernel::signal::TRAMPOLINE_ADDR) {
+            crate::kernel::signal::map_trampoline(&mut space)?;
+        }
+
         // 3. Allocate Kernel Stack
         let kernel_stack = alloc_stack(4).ok_or("Failed to alloc kernel stack")?;
         
@@ -453,6 +431,10 @@ impl Agent {
             cpu_cycles: 0,
             last_scheduled: 0,
             mailbox: SpinLock::new(VecDeque::new()),
+            cwd: self.cwd.clone(),
+            leader: None,
+            exit_value: 0,
+            thread_stack: None,
         };
         
         // Clone File Table (dup)
@@ -469,99 +451,115 @@ impl Agent {
         let vmm = agent.vmm.as_ref().expect("VMM required for fork child");
         agent.context.ttbr0 = vmm.table_base() | ((vmm.asid() as u64) << 48);
 
+        // The child continues the forking thread, TLS pointer included
+        unsafe {
+            core::arch::asm!("mrs {}, tpidr_el0", out(reg) agent.context.tpidr_el0);
+        }
+
+        Ok(agent)
+    }
+
+    /// Create a user thread of this agent
+    ///
+    /// The thread has its own kernel stack, user stack and TLS pointer, and
+    /// shares everything else with `self`: page table, VMAs, files,
+    /// capabilities and signal state all stay on this agent and are reached
+    /// through `leader`.
+    pub fn new_thread(&self, entry: u64, stack_top: u64, arg: u64, tls: u64) -> Result<Self, &'static str> {
+        let vmm = self.vmm.as_ref().ok_or("Threads need a user address space")?;
+        let ttbr0 = vmm.table_base() | ((vmm.asid() as u64) << 48);
+        let kernel_stack = alloc_stack(4).ok_or("Failed to alloc kernel stack")?;
+
+        let mut agent = Agent {
+            id: AgentId::new(),
+            state: AgentState::Ready,
+            context: Context::default(),
+            capabilities: Vec::new(),
+            vmm: None,
+            kernel_stack,
+            user_stack: None,
+            file_table: ProcessFileTable::new(),
+            wake_time: 0,
+            sig_actions: [SigAction::default(); 32],
+            vma_manager: VmaManager::new(),
+            pending_signals: 0,
+            blocked_signals: 0,
+            parent_id: None,
+            cpu_cycles: 0,
+            last_scheduled: 0,
+            mailbox: SpinLock::new(VecDeque::new()),
+            cwd: String::new(),
+            leader: Some(self.id.0),
+            exit_value: 0,
+            thread_stack: None,
+        };
+
+        // Enter user mode through the same trampoline as a new agent
+        agent.context.sp = agent.kernel_stack.top & !0xF;
+        agent.context.lr = user_trampoline as *const () as u64;
+        agent.context.x19 = entry;
+        agent.context.x20 = stack_top & !0xF;
+        agent.context.x21 = arg;
+        agent.context.ttbr0 = ttbr0;
+        agent.context.tpidr_el0 = tls;
+
         Ok(agent)
     }
 
     /// Execute a new program (replace current process)
-    pub fn exec(&mut self, path: &str, frame: &mut crate::kernel::exception::ExceptionFrame) -> Result<(), &'static str> {
+    pub fn exec(&mut self, path: &str, argv: &[&str], envp: &[&str], frame: &mut crate::kernel::exception::ExceptionFrame) -> Result<(), &'static str> {
         // 1. Read file from VFS
-        // We need to read the whole file into a buffer.
-        // For this prototype, we'll use a fixed size buffer on the heap (Vec).
-        // Limit: 1MB for now.
+        // The whole file goes into a heap buffer; only available memory
+        // limits its size.
         
-        let file = crate::fs::VFS.lock().open(path, crate::fs::O_RDONLY).map_err(|_| "File not found")?;
+        let path = crate::fs::vfs::normalize_path(&self.cwd, path);
+        let file = crate::fs::VFS.lock().open(&path, crate::fs::O_RDONLY).map_err(|_| "File not found")?;
         let mut file_lock = file.lock();
         let size = file_lock.seek(crate::fs::SeekFrom::End(0)).map_err(|_| "Seek failed")? as usize;
         file_lock.seek(crate::fs::SeekFrom::Start(0)).map_err(|_| "Seek failed")?;
         
-        if size > 1024 * 1024 {
-            return Err("Executable too large");
-        }
-        
-        let mut buffer = alloc::vec![0u8; size];
-        let read = file_lock.read(&mut buffer).map_err(|_| "Read failed")?;
-        if read != size {
-            return Err("Incomplete read");
+        let mut buffer = Vec::new();
+        buffer.try_reserve_exact(size).map_err(|_| "Executable too large")?;
+        buffer.resize(size, 0);
+        let mut read = 0;
+        while read < size {
+            let n = file_lock.read(&mut buffer[read..]).map_err(|_| "Read failed")?;
+            if n == 0 {
+                return Err("Incomplete read");
+            }
+            read += n;
         }
         drop(file_lock);
         
-        // 2. Parse ELF
-        let loader = crate::kernel::elf::ElfLoader::new(&buffer)?;
-        
-        // 3. Create NEW Address Space
-        let mut new_space = UserAddressSpace::new().ok_or("Failed to create user address space")?;
-        
-        // 4. Load Segments into NEW Space
-        loader.load(&mut new_space)?;
-        
-        // 5. Allocate NEW User Stack
-        let new_user_stack = alloc_stack(4).ok_or("Failed to alloc user stack")?;
-        
-        // 6. Map User Stack
-        let ustack_phys_start = new_user_stack.bottom;
-        let ustack_size = (new_user_stack.top - new_user_stack.bottom) as usize;
-        let ustack_virt_top = 0x0000_FFFF_FFFF_0000;
-        let ustack_virt_bottom = ustack_virt_top - ustack_size as u64;
+        // 2-5. Load into a NEW Address Space with a NEW User Stack
+        let ProgramImage { space: new_space, vmas: new_vma_manager, entry, sp, tp } =
+            load_program(&buffer, argv, envp, &path)?;
         
-        new_space.map_user(ustack_virt_bottom, ustack_phys_start, ustack_size).map_err(|_| "Failed to map user stack")?;
-        
-        // 7. Setup VMAs
-        use crate::kernel::memory::vma::{VMA, VmaPerms, VmaFlags};
-        let mut new_vma_manager = crate::kernel::memory::vma::VmaManager::new();
-        
-        // Stack VMA
-        let stack_vma = VMA::new(
-            ustack_virt_bottom,
-            ustack_size as u64,
-            VmaPerms::RW,
-            VmaFlags { private: true, anonymous: true, fixed: true }
-        );
-        let _ = new_vma_manager.add_vma(stack_vma);
-        
-        // Code VMA (Entry point page)
-        let entry_page = loader.entry_point() & !0xFFF;
-        let code_vma = VMA::new(
-            entry_page,
-            4096,
-            VmaPerms::RX,
-            VmaFlags { private: true, anonymous: false, fixed: true }
-        );
-        let _ = new_vma_manager.add_vma(code_vma);
-        
-        // 8. Commit Changes (Point of no return)
-        // Drop old resources
-        self.vmm = Some(new_space);
-        self.user_stack = Some(new_user_stack);
-        self.vma_manager = new_vma_manager;
+        // 6. Commit Changes (Point of no return)
+        // The old image's frames are released once TTBR0 has moved off it
+        let old_space = self.vmm.replace(new_space);
+        let old_vmas = core::mem::replace(&mut self.vma_manager, new_vma_manager);
+        self.user_stack = None;
         
         // Reset signals?
         self.sig_actions = [SigAction::default(); 32];
         self.pending_signals = 0;
         
-        // 9. Update Exception Frame
+        // 7. Update Exception Frame
         // We are modifying the frame that will be restored upon return from syscall.
-        frame.elr = loader.entry_point();
-        // frame.sp_el0 = ustack_virt_top & !0xF; // Not in frame
+        // Arguments are on the new stack; no register carries over
+        frame.x = [0; 30];
+        frame.x30 = 0;
+        frame.elr = entry;
+        // frame.sp_el0 = sp; // Not in frame
         
-        // Update SP_EL0 directly
-        let new_sp = ustack_virt_top & !0xF;
+        // Update SP_EL0 and the thread pointer directly
         unsafe {
-             core::arch::asm!("msr sp_el0, {}", in(reg) new_sp);
+             core::arch::asm!("msr sp_el0, {}", in(reg) sp);
+             core::arch::asm!("msr tpidr_el0, {}", in(reg) tp);
         }
         
         frame.spsr = 0; // EL0t
-        frame.x[0] = 0; // argc?
-        frame.x[1] = 0; // argv?
         
         // Update TTBR0 in Context (though context is saved on stack, 
         // switch_to uses the one in Agent struct? No, switch_to saves/restores from struct.
@@ -589,9 +587,36 @@ impl Agent {
         let vmm = self.vmm.as_ref().expect("VMM must exist after exec");
         self.context.ttbr0 = vmm.table_base() | ((vmm.asid() as u64) << 48);
         
+        if let Some(mut old_space) = old_space {
+            fault::release_all(&mut old_space, &old_vmas);
+        }
+        
         Ok(())
     }
 
+    /// Check that a user page may be accessed the given way: covered by a
+    /// VMA that allows it (demand-paged memory is populated by the fault
+    /// handler on first touch)
+    pub fn is_user_page(&self, addr: u64, access: Access) -> bool {
+        self.vma_manager.find_vma(addr).is_some_and(|vma| vma.perms.allows(access))
+    }
+
+    /// `is_user_page` for every page of `[addr, addr + len)`
+    pub fn is_user_range(&self, addr: u64, len: usize, access: Access) -> bool {
+        let end = match addr.checked_add(len as u64) {
+            Some(end) => end,
+            None => return false,
+        };
+        let mut page = addr & !0xFFF;
+        while page < end {
+            if !self.is_user_page(page, access) {
+                return false;
+            }
+            page += 4096;
+        }
+        true
+    }
+
     /// Check if agent has a specific capability
     pub fn has_capability(&self, cap_type: crate::kernel::capability::CapabilityType) -> bool {
         for cap in &self.capabilities {
@@ -604,6 +629,59 @@ impl Agent {
 }
 
 
+impl Drop for Agent {
+    fn drop(&mut self) {
+        // Drop this agent's references to its user frames; pages still
+        // shared copy-on-write with a relative stay mapped there
+        if let Some(space) = self.vmm.as_mut() {
+            fault::release_all(space, &self.vma_manager);
+        }
+    }
+}
+
+/// User stack size for ELF agents (256KB, demand-paged)
+const USER_STACK_SIZE: u64 = 256 * 1024;
+
+/// A freshly loaded program, ready to enter
+struct ProgramImage {
+    space: UserAddressSpace,
+    vmas: VmaManager,
+    entry: u64,
+    sp: u64,
+    tp: u64,
+}
+
+/// Load an ELF image into a new address space with a randomised layout and
+/// build its initial stack
+fn load_program(elf_data: &[u8], argv: &[&str], envp: &[&str], execfn: &str) -> Result<ProgramImage, &'static str> {
+    let loader = crate::kernel::elf::ElfLoader::new(elf_data)?;
+    let layout = crate::kernel::memory::aslr::Layout::random();
+    let mut space = UserAddressSpace::new().ok_or("Failed to create user address space")?;
+    let mut vmas = VmaManager::with_mmap_base(layout.mmap_base);
+
+    let loaded = loader.load(&mut space, &mut vmas, layout.load_base).and_then(|image| {
+        map_user_stack(&mut vmas, layout.stack_top, USER_STACK_SIZE, image.stack_perms())?;
+        let sp = crate::kernel::elf::setup_stack(&mut space, &vmas, layout.stack_top, &image, argv, envp, execfn)?;
+        Ok((image, sp))
+    });
+    match loaded {
+        Ok((image, sp)) => Ok(ProgramImage { space, vmas, entry: image.entry, sp, tp: image.tp }),
+        Err(e) => {
+            fault::release_all(&mut space, &vmas);
+            Err(e)
+        }
+    }
+}
+
+/// Reserve a demand-paged user stack of `size` bytes just below `top`
+fn map_user_stack(vmas: &mut VmaManager, top: u64, size: u64, perms: VmaPerms) -> Result<(), &'static str> {
+    let vma = VMA::new(top - size, size, perms, VmaFlags { private: true, anonymous: true, fixed: true });
+    if !vmas.add_vma(vma) {
+        return Err("User stack overlaps a mapping");
+    }
+    Ok(())
+}
+
 /// Trampoline to jump to userspace
 /// 
 /// Called when `switch_to` returns for a user process.
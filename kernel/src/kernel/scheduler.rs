@@ -4,8 +4,9 @@
 
 use alloc::collections::vec_deque::VecDeque;
 use alloc::boxed::Box;
+use crate::kernel::memory::vma::{VmaFlags, VmaPerms};
 use crate::kernel::process::{Agent, AgentState, Context, Message};
-use crate::kernel::sync::SpinLock;
+use crate::kernel::sync::{SpinLock, WaitQueue};
 use core::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
 
 /// Current PIDs running on each core (for deadlock detection)
@@ -98,6 +99,17 @@ impl IntentScheduler {
         self.current_agent_id
     }
 
+    /// The agent whose resources `id` uses: its leader for a user thread,
+    /// `id` itself otherwise
+    pub fn process_of(&self, id: u64) -> u64 {
+        self.get_agent(id).and_then(|agent| agent.leader).unwrap_or(id)
+    }
+
+    /// Process id of the current agent (the leader's id for a thread)
+    pub fn current_process_id(&self) -> Option<u64> {
+        self.current_agent_id.map(|id| self.process_of(id))
+    }
+
     /// Spawn a new kernel agent (simple, no embedding)
     pub fn spawn_simple(&mut self, entry: fn()) -> Result<(), &'static str> {
         let agent = Agent::new_kernel_simple(entry)?;
@@ -134,8 +146,8 @@ impl IntentScheduler {
     }
 
     /// Spawn a new user agent from ELF binary
-    pub fn spawn_user_elf(&mut self, elf_data: &[u8]) -> Result<u64, &'static str> {
-        let mut agent = Agent::new_user_elf(elf_data)?;
+    pub fn spawn_user_elf(&mut self, elf_data: &[u8], argv: &[&str]) -> Result<u64, &'static str> {
+        let mut agent = Agent::new_user_elf(elf_data, argv)?;
         agent.parent_id = self.current_agent_id; // Set parent
         let pid = agent.id.0;
         self.agents.push_back(Box::new(agent));
@@ -155,6 +167,115 @@ impl IntentScheduler {
         Ok(child_id)
     }
 
+    /// Create a user thread in the current agent
+    ///
+    /// With `stack_top == 0` the kernel reserves a demand-paged stack of
+    /// `THREAD_STACK_SIZE` bytes, unmapped again when the thread is joined.
+    pub fn spawn_thread(&mut self, entry: u64, stack_top: u64, arg: u64, tls: u64) -> Result<u64, &'static str> {
+        let process = self.current_process_id().ok_or("No current agent")?;
+        let leader = self.get_agent_mut(process).ok_or("Agent not found")?;
+
+        let reserved = if stack_top == 0 {
+            let start = leader.vma_manager.mmap(THREAD_STACK_SIZE, VmaPerms::RW, VmaFlags::DEFAULT)
+                .ok_or("No room for a thread stack")?;
+            Some((start, start + THREAD_STACK_SIZE))
+        } else {
+            None
+        };
+        let stack_top = reserved.map_or(stack_top, |(_, end)| end);
+
+        let mut thread = match leader.new_thread(entry, stack_top, arg, tls) {
+            Ok(thread) => thread,
+            Err(e) => {
+                if let Some(range) = reserved {
+                    release_thread_stack(leader, range);
+                }
+                return Err(e);
+            }
+        };
+        thread.thread_stack = reserved;
+        let tid = thread.id.0;
+        self.agents.push_back(Box::new(thread));
+        Ok(tid)
+    }
+
+    /// Number of threads the agent `process` runs besides its main thread
+    pub fn thread_count(&self, process: u64) -> usize {
+        self.agents().filter(|agent| agent.leader == Some(process) && agent.state != AgentState::Terminated).count()
+    }
+
+    /// Finish the current thread with `value`, waking threads blocked in
+    /// `join_thread`; the caller must then yield
+    pub fn exit_thread(&mut self, value: u64) {
+        let id = match self.current_agent_id {
+            Some(id) => id,
+            None => return,
+        };
+        let process = self.process_of(id);
+        if let Some(agent) = self.get_agent_mut(id) {
+            agent.exit_value = value;
+            agent.state = AgentState::Terminated;
+        }
+        self.wake_blocked(process);
+    }
+
+    /// Collect the exit value of thread `tid` of the current agent
+    ///
+    /// Returns `Ok(None)` after blocking the caller if the thread is still
+    /// running; the caller should yield and try again.
+    pub fn join_thread(&mut self, tid: u64) -> Result<Option<u64>, &'static str> {
+        let current = self.current_agent_id.ok_or("No current agent")?;
+        let process = self.process_of(current);
+        if tid == current || tid == process {
+            return Err("Can't join this thread");
+        }
+        let idx = self.agents.iter().position(|a| a.id.0 == tid && a.leader == Some(process))
+            .ok_or("Thread not found")?;
+
+        if self.agents[idx].state != AgentState::Terminated {
+            if let Some(agent) = self.get_agent_mut(current) {
+                agent.state = AgentState::Blocked;
+            }
+            return Ok(None);
+        }
+
+        let thread = self.agents.remove(idx).expect("index from position");
+        if let (Some(range), Some(leader)) = (thread.thread_stack, self.get_agent_mut(process)) {
+            release_thread_stack(leader, range);
+        }
+        Ok(Some(thread.exit_value))
+    }
+
+    /// Make the Blocked threads of agent `process` Ready, so waits on
+    /// group-wide events (child exit, thread exit) re-check their condition
+    fn wake_blocked(&mut self, process: u64) {
+        for agent in self.agents.iter_mut() {
+            if (agent.id.0 == process || agent.leader == Some(process)) && agent.state == AgentState::Blocked {
+                agent.state = AgentState::Ready;
+            }
+        }
+    }
+
+    /// Remove finished threads whose agent is gone: nobody is left to join
+    /// them. The current agent (front of the queue) is still on its kernel
+    /// stack and waits for the next pass.
+    fn reap_orphan_threads(&mut self) {
+        let mut i = 1;
+        while i < self.agents.len() {
+            let orphan = match self.agents[i].leader {
+                Some(leader) if self.agents[i].state == AgentState::Terminated => {
+                    self.get_agent(leader).is_none_or(|l| l.state == AgentState::Terminated)
+                }
+                _ => false,
+            };
+            if orphan {
+                self.agents.remove(i);
+            } else {
+                i += 1;
+            }
+        }
+    }
+
     /// Wait for a child process to terminate
     /// 
     /// If `target_pid` is Some(pid), waits for that specific child.
@@ -187,8 +308,10 @@ impl IntentScheduler {
         
         if has_children {
             // Block current task - it will be woken when child exits
-            if let Some(agent) = self.get_agent_mut(parent_id) {
-                agent.state = AgentState::Blocked;
+            if let Some(id) = self.current_agent_id {
+                if let Some(agent) = self.get_agent_mut(id) {
+                    agent.state = AgentState::Blocked;
+                }
             }
             Ok(None) // Indicates blocking, caller should yield
         } else {
@@ -201,23 +324,29 @@ impl IntentScheduler {
     }
 
     /// Exit current agent and wake parent
+    ///
+    /// Called from any thread, this ends the whole agent: its other threads
+    /// are removed, and the leader stays as a zombie for `wait`.
     pub fn exit_current(&mut self, _code: i32) {
         let mut parent_id = None;
         
         if let Some(id) = self.current_agent_id {
+             let process = self.process_of(id);
+             // The calling thread is still on its kernel stack: mark it and
+             // let the reaper remove it once we've switched away
+             self.agents.retain(|agent| agent.leader != Some(process) || agent.id.0 == id);
              if let Some(agent) = self.get_agent_mut(id) {
                  agent.state = AgentState::Terminated;
+             }
+             if let Some(agent) = self.get_agent_mut(process) {
+                 agent.state = AgentState::Terminated;
                  parent_id = agent.parent_id;
              }
         }
         
-        // Wake parent
+        // Wake parent (any of its threads may be in wait)
         if let Some(pid) = parent_id {
-            if let Some(parent) = self.get_agent_mut(pid) {
-                if parent.state == AgentState::Blocked {
-                    parent.state = AgentState::Ready;
-                }
-            }
+            self.wake_blocked(pid);
         }
     }
 
@@ -228,6 +357,10 @@ impl IntentScheduler {
     pub fn schedule(&mut self) -> Option<(*mut Context, *const Context)> {
         self.update_queue_stats(); // Update stats
         
+        // Agents woken by wait queues since the last switch
+        crate::kernel::sync::wait_queue::apply_pending_wakeups(self);
+        self.reap_orphan_threads();
+        
         if self.agents.is_empty() {
             return None;
         }
@@ -344,6 +477,34 @@ impl IntentScheduler {
         None
     }
 
+    /// Execute a closure with mutable access to the current agent's
+    /// process: the agent itself, or its leader when called from a thread
+    ///
+    /// Use this for anything threads share (address space, VMAs, files,
+    /// capabilities, signals); `with_current_agent` for per-thread state.
+    pub fn with_current_process<F, R>(&mut self, f: F) -> Option<R>
+    where
+        F: FnOnce(&mut Agent) -> R,
+    {
+        let id = self.current_process_id()?;
+        self.get_agent_mut(id).map(f)
+    }
+
+    /// The user threads of agent `process` (not including the agent itself)
+    pub fn threads_mut(&mut self, process: u64) -> impl Iterator<Item = &mut Agent> {
+        self.agents.iter_mut().map(|agent| &mut **agent).filter(move |agent| agent.leader == Some(process))
+    }
+
+    /// Iterate over all agents, running and queued
+    pub fn agents(&self) -> impl Iterator<Item = &Agent> {
+        self.agents.iter().map(|agent| &**agent)
+    }
+
+    /// Find an agent by ID (read-only)
+    pub fn get_agent(&self, id: u64) -> Option<&Agent> {
+        self.agents().find(|agent| agent.id.0 == id)
+    }
+
     /// Find an agent by ID
     pub fn get_agent_mut(&mut self, id: u64) -> Option<&mut Agent> {
         for agent in self.agents.iter_mut() {
@@ -368,6 +529,10 @@ impl IntentScheduler {
         if let Some(idx) = found_idx {
             // If it's the current task, we can't just remove it without context switch.
             // Mark it as Terminated so it gets cleaned up on next schedule.
+            // Its threads go with it
+            for thread in self.threads_mut(id) {
+                thread.state = AgentState::Terminated;
+            }
             if self.current_agent_id == Some(id) {
                 self.agents[idx].state = AgentState::Terminated;
                 // We should probably yield here? But this function returns.
@@ -394,12 +559,8 @@ impl IntentScheduler {
             mailbox.push_back(msg);
             drop(mailbox); // Unlock immediately
             
-            // Wake up if sleeping (simplified: wake if ANY sleep, real impl should check if sleeping for MSG)
-            // Ideally we should have AgentState::WaitingForMessage
-            if agent.state == AgentState::Sleeping {
-                agent.state = AgentState::Ready;
-                agent.wake_time = 0; // Cancel sleep timeout
-            }
+            // Wake receivers blocked in sys_ipc_recv (they re-check their own mailbox)
+            MAILBOX_WAIT.wake_all_locked(self);
             Ok(())
         } else {
             Err("Target process not found")
@@ -409,6 +570,20 @@ impl IntentScheduler {
 
 pub static SCHEDULER: SpinLock<IntentScheduler> = SpinLock::new(IntentScheduler::new());
 
+/// Size of the user stack the kernel reserves for a thread (64KB)
+pub const THREAD_STACK_SIZE: u64 = 64 * 1024;
+
+/// Unmap a kernel-reserved thread stack from `leader`'s address space
+fn release_thread_stack(leader: &mut Agent, (start, end): (u64, u64)) {
+    leader.vma_manager.munmap(start, end - start);
+    if let Some(vmm) = leader.vmm.as_mut() {
+        crate::kernel::memory::fault::release_range(vmm, start, end);
+    }
+}
+
+/// Agents blocked waiting for an IPC message
+pub static MAILBOX_WAIT: WaitQueue = WaitQueue::new();
+
 /// Called on every timer interrupt (e.g., 10ms)
 pub fn tick() {
     // Re-arm timer for next tick (10ms = 10,000us)
@@ -442,9 +617,18 @@ pub fn tick() {
         crate::kprintln!("[NEURAL] tick={} uptime={}ms decay_active=true propagate_active=true", ticks, now);
     }
 
+    // Network RX pump: deliver received frames to the protocol stack
+    crate::net::interface::poll();
+
     // TCP retransmission check every 100ms (10 ticks)
     if ticks % 10 == 0 {
+        crate::net::arp::tick();
+        crate::net::ipv4::tick();
+        crate::net::ipv6::tick();
+        crate::net::dhcp::tick();
+        crate::net::dns::tick();
         crate::net::tcp_tick();
+        crate::net::capture::tick();
     }
 
     // ═════════════════════════════════════════════════════════════════════════════════
@@ -464,6 +648,10 @@ pub fn tick() {
         }
     }
 
+    // Pollers re-check each tick: some sources (UARTs) have no wait queue,
+    // and sys_poll timeouts are measured against uptime
+    crate::kernel::sync::POLL_WAIT.wake_all();
+
     let mut scheduler = SCHEDULER.lock();
     
     // 1. Wake up sleeping agents
@@ -543,6 +731,30 @@ mod tests {
         let mut scheduler = IntentScheduler::new();
         assert!(scheduler.schedule().is_none());
     }
+
+    #[test]
+    fn test_orphan_threads_are_reaped() {
+        let mut scheduler = IntentScheduler::new();
+        scheduler.spawn_simple(dummy_task).unwrap();
+        scheduler.spawn_simple(dummy_task).unwrap();
+        let leader = scheduler.agents[0].id.0;
+        let thread = scheduler.agents[1].id.0;
+        scheduler.agents[1].leader = Some(leader);
+
+        assert_eq!(scheduler.process_of(thread), leader);
+        assert_eq!(scheduler.process_of(leader), leader);
+        assert_eq!(scheduler.thread_count(leader), 1);
+
+        // A finished thread stays joinable while its agent lives...
+        scheduler.agents[1].state = AgentState::Terminated;
+        scheduler.reap_orphan_threads();
+        assert!(scheduler.get_agent(thread).is_some());
+
+        // ...and goes with it
+        scheduler.agents[0].state = AgentState::Terminated;
+        scheduler.reap_orphan_threads();
+        assert!(scheduler.get_agent(thread).is_none());
+    }
 }
 
 // Public helpers for main loop
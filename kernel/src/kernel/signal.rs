@@ -1,6 +1,25 @@
-//! Signal Definitions
+//! Signal Definitions and Delivery
 //!
 //! Based on Linux signal numbers.
+//!
+//! `sys_kill` and faults mark signals pending on an agent; they are acted on
+//! when that agent next returns to EL0 (`deliver_pending`, called at the end
+//! of the lower-EL exception handlers). A caught signal gets a `SignalFrame`
+//! pushed on the user stack and the handler is entered with its return
+//! address pointing at a shared trampoline page that calls `SYS_SIGRETURN`.
+//!
+//! Signal state (actions, pending and blocked sets) belongs to the agent and
+//! is shared by its threads: whichever thread next returns to EL0 takes the
+//! signal, and stop signals park every thread.
+
+use core::mem::size_of;
+use crate::kernel::exception::ExceptionFrame;
+use crate::kernel::memory::fault::{self, Access};
+use crate::kernel::memory::paging::{EntryFlags, UserAddressSpace};
+use crate::kernel::memory::uaccess;
+use crate::kernel::process::{Agent, AgentState};
+use crate::kernel::scheduler::{self, IntentScheduler, SCHEDULER};
+use crate::kernel::sync::SpinLock;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(i32)]
@@ -55,6 +74,39 @@ impl Signal {
             _ => None,
         }
     }
+
+    /// Bit for this signal in `pending_signals` / `blocked_signals`
+    pub const fn bit(self) -> u32 {
+        1 << self as u32
+    }
+
+    /// What happens when the signal arrives with `SIG_DFL` installed
+    pub fn default_disposition(self) -> Disposition {
+        match self {
+            Signal::SIGCHLD => Disposition::Ignore,
+            Signal::SIGCONT => Disposition::Continue,
+            Signal::SIGSTOP | Signal::SIGTSTP | Signal::SIGTTIN | Signal::SIGTTOU => Disposition::Stop,
+            _ => Disposition::Terminate,
+        }
+    }
+}
+
+/// Signals that can be neither caught, blocked nor ignored
+pub const UNBLOCKABLE: u32 = Signal::SIGKILL.bit() | Signal::SIGSTOP.bit();
+
+/// Signals that stop an agent by default
+const STOP_SIGNALS: u32 = Signal::SIGSTOP.bit() | Signal::SIGTSTP.bit()
+    | Signal::SIGTTIN.bit() | Signal::SIGTTOU.bit();
+
+/// How a delivered signal is handled
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Disposition {
+    Terminate,
+    Ignore,
+    Stop,
+    Continue,
+    /// Run the user handler at this address
+    Catch(u64),
 }
 
 /// Signal Action
@@ -78,3 +130,390 @@ impl Default for SigAction {
         }
     }
 }
+
+pub use intent_abi::{SA_NODEFER, SA_RESETHAND};
+
+/// Resolve the installed action for `sig`
+pub fn disposition(sig: Signal, action: &SigAction) -> Disposition {
+    if sig.bit() & UNBLOCKABLE != 0 {
+        return sig.default_disposition();
+    }
+    match action.handler {
+        SIG_DFL => sig.default_disposition(),
+        SIG_IGN => Disposition::Ignore,
+        handler => Disposition::Catch(handler),
+    }
+}
+
+/// Lowest-numbered pending signal that is not blocked
+pub fn next_deliverable(pending: u32, blocked: u32) -> Option<Signal> {
+    let mut ready = pending & !(blocked & !UNBLOCKABLE);
+    while ready != 0 {
+        if let Some(sig) = Signal::from_i32(ready.trailing_zeros() as i32) {
+            return Some(sig);
+        }
+        ready &= ready - 1;
+    }
+    None
+}
+
+/// Mark `sig` pending on `agent`, waking it if the signal needs attention
+///
+/// SIGCONT resumes a stopped agent and discards pending stop signals, a stop
+/// signal discards a pending SIGCONT, SIGKILL wakes the agent from any sleep
+/// or stop so it can die, and any unblocked signal cuts a blocking wait
+/// short (EINTR).
+pub fn post(agent: &mut Agent, sig: Signal) {
+    match sig {
+        Signal::SIGCONT => agent.pending_signals &= !STOP_SIGNALS,
+        _ if sig.bit() & STOP_SIGNALS != 0 => {
+            agent.pending_signals &= !Signal::SIGCONT.bit();
+        }
+        _ => {}
+    }
+    agent.pending_signals |= sig.bit();
+    wake(agent, sig);
+}
+
+/// Post `sig` to the agent that `pid` (an agent or one of its threads)
+/// belongs to, waking its threads as `post` wakes the agent
+pub fn post_to_process(scheduler: &mut IntentScheduler, pid: u64, sig: Signal) -> Result<(), &'static str> {
+    let process = scheduler.process_of(pid);
+    post(scheduler.get_agent_mut(process).ok_or("No such process")?, sig);
+    for thread in scheduler.threads_mut(process) {
+        wake(thread, sig);
+    }
+    Ok(())
+}
+
+/// Make `agent` runnable if `sig` needs its attention
+fn wake(agent: &mut Agent, sig: Signal) {
+    if agent.state == AgentState::Stopped && matches!(sig, Signal::SIGCONT | Signal::SIGKILL) {
+        agent.state = AgentState::Ready;
+    }
+    if agent.state == AgentState::Sleeping {
+        agent.state = AgentState::Ready;
+        agent.wake_time = 0;
+    }
+    // The wait loop re-checks its condition and `interrupted()`
+    if agent.state == AgentState::Blocked && sig.bit() & agent.blocked_signals & !UNBLOCKABLE == 0 {
+        agent.state = AgentState::Ready;
+    }
+}
+
+/// Whether a pending signal should cut the current agent's blocking
+/// syscall short (EINTR): one that is unblocked and will run a handler or
+/// terminate the agent
+pub fn interrupted() -> bool {
+    let mut scheduler = SCHEDULER.lock();
+    scheduler.with_current_process(|agent| {
+        let mut ready = agent.pending_signals;
+        while let Some(sig) = next_deliverable(ready, agent.blocked_signals) {
+            if matches!(disposition(sig, &agent.sig_actions[sig as usize]), Disposition::Terminate | Disposition::Catch(_)) {
+                return true;
+            }
+            ready &= !sig.bit();
+        }
+        false
+    }).unwrap_or(false)
+}
+
+/// Raise a synchronous signal (fault) on the current agent
+///
+/// A fault cannot be deferred or ignored: if the agent blocks or ignores it,
+/// the block is lifted and the default action restored, as on Linux.
+pub fn force(sig: Signal) {
+    let mut scheduler = SCHEDULER.lock();
+    scheduler.with_current_process(|agent| {
+        agent.blocked_signals &= !sig.bit();
+        if agent.sig_actions[sig as usize].handler == SIG_IGN {
+            agent.sig_actions[sig as usize] = SigAction::default();
+        }
+        post(agent, sig);
+    });
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// USER HANDLER FRAMES
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Context saved on the user stack while a handler runs
+///
+/// The handler receives its address in x2 and may inspect (or, carefully,
+/// modify) the interrupted registers before returning.
+#[derive(Clone, Copy)]
+#[repr(C)]
+pub struct SignalFrame {
+    /// Registers at the point the signal interrupted the agent
+    pub regs: ExceptionFrame,
+    /// Interrupted user stack pointer
+    pub sp: u64,
+    /// Blocked mask to restore on return
+    pub blocked: u64,
+    pub signo: u64,
+    pub magic: u64,
+}
+
+const SIGFRAME_MAGIC: u64 = 0x5349_4746_5241_4D45; // "SIGFRAME"
+
+/// Fixed user address of the sigreturn trampoline (just above the stack)
+pub const TRAMPOLINE_ADDR: u64 = 0x0000_FFFF_FFFF_F000;
+
+/// `mov x8, #SYS_SIGRETURN; svc #0`
+const TRAMPOLINE_CODE: [u32; 2] = [
+    0xD280_0008 | ((intent_abi::SYS_SIGRETURN as u32) << 5),
+    0xD400_0001,
+];
+
+/// SPSR bits a handler may change: only the NZCV condition flags. Anything
+/// else (mode, interrupt masks) would let sigreturn escalate privilege.
+const SPSR_USER_MASK: u64 = 0xF000_0000;
+
+/// Physical page holding the trampoline, shared read-only by every agent
+static TRAMPOLINE_PAGE: SpinLock<Option<u64>> = SpinLock::new(None);
+
+fn trampoline_page() -> Result<u64, &'static str> {
+    let mut page = TRAMPOLINE_PAGE.lock();
+    if let Some(phys) = *page {
+        return Ok(phys);
+    }
+    let ptr = unsafe { crate::kernel::memory::alloc_pages(1) }.ok_or("Out of memory for trampoline")?;
+    let phys = ptr.as_ptr() as u64;
+    unsafe {
+        core::ptr::write_bytes(ptr.as_ptr(), 0, 4096);
+        core::ptr::copy_nonoverlapping(TRAMPOLINE_CODE.as_ptr(), phys as *mut u32, TRAMPOLINE_CODE.len());
+        // Make the new instructions visible to instruction fetch
+        core::arch::asm!(
+            "dc cvau, {0}",
+            "dsb ish",
+            "ic ivau, {0}",
+            "dsb ish",
+            "isb",
+            in(reg) phys,
+        );
+    }
+    *page = Some(phys);
+    Ok(phys)
+}
+
+/// Map the trampoline into `space` (read-only, user-executable) if needed
+pub fn map_trampoline(space: &mut UserAddressSpace) -> Result<(), &'static str> {
+    if space.is_mapped(TRAMPOLINE_ADDR) {
+        return Ok(());
+    }
+    let phys = trampoline_page()?;
+    space.map_user_page(
+        TRAMPOLINE_ADDR,
+        phys,
+        EntryFlags::ATTR_NORMAL | EntryFlags::AP_RO_USER | EntryFlags::SH_INNER | EntryFlags::PXN,
+    )
+}
+
+fn read_sp_el0() -> u64 {
+    let sp: u64;
+    unsafe { core::arch::asm!("mrs {}, sp_el0", out(reg) sp) };
+    sp
+}
+
+fn write_sp_el0(sp: u64) {
+    unsafe { core::arch::asm!("msr sp_el0, {}", in(reg) sp) };
+}
+
+/// Push a `SignalFrame` and redirect `frame` into the handler
+///
+/// On entry to the handler: x0 = signal number, x1 = 0 (no siginfo),
+/// x2 = signal frame, x30 = trampoline, SP = signal frame.
+fn enter_handler(agent: &mut Agent, frame: &mut ExceptionFrame, sig: Signal, handler: u64) -> Result<(), &'static str> {
+    let space = agent.vmm.as_mut().ok_or("Agent has no user address space")?;
+    map_trampoline(space)?;
+
+    let sp = read_sp_el0();
+    let addr = sp.checked_sub(size_of::<SignalFrame>() as u64).ok_or("Signal stack overflow")? & !0xF;
+    // The scheduler lock is held, so the frame's pages can't be faulted in
+    // (or copy-on-write broken) by the write below: resolve them now
+    fault::populate(space, &agent.vma_manager, addr, size_of::<SignalFrame>(), Access::Write)
+        .map_err(|_| "Signal stack overflow")?;
+
+    let saved = SignalFrame {
+        regs: *frame,
+        sp,
+        blocked: agent.blocked_signals as u64,
+        signo: sig as u64,
+        magic: SIGFRAME_MAGIC,
+    };
+    uaccess::write_user(addr, &saved).map_err(|_| "Signal stack overflow")?;
+
+    frame.x[0] = sig as u64;
+    frame.x[1] = 0;
+    frame.x[2] = addr;
+    frame.x30 = TRAMPOLINE_ADDR;
+    frame.elr = handler;
+    write_sp_el0(addr);
+
+    let action = agent.sig_actions[sig as usize];
+    agent.blocked_signals |= action.mask as u32;
+    if action.flags & SA_NODEFER == 0 {
+        agent.blocked_signals |= sig.bit();
+    }
+    agent.blocked_signals &= !UNBLOCKABLE;
+    if action.flags & SA_RESETHAND != 0 {
+        agent.sig_actions[sig as usize] = SigAction::default();
+    }
+    Ok(())
+}
+
+/// Restore the context saved by `enter_handler` (SYS_SIGRETURN)
+///
+/// The signal frame is expected at the current user stack pointer, which is
+/// where the trampoline finds it once the handler has returned.
+pub fn sigreturn(frame: &mut ExceptionFrame) -> Result<(), &'static str> {
+    let addr = read_sp_el0();
+    if addr & 0xF != 0 {
+        return Err("Bad signal frame");
+    }
+    // Read before taking the scheduler lock, so the frame's pages can fault in
+    let saved: SignalFrame = unsafe { uaccess::read_user(addr) }.map_err(|_| "Bad signal frame")?;
+    if saved.magic != SIGFRAME_MAGIC {
+        return Err("Bad signal frame");
+    }
+
+    let mut scheduler = SCHEDULER.lock();
+    scheduler.with_current_process(|agent| {
+        *frame = saved.regs;
+        frame.spsr = saved.regs.spsr & SPSR_USER_MASK;
+        write_sp_el0(saved.sp);
+        agent.blocked_signals = saved.blocked as u32 & !UNBLOCKABLE;
+        Ok(())
+    }).unwrap_or(Err("No current agent"))
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// DELIVERY
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Act on the current agent's pending, unblocked signals before it returns to EL0
+///
+/// Ignored and continue signals are consumed, a stop signal parks the agent
+/// until SIGCONT, a fatal one terminates it, and the first caught signal
+/// redirects `frame` into its handler (the rest wait for the next return).
+pub fn deliver_pending(frame: &mut ExceptionFrame) {
+    loop {
+        let mut scheduler = SCHEDULER.lock();
+        let next = scheduler.with_current_process(|agent| {
+            let sig = next_deliverable(agent.pending_signals, agent.blocked_signals)?;
+            agent.pending_signals &= !sig.bit();
+            let disp = disposition(sig, &agent.sig_actions[sig as usize]);
+            let result = match disp {
+                Disposition::Catch(handler) => enter_handler(agent, frame, sig, handler),
+                _ => Ok(()),
+            };
+            Some((agent.id.0, sig, disp, result))
+        }).flatten();
+        drop(scheduler);
+
+        let (pid, sig, disp, result) = match next {
+            Some(next) => next,
+            None => return,
+        };
+
+        match disp {
+            Disposition::Ignore | Disposition::Continue => {}
+            Disposition::Terminate => terminate(pid, sig),
+            Disposition::Stop => stop(pid, sig),
+            Disposition::Catch(_) => match result {
+                Ok(()) => return,
+                Err(e) => {
+                    // The handler can't run; like Linux, the agent dies of SIGSEGV
+                    crate::kprintln!("[SIGNAL] pid {}: cannot deliver {:?}: {}", pid, sig, e);
+                    terminate(pid, Signal::SIGSEGV);
+                }
+            },
+        }
+    }
+}
+
+fn terminate(pid: u64, sig: Signal) -> ! {
+    crate::kprintln!("[SIGNAL] pid {} terminated by {:?}", pid, sig);
+    crate::kernel::syscall::sys_exit(128 + sig as i32)
+}
+
+/// Park the current agent and its threads until SIGCONT (or SIGKILL) makes
+/// them Ready
+fn stop(pid: u64, sig: Signal) {
+    crate::kprintln!("[SIGNAL] pid {} stopped by {:?}", pid, sig);
+    let mut scheduler = SCHEDULER.lock();
+    let park = |agent: &mut Agent| {
+        if matches!(agent.state, AgentState::Ready | AgentState::Running) {
+            agent.state = AgentState::Stopped;
+        }
+    };
+    scheduler.with_current_process(park);
+    for thread in scheduler.threads_mut(pid) {
+        park(thread);
+    }
+    drop(scheduler);
+    loop {
+        scheduler::yield_task();
+        let stopped = SCHEDULER.lock()
+            .with_current_agent(|agent| agent.state == AgentState::Stopped)
+            .unwrap_or(false);
+        if !stopped {
+            break;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_dispositions() {
+        assert_eq!(Signal::SIGSEGV.default_disposition(), Disposition::Terminate);
+        assert_eq!(Signal::SIGCHLD.default_disposition(), Disposition::Ignore);
+        assert_eq!(Signal::SIGCONT.default_disposition(), Disposition::Continue);
+        assert_eq!(Signal::SIGTSTP.default_disposition(), Disposition::Stop);
+    }
+
+    #[test]
+    fn test_disposition_honours_action() {
+        let mut action = SigAction::default();
+        assert_eq!(disposition(Signal::SIGTERM, &action), Disposition::Terminate);
+        action.handler = SIG_IGN;
+        assert_eq!(disposition(Signal::SIGTERM, &action), Disposition::Ignore);
+        action.handler = 0x40_1000;
+        assert_eq!(disposition(Signal::SIGTERM, &action), Disposition::Catch(0x40_1000));
+        // SIGKILL and SIGSTOP can't be caught or ignored
+        assert_eq!(disposition(Signal::SIGKILL, &action), Disposition::Terminate);
+        action.handler = SIG_IGN;
+        assert_eq!(disposition(Signal::SIGSTOP, &action), Disposition::Stop);
+    }
+
+    #[test]
+    fn test_next_deliverable_respects_mask() {
+        let pending = Signal::SIGINT.bit() | Signal::SIGTERM.bit();
+        assert_eq!(next_deliverable(pending, 0), Some(Signal::SIGINT));
+        assert_eq!(next_deliverable(pending, Signal::SIGINT.bit()), Some(Signal::SIGTERM));
+        assert_eq!(next_deliverable(pending, pending), None);
+        assert_eq!(next_deliverable(0, 0), None);
+    }
+
+    #[test]
+    fn test_kill_and_stop_are_unblockable() {
+        let pending = Signal::SIGKILL.bit();
+        assert_eq!(next_deliverable(pending, u32::MAX), Some(Signal::SIGKILL));
+        assert_eq!(next_deliverable(Signal::SIGSTOP.bit(), u32::MAX), Some(Signal::SIGSTOP));
+    }
+
+    #[test]
+    fn test_trampoline_encoding() {
+        // mov x8, #44; svc #0
+        assert_eq!(TRAMPOLINE_CODE, [0xD280_0588, 0xD400_0001]);
+    }
+
+    #[test]
+    fn test_signal_frame_alignment() {
+        assert_eq!(size_of::<SignalFrame>() % 8, 0);
+        assert_eq!(size_of::<ExceptionFrame>(), 280);
+    }
+}
@@ -1,3 +1,5 @@
 pub mod spinlock;
+pub mod wait_queue;
 
 pub use spinlock::{SpinLock, RawSpinLock};
+pub use wait_queue::{WaitQueue, EINTR, EWOULDBLOCK, POLL_WAIT};
@@ -149,7 +149,7 @@ pub fn get_wait_graph() -> BTreeMap<u64, Vec<u64>> {
     let registry = LOCK_REGISTRY.lock();
     let mut graph = BTreeMap::new();
     
-    for (_, info) in registry.iter() {
+    for info in registry.values() {
         if let Some(holder) = info.holder {
             for &waiter in &info.waiters {
                 graph.entry(waiter).or_insert_with(Vec::new).push(holder);
@@ -198,18 +198,7 @@ impl<T: ?Sized> SpinLock<T> {
             0
         };
         
-        // Lazy ID initialization
-        let mut lock_id = self.id.load(Ordering::Relaxed);
-        if lock_id == 0 {
-            let new_id = NEXT_LOCK_ID.fetch_add(1, Ordering::Relaxed);
-            match self.id.compare_exchange(0, new_id, Ordering::Relaxed, Ordering::Relaxed) {
-                Ok(_) => {
-                    lock_id = new_id;
-                    register_lock(lock_id);
-                }
-                Err(actual) => lock_id = actual,
-            }
-        }
+        let lock_id = self.lock_id();
         
         // Try to acquire
         if self.lock.compare_exchange_weak(
@@ -259,6 +248,48 @@ impl<T: ?Sized> SpinLock<T> {
         }
     }
     
+    /// Acquire the lock only if it is free right now
+    pub fn try_lock(&self) -> Option<SpinLockGuard<'_, T>> {
+        let saved_int_state = arch::irq_disable();
+        if self.lock.compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed).is_err() {
+            arch::irq_restore(saved_int_state);
+            return None;
+        }
+
+        let core_id = arch::core_id();
+        let pid = if core_id < 4 {
+            crate::kernel::scheduler::CURRENT_PIDS[core_id as usize].load(Ordering::Relaxed)
+        } else {
+            0
+        };
+        let lock_id = self.lock_id();
+        if pid != 0 {
+            record_acquire(lock_id, pid as u64);
+        }
+        Some(SpinLockGuard {
+            lock: self,
+            saved_int_state,
+            pid: pid as u64,
+            lock_id,
+        })
+    }
+
+    /// Lazy ID initialization
+    fn lock_id(&self) -> usize {
+        let lock_id = self.id.load(Ordering::Relaxed);
+        if lock_id != 0 {
+            return lock_id;
+        }
+        let new_id = NEXT_LOCK_ID.fetch_add(1, Ordering::Relaxed);
+        match self.id.compare_exchange(0, new_id, Ordering::Relaxed, Ordering::Relaxed) {
+            Ok(_) => {
+                register_lock(new_id);
+                new_id
+            }
+            Err(actual) => actual,
+        }
+    }
+
     pub fn unlock(&self, pid: u64, lock_id: usize) {
         if pid != 0 && lock_id != 0 {
             record_release(lock_id);
@@ -0,0 +1,180 @@
+//! Wait Queues
+//!
+//! Lets agents sleep (`AgentState::Blocked`) until an event occurs, instead
+//! of spinning on `wfi` or `yield_task()`.
+//!
+//! Waiters follow the prepare/check/yield pattern so a wake-up that races
+//! with the condition check (e.g. from a NIC interrupt) is never lost:
+//!
+//! 1. `prepare_to_wait()` marks the agent Blocked and enqueues it
+//! 2. the condition is checked again; if it now holds, `finish_wait()`
+//! 3. otherwise `yield_task()` until a waker sets the agent Ready
+//!
+//! A signal that will run a handler or kill the agent also makes it Ready
+//! (see `signal::post`), and the wait then fails with `EINTR`.
+//!
+//! Wakers never take the scheduler lock: woken IDs are handed to the
+//! scheduler through `PENDING_WAKEUPS` and applied on the next `schedule()`.
+//! This keeps `wake_all()` safe from IRQ context and from `Drop` impls that
+//! run while the scheduler is locked (e.g. closing the last pipe end).
+
+use alloc::vec::Vec;
+use super::SpinLock;
+use crate::kernel::process::AgentState;
+use crate::kernel::scheduler::{self, IntentScheduler, SCHEDULER};
+use crate::kernel::signal;
+
+/// Error returned by non-blocking operations that would have to wait
+pub const EWOULDBLOCK: &str = "Operation would block";
+
+/// Error returned by a blocking wait that a signal cut short
+pub const EINTR: &str = "Interrupted system call";
+
+/// Agents woken since the scheduler last ran
+static PENDING_WAKEUPS: SpinLock<Vec<u64>> = SpinLock::new(Vec::new());
+
+/// Move woken agents from Blocked to Ready (called by the scheduler)
+pub fn apply_pending_wakeups(sched: &mut IntentScheduler) {
+    let woken: Vec<u64> = PENDING_WAKEUPS.lock().drain(..).collect();
+    for id in woken {
+        if let Some(agent) = sched.get_agent_mut(id) {
+            if agent.state == AgentState::Blocked {
+                agent.state = AgentState::Ready;
+            }
+        }
+    }
+}
+
+/// Agents blocked in sys_poll; woken whenever any other queue is woken
+pub static POLL_WAIT: WaitQueue = WaitQueue::new();
+
+/// A set of agents waiting for the same event
+pub struct WaitQueue {
+    waiters: SpinLock<Vec<u64>>, // Agent IDs
+}
+
+impl WaitQueue {
+    pub const fn new() -> Self {
+        Self {
+            waiters: SpinLock::new(Vec::new()),
+        }
+    }
+
+    /// Are any agents waiting?
+    pub fn is_empty(&self) -> bool {
+        self.waiters.lock().is_empty()
+    }
+
+    /// Block the current agent on this queue (scheduler already locked)
+    ///
+    /// Returns false if there is no current agent to block.
+    pub fn prepare_to_wait_locked(&self, sched: &mut IntentScheduler) -> bool {
+        let id = sched.with_current_agent(|agent| {
+            agent.state = AgentState::Blocked;
+            agent.id.0
+        });
+        match id {
+            Some(id) => {
+                let mut waiters = self.waiters.lock();
+                if !waiters.contains(&id) {
+                    waiters.push(id);
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Block the current agent on this queue
+    ///
+    /// The agent keeps running until it calls `yield_task()`.
+    pub fn prepare_to_wait(&self) -> bool {
+        let mut sched = SCHEDULER.lock();
+        self.prepare_to_wait_locked(&mut sched)
+    }
+
+    /// Leave the queue after waking up (or if the wait was not needed)
+    pub fn finish_wait(&self) {
+        let mut sched = SCHEDULER.lock();
+        let id = sched.with_current_agent(|agent| {
+            if agent.state == AgentState::Blocked {
+                agent.state = AgentState::Running;
+            }
+            agent.id.0
+        });
+        if let Some(id) = id {
+            self.waiters.lock().retain(|&w| w != id);
+        }
+    }
+
+    /// Wake every waiter
+    pub fn wake_all(&self) {
+        // Pollers may be watching this event too
+        if !core::ptr::eq(self, &POLL_WAIT) {
+            POLL_WAIT.wake_all();
+        }
+        let mut waiters = self.waiters.lock();
+        if waiters.is_empty() {
+            return;
+        }
+        PENDING_WAKEUPS.lock().extend(waiters.drain(..));
+    }
+
+    /// Wake every waiter immediately (scheduler already locked)
+    pub fn wake_all_locked(&self, sched: &mut IntentScheduler) {
+        self.wake_all();
+        apply_pending_wakeups(sched);
+    }
+
+    /// Block until `poll` produces a value
+    ///
+    /// `poll` must not hold the scheduler lock. Fails with `EINTR` if a
+    /// signal needs the agent first, and with `EWOULDBLOCK` when there is no
+    /// current agent to block (early boot / kernel context).
+    pub fn wait_until<T>(&self, mut poll: impl FnMut() -> Option<T>) -> Result<T, &'static str> {
+        loop {
+            if let Some(v) = poll() {
+                return Ok(v);
+            }
+            if signal::interrupted() {
+                return Err(EINTR);
+            }
+            if !self.prepare_to_wait() {
+                return Err(EWOULDBLOCK);
+            }
+            // Re-check: the event may have fired before we were queued
+            if let Some(v) = poll() {
+                self.finish_wait();
+                return Ok(v);
+            }
+            scheduler::yield_task();
+            self.finish_wait();
+        }
+    }
+}
+
+impl Default for WaitQueue {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wait_until_ready_immediately() {
+        let queue = WaitQueue::new();
+        assert_eq!(queue.wait_until(|| Some(7)), Ok(7));
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn test_wait_without_agent() {
+        // No current agent: nothing to block, report would-block
+        let queue = WaitQueue::new();
+        assert_eq!(queue.wait_until(|| None::<u32>), Err(EWOULDBLOCK));
+        assert!(queue.is_empty());
+    }
+}
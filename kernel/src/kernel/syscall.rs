@@ -5,79 +5,181 @@
 use crate::kernel::scheduler::{self, SCHEDULER};
 use crate::fs::vfs;
 use crate::kprintln;
-use crate::kernel::signal::{Signal, SigAction};
+use crate::kernel::signal::{self, Signal, SigAction};
 use alloc::sync::Arc;
 use crate::kernel::sync::SpinLock;
 use crate::fs::pipe;
 use crate::kernel::memory::paging::UserAddressSpace;
+use crate::kernel::memory::PAGE_SIZE;
+use crate::kernel::memory::fault::{self, Access};
+use crate::kernel::memory::uaccess;
+use crate::kernel::futex;
+use crate::kernel::memory::object::MemObject;
+use crate::kernel::memory::vma::{VmaBacking, VmaFlags, VmaPerms, VMA};
 use crate::kernel::capability::CapabilityType;
+use crate::fs::FileOps;
+use crate::net::socket::{self, SockAddr, SocketFile, TcpSocket};
+use crate::net::tcp::{self, TcpState};
+use crate::net::IpAddr;
+use intent_abi::{self as abi, PollFd};
+use crate::kernel::sync::{POLL_WAIT, EWOULDBLOCK};
+use alloc::vec::Vec;
+use alloc::string::String;
+use crate::kernel::process::{AgentId, Message};
+use crate::intent::ConceptID;
+
+/// Largest kernel bounce buffer a single read or write goes through
+const MAX_IO_CHUNK: usize = 64 * 1024;
+
+/// Check that the current agent may access `[ptr, ptr + len)` the given way
+///
+/// Agents with their own address space need a VMA that allows the access on
+/// every page; kernel agents only get the user range check. Must be called
+/// without the scheduler lock.
+fn user_access_ok(ptr: u64, len: usize, access: Access) -> bool {
+    let in_range = match access {
+        Access::Write => crate::kernel::memory::validate_write_ptr(ptr as *mut u8, len).is_ok(),
+        _ => crate::kernel::memory::validate_read_ptr(ptr as *const u8, len).is_ok(),
+    };
+    in_range && SCHEDULER.lock().with_current_process(|agent| {
+        agent.vmm.is_none() || agent.is_user_range(ptr, len, access)
+    }).unwrap_or(false)
+}
 
 /// Check if current agent has Driver capability
 fn check_privileged_io() -> bool {
+    check_capability(CapabilityType::Driver)
+}
+
+/// Whether the current agent holds a capability of this type
+fn check_capability(cap_type: CapabilityType) -> bool {
     let mut scheduler = SCHEDULER.lock();
-    scheduler.with_current_agent(|agent| {
-        agent.has_capability(CapabilityType::Driver)
+    scheduler.with_current_process(|agent| {
+        agent.has_capability(cap_type)
     }).unwrap_or(false)
 }
 
 /// System Call Numbers
+///
+/// Discriminants come from the shared `intent_abi` crate so user programs
+/// and the dispatcher always agree.
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 #[repr(u64)]
 pub enum SyscallNumber {
-    Exit = 0,
-    Yield = 1,
-    Print = 2,
-    Sleep = 3,
-    Open = 4,
-    Close = 5,
-    Read = 6,
-    Write = 7,
-    Kill = 8,
-    SigAction = 9,
-    Pipe = 10,
-    Dup2 = 11,
-    Mmap = 12,
-    Munmap = 13,
-    Socket = 14,
-    Bind = 15,
-    Connect = 16,
-    GetPid = 17,
-    Fork = 18,
-    Wait = 19,
-    Exec = 20,
-    RecvFrom = 21,
-    ParseIntent = 22,
-    Getdents64 = 23,
+    Exit = abi::SYS_EXIT,
+    Yield = abi::SYS_YIELD,
+    Print = abi::SYS_PRINT,
+    Sleep = abi::SYS_SLEEP,
+    Open = abi::SYS_OPEN,
+    Close = abi::SYS_CLOSE,
+    Read = abi::SYS_READ,
+    Write = abi::SYS_WRITE,
+    Kill = abi::SYS_KILL,
+    SigAction = abi::SYS_SIGACTION,
+    Pipe = abi::SYS_PIPE,
+    Dup2 = abi::SYS_DUP2,
+    Mmap = abi::SYS_MMAP,
+    Munmap = abi::SYS_MUNMAP,
+    Socket = abi::SYS_SOCKET,
+    Bind = abi::SYS_BIND,
+    Connect = abi::SYS_CONNECT,
+    GetPid = abi::SYS_GETPID,
+    Fork = abi::SYS_FORK,
+    Wait = abi::SYS_WAIT,
+    Exec = abi::SYS_EXEC,
+    RecvFrom = abi::SYS_RECVFROM,
+    ParseIntent = abi::SYS_PARSE_INTENT,
+    Getdents64 = abi::SYS_GETDENTS64,
+    BindUdp = abi::SYS_BIND_UDP,
+    RecvFromFd = abi::SYS_RECVFROM_FD,
+    IpcSend = abi::SYS_IPC_SEND,
+    IpcRecv = abi::SYS_IPC_RECV,
+    Announce = abi::SYS_ANNOUNCE,
+    Listen = abi::SYS_LISTEN,
+    Accept = abi::SYS_ACCEPT,
+    Send = abi::SYS_SEND,
+    Recv = abi::SYS_RECV,
+    Shutdown = abi::SYS_SHUTDOWN,
+    Poll = abi::SYS_POLL,
+    Chdir = abi::SYS_CHDIR,
+    Getcwd = abi::SYS_GETCWD,
+    Mkdir = abi::SYS_MKDIR,
+    Unlink = abi::SYS_UNLINK,
+    Rename = abi::SYS_RENAME,
+    Stat = abi::SYS_STAT,
+    Truncate = abi::SYS_TRUNCATE,
+    GetAddrInfo = abi::SYS_GETADDRINFO,
+    SetSockOpt = abi::SYS_SETSOCKOPT,
+    SigReturn = abi::SYS_SIGRETURN,
+    SigProcMask = abi::SYS_SIGPROCMASK,
+    Msync = abi::SYS_MSYNC,
+    ShmOpen = abi::SYS_SHM_OPEN,
+    ShmUnlink = abi::SYS_SHM_UNLINK,
+    ThreadCreate = abi::SYS_THREAD_CREATE,
+    ThreadExit = abi::SYS_THREAD_EXIT,
+    Join = abi::SYS_JOIN,
+    Futex = abi::SYS_FUTEX,
+    GetTid = abi::SYS_GETTID,
     Unknown,
 }
 
 impl From<u64> for SyscallNumber {
     fn from(n: u64) -> Self {
         match n {
-            0 => SyscallNumber::Exit,
-            1 => SyscallNumber::Yield,
-            2 => SyscallNumber::Print,
-            3 => SyscallNumber::Sleep,
-            4 => SyscallNumber::Open,
-            5 => SyscallNumber::Close,
-            6 => SyscallNumber::Read,
-            7 => SyscallNumber::Write,
-            8 => SyscallNumber::Kill,
-            9 => SyscallNumber::SigAction,
-            10 => SyscallNumber::Pipe,
-            11 => SyscallNumber::Dup2,
-            12 => SyscallNumber::Mmap,
-            13 => SyscallNumber::Munmap,
-            14 => SyscallNumber::Socket,
-            15 => SyscallNumber::Bind,
-            16 => SyscallNumber::Connect,
-            17 => SyscallNumber::GetPid,
-            18 => SyscallNumber::Fork,
-            19 => SyscallNumber::Wait,
-            20 => SyscallNumber::Exec,
-            21 => SyscallNumber::RecvFrom,
-            22 => SyscallNumber::ParseIntent,
-            23 => SyscallNumber::Getdents64,
+            abi::SYS_EXIT => SyscallNumber::Exit,
+            abi::SYS_YIELD => SyscallNumber::Yield,
+            abi::SYS_PRINT => SyscallNumber::Print,
+            abi::SYS_SLEEP => SyscallNumber::Sleep,
+            abi::SYS_OPEN => SyscallNumber::Open,
+            abi::SYS_CLOSE => SyscallNumber::Close,
+            abi::SYS_READ => SyscallNumber::Read,
+            abi::SYS_WRITE => SyscallNumber::Write,
+            abi::SYS_KILL => SyscallNumber::Kill,
+            abi::SYS_SIGACTION => SyscallNumber::SigAction,
+            abi::SYS_PIPE => SyscallNumber::Pipe,
+            abi::SYS_DUP2 => SyscallNumber::Dup2,
+            abi::SYS_MMAP => SyscallNumber::Mmap,
+            abi::SYS_MUNMAP => SyscallNumber::Munmap,
+            abi::SYS_SOCKET => SyscallNumber::Socket,
+            abi::SYS_BIND => SyscallNumber::Bind,
+            abi::SYS_CONNECT => SyscallNumber::Connect,
+            abi::SYS_GETPID => SyscallNumber::GetPid,
+            abi::SYS_FORK => SyscallNumber::Fork,
+            abi::SYS_WAIT => SyscallNumber::Wait,
+            abi::SYS_EXEC => SyscallNumber::Exec,
+            abi::SYS_RECVFROM => SyscallNumber::RecvFrom,
+            abi::SYS_PARSE_INTENT => SyscallNumber::ParseIntent,
+            abi::SYS_GETDENTS64 => SyscallNumber::Getdents64,
+            abi::SYS_BIND_UDP => SyscallNumber::BindUdp,
+            abi::SYS_RECVFROM_FD => SyscallNumber::RecvFromFd,
+            abi::SYS_IPC_SEND => SyscallNumber::IpcSend,
+            abi::SYS_IPC_RECV => SyscallNumber::IpcRecv,
+            abi::SYS_ANNOUNCE => SyscallNumber::Announce,
+            abi::SYS_LISTEN => SyscallNumber::Listen,
+            abi::SYS_ACCEPT => SyscallNumber::Accept,
+            abi::SYS_SEND => SyscallNumber::Send,
+            abi::SYS_RECV => SyscallNumber::Recv,
+            abi::SYS_SHUTDOWN => SyscallNumber::Shutdown,
+            abi::SYS_POLL => SyscallNumber::Poll,
+            abi::SYS_CHDIR => SyscallNumber::Chdir,
+            abi::SYS_GETCWD => SyscallNumber::Getcwd,
+            abi::SYS_MKDIR => SyscallNumber::Mkdir,
+            abi::SYS_UNLINK => SyscallNumber::Unlink,
+            abi::SYS_RENAME => SyscallNumber::Rename,
+            abi::SYS_STAT => SyscallNumber::Stat,
+            abi::SYS_TRUNCATE => SyscallNumber::Truncate,
+            abi::SYS_GETADDRINFO => SyscallNumber::GetAddrInfo,
+            abi::SYS_SETSOCKOPT => SyscallNumber::SetSockOpt,
+            abi::SYS_SIGRETURN => SyscallNumber::SigReturn,
+            abi::SYS_SIGPROCMASK => SyscallNumber::SigProcMask,
+            abi::SYS_MSYNC => SyscallNumber::Msync,
+            abi::SYS_SHM_OPEN => SyscallNumber::ShmOpen,
+            abi::SYS_SHM_UNLINK => SyscallNumber::ShmUnlink,
+            abi::SYS_THREAD_CREATE => SyscallNumber::ThreadCreate,
+            abi::SYS_THREAD_EXIT => SyscallNumber::ThreadExit,
+            abi::SYS_JOIN => SyscallNumber::Join,
+            abi::SYS_FUTEX => SyscallNumber::Futex,
+            abi::SYS_GETTID => SyscallNumber::GetTid,
             _ => SyscallNumber::Unknown,
         }
     }
@@ -92,7 +194,6 @@ impl From<u64> for SyscallNumber {
 /// Arguments are passed in x0-x7.
 /// Return value is placed in x0.
 pub fn dispatcher(num: u64, arg0: u64, arg1: u64, arg2: u64, arg3: u64, frame: &mut crate::kernel::exception::ExceptionFrame) -> u64 {
-    let _ = arg3; // Currently unused, but available for syscalls that need it
     let syscall = SyscallNumber::from(num);
 
     
@@ -135,14 +236,15 @@ pub fn dispatcher(num: u64, arg0: u64, arg1: u64, arg2: u64, arg3: u64, frame: &
             sys_sigaction(arg0 as i32, arg1, arg2)
         }
         SyscallNumber::Pipe => {
-            sys_pipe(arg0)
+            // arg0: pipefd_ptr, arg1: flags (O_NONBLOCK)
+            sys_pipe(arg0, arg1)
         }
         SyscallNumber::Dup2 => {
             sys_dup2(arg0, arg1)
         }
         SyscallNumber::Mmap => {
-            // arg0: len, arg1: perms, arg2: flags
-            sys_mmap(arg0, arg1, arg2)
+            // arg0: len, arg1: prot, arg2: flags, arg3: fd, x4: offset
+            sys_mmap(arg0, arg1, arg2, arg3, frame.x[4])
         }
         SyscallNumber::Munmap => {
             // arg0: addr, arg1: len
@@ -160,6 +262,129 @@ pub fn dispatcher(num: u64, arg0: u64, arg1: u64, arg2: u64, arg3: u64, frame: &
             // arg0: fd, arg1: addr_ptr, arg2: addr_len
             sys_connect(arg0, arg1, arg2)
         }
+        SyscallNumber::BindUdp => {
+            // arg0: port
+            if !check_privileged_io() {
+                crate::kprintln!("[SECURITY] sys_bind_udp DENIED: Missing Driver Capability");
+                u64::MAX // EPERM
+            } else {
+                sys_bind_udp(arg0)
+            }
+        }
+        SyscallNumber::RecvFromFd => {
+            // arg0: fd, arg1: buf_ptr, arg2: len, arg3: src_addr_ptr (optional)
+            sys_recvfrom_fd(arg0, arg1, arg2, arg3)
+        }
+        SyscallNumber::IpcSend => {
+            // arg0: target pid, arg1: msg_ptr (IPC_MSG_SIZE bytes)
+            sys_ipc_send(arg0, arg1)
+        }
+        SyscallNumber::IpcRecv => {
+            // arg0: buf_ptr (IPC_MSG_SIZE bytes)
+            sys_ipc_recv(arg0)
+        }
+        SyscallNumber::Announce => {
+            // arg0: concept_id
+            sys_announce(arg0)
+        }
+        SyscallNumber::Listen => {
+            // arg0: fd, arg1: backlog
+            sys_listen(arg0, arg1)
+        }
+        SyscallNumber::Accept => {
+            // arg0: fd, arg1: addr_ptr (optional), arg2: addr_len
+            sys_accept(arg0, arg1, arg2)
+        }
+        SyscallNumber::Send => {
+            // arg0: fd, arg1: buf_ptr, arg2: len, arg3: flags
+            sys_send(arg0, arg1, arg2, arg3)
+        }
+        SyscallNumber::Recv => {
+            // arg0: fd, arg1: buf_ptr, arg2: len, arg3: flags
+            sys_recv(arg0, arg1, arg2, arg3)
+        }
+        SyscallNumber::Shutdown => {
+            // arg0: fd, arg1: how
+            sys_shutdown(arg0, arg1)
+        }
+        SyscallNumber::Poll => {
+            // arg0: fds_ptr, arg1: nfds, arg2: timeout_ms (negative = forever)
+            sys_poll(arg0, arg1, arg2 as i64)
+        }
+        SyscallNumber::Chdir => {
+            // arg0: path_ptr
+            sys_chdir(arg0)
+        }
+        SyscallNumber::Getcwd => {
+            // arg0: buf_ptr, arg1: len
+            sys_getcwd(arg0, arg1)
+        }
+        SyscallNumber::Mkdir => {
+            // arg0: path_ptr
+            sys_path_op(arg0, "mkdir", |vfs, path| vfs.mkdir(path))
+        }
+        SyscallNumber::Unlink => {
+            // arg0: path_ptr
+            sys_path_op(arg0, "unlink", |vfs, path| vfs.unlink(path))
+        }
+        SyscallNumber::Rename => {
+            // arg0: old_path_ptr, arg1: new_path_ptr
+            sys_rename(arg0, arg1)
+        }
+        SyscallNumber::Stat => {
+            // arg0: path_ptr, arg1: stat_ptr
+            sys_stat(arg0, arg1)
+        }
+        SyscallNumber::Truncate => {
+            // arg0: path_ptr, arg1: size
+            sys_path_op(arg0, "truncate", |vfs, path| vfs.truncate(path, arg1))
+        }
+        SyscallNumber::GetAddrInfo => {
+            // arg0: name_ptr, arg1: addrs_ptr, arg2: max addresses
+            sys_getaddrinfo(arg0, arg1, arg2)
+        }
+        SyscallNumber::SetSockOpt => {
+            // arg0: fd, arg1: level, arg2: option name, arg3: value
+            sys_setsockopt(arg0, arg1, arg2, arg3)
+        }
+        SyscallNumber::SigReturn => {
+            sys_sigreturn(frame)
+        }
+        SyscallNumber::SigProcMask => {
+            // arg0: how, arg1: mask
+            sys_sigprocmask(arg0, arg1)
+        }
+        SyscallNumber::Msync => {
+            // arg0: addr, arg1: len
+            sys_msync(arg0, arg1)
+        }
+        SyscallNumber::ShmOpen => {
+            // arg0: name_ptr, arg1: size, arg2: flags
+            sys_shm_open(arg0, arg1, arg2)
+        }
+        SyscallNumber::ShmUnlink => {
+            // arg0: name_ptr
+            sys_shm_unlink(arg0)
+        }
+        SyscallNumber::ThreadCreate => {
+            // arg0: entry, arg1: arg, arg2: stack_top, arg3: tls
+            sys_thread_create(arg0, arg1, arg2, arg3)
+        }
+        SyscallNumber::ThreadExit => {
+            // arg0: value
+            sys_thread_exit(arg0)
+        }
+        SyscallNumber::Join => {
+            // arg0: tid, arg1: value_ptr
+            sys_join(arg0, arg1)
+        }
+        SyscallNumber::Futex => {
+            // arg0: addr, arg1: op, arg2: val, arg3: timeout_ms
+            sys_futex(arg0, arg1, arg2, arg3)
+        }
+        SyscallNumber::GetTid => {
+            sys_gettid()
+        }
         SyscallNumber::GetPid => {
             sys_getpid()
         }
@@ -170,7 +395,8 @@ pub fn dispatcher(num: u64, arg0: u64, arg1: u64, arg2: u64, arg3: u64, frame: &
             sys_wait(arg0 as i32)
         }
         SyscallNumber::Exec => {
-            sys_exec(arg0, frame)
+            // arg0: path_ptr, arg1: argv, arg2: envp
+            sys_exec(arg0, arg1, arg2, frame)
         }
         SyscallNumber::RecvFrom => {
             sys_recvfrom(arg0, arg1, arg2)
@@ -204,14 +430,34 @@ pub fn dispatcher(num: u64, arg0: u64, arg1: u64, arg2: u64, arg3: u64, frame: &
     result
 }
 
-fn sys_exit(code: i32) -> ! {
+pub(crate) fn sys_exit(code: i32) -> ! {
     kprintln!("Process exited with code {}", code);
     
+    // Shared file mappings reach their files, as if unmapped
+    let _ = sync_mappings(0, u64::MAX);
+    
     // Set state to Terminated and wake parent
     let mut scheduler = SCHEDULER.lock();
     scheduler.exit_current(code);
     drop(scheduler);
-    
+    leave_terminated()
+}
+
+/// thread_exit(value): end the calling thread, or the whole agent if it is
+/// the main thread
+fn sys_thread_exit(value: u64) -> ! {
+    let mut scheduler = SCHEDULER.lock();
+    if scheduler.current_pid() == scheduler.current_process_id() {
+        drop(scheduler);
+        sys_exit(value as i32);
+    }
+    scheduler.exit_thread(value);
+    drop(scheduler);
+    leave_terminated()
+}
+
+/// Switch away from a Terminated agent or thread for good
+fn leave_terminated() -> ! {
     // Try to yield to another task
     scheduler::yield_task();
     
@@ -276,37 +522,20 @@ fn sys_sleep(ms: u64) {
 }
 
 fn sys_print(ptr: u64, len: u64) -> u64 {
-    let ptr_raw = ptr as *const u8;
     let len = len as usize;
     
     if len > 1024 {
         return u64::MAX; // Error: Too long
     }
     
-    // Validate against VMM if possible
-    let mut scheduler = SCHEDULER.lock();
-    let valid = scheduler.with_current_agent(|agent| {
-        if let Some(vmm) = &agent.vmm {
-            // Check start and end pages
-            let start = ptr;
-            let end = ptr + len as u64;
-            let mut curr = start & !0xFFF;
-            while curr < end {
-                if !UserAddressSpace::is_mapped(vmm, curr) { return false; }
-                curr += 4096;
-            }
-            true
-        } else {
-            // Kernel thread or no VMM, fallback to range check
-            crate::kernel::memory::validate_read_ptr(ptr_raw, len).is_ok()
-        }
-    }).unwrap_or(false);
-    drop(scheduler);
-
-    if !valid { return u64::MAX; }
+    // Validate against the agent's VMAs
+    if !user_access_ok(ptr, len, Access::Read) { return u64::MAX; }
     
-
-    match unsafe { crate::kernel::memory::validate_user_str(ptr_raw, len) } {
+    let mut buf = [0u8; 1024];
+    if uaccess::copy_from_user(&mut buf[..len], ptr).is_err() {
+        return u64::MAX; // EFAULT
+    }
+    match core::str::from_utf8(&buf[..len]) {
         Ok(s) => {
             crate::kprint!("{}", s);
             0
@@ -322,46 +551,28 @@ fn sys_open(path_ptr: u64, flags: u64) -> u64 {
         return u64::MAX; // EPERM
     }
 
-    let ptr = path_ptr as *const u8;
-    
-    // Validate pointer
-    let mut scheduler = SCHEDULER.lock();
-    let valid = scheduler.with_current_agent(|agent| {
-        if let Some(vmm) = &agent.vmm {
-            UserAddressSpace::is_mapped(vmm, path_ptr)
-        } else {
-            crate::kernel::memory::validate_read_ptr(ptr, 1).is_ok()
-        }
-    }).unwrap_or(false);
-    
-    if !valid { 
-        return u64::MAX; 
-    }
-    // Drop lock to allow VFS open (which might take time, though VFS uses SpinLock so we should be careful about lock ordering)
-    // SCHEDULER lock is held? No, we dropped it?
-    // Wait, with_current_agent takes &mut self, so we hold the lock during closure.
-    // We need to drop scheduler lock before VFS open.
-    drop(scheduler);
-
-    // Read up to 64 bytes for path
-    let mut path_buf = [0u8; 64];
-    let mut len = 0;
-    for i in 0..64 {
-        let c = unsafe { *ptr.add(i) };
-        if c == 0 { break; }
-        path_buf[i] = c;
-        len += 1;
-    }
-    let path = core::str::from_utf8(&path_buf[0..len]).unwrap_or("");
+    let path = match resolve_user_path(path_ptr) {
+        Some(path) => path,
+        None => return u64::MAX, // EFAULT / ENAMETOOLONG
+    };
     
     // Open file via VFS
-    let file_res = crate::fs::VFS.lock().open(path, flags as usize);
+    let file_res = crate::fs::VFS.lock().open(&path, flags as usize);
     
     match file_res {
         Ok(file) => {
+            // Device files may demand more than the Driver capability
+            let required = file.lock().required_capability();
+            
             // Allocate FD in current process
             let mut scheduler = SCHEDULER.lock();
-            scheduler.with_current_agent(|agent| {
+            scheduler.with_current_process(|agent| {
+                if let Some(cap) = required {
+                    if !agent.has_capability(cap) {
+                        crate::kprintln!("[SECURITY] sys_open DENIED: Missing {:?} Capability", cap);
+                        return u64::MAX; // EACCES
+                    }
+                }
                 match agent.file_table.alloc_fd(file, flags as usize) {
                     Ok(fd) => fd as u64,
                     Err(_) => u64::MAX // EMFILE
@@ -372,9 +583,176 @@ fn sys_open(path_ptr: u64, flags: u64) -> u64 {
     }
 }
 
+/// Copy a NUL-terminated path (at most `PATH_MAX` bytes) from user memory
+fn read_user_path(path_ptr: u64) -> Option<String> {
+    read_user_str(path_ptr, abi::PATH_MAX)
+}
+
+/// Read a NUL-terminated user string of fewer than `max` bytes
+///
+/// Each page the string reaches is checked before the first byte on it is
+/// read, so a string running off the end of a mapping fails cleanly.
+fn read_user_str(str_ptr: u64, max: usize) -> Option<String> {
+    let mut bytes = Vec::new();
+    for i in 0..max {
+        let addr = str_ptr.checked_add(i as u64)?;
+        if i == 0 || addr as usize % PAGE_SIZE == 0 {
+            let len = core::cmp::min(PAGE_SIZE - addr as usize % PAGE_SIZE, max - i);
+            if !user_access_ok(addr, len, Access::Read) {
+                return None;
+            }
+        }
+        let c: u8 = unsafe { uaccess::read_user(addr) }.ok()?;
+        if c == 0 {
+            return String::from_utf8(bytes).ok();
+        }
+        bytes.push(c);
+    }
+    None // Not terminated within max
+}
+
+/// Read a NULL-terminated array of user strings (argv, envp), charging each
+/// string plus its pointer to `budget`
+fn read_user_strings(array_ptr: u64, budget: &mut usize) -> Option<Vec<String>> {
+    let mut strings = Vec::new();
+    if array_ptr == 0 {
+        return Some(strings);
+    }
+    if array_ptr & 7 != 0 {
+        return None;
+    }
+    let mut entry = array_ptr;
+    loop {
+        if !user_access_ok(entry, 8, Access::Read) {
+            return None;
+        }
+        let str_ptr: u64 = unsafe { uaccess::read_user(entry) }.ok()?;
+        if str_ptr == 0 {
+            return Some(strings);
+        }
+        let string = read_user_str(str_ptr, *budget)?;
+        *budget = budget.checked_sub(string.len() + 1 + 8)?;
+        strings.push(string);
+        entry = entry.checked_add(8)?;
+    }
+}
+
+/// Read a user path and canonicalise it against the caller's cwd
+fn resolve_user_path(path_ptr: u64) -> Option<String> {
+    let path = read_user_path(path_ptr)?;
+    let mut scheduler = SCHEDULER.lock();
+    let cwd = scheduler.with_current_process(|agent| agent.cwd.clone())
+        .unwrap_or_else(|| String::from("/"));
+    drop(scheduler);
+    Some(vfs::normalize_path(&cwd, &path))
+}
+
+/// Run a VFS operation on one user path
+fn sys_path_op(path_ptr: u64, name: &str, op: impl FnOnce(&vfs::VfsManager, &str) -> Result<(), &'static str>) -> u64 {
+    if !check_privileged_io() {
+        crate::kprintln!("[SECURITY] sys_{} DENIED: Missing Driver Capability", name);
+        return u64::MAX; // EPERM
+    }
+    let path = match resolve_user_path(path_ptr) {
+        Some(path) => path,
+        None => return u64::MAX, // EFAULT
+    };
+    match op(&crate::fs::VFS.lock(), &path) {
+        Ok(()) => 0,
+        Err(_) => u64::MAX,
+    }
+}
+
+fn sys_chdir(path_ptr: u64) -> u64 {
+    if !check_privileged_io() {
+        crate::kprintln!("[SECURITY] sys_chdir DENIED: Missing Driver Capability");
+        return u64::MAX; // EPERM
+    }
+    let path = match resolve_user_path(path_ptr) {
+        Some(path) => path,
+        None => return u64::MAX, // EFAULT
+    };
+    
+    // Must exist and be a directory
+    match crate::fs::VFS.lock().stat(&path) {
+        Ok(st) if st.mode & vfs::S_IFMT == vfs::S_IFDIR => {}
+        _ => return u64::MAX, // ENOENT / ENOTDIR
+    }
+    
+    let mut scheduler = SCHEDULER.lock();
+    scheduler.with_current_process(|agent| {
+        agent.cwd = path;
+        0
+    }).unwrap_or(u64::MAX)
+}
+
+fn sys_getcwd(buf_ptr: u64, len: u64) -> u64 {
+    let len = len as usize;
+    if !user_access_ok(buf_ptr, len, Access::Write) {
+        return u64::MAX; // EFAULT
+    }
+    
+    let mut scheduler = SCHEDULER.lock();
+    let cwd = match scheduler.with_current_process(|agent| agent.cwd.clone()) {
+        Some(cwd) => cwd,
+        None => return u64::MAX,
+    };
+    drop(scheduler);
+    
+    // Needs room for the NUL terminator
+    if cwd.len() + 1 > len {
+        return u64::MAX; // ERANGE
+    }
+    let mut bytes = cwd.into_bytes();
+    bytes.push(0);
+    if uaccess::copy_to_user(buf_ptr, &bytes).is_err() {
+        return u64::MAX; // EFAULT
+    }
+    (bytes.len() - 1) as u64
+}
+
+fn sys_rename(old_ptr: u64, new_ptr: u64) -> u64 {
+    if !check_privileged_io() {
+        crate::kprintln!("[SECURITY] sys_rename DENIED: Missing Driver Capability");
+        return u64::MAX; // EPERM
+    }
+    let (from, to) = match (resolve_user_path(old_ptr), resolve_user_path(new_ptr)) {
+        (Some(from), Some(to)) => (from, to),
+        _ => return u64::MAX, // EFAULT
+    };
+    match crate::fs::VFS.lock().rename(&from, &to) {
+        Ok(()) => 0,
+        Err(_) => u64::MAX,
+    }
+}
+
+fn sys_stat(path_ptr: u64, stat_ptr: u64) -> u64 {
+    if !check_privileged_io() {
+        crate::kprintln!("[SECURITY] sys_stat DENIED: Missing Driver Capability");
+        return u64::MAX; // EPERM
+    }
+    if !user_access_ok(stat_ptr, core::mem::size_of::<abi::Stat>(), Access::Write) {
+        return u64::MAX; // EFAULT
+    }
+    let path = match resolve_user_path(path_ptr) {
+        Some(path) => path,
+        None => return u64::MAX, // EFAULT
+    };
+    
+    let st = match crate::fs::VFS.lock().stat(&path) {
+        Ok(st) => st,
+        Err(_) => return u64::MAX, // ENOENT
+    };
+    let stat = abi::Stat { size: st.size, mode: st.mode, _pad: 0, inode: st.inode };
+    match uaccess::write_user(stat_ptr, &stat) {
+        Ok(()) => 0,
+        Err(_) => u64::MAX, // EFAULT
+    }
+}
+
 fn sys_close(fd: u64) -> u64 {
     let mut scheduler = SCHEDULER.lock();
-    scheduler.with_current_agent(|agent| {
+    scheduler.with_current_process(|agent| {
         match agent.file_table.close_fd(fd as usize) {
             Ok(_) => 0,
             Err(_) => u64::MAX
@@ -389,52 +767,43 @@ fn sys_read(fd: u64, buf_ptr: u64, len: u64) -> u64 {
         return u64::MAX; // EPERM
     }
 
-    let buf_raw = buf_ptr as *mut u8;
     let len = len as usize;
     
-    let mut scheduler = SCHEDULER.lock();
-    let valid = scheduler.with_current_agent(|agent| {
-        if let Some(vmm) = &agent.vmm {
-             let start = buf_ptr;
-             let end = buf_ptr + len as u64;
-             let mut curr = start & !0xFFF;
-             while curr < end {
-                 if !UserAddressSpace::is_mapped(vmm, curr) { return false; }
-                 curr += 4096;
-             }
-             true
-        } else {
-             crate::kernel::memory::validate_read_ptr(buf_raw, len).is_ok()
-        }
-    }).unwrap_or(false);
-    
-    if !valid {
+    // The file writes into the buffer
+    if !user_access_ok(buf_ptr, len, Access::Write) {
         return u64::MAX;
     }
     
-    // We need to keep scheduler locked or re-lock to access file table?
-    // We dropped lock? No, we are holding it inside with_current_agent if we didn't drop it.
-    // But we need to access file table.
-    // Let's just do it all in one go if possible, but reading file might block/take time?
-    // VFS read takes SpinLock on file.
-    // If we hold Scheduler lock and take File lock, is it safe?
-    // Scheduler -> File.
-    // Does File ever take Scheduler? No.
-    // So it should be safe.
-    
-    scheduler.with_current_agent(|agent| {
-        if let Ok(desc) = agent.file_table.get_fd(fd as usize) {
-            let mut file = desc.file.lock();
-            // Create a slice from user pointer
-            let buf = unsafe { core::slice::from_raw_parts_mut(buf_raw, len) };
-            match file.read(buf) {
-                Ok(n) => n as u64,
-                Err(_) => u64::MAX
-            }
-        } else {
-            u64::MAX
-        }
-    }).unwrap_or(u64::MAX)
+    let file = match current_file(fd) {
+        Some(file) => file,
+        None => return u64::MAX,
+    };
+    // Read into a kernel buffer; a large read may come back short
+    let mut buf = alloc::vec![0u8; len.min(MAX_IO_CHUNK)];
+    match read_unlocked(&file, &mut buf) {
+        Ok(n) => match uaccess::copy_to_user(buf_ptr, &buf[..n]) {
+            Ok(()) => n as u64,
+            Err(_) => u64::MAX, // EFAULT
+        },
+        Err(_) => u64::MAX
+    }
+}
+
+/// Read from `file`, sleeping on its read queue with the file unlocked so
+/// other users of it (threads, forked children, poll) aren't locked out
+fn read_unlocked(file: &SpinLock<dyn FileOps>, buf: &mut [u8]) -> Result<usize, &'static str> {
+    let queue = file.lock().read_queue();
+    match queue {
+        Some(queue) => match queue.wait_until(|| match file.lock().try_read(&mut buf[..]) {
+            Err(EWOULDBLOCK) => None,
+            res => Some(res),
+        }) {
+            Ok(res) => res,
+            Err(EWOULDBLOCK) => Ok(0), // No agent to block
+            Err(e) => Err(e),
+        },
+        None => file.lock().read(buf),
+    }
 }
 
 fn sys_write(fd: u64, buf_ptr: u64, len: u64) -> u64 {
@@ -444,59 +813,62 @@ fn sys_write(fd: u64, buf_ptr: u64, len: u64) -> u64 {
         return u64::MAX; // EPERM
     }
 
-    let buf_raw = buf_ptr as *const u8;
     let len = len as usize;
     
-    let mut scheduler = SCHEDULER.lock();
-    let valid = scheduler.with_current_agent(|agent| {
-        if let Some(vmm) = &agent.vmm {
-             let start = buf_ptr;
-             let end = buf_ptr + len as u64;
-             let mut curr = start & !0xFFF;
-             while curr < end {
-                 if !UserAddressSpace::is_mapped(vmm, curr) { return false; }
-                 curr += 4096;
-             }
-             true
-        } else {
-             crate::kernel::memory::validate_read_ptr(buf_raw, len).is_ok()
-        }
-    }).unwrap_or(false);
-    
-    if !valid {
+    if !user_access_ok(buf_ptr, len, Access::Read) {
         return u64::MAX;
     }
     
-    scheduler.with_current_agent(|agent| {
-        if let Ok(desc) = agent.file_table.get_fd(fd as usize) {
-            let mut file = desc.file.lock();
-            let buf = unsafe { core::slice::from_raw_parts(buf_raw, len) };
-            match file.write(buf) {
-                Ok(n) => n as u64,
-                Err(_) => u64::MAX
+    let file = match current_file(fd) {
+        Some(file) => file,
+        None => return u64::MAX,
+    };
+    // Copy through a kernel buffer one chunk at a time, stopping at a short write
+    let mut buf = alloc::vec![0u8; len.min(MAX_IO_CHUNK)];
+    let mut written = 0;
+    while written < len {
+        let chunk = &mut buf[..(len - written).min(MAX_IO_CHUNK)];
+        if uaccess::copy_from_user(chunk, buf_ptr + written as u64).is_err() {
+            return if written > 0 { written as u64 } else { u64::MAX }; // EFAULT
+        }
+        match file.lock().write(chunk) {
+            Ok(n) => {
+                written += n;
+                if n < chunk.len() {
+                    break;
+                }
             }
-        } else {
-            u64::MAX
+            Err(_) if written > 0 => break,
+            Err(_) => return u64::MAX,
         }
-    }).unwrap_or(u64::MAX)
+    }
+    written as u64
 }
 
 fn sys_kill(pid: u64, sig: i32) -> u64 {
+    let signal = match Signal::from_i32(sig) {
+        Some(signal) => signal,
+        None => return u64::MAX, // Invalid signal
+    };
+    let privileged = check_capability(CapabilityType::System);
+    // Acted on when one of the target's threads next returns to user mode
     let mut scheduler = SCHEDULER.lock();
-    if let Some(agent) = scheduler.get_agent_mut(pid) {
-        if let Some(signal) = Signal::from_i32(sig) {
-            agent.pending_signals |= 1 << (signal as u32);
-            // If agent is Sleeping, wake it up?
-            if agent.state == crate::kernel::process::AgentState::Sleeping {
-                agent.state = crate::kernel::process::AgentState::Ready;
-                agent.wake_time = 0;
-            }
-            0
-        } else {
-            u64::MAX // Invalid signal
+    // Only our own process and our children, unless System
+    let caller = scheduler.current_process_id();
+    let target = scheduler.process_of(pid);
+    let related = caller.is_some_and(|caller| {
+        target == caller || scheduler.get_agent(target).is_some_and(|agent| agent.parent_id == Some(caller))
+    });
+    if !related && !privileged {
+        if scheduler.get_agent(target).is_none() {
+            return u64::MAX; // ESRCH
         }
-    } else {
-        u64::MAX // ESRCH
+        crate::kprintln!("[SECURITY] sys_kill DENIED: pid {} is not ours", pid);
+        return u64::MAX; // EPERM
+    }
+    match signal::post_to_process(&mut scheduler, pid, signal) {
+        Ok(()) => 0,
+        Err(_) => u64::MAX, // ESRCH
     }
 }
 
@@ -505,62 +877,89 @@ fn sys_sigaction(sig: i32, act_ptr: u64, oldact_ptr: u64) -> u64 {
         Some(s) => s,
         None => return u64::MAX,
     };
+    // SIGKILL and SIGSTOP always take their default action
+    if act_ptr != 0 && signal.bit() & signal::UNBLOCKABLE != 0 {
+        return u64::MAX;
+    }
     
     // Validate pointers
-    let mut scheduler = SCHEDULER.lock();
-    
-    // Check if we can access the memory
-    let valid = scheduler.with_current_agent(|agent: &mut crate::kernel::process::Agent| {
-        let mut ok = true;
-        if oldact_ptr != 0 {
-            if let Some(vmm) = &agent.vmm {
-                if !UserAddressSpace::is_mapped(vmm, oldact_ptr) { ok = false; }
-            } else {
-                if crate::kernel::memory::validate_write_ptr(oldact_ptr as *mut u8, core::mem::size_of::<SigAction>()).is_err() { ok = false; }
-            }
-        }
-        if act_ptr != 0 {
-            if let Some(vmm) = &agent.vmm {
-                if !UserAddressSpace::is_mapped(vmm, act_ptr) { ok = false; }
-            } else {
-                if crate::kernel::memory::validate_read_ptr(act_ptr as *const u8, core::mem::size_of::<SigAction>()).is_err() { ok = false; }
-            }
-        }
-        ok
-    }).unwrap_or(false);
-
+    let size = core::mem::size_of::<SigAction>();
+    let valid = (oldact_ptr == 0 || user_access_ok(oldact_ptr, size, Access::Write))
+        && (act_ptr == 0 || user_access_ok(act_ptr, size, Access::Read));
     if !valid {
         return u64::MAX;
     }
 
-    scheduler.with_current_agent(|agent| {
-        let sig_idx = signal as usize;
-        
-        if oldact_ptr != 0 {
-            let old_act = agent.sig_actions[sig_idx];
-            // Write old_act to user memory
-            let ptr = oldact_ptr as *mut SigAction;
-            unsafe { *ptr = old_act };
+    // User memory is only touched without the scheduler lock, so the page
+    // fault handler can populate the page or break copy-on-write
+    let new_act = if act_ptr != 0 {
+        match unsafe { uaccess::read_user::<SigAction>(act_ptr) } {
+            Ok(act) => Some(act),
+            Err(_) => return u64::MAX, // EFAULT
         }
-        
-        if act_ptr != 0 {
-            let ptr = act_ptr as *const SigAction;
-            let new_act = unsafe { *ptr };
+    } else {
+        None
+    };
+
+    let old_act = SCHEDULER.lock().with_current_process(|agent| {
+        let sig_idx = signal as usize;
+        let old_act = agent.sig_actions[sig_idx];
+        if let Some(new_act) = new_act {
             agent.sig_actions[sig_idx] = new_act;
         }
-        0
+        old_act
+    });
+    let old_act = match old_act {
+        Some(act) => act,
+        None => return u64::MAX,
+    };
+
+    if oldact_ptr != 0 {
+        // Write old_act to user memory
+        if uaccess::write_user(oldact_ptr, &old_act).is_err() {
+            return u64::MAX; // EFAULT
+        }
+    }
+    0
+}
+
+fn sys_sigreturn(frame: &mut crate::kernel::exception::ExceptionFrame) -> u64 {
+    match signal::sigreturn(frame) {
+        // Hand the interrupted x0 back unchanged
+        Ok(()) => frame.x[0],
+        Err(e) => {
+            kprintln!("sigreturn failed: {}", e);
+            signal::force(Signal::SIGSEGV);
+            u64::MAX
+        }
+    }
+}
+
+fn sys_sigprocmask(how: u64, mask: u64) -> u64 {
+    let mask = mask as u32 & !signal::UNBLOCKABLE;
+    let mut scheduler = SCHEDULER.lock();
+    scheduler.with_current_process(|agent| {
+        let old = agent.blocked_signals;
+        agent.blocked_signals = match how {
+            abi::SIG_BLOCK => old | mask,
+            abi::SIG_UNBLOCK => old & !mask,
+            abi::SIG_SETMASK => mask,
+            _ => return u64::MAX,
+        };
+        old as u64
     }).unwrap_or(u64::MAX)
 }
 
-fn sys_pipe(pipefd_ptr: u64) -> u64 {
+fn sys_pipe(pipefd_ptr: u64, flags: u64) -> u64 {
     // 1. Create pipe
     let (reader, writer) = pipe::create_pipe();
+    let nonblock = flags as usize & vfs::O_NONBLOCK;
     
     // 2. Allocate FDs
     let mut scheduler = SCHEDULER.lock();
-    let res: Option<Result<(usize, usize), &'static str>> = scheduler.with_current_agent(|agent| {
-        let r_fd = agent.file_table.alloc_fd(Arc::new(SpinLock::new(reader)), vfs::O_RDONLY)?;
-        let w_fd = agent.file_table.alloc_fd(Arc::new(SpinLock::new(writer)), vfs::O_WRONLY)?;
+    let res: Option<Result<(usize, usize), &'static str>> = scheduler.with_current_process(|agent| {
+        let r_fd = agent.file_table.alloc_fd(Arc::new(SpinLock::new(reader)), vfs::O_RDONLY | nonblock)?;
+        let w_fd = agent.file_table.alloc_fd(Arc::new(SpinLock::new(writer)), vfs::O_WRONLY | nonblock)?;
         Ok((r_fd, w_fd))
     });
     drop(scheduler);
@@ -569,15 +968,11 @@ fn sys_pipe(pipefd_ptr: u64) -> u64 {
         Some(Ok((r, w))) => {
             // Write FDs to user memory
             // Write FDs to user memory
-            let ptr = pipefd_ptr as *mut i32;
-            
             // Validate pointer
             // We need to check if we can write 8 bytes (2 x i32)
-            if crate::kernel::memory::validate_write_ptr(ptr as *mut u8, 8).is_ok() {
-                unsafe {
-                   *ptr = r as i32;
-                   *ptr.add(1) = w as i32;
-                }
+            if user_access_ok(pipefd_ptr, 8, Access::Write)
+                && uaccess::write_user(pipefd_ptr, &[r as i32, w as i32]).is_ok()
+            {
                 crate::kprintln!("Pipe created: read={}, write={}", r, w);
                 0
             } else {
@@ -591,7 +986,7 @@ fn sys_pipe(pipefd_ptr: u64) -> u64 {
 
 fn sys_dup2(oldfd: u64, newfd: u64) -> u64 {
     let mut scheduler = SCHEDULER.lock();
-    scheduler.with_current_agent(|agent| {
+    scheduler.with_current_process(|agent| {
         match agent.file_table.dup2(oldfd as usize, newfd as usize) {
             Ok(fd) => fd as u64,
             Err(_) => u64::MAX
@@ -599,127 +994,595 @@ fn sys_dup2(oldfd: u64, newfd: u64) -> u64 {
     }).unwrap_or(u64::MAX)
 }
 
-fn sys_mmap(len: u64, perms: u64, flags: u64) -> u64 {
-    use crate::kernel::memory::vma::{VmaPerms, VmaFlags};
+/// mmap(len, prot, flags, fd, offset)
+///
+/// Only the VMA is created here: pages are mapped by the page fault handler
+/// on first touch, zero-filled or from the backing object, so large mappings
+/// cost nothing up front. A file's pages are read into the page cache now,
+/// so those faults never wait for I/O.
+fn sys_mmap(len: u64, prot: u64, flags: u64, fd: u64, offset: u64) -> u64 {
+    let vma_perms = VmaPerms::new(prot & abi::PROT_READ != 0, prot & abi::PROT_WRITE != 0, prot & abi::PROT_EXEC != 0);
+    let shared = flags & abi::MAP_SHARED != 0;
+    let anonymous = flags & abi::MAP_ANONYMOUS != 0;
+    let vma_flags = VmaFlags { private: !shared, anonymous, fixed: flags & abi::MAP_FIXED != 0 };
     
-    // Decode permissions
-    let r = (perms & 1) != 0;
-    let w = (perms & 2) != 0;
-    let x = (perms & 4) != 0;
-    let vma_perms = VmaPerms::new(r, w, x);
-    
-    // Decode flags (simplified)
-    // 1 = Private, 2 = Anonymous, 4 = Fixed
-    let private = (flags & 1) != 0;
-    let anonymous = (flags & 2) != 0;
-    let fixed = (flags & 4) != 0;
-    let vma_flags = VmaFlags { private, anonymous, fixed };
-    
-    let mut scheduler = SCHEDULER.lock();
-    let res = scheduler.with_current_agent(|agent: &mut crate::kernel::process::Agent| {
-        // 1. Allocate VMA
-        let addr = agent.vma_manager.mmap(len, vma_perms, vma_flags)?;
-        
-        // 2. Map pages if Anonymous
-        if anonymous {
-            if let Some(vmm) = &mut agent.vmm {
-                // Allocate pages
-                // Align len to page size
-                let align = 4096;
-                let size = (len + align - 1) & !(align - 1);
-                let pages = size / 4096;
-                
-                if let Some(ptr) = unsafe { crate::kernel::memory::alloc_pages(pages as usize) } {
-                    let phys = ptr.as_ptr() as u64;
-                    // Map to user space
-                    if UserAddressSpace::map_user(vmm, addr, phys, size as usize).is_err() {
-                        // Rollback VMA
-                        agent.vma_manager.munmap(addr, len);
-                        return None;
-                    }
-                    // Zero memory
-                    unsafe { core::ptr::write_bytes(ptr.as_ptr(), 0, size as usize) };
-                } else {
-                    return None; // OOM
-                }
+    if len == 0 {
+        return u64::MAX;
+    }
+
+    let backing = if !anonymous {
+        match mapping_backing(fd, offset, len, shared && vma_perms.write) {
+            Ok(backing) => Some(backing),
+            Err(e) => {
+                crate::kprintln!("[MMAP] fd {}: {}", fd, e);
+                return u64::MAX;
             }
         }
-        
-        Some(addr)
+    } else if shared {
+        // Shared anonymous memory lives in an object so forked children
+        // keep seeing the same pages
+        let object = Arc::new(SpinLock::new(MemObject::new(len)));
+        Some(VmaBacking { object, offset: 0, file: None })
+    } else {
+        None
+    };
+    
+    let mut scheduler = SCHEDULER.lock();
+    let res = scheduler.with_current_process(|agent: &mut crate::kernel::process::Agent| {
+        agent.vma_manager.mmap_backed(len, vma_perms, vma_flags, backing)
     });
     
     res.flatten().unwrap_or(u64::MAX)
 }
 
+/// The object behind `fd` for a mapping of `[offset, offset + len)`
+///
+/// Shared memory objects are mapped as they are; regular files go through
+/// the page cache, with the range loaded before it is mapped.
+fn mapping_backing(fd: u64, offset: u64, len: u64, write_shared: bool) -> Result<VmaBacking, &'static str> {
+    if offset & 0xFFF != 0 {
+        return Err("Offset not page aligned");
+    }
+    let mut scheduler = SCHEDULER.lock();
+    let (file, flags) = scheduler.with_current_process(|agent| {
+        agent.file_table.get_fd(fd as usize).ok().map(|desc| (desc.file.clone(), desc.flags))
+    }).flatten().ok_or("Bad file descriptor")?;
+    drop(scheduler);
+
+    // Stores through a shared mapping end up in the file
+    if write_shared && flags & (vfs::O_WRONLY | vfs::O_RDWR) == 0 {
+        return Err("File not open for writing");
+    }
+
+    let object = file.lock().memory_object();
+    if let Some(object) = object {
+        return Ok(VmaBacking { object, offset, file: None });
+    }
+    let object = crate::fs::page_cache::object_for(&file)?;
+    crate::fs::page_cache::load(&object, &*file, offset / 4096, len.div_ceil(4096))?;
+    Ok(VmaBacking { object, offset, file: Some(file) })
+}
+
 fn sys_munmap(addr: u64, len: u64) -> u64 {
     let mut scheduler = SCHEDULER.lock();
-    let success = scheduler.with_current_agent(|agent: &mut crate::kernel::process::Agent| {
+    let unmapped = scheduler.with_current_process(|agent: &mut crate::kernel::process::Agent| {
         // 1. Remove VMA
-        if let Some(vma) = agent.vma_manager.munmap(addr, len) {
-            // 2. Unmap pages
-            if let Some(vmm) = &mut agent.vmm {
-                let align = 4096;
-                let size = (len + align - 1) & !(align - 1);
-                let mut curr = addr;
-                let end = addr + size;
-                
-                while curr < end {
-                    // Unmap from page table
-                    if let Ok(Some(phys)) = UserAddressSpace::unmap_page(vmm, curr) {
-                        // If anonymous, free the physical page
-                        if vma.flags.anonymous {
-                            unsafe {
-                                // Convert physical address to NonNull pointer
-                                // We assume direct mapping or we reconstruct the pointer
-                                // alloc_pages returns NonNull<u8>
-                                // We need to reconstruct it.
-                                if let Some(ptr) = core::ptr::NonNull::new(phys as *mut u8) {
-                                    crate::kernel::memory::free_pages(ptr, 1);
-                                }
-                            }
-                        }
-                    }
-                    curr += 4096;
-                }
-            }
-            true
-        } else {
-            false
+        let vma = agent.vma_manager.munmap(addr, len)?;
+        // 2. Unmap populated pages and drop their frame references
+        if let Some(vmm) = &mut agent.vmm {
+            let size = (len + 4095) & !4095;
+            fault::release_range(vmm, addr, addr + size);
         }
-    }).unwrap_or(false);
+        Some(vma)
+    }).flatten();
+    drop(scheduler);
     
-    if success { 0 } else { u64::MAX }
+    // 3. Shared file pages are still in the page cache: write them back
+    match unmapped {
+        Some(vma) => match crate::fs::page_cache::sync_vma(&vma, vma.start, vma.end) {
+            Ok(()) => 0,
+            Err(_) => u64::MAX,
+        },
+        None => u64::MAX,
+    }
 }
 
-// Socket syscalls are stubbed - full socket implementation pending
-fn sys_socket(_domain: u64, _type_: u64, _protocol: u64) -> u64 {
-    // TODO: Implement Socket/SocketType when socket module is complete
-    u64::MAX // ENOSYS - function not implemented
+/// msync(addr, len): write `MAP_SHARED` file pages in the range back
+fn sys_msync(addr: u64, len: u64) -> u64 {
+    if addr & 0xFFF != 0 {
+        return u64::MAX; // EINVAL
+    }
+    match sync_mappings(addr, addr.saturating_add(len)) {
+        Ok(true) => 0,
+        _ => u64::MAX,
+    }
 }
 
-fn sys_bind(_fd: u64, _addr_ptr: u64, _addr_len: u64) -> u64 {
-    // TODO: Implement when Socket type is available
-    u64::MAX // ENOSYS
+/// Write back the current agent's shared file mappings in `[start, end)`
+///
+/// The VMAs are cloned out so the file I/O runs without the scheduler lock.
+/// Returns false if nothing is mapped in the range.
+fn sync_mappings(start: u64, end: u64) -> Result<bool, &'static str> {
+    let mut scheduler = SCHEDULER.lock();
+    let vmas: Vec<VMA> = scheduler.with_current_process(|agent| {
+        agent.vma_manager.vmas.iter().filter(|vma| vma.overlaps(start, end)).cloned().collect()
+    }).unwrap_or_default();
+    drop(scheduler);
+
+    for vma in &vmas {
+        crate::fs::page_cache::sync_vma(vma, start, end)?;
+    }
+    Ok(!vmas.is_empty())
 }
 
-fn sys_connect(_fd: u64, _addr_ptr: u64, _addr_len: u64) -> u64 {
-    // TODO: Implement when Socket type is available  
-    u64::MAX // ENOSYS
+/// shm_open(name, size, flags): descriptor for a named shared memory object
+fn sys_shm_open(name_ptr: u64, size: u64, flags: u64) -> u64 {
+    if !check_capability(CapabilityType::Memory) {
+        crate::kprintln!("[SECURITY] sys_shm_open DENIED: Missing Memory Capability");
+        return u64::MAX; // EPERM
+    }
+    let name = match read_user_path(name_ptr) {
+        Some(name) => name,
+        None => return u64::MAX, // EFAULT
+    };
+    let object = match crate::fs::shm::open(&name, size, flags as usize & vfs::O_CREAT != 0) {
+        Ok(object) => object,
+        Err(_) => return u64::MAX, // ENOENT / EINVAL
+    };
+
+    let file: Arc<SpinLock<dyn vfs::FileOps>> = Arc::new(SpinLock::new(crate::fs::shm::ShmFile::new(object)));
+    let mut scheduler = SCHEDULER.lock();
+    scheduler.with_current_process(|agent| {
+        match agent.file_table.alloc_fd(file, (flags as usize) & !vfs::O_CREAT) {
+            Ok(fd) => fd as u64,
+            Err(_) => u64::MAX, // EMFILE
+        }
+    }).unwrap_or(u64::MAX)
+}
+
+/// shm_unlink(name): remove a shared memory object's name
+fn sys_shm_unlink(name_ptr: u64) -> u64 {
+    if !check_capability(CapabilityType::Memory) {
+        crate::kprintln!("[SECURITY] sys_shm_unlink DENIED: Missing Memory Capability");
+        return u64::MAX; // EPERM
+    }
+    let name = match read_user_path(name_ptr) {
+        Some(name) => name,
+        None => return u64::MAX, // EFAULT
+    };
+    match crate::fs::shm::unlink(&name) {
+        Ok(()) => 0,
+        Err(_) => u64::MAX, // ENOENT
+    }
 }
 
+// ═══════════════════════════════════════════════════════════════════════════════
+// SOCKETS
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Fetch an open file from the current agent's table
+///
+/// The Arc is cloned out so the scheduler lock is released before the
+/// file is used (socket operations may yield while waiting).
+fn current_file(fd: u64) -> Option<Arc<SpinLock<dyn vfs::FileOps>>> {
+    let mut scheduler = SCHEDULER.lock();
+    scheduler.with_current_process(|agent| {
+        agent.file_table.get_fd(fd as usize).ok().map(|desc| desc.file.clone())
+    }).flatten()
+}
+
+/// Run `f` on the socket behind `fd` if it is of type `T`
+fn with_socket<T: 'static, R>(fd: u64, f: impl FnOnce(&mut T) -> R) -> Option<R> {
+    let file = current_file(fd)?;
+    let mut guard = file.lock();
+    let sock = guard.as_any().downcast_mut::<T>()?;
+    Some(f(sock))
+}
+
+/// Address family of the socket behind `fd`
+fn socket_family(fd: u64) -> Option<u16> {
+    with_socket::<TcpSocket, _>(fd, |s| s.family)
+        .or_else(|| with_socket::<SocketFile, _>(fd, |s| s.family))
+}
+
+/// Read a `sockaddr_in` or `sockaddr_in6` (matching `family`) from user memory
+fn read_sockaddr(addr_ptr: u64, addr_len: u64, family: u16) -> Option<SockAddr> {
+    let size = SockAddr::size_of(family)?;
+    if (addr_len as usize) < size {
+        return None;
+    }
+    if !user_access_ok(addr_ptr, size, Access::Read) {
+        return None;
+    }
+    let addr = unsafe {
+        if family == socket::AF_INET6 {
+            SockAddr::V6(uaccess::read_user(addr_ptr).ok()?)
+        } else {
+            SockAddr::V4(uaccess::read_user(addr_ptr).ok()?)
+        }
+    };
+    let stored_family = match addr {
+        SockAddr::V4(sa) => sa.family,
+        SockAddr::V6(sa) => sa.family,
+    };
+    if stored_family != family {
+        return None; // EAFNOSUPPORT
+    }
+    Some(addr)
+}
+
+/// Write a socket address to user memory (already validated)
+fn write_sockaddr(addr_ptr: u64, addr: SockAddr) -> Result<(), &'static str> {
+    match addr {
+        SockAddr::V4(sa) => uaccess::write_user(addr_ptr, &sa),
+        SockAddr::V6(sa) => uaccess::write_user(addr_ptr, &sa),
+    }
+}
+
+/// Install a new socket in the current agent's file table
+fn install_socket(file: Arc<SpinLock<dyn vfs::FileOps>>, flags: usize) -> u64 {
+    let mut scheduler = SCHEDULER.lock();
+    match scheduler.with_current_process(|agent| agent.file_table.alloc_fd(file, vfs::O_RDWR | flags)) {
+        Some(Ok(fd)) => fd as u64,
+        _ => u64::MAX, // EMFILE
+    }
+}
+
+fn sys_socket(domain: u64, type_: u64, _protocol: u64) -> u64 {
+    // Raw network access is a driver-level privilege
+    if !check_privileged_io() {
+        crate::kprintln!("[SECURITY] sys_socket DENIED: Missing Driver Capability");
+        return u64::MAX; // EPERM
+    }
+    let family = match domain {
+        d if d == socket::AF_INET as u64 => socket::AF_INET,
+        d if d == socket::AF_INET6 as u64 => socket::AF_INET6,
+        _ => return u64::MAX, // EAFNOSUPPORT
+    };
+
+    let file: Arc<SpinLock<dyn vfs::FileOps>> = match type_ & !socket::SOCK_NONBLOCK {
+        socket::SOCK_STREAM => {
+            let mut sock = TcpSocket::new();
+            sock.family = family;
+            Arc::new(SpinLock::new(sock))
+        }
+        socket::SOCK_DGRAM => {
+            let mut sock = SocketFile::new(0);
+            sock.family = family;
+            Arc::new(SpinLock::new(sock))
+        }
+        _ => return u64::MAX, // EPROTONOSUPPORT
+    };
+    let flags = if type_ & socket::SOCK_NONBLOCK != 0 { vfs::O_NONBLOCK } else { 0 };
+    install_socket(file, flags)
+}
+
+fn sys_bind(fd: u64, addr_ptr: u64, addr_len: u64) -> u64 {
+    let family = match socket_family(fd) {
+        Some(family) => family,
+        None => return u64::MAX, // ENOTSOCK
+    };
+    let addr = match read_sockaddr(addr_ptr, addr_len, family) {
+        Some(a) => a,
+        None => return u64::MAX, // EINVAL
+    };
+
+    let res = with_socket::<TcpSocket, _>(fd, |s| s.bind(addr.ip(), addr.port()))
+        .or_else(|| with_socket::<SocketFile, _>(fd, |s| s.bind(addr.port())));
+    match res {
+        Some(Ok(())) => 0,
+        _ => u64::MAX,
+    }
+}
+
+fn sys_connect(fd: u64, addr_ptr: u64, addr_len: u64) -> u64 {
+    let family = match socket_family(fd) {
+        Some(family) => family,
+        None => return u64::MAX, // ENOTSOCK
+    };
+    let addr = match read_sockaddr(addr_ptr, addr_len, family) {
+        Some(a) => a,
+        None => return u64::MAX, // EINVAL
+    };
+
+    // UDP: just record the default peer
+    if let Some(res) = with_socket::<SocketFile, _>(fd, |s| s.connect(addr.ip(), addr.port())) {
+        return if res.is_ok() { 0 } else { u64::MAX };
+    }
+
+    let nonblocking = match with_socket::<TcpSocket, _>(fd, |s| s.connect(addr.ip(), addr.port()).map(|_| s.nonblocking)) {
+        Some(Ok(nonblocking)) => nonblocking,
+        _ => return u64::MAX,
+    };
+    if nonblocking {
+        // SYN sent; the handshake completes in the background (EINPROGRESS)
+        return 0;
+    }
+
+    // Sleep until the handshake finishes or fails
+    let state = tcp::SOCKET_WAIT.wait_until(|| {
+        match with_socket::<TcpSocket, _>(fd, |s| s.connection_state()) {
+            Some(Some(TcpState::SynSent)) | Some(Some(TcpState::SynReceived)) => None,
+            other => Some(other.flatten()),
+        }
+    });
+    match state {
+        Ok(Some(TcpState::Established)) => 0,
+        _ => u64::MAX, // ECONNREFUSED / EINTR
+    }
+}
+
+fn sys_listen(fd: u64, backlog: u64) -> u64 {
+    let backlog = backlog.min(tcp::SOMAXCONN as u64) as usize;
+    match with_socket::<TcpSocket, _>(fd, |s| s.listen(backlog)) {
+        Some(Ok(())) => 0,
+        _ => u64::MAX,
+    }
+}
+
+fn sys_accept(fd: u64, addr_ptr: u64, addr_len: u64) -> u64 {
+    let (nonblocking, family) = match with_socket::<TcpSocket, _>(fd, |s| (s.nonblocking, s.family)) {
+        Some(info) => info,
+        None => return u64::MAX, // ENOTSOCK
+    };
+    if addr_ptr != 0 {
+        let size = SockAddr::size_of(family).unwrap_or(usize::MAX);
+        if (addr_len as usize) < size
+            || !user_access_ok(addr_ptr, size, Access::Write)
+        {
+            return u64::MAX; // EFAULT
+        }
+    }
+
+    let accept = || match with_socket::<TcpSocket, _>(fd, |s| s.try_accept()) {
+        Some(Ok(Some(conn))) => Some(Ok(conn)),
+        Some(Ok(None)) => None,
+        _ => Some(Err(())),
+    };
+    let res = if nonblocking {
+        accept() // None: EWOULDBLOCK
+    } else {
+        tcp::SOCKET_WAIT.wait_until(accept).ok()
+    };
+    let conn = match res {
+        Some(Ok(conn)) => conn,
+        _ => return u64::MAX, // EINVAL / EWOULDBLOCK / EINTR
+    };
+
+    if addr_ptr != 0 {
+        if let Some(peer) = conn.peer_addr() {
+            if write_sockaddr(addr_ptr, peer).is_err() {
+                return u64::MAX; // EFAULT
+            }
+        }
+    }
+    install_socket(Arc::new(SpinLock::new(conn)), 0)
+}
+
+fn sys_send(fd: u64, buf_ptr: u64, len: u64, _flags: u64) -> u64 {
+    let len = len as usize;
+    if !user_access_ok(buf_ptr, len, Access::Read) {
+        return u64::MAX; // EFAULT
+    }
+    // A large send may be short, like a full send buffer
+    let mut buf = alloc::vec![0u8; len.min(MAX_IO_CHUNK)];
+    if uaccess::copy_from_user(&mut buf, buf_ptr).is_err() {
+        return u64::MAX; // EFAULT
+    }
+
+    let res = with_socket::<TcpSocket, _>(fd, |s| s.send(&buf))
+        .or_else(|| with_socket::<SocketFile, _>(fd, |s| s.write(&buf)));
+    match res {
+        Some(Ok(n)) => n as u64,
+        _ => u64::MAX,
+    }
+}
+
+fn sys_recv(fd: u64, buf_ptr: u64, len: u64, _flags: u64) -> u64 {
+    let len = len as usize;
+    if !user_access_ok(buf_ptr, len, Access::Write) {
+        return u64::MAX; // EFAULT
+    }
+    let mut buf = alloc::vec![0u8; len.min(MAX_IO_CHUNK)];
+    let n = match recv_into(fd, &mut buf) {
+        Some(n) => n,
+        None => return u64::MAX,
+    };
+    match uaccess::copy_to_user(buf_ptr, &buf[..n]) {
+        Ok(()) => n as u64,
+        Err(_) => u64::MAX, // EFAULT
+    }
+}
+
+/// Receive from a UDP or TCP socket into a kernel buffer
+fn recv_into(fd: u64, buf: &mut [u8]) -> Option<usize> {
+    // UDP: same as read(), sleeping on the RX wait queue with the file unlocked
+    if let Some(file) = current_file(fd).filter(|f| f.lock().as_any().is::<SocketFile>()) {
+        return read_unlocked(&file, buf).ok();
+    }
+
+    // TCP: poll with the file unlocked so other agents can use the socket
+    let nonblocking = with_socket::<TcpSocket, _>(fd, |s| s.nonblocking)?; // ENOTSOCK
+    let mut recv = || match with_socket::<TcpSocket, _>(fd, |s| s.recv(&mut buf[..])) {
+        Some(Ok(Some(n))) => Some(Ok(n)),
+        Some(Ok(None)) => None,
+        _ => Some(Err(())),
+    };
+    let res = if nonblocking {
+        recv() // None: EWOULDBLOCK
+    } else {
+        tcp::SOCKET_WAIT.wait_until(recv).ok()
+    };
+    match res {
+        Some(Ok(n)) => Some(n),
+        _ => None,
+    }
+}
+
+fn sys_shutdown(fd: u64, how: u64) -> u64 {
+    match with_socket::<TcpSocket, _>(fd, |s| s.shutdown(how)) {
+        Some(Ok(())) => 0,
+        _ => u64::MAX,
+    }
+}
+
+fn sys_setsockopt(fd: u64, level: u64, name: u64, value: u64) -> u64 {
+    match with_socket::<TcpSocket, _>(fd, |s| s.setsockopt(level, name, value)) {
+        Some(Ok(())) => 0,
+        _ => u64::MAX, // ENOTSOCK / ENOPROTOOPT
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// POLL
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Upper bound on entries per poll() call
+const POLL_MAX_FDS: usize = 256;
+
+/// Fill in `revents` for every entry, returning how many are ready
+fn poll_scan(fds: &mut [PollFd]) -> usize {
+    let mut ready = 0;
+    for pfd in fds.iter_mut() {
+        pfd.revents = if pfd.fd == abi::POLL_FD_MAILBOX {
+            let mut scheduler = SCHEDULER.lock();
+            let pending = scheduler.with_current_process(|agent| !agent.mailbox.lock().is_empty())
+                .unwrap_or(false);
+            if pending { vfs::POLLIN } else { 0 }
+        } else if pfd.fd < 0 {
+            0 // Ignored entry
+        } else {
+            match current_file(pfd.fd as u64) {
+                Some(file) => file.lock().poll_ready(),
+                None => vfs::POLLNVAL,
+            }
+        };
+        // Errors and hangups are reported even if not requested
+        pfd.revents &= pfd.events | vfs::POLLERR | vfs::POLLHUP | vfs::POLLNVAL;
+        if pfd.revents != 0 {
+            ready += 1;
+        }
+    }
+    ready
+}
+
+fn sys_poll(fds_ptr: u64, nfds: u64, timeout_ms: i64) -> u64 {
+    let nfds = nfds as usize;
+    if nfds > POLL_MAX_FDS {
+        return u64::MAX; // EINVAL
+    }
+    let size = nfds * core::mem::size_of::<PollFd>();
+    if nfds > 0 && !user_access_ok(fds_ptr, size, Access::Write) {
+        return u64::MAX; // EFAULT
+    }
+
+    let entry = |i: usize| fds_ptr + (i * core::mem::size_of::<PollFd>()) as u64;
+    let mut fds: Vec<PollFd> = match (0..nfds)
+        .map(|i| unsafe { uaccess::read_user(entry(i)) })
+        .collect()
+    {
+        Ok(fds) => fds,
+        Err(_) => return u64::MAX, // EFAULT
+    };
+
+    let deadline = if timeout_ms >= 0 {
+        Some(crate::drivers::timer::uptime_ms() + timeout_ms as u64)
+    } else {
+        None
+    };
+    let expired = || deadline.map_or(false, |d| crate::drivers::timer::uptime_ms() >= d);
+
+    // Any wake_all() also wakes POLL_WAIT, and tick() wakes it every 10ms,
+    // so the deadline is re-checked at tick granularity.
+    let ready = match POLL_WAIT.wait_until(|| {
+        let n = poll_scan(&mut fds);
+        if n > 0 || timeout_ms == 0 || expired() { Some(n) } else { None }
+    }) {
+        Ok(n) => n,
+        Err(EWOULDBLOCK) => poll_scan(&mut fds), // No agent to block
+        Err(_) => return u64::MAX, // EINTR
+    };
+
+    for (i, pfd) in fds.iter().enumerate() {
+        if uaccess::write_user(entry(i), pfd).is_err() {
+            return u64::MAX; // EFAULT
+        }
+    }
+    ready as u64
+}
 
 fn sys_getpid() -> u64 {
+    let scheduler = SCHEDULER.lock();
+    scheduler.current_process_id().unwrap_or(u64::MAX)
+}
+
+fn sys_gettid() -> u64 {
     let scheduler = SCHEDULER.lock();
     scheduler.current_pid().unwrap_or(u64::MAX)
 }
 
+/// thread_create(entry, arg, stack_top, tls) -> tid
+fn sys_thread_create(entry: u64, arg: u64, stack_top: u64, tls: u64) -> u64 {
+    let mut scheduler = SCHEDULER.lock();
+    let entry_ok = scheduler.with_current_process(|agent| agent.is_user_page(entry, Access::Execute))
+        .unwrap_or(false);
+    if !entry_ok {
+        return u64::MAX; // EFAULT
+    }
+    match scheduler.spawn_thread(entry, stack_top, arg, tls) {
+        Ok(tid) => tid,
+        Err(e) => {
+            kprintln!("Thread create failed: {}", e);
+            u64::MAX
+        }
+    }
+}
+
+/// join(tid, value_ptr): wait for a thread to exit and collect its value
+fn sys_join(tid: u64, value_ptr: u64) -> u64 {
+    if value_ptr != 0 && !user_access_ok(value_ptr, 8, Access::Write) {
+        return u64::MAX; // EFAULT
+    }
+    let value = loop {
+        let mut scheduler = SCHEDULER.lock();
+        match scheduler.join_thread(tid) {
+            Ok(Some(value)) => break value,
+            Ok(None) => {
+                // Blocked until a thread of this agent exits
+                drop(scheduler);
+                scheduler::yield_task();
+                if signal::interrupted() {
+                    return u64::MAX; // EINTR
+                }
+            }
+            Err(_) => return u64::MAX, // ESRCH / EDEADLK
+        }
+    };
+    if value_ptr != 0 && uaccess::write_user(value_ptr, &value).is_err() {
+        return u64::MAX; // EFAULT
+    }
+    0
+}
+
+/// futex(addr, op, val, timeout_ms)
+fn sys_futex(addr: u64, op: u64, val: u64, timeout_ms: u64) -> u64 {
+    match op {
+        abi::FUTEX_WAIT => match futex::wait(addr, val as u32, timeout_ms) {
+            Ok(()) => 0,
+            Err(_) => u64::MAX, // EAGAIN / ETIMEDOUT / EINTR
+        },
+        abi::FUTEX_WAKE => futex::wake(addr, val).unwrap_or(u64::MAX),
+        _ => u64::MAX, // EINVAL
+    }
+}
+
 fn sys_fork(frame: &crate::kernel::exception::ExceptionFrame) -> u64 {
     // Read sp_el0
     let sp_el0: u64;
     unsafe { core::arch::asm!("mrs {}, sp_el0", out(reg) sp_el0); }
     
     let mut scheduler = SCHEDULER.lock();
-    if let Some(parent_id) = scheduler.current_pid() {
+    if let Some(parent_id) = scheduler.current_process_id() {
         match scheduler.fork_agent(parent_id, frame, sp_el0) {
             Ok(child_pid) => child_pid,
             Err(e) => {
@@ -742,7 +1605,7 @@ fn sys_wait(pid: i32) -> u64 {
     };
 
     let mut scheduler = SCHEDULER.lock();
-    if let Some(current_pid) = scheduler.current_pid() {
+    if let (Some(current_pid), Some(thread_id)) = (scheduler.current_process_id(), scheduler.current_pid()) {
         match scheduler.wait_child(current_pid, target_pid) {
             Ok(Some(reaped)) => reaped,
             Ok(None) => {
@@ -750,14 +1613,17 @@ fn sys_wait(pid: i32) -> u64 {
                 drop(scheduler);
                 scheduler::yield_task();
                 
-                // Loop until child is reaped or error
+                // Loop until child is reaped, error or signal
                 loop {
+                    if signal::interrupted() {
+                        return u64::MAX; // EINTR
+                    }
                     let mut scheduler = SCHEDULER.lock();
                     match scheduler.wait_child(current_pid, target_pid) {
                         Ok(Some(reaped)) => return reaped,
                         Ok(None) => {
                              // Still waiting, ensure blocked
-                             if let Some(agent) = scheduler.get_agent_mut(current_pid) {
+                             if let Some(agent) = scheduler.get_agent_mut(thread_id) {
                                  agent.state = crate::kernel::process::AgentState::Blocked;
                              }
                              drop(scheduler);
@@ -776,27 +1642,34 @@ fn sys_wait(pid: i32) -> u64 {
 
 
 
-fn sys_exec(path_ptr: u64, frame: &mut crate::kernel::exception::ExceptionFrame) -> u64 {
-    // Validate pointer
-    let ptr = path_ptr as *const u8;
-    if crate::kernel::memory::validate_read_ptr(ptr, 1).is_err() {
-        return u64::MAX;
-    }
-    
-    // Read path string
-    let mut path_buf = [0u8; 64];
-    let mut len = 0;
-    for i in 0..64 {
-        let c = unsafe { *ptr.add(i) };
-        if c == 0 { break; }
-        path_buf[i] = c;
-        len += 1;
-    }
-    let path = core::str::from_utf8(&path_buf[0..len]).unwrap_or("");
+fn sys_exec(path_ptr: u64, argv_ptr: u64, envp_ptr: u64, frame: &mut crate::kernel::exception::ExceptionFrame) -> u64 {
+    // Read path string (resolved against cwd by exec)
+    let path = match read_user_path(path_ptr) {
+        Some(path) => path,
+        None => return u64::MAX,
+    };
+
+    // Copy the arguments out before the old image goes away
+    let mut budget = abi::ARG_MAX;
+    let (argv, envp) = match (read_user_strings(argv_ptr, &mut budget), read_user_strings(envp_ptr, &mut budget)) {
+        (Some(argv), Some(envp)) => (argv, envp),
+        _ => return u64::MAX, // EFAULT / E2BIG
+    };
+    let argv: Vec<&str> = match argv_ptr {
+        0 => alloc::vec![path.as_str()],
+        _ => argv.iter().map(String::as_str).collect(),
+    };
+    let envp: Vec<&str> = envp.iter().map(String::as_str).collect();
     
     let mut scheduler = SCHEDULER.lock();
-    let res = scheduler.with_current_agent(|agent: &mut crate::kernel::process::Agent| {
-        match agent.exec(path, frame) {
+    // The old image's threads would be left running in the new one
+    let process = scheduler.current_process_id();
+    if process != scheduler.current_pid() || process.is_some_and(|pid| scheduler.thread_count(pid) > 0) {
+        kprintln!("Exec failed: agent has other threads");
+        return u64::MAX;
+    }
+    let res = scheduler.with_current_process(|agent: &mut crate::kernel::process::Agent| {
+        match agent.exec(&path, &argv, &envp, frame) {
             Ok(_) => 0,
             Err(e) => {
                 kprintln!("Exec failed: {}", e);
@@ -809,11 +1682,10 @@ fn sys_exec(path_ptr: u64, frame: &mut crate::kernel::exception::ExceptionFrame)
 }
 
 fn sys_recvfrom(port: u64, buf_ptr: u64, buf_len: u64) -> u64 {
-    let buf_raw = buf_ptr as *mut u8;
     let len = buf_len as usize;
     
     // Validate buffer pointer
-    if crate::kernel::memory::validate_write_ptr(buf_raw, len).is_err() {
+    if !user_access_ok(buf_ptr, len, Access::Write) {
         return u64::MAX; // EFAULT
     }
     
@@ -821,12 +1693,8 @@ fn sys_recvfrom(port: u64, buf_ptr: u64, buf_len: u64) -> u64 {
     if let Some(msg) = crate::net::udp::recv_from(port as u16) {
         // Copy payload to user buffer
         let copy_len = msg.payload.len().min(len);
-        unsafe {
-            core::ptr::copy_nonoverlapping(
-                msg.payload.as_ptr(),
-                buf_raw,
-                copy_len
-            );
+        if uaccess::copy_to_user(buf_ptr, &msg.payload[..copy_len]).is_err() {
+            return u64::MAX; // EFAULT
         }
         
         // Return number of bytes copied
@@ -838,18 +1706,171 @@ fn sys_recvfrom(port: u64, buf_ptr: u64, buf_len: u64) -> u64 {
     }
 }
 
+fn sys_bind_udp(port: u64) -> u64 {
+    if port == 0 || port > u16::MAX as u64 {
+        return u64::MAX; // EINVAL
+    }
+    let mut sock = SocketFile::new(0);
+    if sock.bind(port as u16).is_err() {
+        return u64::MAX; // EADDRINUSE
+    }
+    install_socket(Arc::new(SpinLock::new(sock)), 0)
+}
+
+fn sys_recvfrom_fd(fd: u64, buf_ptr: u64, len: u64, src_ptr: u64) -> u64 {
+    let len = len as usize;
+    if !user_access_ok(buf_ptr, len, Access::Write) {
+        return u64::MAX; // EFAULT
+    }
+    let (port, nonblocking, family) = match with_socket::<SocketFile, _>(fd, |s| (s.port, s.nonblocking, s.family)) {
+        Some((port, nonblocking, family)) if port != 0 => (port, nonblocking, family),
+        _ => return u64::MAX, // ENOTSOCK
+    };
+    // IPv4 sockets report 8 bytes, IPv6 sockets 20 (see SYS_RECVFROM_FD)
+    let src_len = if family == socket::AF_INET6 { 20 } else { 8 };
+    if src_ptr != 0 && !user_access_ok(src_ptr, src_len, Access::Write) {
+        return u64::MAX; // EFAULT
+    }
+
+    // Sleep until a datagram arrives (the file stays unlocked meanwhile)
+    let msg = if nonblocking {
+        socket::take_datagram(port, family)
+    } else {
+        crate::net::udp::RX_WAIT.wait_until(|| socket::take_datagram(port, family)).ok()
+    };
+    let msg = match msg {
+        Some(msg) => msg,
+        None => return u64::MAX, // EWOULDBLOCK / EINTR
+    };
+
+    let copy_len = msg.payload.len().min(len);
+    if uaccess::copy_to_user(buf_ptr, &msg.payload[..copy_len]).is_err() {
+        return u64::MAX; // EFAULT
+    }
+
+    if src_ptr != 0 {
+        // Source address (IPv4, or IPv6 with IPv4 mapped), port (big-endian), padding
+        let mut src = [0u8; 20];
+        let addr_len = match msg.src_addr {
+            IpAddr::V4(ip) if family == socket::AF_INET => {
+                src[..4].copy_from_slice(&ip.0);
+                4
+            }
+            ip => {
+                src[..16].copy_from_slice(&ip.to_ipv6().0);
+                16
+            }
+        };
+        src[addr_len..addr_len + 2].copy_from_slice(&msg.src_port.to_be_bytes());
+        if uaccess::copy_to_user(src_ptr, &src[..src_len]).is_err() {
+            return u64::MAX; // EFAULT
+        }
+    }
+
+    copy_len as u64
+}
+
+/// Most addresses returned by one `SYS_GETADDRINFO`
+const GETADDRINFO_MAX: usize = 16;
+
+fn sys_getaddrinfo(name_ptr: u64, addrs_ptr: u64, max: u64) -> u64 {
+    // Queries go out on the wire like any other socket traffic
+    if !check_privileged_io() {
+        crate::kprintln!("[SECURITY] sys_getaddrinfo DENIED: Missing Driver Capability");
+        return u64::MAX; // EPERM
+    }
+    let name = match read_user_path(name_ptr) {
+        Some(name) => name,
+        None => return u64::MAX, // EFAULT
+    };
+    let max = (max as usize).min(GETADDRINFO_MAX);
+    if max == 0 || !user_access_ok(addrs_ptr, max * 4, Access::Write) {
+        return u64::MAX; // EINVAL / EFAULT
+    }
+
+    // Blocks without holding any lock
+    let addrs = match crate::net::dns::resolve_ipv4(&name) {
+        Ok(addrs) if !addrs.is_empty() => addrs,
+        _ => return u64::MAX, // EAI_NONAME / EAI_AGAIN
+    };
+
+    let count = addrs.len().min(max);
+    let out: Vec<u8> = addrs.iter().take(count).flat_map(|addr| addr.0).collect();
+    if uaccess::copy_to_user(addrs_ptr, &out).is_err() {
+        return u64::MAX; // EFAULT
+    }
+    count as u64
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// IPC
+// ═══════════════════════════════════════════════════════════════════════════════
+
+fn sys_ipc_send(pid: u64, msg_ptr: u64) -> u64 {
+    if !user_access_ok(msg_ptr, abi::IPC_MSG_SIZE, Access::Read) {
+        return u64::MAX; // EFAULT
+    }
+
+    let mut data = [0u8; abi::IPC_MSG_SIZE];
+    if uaccess::copy_from_user(&mut data, msg_ptr).is_err() {
+        return u64::MAX; // EFAULT
+    }
+
+    let mut scheduler = SCHEDULER.lock();
+    let sender = match scheduler.current_process_id() {
+        Some(pid) => AgentId(pid),
+        None => return u64::MAX,
+    };
+    match scheduler.send_message(pid, Message { sender, data }) {
+        Ok(()) => 0,
+        Err(_) => u64::MAX, // ESRCH / EAGAIN
+    }
+}
+
+fn sys_ipc_recv(buf_ptr: u64) -> u64 {
+    if !user_access_ok(buf_ptr, abi::IPC_MSG_SIZE, Access::Write) {
+        return u64::MAX; // EFAULT
+    }
+
+    // Sleep until send_message() delivers to our mailbox
+    let msg = scheduler::MAILBOX_WAIT.wait_until(|| {
+        let mut scheduler = SCHEDULER.lock();
+        scheduler.with_current_process(|agent| agent.mailbox.lock().pop_front()).flatten()
+    });
+
+    match msg {
+        Ok(msg) => match uaccess::copy_to_user(buf_ptr, &msg.data) {
+            Ok(()) => msg.sender.0,
+            Err(_) => u64::MAX, // EFAULT
+        },
+        Err(_) => u64::MAX, // EWOULDBLOCK / EINTR
+    }
+}
+
+fn sys_announce(concept_id: u64) -> u64 {
+    let pid = match SCHEDULER.lock().current_process_id() {
+        Some(pid) => pid,
+        None => return u64::MAX,
+    };
+    kprintln!("[IPC] PID {} announced concept 0x{:X}", pid, concept_id);
+    crate::apps::registry::REGISTRY.lock().register_pid(ConceptID(concept_id), pid);
+    0
+}
+
 fn sys_parse_intent(ptr: u64, len: u64) -> u64 {
-    let ptr_raw = ptr as *const u8;
     let len = len as usize;
     
     // Validate pointer
-    if crate::kernel::memory::validate_read_ptr(ptr_raw, len).is_err() {
+    if !user_access_ok(ptr, len, Access::Read) {
         return u64::MAX;
     }
     
     // Read string
-    let slice = unsafe { core::slice::from_raw_parts(ptr_raw, len) };
-    let input = match core::str::from_utf8(slice) {
+    let mut bytes = alloc::vec![0u8; len];
+    if uaccess::copy_from_user(&mut bytes, ptr).is_err() {
+        return u64::MAX; // EFAULT
+    }
+    let input = match core::str::from_utf8(&bytes) {
         Ok(s) => s,
         Err(_) => return u64::MAX,
     };
@@ -872,93 +1893,96 @@ fn sys_parse_intent(ptr: u64, len: u64) -> u64 {
 
 fn sys_getdents64(fd: u64, buf_ptr: u64, len: u64) -> u64 {
     // Validate write access to user buffer
-    let buf_raw = buf_ptr as *mut u8;
     let buf_len = len as usize;
-    if crate::kernel::memory::validate_write_ptr(buf_raw, buf_len).is_err() {
+    if !user_access_ok(buf_ptr, buf_len, Access::Write) {
         return u64::MAX; // EFAULT
     }
     
+    // Clone the file out so readdir runs without the scheduler lock
+    // (procfs needs it to list agents)
+    let file = match current_file(fd) {
+        Some(file) => file,
+        None => {
+            kprintln!("[DEBUG] sys_getdents64: Invalid file descriptor {}", fd);
+            return u64::MAX; // EBADF
+        }
+    };
+    let mut file = file.lock();
     let mut bytes_written = 0;
-    
-    let mut scheduler = SCHEDULER.lock();
-    let res = scheduler.with_current_agent(|agent| {
-        if let Ok(desc) = agent.file_table.get_fd(fd as usize) {
-            let mut file = desc.file.lock();
-            
-            // Loop until buffer is full or directories exhausted
-            loop {
-                match file.readdir() {
-                    Ok(Some(entry)) => {
-                        // Struct linux_dirent64 {
-                        //    u64        d_ino;    // 8 bytes
-                        //    s64        d_off;    // 8 bytes
-                        //    unsigned short d_reclen; // 2 bytes
-                        //    unsigned char  d_type;   // 1 byte
-                        //    char           d_name[]; // variable
-                        // }
-                        
-                        let name_len = entry.name.len();
-                        // 8 + 8 + 2 + 1 + name_len + 1 (null terminator)
-                        let raw_size = 19 + name_len + 1;
-                        // Align to 8 bytes
-                        let reclen = (raw_size + 7) & !7;
-                        
-                        if bytes_written + reclen > buf_len {
-                            // Buffer full - rewind? 
-                            // Current readdir implementation consumes state. 
-                            // We should probably push back or support seeking directory.
-                            // For simplicity, stop here.
-                            // But we already consumed the entry!
-                            // TODO: Add push-back or peek support to readdir 
-                            // For now, we just lose this entry if it doesn't fit on the last call.
-                            // But usually buffers are large (4k).
-                            break;
-                        }
-                        
-                        // Serialize
-                        let d_ino = 1u64; // Fake inode
-                        let d_off = 0i64; // Offset not tracked yet
-                        let d_reclen = reclen as u16;
-                        let d_type = if entry.is_dir { 4 } else { 8 }; // DT_DIR=4, DT_REG=8
-                        
-                        unsafe {
-                            let ptr = buf_raw.add(bytes_written);
-                            // d_ino
-                            *(ptr as *mut u64) = d_ino;
-                            // d_off
-                            *(ptr.add(8) as *mut i64) = d_off;
-                            // d_reclen
-                            *(ptr.add(16) as *mut u16) = d_reclen;
-                            // d_type
-                            *ptr.add(18) = d_type;
-                            // d_name
-                            core::ptr::copy_nonoverlapping(entry.name.as_ptr(), ptr.add(19), name_len);
-                            *ptr.add(19 + name_len) = 0; // Null terminator
-                            // Zero padding
-                            for i in (19 + name_len + 1)..reclen {
-                                *ptr.add(bytes_written + i) = 0;
-                            }
-                        }
-                        
-                        bytes_written += reclen;
-                    },
-                    Ok(None) => {
-                        kprintln!("[DEBUG] sys_getdents64: EOF reached");
-                        break; // EOF
-                    }, 
-                    Err(e) => {
-                        kprintln!("[DEBUG] sys_getdents64: Error reading directory: {:?}", e);
-                        break; // Error
-                    },
+
+    // Loop until buffer is full or directories exhausted
+    loop {
+        match file.readdir() {
+            Ok(Some(entry)) => {
+                // Struct linux_dirent64 {
+                //    u64        d_ino;    // 8 bytes
+                //    s64        d_off;    // 8 bytes
+                //    unsigned short d_reclen; // 2 bytes
+                //    unsigned char  d_type;   // 1 byte
+                //    char           d_name[]; // variable
+                // }
+                
+                let name_len = entry.name.len();
+                // 8 + 8 + 2 + 1 + name_len + 1 (null terminator)
+                let raw_size = 19 + name_len + 1;
+                // Align to 8 bytes
+                let reclen = (raw_size + 7) & !7;
+                
+                if bytes_written + reclen > buf_len {
+                    // Buffer full - rewind? 
+                    // Current readdir implementation consumes state. 
+                    // We should probably push back or support seeking directory.
+                    // For simplicity, stop here.
+                    // But we already consumed the entry!
+                    // TODO: Add push-back or peek support to readdir 
+                    // For now, we just lose this entry if it doesn't fit on the last call.
+                    // But usually buffers are large (4k).
+                    break;
                 }
-            }
-            kprintln!("[DEBUG] sys_getdents64: Returning {} bytes written", bytes_written);
-            bytes_written as u64
-        } else {
-            kprintln!("[DEBUG] sys_getdents64: Invalid file descriptor {}", fd);
-            u64::MAX // EBADF
+                
+                // Serialize
+                let d_ino = 1u64; // Fake inode
+                let d_off = 0i64; // Offset not tracked yet
+                let d_reclen = reclen as u16;
+                let d_type = if entry.is_dir { 4 } else { 8 }; // DT_DIR=4, DT_REG=8
+                
+                let mut record = alloc::vec![0u8; reclen]; // NUL terminator and padding stay zero
+                record[0..8].copy_from_slice(&d_ino.to_ne_bytes());
+                record[8..16].copy_from_slice(&d_off.to_ne_bytes());
+                record[16..18].copy_from_slice(&d_reclen.to_ne_bytes());
+                record[18] = d_type;
+                record[19..19 + name_len].copy_from_slice(entry.name.as_bytes());
+                if uaccess::copy_to_user(buf_ptr + bytes_written as u64, &record).is_err() {
+                    return u64::MAX; // EFAULT
+                }
+                
+                bytes_written += reclen;
+            },
+            Ok(None) => {
+                kprintln!("[DEBUG] sys_getdents64: EOF reached");
+                break; // EOF
+            }, 
+            Err(e) => {
+                kprintln!("[DEBUG] sys_getdents64: Error reading directory: {:?}", e);
+                break; // Error
+            },
         }
-    }).unwrap_or(u64::MAX);
+    }
+    kprintln!("[DEBUG] sys_getdents64: Returning {} bytes written", bytes_written);
+    bytes_written as u64
+}
 
-    res
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_abi_numbers_round_trip() {
+        for n in abi::SYS_EXIT..=abi::SYS_GETTID {
+            let syscall = SyscallNumber::from(n);
+            assert_ne!(syscall, SyscallNumber::Unknown, "syscall {} not dispatched", n);
+            assert_eq!(syscall as u64, n);
+        }
+        assert_eq!(SyscallNumber::from(abi::SYS_GETTID + 1), SyscallNumber::Unknown);
+    }
 }
@@ -120,6 +120,7 @@ pub extern "C" fn kernel_main() -> ! {
     // Use a default MAC (VirtIO will detect its own, RP1 needs one)
     let mac = drivers::ethernet::MacAddr::from_bytes([0x52, 0x54, 0x00, 0x12, 0x34, 0x56]);
     drivers::ethernet::init(mac);
+    net::init();
     
     // Initialize mailbox (GPU communication)
     if machine == dtb::MachineType::RaspberryPi5 {
@@ -378,6 +379,13 @@ flow:
             kprintln!("       Mounted SD Card at /");
         } else {
             kprintln!("       Failed to mount SD card");
+            // Fall back to the initrd (if any) in RAM
+            let root = match drivers::ramdisk::archive() {
+                Some(archive) => fs::tmpfs::TmpFs::with_lower(archive),
+                None => fs::tmpfs::TmpFs::new(),
+            };
+            let _ = fs::mount("/", root);
+            kprintln!("       Mounted tmpfs at /");
         }
     } else {
         // QEMU / VirtIO Block
@@ -404,7 +412,28 @@ flow:
         } else {
             kprintln!("       VirtIO Block init failed (expected if no -drive)");
         }
+        
+        // No disk: run from RAM
+        if fs::VFS.lock().stat("/").is_err() {
+            let _ = fs::mount("/", fs::tmpfs::TmpFs::new());
+            kprintln!("       Mounted tmpfs at /");
+        }
     }
+    
+    // Scratch space, never persisted
+    let _ = fs::mount("/tmp", fs::tmpfs::TmpFs::new());
+    kprintln!("       Mounted tmpfs at /tmp");
+    
+    // Device nodes
+    let _ = fs::mount("/dev", fs::devfs::DevFs::new());
+    kprintln!("       Mounted devfs at /dev");
+
+    // Kernel introspection
+    let _ = fs::mount("/proc", fs::procfs::ProcFs::new());
+    kprintln!("       Mounted procfs at /proc");
+
+    // Static network settings, used if DHCP gets no answer
+    load_net_fallback();
 
     // ═══════════════════════════════════════════════════════════════════════════════
     // PERSISTENCE TEST
@@ -460,7 +489,7 @@ flow:
                     
                     // Spawn User Process
                     let mut scheduler = kernel::scheduler::SCHEDULER.lock();
-                    match scheduler.spawn_user_elf(&buf) {
+                    match scheduler.spawn_user_elf(&buf, &["/init"]) {
                         Ok(_) => kprintln!("       Spawned User Process 1 (init)"),
                         Err(e) => kprintln!("       Failed to spawn user process 1: {}", e),
                     }
@@ -477,7 +506,7 @@ flow:
             kprintln!("       Loading embedded init...");
             // Embed the binary
             let init_bin = include_bytes!("../../user/init/target/aarch64-unknown-none/release/init");
-            match kernel::scheduler::SCHEDULER.lock().spawn_user_elf(init_bin) {
+            match kernel::scheduler::SCHEDULER.lock().spawn_user_elf(init_bin, &["/init"]) {
                 Ok(_) => kprintln!("       Spawned Embedded User Process (init)"),
                 Err(e) => kprintln!("       Failed to spawn embedded init: {}", e),
             }
@@ -519,6 +548,7 @@ fn async_executor_agent() {
     let mut executor = kernel::async_core::Executor::new();
     executor.spawn(steno_loop());
     executor.spawn(usb_loop());
+    executor.spawn(intent::remote::serve(intent::remote::DEFAULT_PORT));
     executor.run();
 }
 
@@ -934,6 +964,21 @@ fn demo_llm() {
     kprintln!("       ════════════════════════════════════════════");
 }
 
+/// Read the `net_*` keys of /config.txt for the DHCP fallback
+fn load_net_fallback() {
+    let Ok(file) = fs::VFS.lock().open("/config.txt", 0) else { return };
+    let mut text = alloc::vec::Vec::new();
+    let mut buf = [0u8; 512];
+    let mut file = file.lock();
+    while let Ok(n) = file.read(&mut buf) {
+        if n == 0 { break; }
+        text.extend_from_slice(&buf[..n]);
+    }
+    if let Ok(text) = core::str::from_utf8(&text) {
+        net::dhcp::set_static_config(text);
+    }
+}
+
 #[allow(dead_code)]
 fn persistence_test() {
     kprintln!("\n[TEST] Testing Persistence...");
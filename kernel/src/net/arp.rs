@@ -1,6 +1,8 @@
 use alloc::vec::Vec;
+use alloc::collections::VecDeque;
 use core::convert::TryInto;
 use crate::net::ethernet::MacAddress;
+use crate::net::interface::InterfaceId;
 use crate::net::ip::Ipv4Addr;
 use crate::kernel::sync::SpinLock;
 
@@ -97,6 +99,18 @@ impl ArpPacket {
 
 /// Maximum ARP cache entries
 const MAX_ARP_ENTRIES: usize = 16;
+/// Entries are forgotten this long after they were learned (RFC 826 practice)
+const ARP_TIMEOUT_MS: u64 = 20 * 60 * 1000;
+
+/// Destinations that may be awaiting a reply at once
+const MAX_PENDING: usize = 8;
+/// Frames held per unresolved destination (enough for the fragments of
+/// one maximum-size IPv4 datagram)
+const MAX_QUEUED_FRAMES: usize = 48;
+/// Delay between repeated requests
+const ARP_RETRY_MS: u64 = 1000;
+/// Requests sent before the destination is declared unreachable
+const ARP_MAX_REQUESTS: u8 = 3;
 
 /// ARP cache entry
 #[derive(Debug, Clone, Copy)]
@@ -104,106 +118,262 @@ struct ArpEntry {
     ip: Ipv4Addr,
     mac: MacAddress,
     timestamp_ms: u64, // Time when entry was added (for expiration)
+    last_used_ms: u64, // Time of the last lookup hit (for LRU replacement)
 }
 
 /// ARP cache
 struct ArpCache {
     entries: [Option<ArpEntry>; MAX_ARP_ENTRIES],
-    count: usize,
 }
 
 impl ArpCache {
     const fn new() -> Self {
         Self {
             entries: [None; MAX_ARP_ENTRIES],
-            count: 0,
         }
     }
-    
-    fn lookup(&self, ip: Ipv4Addr) -> Option<MacAddress> {
-        let now = crate::drivers::timer::uptime_ms();
-        const ARP_TIMEOUT_MS: u64 = 20 * 60 * 1000; // 20 minutes per RFC 826
-        
-        for entry in self.entries.iter().flatten() {
-            if entry.ip == ip {
-                // Check if entry has expired
-                if now.saturating_sub(entry.timestamp_ms) < ARP_TIMEOUT_MS {
-                    return Some(entry.mac);
-                }
-                // Entry expired, will be replaced
-            }
+
+    fn lookup(&mut self, ip: Ipv4Addr, now: u64) -> Option<MacAddress> {
+        let slot = self.entries.iter_mut().find(|e| e.is_some_and(|e| e.ip == ip))?;
+        let entry = slot.as_mut()?;
+        if now.saturating_sub(entry.timestamp_ms) >= ARP_TIMEOUT_MS {
+            *slot = None; // Expired: force a fresh request
+            return None;
         }
-        None
+        entry.last_used_ms = now;
+        Some(entry.mac)
     }
-    
-    fn insert(&mut self, ip: Ipv4Addr, mac: MacAddress) {
-        let now = crate::drivers::timer::uptime_ms();
-        
+
+    fn insert(&mut self, ip: Ipv4Addr, mac: MacAddress, now: u64) {
         // Check if already exists (update timestamp)
-        for entry in self.entries.iter_mut().flatten() {
-            if entry.ip == ip {
-                entry.mac = mac;
-                entry.timestamp_ms = now;
-                return;
+        if let Some(entry) = self.entries.iter_mut().flatten().find(|e| e.ip == ip) {
+            entry.mac = mac;
+            entry.timestamp_ms = now;
+            return;
+        }
+
+        let fresh = ArpEntry { ip, mac, timestamp_ms: now, last_used_ms: now };
+
+        // Find empty slot, otherwise replace the least recently used entry
+        let slot = match self.entries.iter().position(|e| e.is_none()) {
+            Some(idx) => idx,
+            None => self.entries.iter().enumerate()
+                .min_by_key(|(_, e)| e.map_or(0, |e| e.last_used_ms))
+                .map_or(0, |(idx, _)| idx),
+        };
+        self.entries[slot] = Some(fresh);
+    }
+
+    /// Drop entries older than `ARP_TIMEOUT_MS`
+    fn expire(&mut self, now: u64) {
+        for slot in self.entries.iter_mut() {
+            if slot.is_some_and(|e| now.saturating_sub(e.timestamp_ms) >= ARP_TIMEOUT_MS) {
+                *slot = None;
             }
         }
-        
-        // Find empty slot
-        for entry in &mut self.entries {
-            if entry.is_none() {
-                *entry = Some(ArpEntry { ip, mac, timestamp_ms: now });
-                self.count += 1;
-                return;
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// PENDING RESOLUTIONS
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Frames waiting for the MAC address of `ip`
+struct Pending {
+    /// Interface the request goes out on, and the frames after it
+    iface: InterfaceId,
+    ip: Ipv4Addr,
+    /// Complete Ethernet frames; the destination MAC is filled in on reply
+    frames: VecDeque<Vec<u8>>,
+    requests_sent: u8,
+    next_retry_ms: u64,
+}
+
+/// Cache plus the packets held until their next hop answers
+struct ArpTable {
+    cache: ArpCache,
+    pending: Vec<Pending>,
+}
+
+/// What the caller must transmit after updating the table
+#[derive(Debug, PartialEq, Eq)]
+enum Action {
+    /// Send this frame now (destination MAC known)
+    Send(Vec<u8>),
+    /// Frame queued; broadcast a request for this IP
+    Request(Ipv4Addr),
+    /// Frame queued behind an outstanding request
+    Queued,
+}
+
+impl ArpTable {
+    const fn new() -> Self {
+        Self {
+            cache: ArpCache::new(),
+            pending: Vec::new(),
+        }
+    }
+
+    /// Address `frame` to `next_hop`, or hold it until the hop resolves
+    fn submit(&mut self, iface: InterfaceId, next_hop: Ipv4Addr, mut frame: Vec<u8>, now: u64) -> Result<Action, &'static str> {
+        if let Some(mac) = self.cache.lookup(next_hop, now) {
+            frame[0..6].copy_from_slice(&mac.0);
+            return Ok(Action::Send(frame));
+        }
+
+        if let Some(pending) = self.pending.iter_mut().find(|p| p.ip == next_hop) {
+            if pending.frames.len() >= MAX_QUEUED_FRAMES {
+                // Oldest frame makes way, like a full device queue
+                pending.frames.pop_front();
             }
+            pending.frames.push_back(frame);
+            return Ok(Action::Queued);
         }
-        
-        // Cache full, replace first entry
-        self.entries[0] = Some(ArpEntry { ip, mac, timestamp_ms: now });
+
+        if self.pending.len() >= MAX_PENDING {
+            return Err("ARP queue full");
+        }
+        let mut frames = VecDeque::new();
+        frames.push_back(frame);
+        self.pending.push(Pending {
+            iface,
+            ip: next_hop,
+            frames,
+            requests_sent: 1,
+            next_retry_ms: now + ARP_RETRY_MS,
+        });
+        Ok(Action::Request(next_hop))
+    }
+
+    /// Record a mapping; returns the frames it releases, already addressed
+    fn learn(&mut self, ip: Ipv4Addr, mac: MacAddress, now: u64) -> Vec<(InterfaceId, Vec<u8>)> {
+        self.cache.insert(ip, mac, now);
+        let Some(idx) = self.pending.iter().position(|p| p.ip == ip) else {
+            return Vec::new();
+        };
+        let pending = self.pending.swap_remove(idx);
+        pending.frames.into_iter().map(|mut frame| {
+            frame[0..6].copy_from_slice(&mac.0);
+            (pending.iface, frame)
+        }).collect()
+    }
+
+    /// Age the cache and collect the requests to repeat
+    ///
+    /// Destinations that stay silent after `ARP_MAX_REQUESTS` are dropped
+    /// together with their queued frames.
+    fn tick(&mut self, now: u64) -> Vec<(InterfaceId, Ipv4Addr)> {
+        self.cache.expire(now);
+
+        let mut retry = Vec::new();
+        self.pending.retain_mut(|p| {
+            if now < p.next_retry_ms {
+                return true;
+            }
+            if p.requests_sent >= ARP_MAX_REQUESTS {
+                crate::kprintln!("[ARP] {} unreachable, dropped {} frame(s)", p.ip, p.frames.len());
+                return false;
+            }
+            p.requests_sent += 1;
+            p.next_retry_ms = now + ARP_RETRY_MS;
+            retry.push((p.iface, p.ip));
+            true
+        });
+        retry
     }
 }
 
-/// Global ARP cache
-static ARP_CACHE: SpinLock<ArpCache> = SpinLock::new(ArpCache::new());
+/// Global ARP state
+static ARP_TABLE: SpinLock<ArpTable> = SpinLock::new(ArpTable::new());
+
+fn now_ms() -> u64 {
+    crate::drivers::timer::uptime_ms()
+}
 
 /// Resolve an IP address to a MAC address
 ///
-/// Returns cached MAC if available, otherwise returns None.
-/// A full implementation would send an ARP request and wait.
+/// Only consults the cache; use `send_to` to transmit through resolution.
 pub fn resolve(ip: Ipv4Addr) -> Option<MacAddress> {
-    ARP_CACHE.lock().lookup(ip)
+    ARP_TABLE.lock().cache.lookup(ip, now_ms())
 }
 
-/// Add an entry to the ARP cache
+/// Add an entry to the ARP cache, flushing any frames waiting for it
 pub fn cache_insert(ip: Ipv4Addr, mac: MacAddress) {
-    ARP_CACHE.lock().insert(ip, mac);
+    let released = ARP_TABLE.lock().learn(ip, mac, now_ms());
+    for (iface, frame) in released {
+        let _ = crate::net::interface::send_frame_on(iface, &frame);
+    }
+}
+
+/// Transmit an Ethernet frame on `iface` whose next hop is `next_hop`
+///
+/// If the hop's MAC is unknown the frame is queued, a request goes out and
+/// the frame is sent when the reply arrives.
+pub fn send_to(iface: InterfaceId, next_hop: Ipv4Addr, frame: Vec<u8>) -> Result<(), &'static str> {
+    let action = ARP_TABLE.lock().submit(iface, next_hop, frame, now_ms())?;
+    match action {
+        Action::Send(frame) => crate::net::interface::send_frame_on(iface, &frame),
+        Action::Request(ip) => request(iface, ip),
+        Action::Queued => Ok(()),
+    }
+}
+
+/// Periodic maintenance: repeat requests and expire entries
+pub fn tick() {
+    let retry = ARP_TABLE.lock().tick(now_ms());
+    for (iface, ip) in retry {
+        let _ = request(iface, ip);
+    }
 }
 
-/// Handle incoming ARP packet
-pub fn handle_packet(data: &[u8]) -> Result<(), &'static str> {
+fn packet(iface: InterfaceId, operation: ArpOperation, target_hw_addr: MacAddress, target_proto_addr: Ipv4Addr) -> ArpPacket {
+    let cfg = crate::net::config();
+    ArpPacket {
+        hardware_type: 1,
+        protocol_type: 0x0800,
+        hw_addr_len: 6,
+        proto_addr_len: 4,
+        operation,
+        sender_hw_addr: crate::net::interface::mac_address(iface).unwrap_or(cfg.mac_addr),
+        sender_proto_addr: cfg.ip_addr,
+        target_hw_addr,
+        target_proto_addr,
+    }
+}
+
+/// Broadcast a who-has request for `ip` on `iface`; the reply fills the cache
+pub fn request(iface: InterfaceId, ip: Ipv4Addr) -> Result<(), &'static str> {
+    crate::net::interface::send_arp(iface, &packet(iface, ArpOperation::Request, MacAddress::ZERO, ip))
+}
+
+/// Gratuitous ARP: announce our address so neighbours refresh their caches
+pub fn announce() -> Result<(), &'static str> {
+    let ip = crate::net::config().ip_addr;
+    if ip == Ipv4Addr::ANY {
+        return Ok(()); // Nothing to announce yet
+    }
+    // A request for our own address, sender and target both us
+    let iface = crate::net::interface::default_id().ok_or("No network interface")?;
+    request(iface, ip)
+}
+
+/// Handle an ARP packet received on `iface`
+pub fn handle_packet(iface: InterfaceId, data: &[u8]) -> Result<(), &'static str> {
     let packet = ArpPacket::parse(data)?;
-    
+
     // Cache the sender's MAC (we learned something!)
-    cache_insert(packet.sender_proto_addr, packet.sender_hw_addr);
-    
+    if packet.sender_proto_addr != Ipv4Addr::ANY {
+        cache_insert(packet.sender_proto_addr, packet.sender_hw_addr);
+    }
+
     match packet.operation {
         ArpOperation::Request => {
             // Check if this request is for our IP
             let cfg = crate::net::config();
-            if packet.target_proto_addr == cfg.ip_addr {
+            if packet.target_proto_addr == cfg.ip_addr && cfg.ip_addr != Ipv4Addr::ANY {
                 // Send ARP reply
-                let reply = ArpPacket {
-                    hardware_type: 1,
-                    protocol_type: 0x0800,
-                    hw_addr_len: 6,
-                    proto_addr_len: 4,
-                    operation: ArpOperation::Reply,
-                    sender_hw_addr: cfg.mac_addr,
-                    sender_proto_addr: cfg.ip_addr,
-                    target_hw_addr: packet.sender_hw_addr,
-                    target_proto_addr: packet.sender_proto_addr,
-                };
-                
-                let _ = crate::net::interface::send_arp_reply(&reply);
+                let reply = self::packet(iface, ArpOperation::Reply, packet.sender_hw_addr, packet.sender_proto_addr);
+                let _ = crate::net::interface::send_arp(iface, &reply);
             }
         }
         ArpOperation::Reply => {
@@ -211,6 +381,72 @@ pub fn handle_packet(data: &[u8]) -> Result<(), &'static str> {
         }
         _ => {}
     }
-    
+
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    fn ip(last: u8) -> Ipv4Addr {
+        Ipv4Addr([10, 0, 2, last])
+    }
+
+    fn mac(last: u8) -> MacAddress {
+        MacAddress([0x52, 0x54, 0, 0, 0, last])
+    }
+
+    #[test]
+    fn test_cache_expiry() {
+        let mut cache = ArpCache::new();
+        cache.insert(ip(1), mac(1), 0);
+        assert_eq!(cache.lookup(ip(1), ARP_TIMEOUT_MS - 1), Some(mac(1)));
+        assert_eq!(cache.lookup(ip(1), ARP_TIMEOUT_MS), None);
+        assert!(cache.entries.iter().all(|e| e.is_none()));
+    }
+
+    #[test]
+    fn test_cache_lru_replacement() {
+        let mut cache = ArpCache::new();
+        for i in 0..MAX_ARP_ENTRIES as u8 {
+            cache.insert(ip(i), mac(i), i as u64);
+        }
+        // Touch the oldest so the second oldest becomes the victim
+        assert!(cache.lookup(ip(0), 100).is_some());
+        cache.insert(ip(200), mac(200), 101);
+        assert!(cache.lookup(ip(0), 102).is_some());
+        assert!(cache.lookup(ip(1), 102).is_none());
+        assert_eq!(cache.lookup(ip(200), 102), Some(mac(200)));
+    }
+
+    #[test]
+    fn test_pending_released_on_reply() {
+        let mut table = ArpTable::new();
+        let frame = vec![0u8; 42];
+        assert_eq!(table.submit(1, ip(2), frame.clone(), 0), Ok(Action::Request(ip(2))));
+        assert_eq!(table.submit(1, ip(2), frame.clone(), 1), Ok(Action::Queued));
+
+        let released = table.learn(ip(2), mac(2), 5);
+        assert_eq!(released.len(), 2);
+        assert!(released.iter().all(|(iface, f)| *iface == 1 && f[0..6] == mac(2).0));
+        assert!(table.pending.is_empty());
+
+        match table.submit(1, ip(2), frame, 6) {
+            Ok(Action::Send(f)) => assert_eq!(f[0..6], mac(2).0),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_retry_then_give_up() {
+        let mut table = ArpTable::new();
+        table.submit(1, ip(3), vec![0u8; 42], 0).unwrap();
+        assert!(table.tick(ARP_RETRY_MS - 1).is_empty());
+        assert_eq!(table.tick(ARP_RETRY_MS), vec![(1, ip(3))]);
+        assert_eq!(table.tick(2 * ARP_RETRY_MS), vec![(1, ip(3))]);
+        assert!(table.tick(3 * ARP_RETRY_MS).is_empty());
+        assert!(table.pending.is_empty());
+    }
+}
@@ -0,0 +1,633 @@
+//! Packet Capture (libpcap)
+//!
+//! When a capture is running, `interface` hands every frame it transmits
+//! or receives to `tap`. Frames that pass the `CaptureFilter` become
+//! classic libpcap records (microsecond timestamps, Ethernet link type)
+//! that Wireshark and tcpdump open directly.
+//!
+//! Records are queued in memory and written out from `tick`, never from
+//! the send/receive path. `tick` runs in the timer interrupt, so each call
+//! hands the sink only a small budget of bytes. The queue is bounded: when
+//! the sink falls behind, frames are counted as dropped instead of stalling
+//! the stack.
+//!
+//! Two sinks are available. A VFS file is the easiest to use. The UART
+//! sink works before any file system is mounted: each record becomes one
+//! hex line prefixed with `#PCAP `, so a capture can be pulled out of a
+//! serial log with
+//!
+//! ```text
+//! grep '^#PCAP ' serial.log | cut -c7- | xxd -r -p > capture.pcap
+//! ```
+
+use alloc::collections::VecDeque;
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicBool, Ordering};
+use crate::fs::vfs::FileOps;
+use crate::kernel::sync::SpinLock;
+use crate::net::ip::{IpAddr, Ipv4Addr, Ipv6Addr};
+use crate::net::interface::MAX_FRAME_SIZE;
+
+const PCAP_MAGIC: u32 = 0xA1B2_C3D4;
+const PCAP_VERSION_MAJOR: u16 = 2;
+const PCAP_VERSION_MINOR: u16 = 4;
+const LINKTYPE_ETHERNET: u32 = 1;
+const GLOBAL_HEADER_LEN: usize = 24;
+const RECORD_HEADER_LEN: usize = 16;
+
+/// Bytes queued before new frames are dropped
+const MAX_PENDING_BYTES: usize = 256 * 1024;
+/// Bytes one `tick` hands the UART: 512 hex digits, about 45 ms of the
+/// polled line at 115200 baud, well inside the 100 ms between ticks
+const UART_BYTES_PER_TICK: usize = 256;
+/// Bytes one `tick` hands a file sink
+const FILE_BYTES_PER_TICK: usize = 16 * 1024;
+/// Line prefix marking a record on the UART
+pub const UART_PREFIX: &str = "#PCAP ";
+
+const ETHERTYPE_IPV4: u16 = 0x0800;
+const ETHERTYPE_ARP: u16 = 0x0806;
+const ETHERTYPE_IPV6: u16 = 0x86DD;
+const ETH_HEADER_LEN: usize = 14;
+const IPV6_HEADER_LEN: usize = 40;
+
+const PROTO_ICMP: u8 = 1;
+const PROTO_TCP: u8 = 6;
+const PROTO_UDP: u8 = 17;
+const PROTO_ICMPV6: u8 = 58;
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// FILTER
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Protocols a filter can select
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Protocol {
+    Arp,
+    /// ICMP and ICMPv6
+    Icmp,
+    Tcp,
+    Udp,
+}
+
+impl Protocol {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "arp" => Some(Protocol::Arp),
+            "icmp" | "icmp6" | "icmpv6" => Some(Protocol::Icmp),
+            "tcp" => Some(Protocol::Tcp),
+            "udp" => Some(Protocol::Udp),
+            _ => None,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Protocol::Arp => "arp",
+            Protocol::Icmp => "icmp",
+            Protocol::Tcp => "tcp",
+            Protocol::Udp => "udp",
+        }
+    }
+}
+
+/// Which frames are captured; every unset field matches anything
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CaptureFilter {
+    pub protocol: Option<Protocol>,
+    /// Source or destination port (TCP/UDP)
+    pub port: Option<u16>,
+    /// Source or destination address
+    pub host: Option<IpAddr>,
+}
+
+impl CaptureFilter {
+    /// Parse a tcpdump-like expression, e.g. `tcp port 80 host 10.0.2.2`
+    ///
+    /// Terms may come in any order; an empty expression, `any` or `all`
+    /// captures everything.
+    pub fn parse(expr: &str) -> Result<Self, &'static str> {
+        let mut filter = Self::default();
+        let mut words = expr.split_whitespace();
+        while let Some(word) = words.next() {
+            match word {
+                "any" | "all" => {}
+                "port" => {
+                    let port = words.next().ok_or("Missing port number")?;
+                    filter.port = Some(port.parse().map_err(|_| "Invalid port number")?);
+                }
+                "host" => {
+                    let host = words.next().ok_or("Missing host address")?;
+                    filter.host = Some(IpAddr::parse(host).ok_or("Invalid host address")?);
+                }
+                _ => {
+                    filter.protocol = Some(Protocol::from_name(word).ok_or("Unknown filter term")?);
+                }
+            }
+        }
+        if filter.port.is_some() && matches!(filter.protocol, Some(Protocol::Arp | Protocol::Icmp)) {
+            return Err("Ports only apply to tcp and udp");
+        }
+        Ok(filter)
+    }
+
+    /// Does an Ethernet frame pass the filter?
+    pub fn matches(&self, frame: &[u8]) -> bool {
+        if *self == Self::default() {
+            return true;
+        }
+        let Some(info) = dissect(frame) else { return false };
+
+        if let Some(protocol) = self.protocol {
+            if info.protocol != Some(protocol) {
+                return false;
+            }
+        }
+        if let Some(host) = self.host {
+            if info.src != Some(host) && info.dst != Some(host) {
+                return false;
+            }
+        }
+        if let Some(port) = self.port {
+            if info.src_port != Some(port) && info.dst_port != Some(port) {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// The filter as an expression `parse` accepts
+    pub fn describe(&self) -> String {
+        let mut terms = Vec::new();
+        if let Some(protocol) = self.protocol {
+            terms.push(protocol.name().to_string());
+        }
+        if let Some(port) = self.port {
+            terms.push(alloc::format!("port {}", port));
+        }
+        if let Some(host) = self.host {
+            terms.push(alloc::format!("host {}", host));
+        }
+        if terms.is_empty() {
+            return "all".to_string();
+        }
+        terms.join(" ")
+    }
+}
+
+/// The parts of a frame a filter looks at
+#[derive(Debug, Default, PartialEq, Eq)]
+struct FrameInfo {
+    protocol: Option<Protocol>,
+    src: Option<IpAddr>,
+    dst: Option<IpAddr>,
+    src_port: Option<u16>,
+    dst_port: Option<u16>,
+}
+
+/// Pull addresses, protocol and ports out of an Ethernet frame
+///
+/// Returns `None` for frames too short to carry the header they claim.
+fn dissect(frame: &[u8]) -> Option<FrameInfo> {
+    let ethertype = u16::from_be_bytes([*frame.get(12)?, *frame.get(13)?]);
+    let packet = &frame[ETH_HEADER_LEN..];
+    let mut info = FrameInfo::default();
+
+    let (proto, payload) = match ethertype {
+        ETHERTYPE_ARP => {
+            // Sender and target protocol addresses of Ethernet/IPv4 ARP
+            info.protocol = Some(Protocol::Arp);
+            if packet.len() >= 28 {
+                info.src = Some(IpAddr::V4(Ipv4Addr(packet[14..18].try_into().ok()?)));
+                info.dst = Some(IpAddr::V4(Ipv4Addr(packet[24..28].try_into().ok()?)));
+            }
+            return Some(info);
+        }
+        ETHERTYPE_IPV4 => {
+            if packet.len() < 20 || packet[0] >> 4 != 4 {
+                return None;
+            }
+            let ihl = usize::from(packet[0] & 0x0F) * 4;
+            if ihl < 20 {
+                return None;
+            }
+            info.src = Some(IpAddr::V4(Ipv4Addr(packet[12..16].try_into().ok()?)));
+            info.dst = Some(IpAddr::V4(Ipv4Addr(packet[16..20].try_into().ok()?)));
+            // Only the first fragment carries the transport header
+            let offset = u16::from_be_bytes([packet[6], packet[7]]) & 0x1FFF;
+            let payload = if offset == 0 { packet.get(ihl..) } else { None };
+            (packet[9], payload)
+        }
+        ETHERTYPE_IPV6 => {
+            if packet.len() < IPV6_HEADER_LEN || packet[0] >> 4 != 6 {
+                return None;
+            }
+            info.src = Some(IpAddr::V6(Ipv6Addr(packet[8..24].try_into().ok()?)));
+            info.dst = Some(IpAddr::V6(Ipv6Addr(packet[24..40].try_into().ok()?)));
+            // Extension headers are not walked; we never send them
+            (packet[6], packet.get(IPV6_HEADER_LEN..))
+        }
+        _ => return Some(info),
+    };
+
+    info.protocol = match proto {
+        PROTO_ICMP | PROTO_ICMPV6 => Some(Protocol::Icmp),
+        PROTO_TCP => Some(Protocol::Tcp),
+        PROTO_UDP => Some(Protocol::Udp),
+        _ => None,
+    };
+    if matches!(info.protocol, Some(Protocol::Tcp | Protocol::Udp)) {
+        if let Some(ports) = payload.and_then(|p| p.get(..4)) {
+            info.src_port = Some(u16::from_be_bytes([ports[0], ports[1]]));
+            info.dst_port = Some(u16::from_be_bytes([ports[2], ports[3]]));
+        }
+    }
+    Some(info)
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// PCAP FORMAT
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// File header: native byte order, microsecond timestamps, Ethernet
+pub fn global_header() -> [u8; GLOBAL_HEADER_LEN] {
+    let mut header = [0u8; GLOBAL_HEADER_LEN];
+    header[0..4].copy_from_slice(&PCAP_MAGIC.to_le_bytes());
+    header[4..6].copy_from_slice(&PCAP_VERSION_MAJOR.to_le_bytes());
+    header[6..8].copy_from_slice(&PCAP_VERSION_MINOR.to_le_bytes());
+    // thiszone and sigfigs stay zero
+    header[16..20].copy_from_slice(&(MAX_FRAME_SIZE as u32).to_le_bytes());
+    header[20..24].copy_from_slice(&LINKTYPE_ETHERNET.to_le_bytes());
+    header
+}
+
+/// One record: timestamp, captured and original length, then the frame
+pub fn record(frame: &[u8], timestamp_us: u64) -> Vec<u8> {
+    let captured = frame.len().min(MAX_FRAME_SIZE);
+    let mut rec = Vec::with_capacity(RECORD_HEADER_LEN + captured);
+    rec.extend_from_slice(&((timestamp_us / 1_000_000) as u32).to_le_bytes());
+    rec.extend_from_slice(&((timestamp_us % 1_000_000) as u32).to_le_bytes());
+    rec.extend_from_slice(&(captured as u32).to_le_bytes());
+    rec.extend_from_slice(&(frame.len() as u32).to_le_bytes());
+    rec.extend_from_slice(&frame[..captured]);
+    rec
+}
+
+/// A chunk of pcap data as one `#PCAP ` line
+fn hex_line(chunk: &[u8]) -> String {
+    const DIGITS: &[u8; 16] = b"0123456789abcdef";
+    let mut line = String::with_capacity(UART_PREFIX.len() + chunk.len() * 2 + 1);
+    line.push_str(UART_PREFIX);
+    for byte in chunk {
+        line.push(DIGITS[usize::from(byte >> 4)] as char);
+        line.push(DIGITS[usize::from(byte & 0x0F)] as char);
+    }
+    line.push('\n');
+    line
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// CAPTURE SESSION
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Where the pcap stream goes
+pub enum CaptureSink {
+    File(Arc<SpinLock<dyn FileOps>>),
+    Uart,
+}
+
+/// Counters reported by `status` and `stop`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CaptureStats {
+    /// Frames that passed the filter and were queued
+    pub captured: u64,
+    /// Frames lost because the queue was full
+    pub dropped: u64,
+    /// Bytes handed to the sink, headers included
+    pub written: u64,
+}
+
+struct Capture {
+    filter: CaptureFilter,
+    sink: CaptureSink,
+    /// Global header, then one record per chunk
+    pending: VecDeque<Vec<u8>>,
+    pending_bytes: usize,
+    stats: CaptureStats,
+}
+
+impl Capture {
+    fn new(filter: CaptureFilter, sink: CaptureSink) -> Self {
+        let mut capture = Self {
+            filter,
+            sink,
+            pending: VecDeque::new(),
+            pending_bytes: 0,
+            stats: CaptureStats::default(),
+        };
+        capture.queue(global_header().to_vec());
+        capture
+    }
+
+    fn queue(&mut self, chunk: Vec<u8>) {
+        self.pending_bytes += chunk.len();
+        self.pending.push_back(chunk);
+    }
+
+    fn offer(&mut self, frame: &[u8], timestamp_us: u64) {
+        if !self.filter.matches(frame) {
+            return;
+        }
+        if self.pending_bytes + RECORD_HEADER_LEN + frame.len() > MAX_PENDING_BYTES {
+            self.stats.dropped += 1;
+            return;
+        }
+        self.stats.captured += 1;
+        self.queue(record(frame, timestamp_us));
+    }
+
+    /// Hand queued chunks to the sink
+    ///
+    /// Unless `wait` is set, at most the sink's per-tick budget goes out
+    /// (a record may be split across ticks) and a file that is busy
+    /// elsewhere is left for the next tick.
+    fn flush(&mut self, wait: bool) -> Result<(), &'static str> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+        let mut budget = match self.sink {
+            _ if wait => usize::MAX,
+            CaptureSink::File(_) => FILE_BYTES_PER_TICK,
+            CaptureSink::Uart => UART_BYTES_PER_TICK,
+        };
+        let mut file = match &self.sink {
+            CaptureSink::File(file) => match file.try_lock() {
+                Some(guard) => Some(guard),
+                None if wait => Some(file.lock()),
+                None => return Ok(()),
+            },
+            CaptureSink::Uart => None,
+        };
+        while budget > 0 {
+            let Some(chunk) = self.pending.front_mut() else { break };
+            let part = &chunk[..chunk.len().min(budget)];
+            let written = match &mut file {
+                Some(file) => file.write(part)?,
+                None => {
+                    // Hex lines concatenate, so a split record decodes fine
+                    crate::drivers::uart::send_str(&hex_line(part));
+                    part.len()
+                }
+            };
+            if written == 0 {
+                return Err("Capture file is full");
+            }
+            self.stats.written += written as u64;
+            self.pending_bytes -= written;
+            budget -= written;
+            if written < chunk.len() {
+                chunk.drain(..written);
+            } else {
+                self.pending.pop_front();
+            }
+        }
+        Ok(())
+    }
+}
+
+static CAPTURE: SpinLock<Option<Capture>> = SpinLock::new(None);
+/// Lets `tap` skip the lock while nothing is being captured
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+
+/// Destination named in a capture request
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Target {
+    Uart,
+    File(String),
+}
+
+/// Split `<filter> [to <path> | uart]` into filter and destination
+///
+/// Without a destination the capture goes to the UART.
+pub fn parse_request(spec: &str) -> Result<(CaptureFilter, Target), &'static str> {
+    let words: Vec<&str> = spec.split_whitespace().collect();
+    let (filter, target) = match words.as_slice() {
+        [filter @ .., "to", path] => (filter, Target::File(String::from(*path))),
+        [.., "to"] => return Err("Missing capture file"),
+        [filter @ .., "uart"] => (filter, Target::Uart),
+        filter => (filter, Target::Uart),
+    };
+    Ok((CaptureFilter::parse(&filter.join(" "))?, target))
+}
+
+/// Start capturing; fails if a capture is already running
+pub fn start(filter: CaptureFilter, sink: CaptureSink) -> Result<(), &'static str> {
+    let mut capture = CAPTURE.lock();
+    if capture.is_some() {
+        return Err("Capture already running");
+    }
+    *capture = Some(Capture::new(filter, sink));
+    ACTIVE.store(true, Ordering::Release);
+    Ok(())
+}
+
+/// Start capturing into a new file at `path`; an existing file is refused
+pub fn start_file(filter: CaptureFilter, path: &str) -> Result<(), &'static str> {
+    if is_active() {
+        return Err("Capture already running");
+    }
+    // `create` truncates, so never point a capture at an existing file
+    let file = {
+        let vfs = crate::fs::vfs::VFS.lock();
+        if vfs.stat(path).is_ok() {
+            return Err("File exists");
+        }
+        vfs.create(path)?
+    };
+    start(filter, CaptureSink::File(file))
+}
+
+/// Stop capturing, write out what is queued and close the sink
+pub fn stop() -> Result<CaptureStats, &'static str> {
+    let mut capture = CAPTURE.lock().take().ok_or("No capture running")?;
+    ACTIVE.store(false, Ordering::Release);
+    let flushed = capture.flush(true);
+    if let CaptureSink::File(file) = &capture.sink {
+        let _ = file.lock().close();
+    }
+    flushed.map(|()| capture.stats)
+}
+
+pub fn is_active() -> bool {
+    ACTIVE.load(Ordering::Acquire)
+}
+
+/// Filter and counters of the running capture
+pub fn status() -> Option<(CaptureFilter, CaptureStats)> {
+    CAPTURE.lock().as_ref().map(|c| (c.filter, c.stats))
+}
+
+/// Called by `interface` for every frame sent or received
+pub fn tap(frame: &[u8]) {
+    if !is_active() {
+        return;
+    }
+    let now = crate::drivers::timer::uptime_us();
+    if let Some(capture) = CAPTURE.lock().as_mut() {
+        capture.offer(frame, now);
+    }
+}
+
+/// Write queued records out; called from the timer tick
+pub fn tick() {
+    if !is_active() {
+        return;
+    }
+    let Some(mut guard) = CAPTURE.try_lock() else { return };
+    let Some(capture) = guard.as_mut() else { return };
+    if let Err(e) = capture.flush(false) {
+        // Keep the stack running; the capture simply stops growing
+        crate::kprintln!("[PCAP] Write failed: {}", e);
+        capture.pending.clear();
+        capture.pending_bytes = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Ethernet + IPv4 + 4 bytes of transport ports
+    fn ipv4_frame(proto: u8, src: [u8; 4], dst: [u8; 4], sport: u16, dport: u16) -> Vec<u8> {
+        let mut frame = alloc::vec![0u8; ETH_HEADER_LEN + 20 + 8];
+        frame[12..14].copy_from_slice(&ETHERTYPE_IPV4.to_be_bytes());
+        let ip = &mut frame[ETH_HEADER_LEN..];
+        ip[0] = 0x45;
+        ip[9] = proto;
+        ip[12..16].copy_from_slice(&src);
+        ip[16..20].copy_from_slice(&dst);
+        ip[20..22].copy_from_slice(&sport.to_be_bytes());
+        ip[22..24].copy_from_slice(&dport.to_be_bytes());
+        frame
+    }
+
+    #[test]
+    fn test_filter_parse() {
+        let filter = CaptureFilter::parse("tcp port 80 host 10.0.2.2").unwrap();
+        assert_eq!(filter.protocol, Some(Protocol::Tcp));
+        assert_eq!(filter.port, Some(80));
+        assert_eq!(filter.host, IpAddr::parse("10.0.2.2"));
+        assert_eq!(CaptureFilter::parse(&filter.describe()).unwrap(), filter);
+
+        assert_eq!(CaptureFilter::parse("").unwrap(), CaptureFilter::default());
+        assert_eq!(CaptureFilter::parse("all").unwrap().describe(), "all");
+        assert!(CaptureFilter::parse("port").is_err());
+        assert!(CaptureFilter::parse("port http").is_err());
+        assert!(CaptureFilter::parse("bogus").is_err());
+        assert!(CaptureFilter::parse("icmp port 7").is_err());
+    }
+
+    #[test]
+    fn test_filter_match() {
+        let frame = ipv4_frame(PROTO_TCP, [10, 0, 2, 15], [10, 0, 2, 2], 49152, 80);
+        assert!(CaptureFilter::default().matches(&frame));
+        assert!(CaptureFilter::parse("tcp port 80").unwrap().matches(&frame));
+        assert!(CaptureFilter::parse("host 10.0.2.15").unwrap().matches(&frame));
+        assert!(!CaptureFilter::parse("udp").unwrap().matches(&frame));
+        assert!(!CaptureFilter::parse("port 443").unwrap().matches(&frame));
+        assert!(!CaptureFilter::parse("host 10.0.2.3").unwrap().matches(&frame));
+
+        // Later fragments have no ports to match
+        let mut fragment = frame.clone();
+        fragment[ETH_HEADER_LEN + 7] = 1;
+        assert!(!CaptureFilter::parse("port 80").unwrap().matches(&fragment));
+        assert!(CaptureFilter::parse("tcp").unwrap().matches(&fragment));
+
+        let mut arp = alloc::vec![0u8; ETH_HEADER_LEN + 28];
+        arp[12..14].copy_from_slice(&ETHERTYPE_ARP.to_be_bytes());
+        arp[ETH_HEADER_LEN + 14..ETH_HEADER_LEN + 18].copy_from_slice(&[10, 0, 2, 2]);
+        assert!(CaptureFilter::parse("arp host 10.0.2.2").unwrap().matches(&arp));
+        assert!(!CaptureFilter::parse("tcp").unwrap().matches(&arp));
+
+        // An IHL below the minimum header is a truncated packet
+        let mut short = frame.clone();
+        short[ETH_HEADER_LEN] = 0x44;
+        assert_eq!(dissect(&short), None);
+        assert!(!CaptureFilter::parse("tcp").unwrap().matches(&short));
+    }
+
+    #[test]
+    fn test_pcap_layout() {
+        let header = global_header();
+        assert_eq!(&header[0..4], &[0xD4, 0xC3, 0xB2, 0xA1]);
+        assert_eq!(u16::from_le_bytes([header[4], header[5]]), 2);
+        assert_eq!(u16::from_le_bytes([header[6], header[7]]), 4);
+        assert_eq!(u32::from_le_bytes(header[20..24].try_into().unwrap()), LINKTYPE_ETHERNET);
+
+        let frame = [0xAAu8; 60];
+        let rec = record(&frame, 3_000_042);
+        assert_eq!(rec.len(), RECORD_HEADER_LEN + 60);
+        assert_eq!(u32::from_le_bytes(rec[0..4].try_into().unwrap()), 3);
+        assert_eq!(u32::from_le_bytes(rec[4..8].try_into().unwrap()), 42);
+        assert_eq!(u32::from_le_bytes(rec[8..12].try_into().unwrap()), 60);
+        assert_eq!(&rec[RECORD_HEADER_LEN..], &frame[..]);
+
+        assert_eq!(hex_line(&[0x0F, 0xA0]), "#PCAP 0fa0\n");
+    }
+
+    #[test]
+    fn test_parse_request() {
+        let (filter, target) = parse_request("udp port 53 to /tmp/dns.pcap").unwrap();
+        assert_eq!(filter.describe(), "udp port 53");
+        assert_eq!(target, Target::File(String::from("/tmp/dns.pcap")));
+
+        let (filter, target) = parse_request("arp uart").unwrap();
+        assert_eq!(filter.protocol, Some(Protocol::Arp));
+        assert_eq!(target, Target::Uart);
+
+        assert_eq!(parse_request("").unwrap(), (CaptureFilter::default(), Target::Uart));
+        assert!(parse_request("tcp to").is_err());
+    }
+
+    #[test]
+    fn test_queue_limit() {
+        let mut capture = Capture::new(CaptureFilter::parse("udp").unwrap(), CaptureSink::Uart);
+        let udp = ipv4_frame(PROTO_UDP, [10, 0, 2, 15], [10, 0, 2, 3], 68, 67);
+        let tcp = ipv4_frame(PROTO_TCP, [10, 0, 2, 15], [10, 0, 2, 3], 1, 2);
+        capture.offer(&tcp, 0);
+        assert_eq!(capture.stats.captured, 0);
+
+        let per_record = RECORD_HEADER_LEN + udp.len();
+        let fit = (MAX_PENDING_BYTES - GLOBAL_HEADER_LEN) / per_record;
+        for _ in 0..fit + 3 {
+            capture.offer(&udp, 0);
+        }
+        assert_eq!(capture.stats.captured, fit as u64);
+        assert_eq!(capture.stats.dropped, 3);
+        assert_eq!(capture.pending.len(), fit + 1);
+    }
+
+    #[test]
+    fn test_flush_budget() {
+        use crate::fs::vfs::Filesystem;
+        let file = crate::fs::tmpfs::TmpFs::new().create("/capture.pcap").unwrap();
+        let mut capture = Capture::new(CaptureFilter::default(), CaptureSink::File(file.clone()));
+        let frame = ipv4_frame(PROTO_UDP, [10, 0, 2, 15], [10, 0, 2, 3], 68, 67);
+        let records = FILE_BYTES_PER_TICK / (RECORD_HEADER_LEN + frame.len()) + 1;
+        for _ in 0..records {
+            capture.offer(&frame, 0);
+        }
+        let total = capture.pending_bytes;
+
+        capture.flush(false).unwrap();
+        assert_eq!(capture.stats.written, FILE_BYTES_PER_TICK as u64);
+        assert_eq!(capture.pending_bytes, total - FILE_BYTES_PER_TICK);
+
+        capture.flush(true).unwrap();
+        assert_eq!(capture.stats.written, total as u64);
+        assert!(capture.pending.is_empty());
+        assert_eq!(file.lock().stat().unwrap().size, total as u64);
+    }
+}
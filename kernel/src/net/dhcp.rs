@@ -0,0 +1,689 @@
+//! DHCPv4 Client (RFC 2131)
+//!
+//! Configures `NetConfig` at boot: DISCOVER → OFFER → REQUEST → ACK. The
+//! lease is renewed with the server at T1, rebound by broadcast at T2 and
+//! dropped when it expires. If no server answers, the static settings
+//! from `config.txt` are applied instead:
+//!
+//! ```text
+//! net_ip=192.168.1.50
+//! net_netmask=255.255.255.0
+//! net_gateway=192.168.1.1
+//! net_dns=192.168.1.1,1.1.1.1
+//! ```
+//!
+//! The protocol logic in `DhcpClient` is pure (messages and time in,
+//! events out); `tick` performs the I/O from the timer tick.
+
+use alloc::vec::Vec;
+use core::convert::TryInto;
+use crate::kernel::sync::SpinLock;
+use crate::net::ip::Ipv4Addr;
+use crate::net::ethernet::MacAddress;
+use crate::net::{udp, NetConfig, MAX_DNS_SERVERS};
+
+pub const SERVER_PORT: u16 = 67;
+pub const CLIENT_PORT: u16 = 68;
+
+const BOOTREQUEST: u8 = 1;
+const BOOTREPLY: u8 = 2;
+const MAGIC_COOKIE: [u8; 4] = [99, 130, 83, 99];
+/// Ask servers to broadcast replies: we cannot receive unicast before
+/// we have an address
+const FLAG_BROADCAST: u16 = 0x8000;
+/// op .. file, before the magic cookie
+const FIXED_LEN: usize = 236;
+
+// Message types (option 53)
+pub const DISCOVER: u8 = 1;
+pub const OFFER: u8 = 2;
+pub const REQUEST: u8 = 3;
+pub const ACK: u8 = 5;
+pub const NAK: u8 = 6;
+
+// Options
+const OPT_PAD: u8 = 0;
+const OPT_SUBNET_MASK: u8 = 1;
+const OPT_ROUTER: u8 = 3;
+const OPT_DNS: u8 = 6;
+const OPT_REQUESTED_IP: u8 = 50;
+const OPT_LEASE_TIME: u8 = 51;
+const OPT_MSG_TYPE: u8 = 53;
+const OPT_SERVER_ID: u8 = 54;
+const OPT_PARAM_LIST: u8 = 55;
+const OPT_RENEWAL_TIME: u8 = 58;
+const OPT_REBINDING_TIME: u8 = 59;
+const OPT_END: u8 = 255;
+
+/// First retransmission delay; doubles per attempt
+const RETRY_MS: u64 = 2_000;
+/// DISCOVER/REQUEST attempts before falling back
+const MAX_ATTEMPTS: u32 = 4;
+/// Pause before trying again after giving up
+const RESTART_MS: u64 = 60_000;
+/// Lower bound between RENEWING/REBINDING retransmissions (RFC 2131 4.4.5)
+const MIN_RENEW_RETRY_MS: u64 = 60_000;
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// MESSAGES
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// A DHCP message with the options this client understands
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct DhcpMessage {
+    pub op: u8,
+    pub xid: u32,
+    pub flags: u16,
+    pub ciaddr: Option<Ipv4Addr>,
+    pub yiaddr: Option<Ipv4Addr>,
+    pub chaddr: [u8; 6],
+    pub msg_type: u8,
+    pub subnet_mask: Option<Ipv4Addr>,
+    pub router: Option<Ipv4Addr>,
+    pub dns: Vec<Ipv4Addr>,
+    pub server_id: Option<Ipv4Addr>,
+    pub requested_ip: Option<Ipv4Addr>,
+    pub lease_secs: Option<u32>,
+    pub renewal_secs: Option<u32>,
+    pub rebinding_secs: Option<u32>,
+}
+
+fn addr_or_zero(addr: Option<Ipv4Addr>) -> [u8; 4] {
+    addr.unwrap_or(Ipv4Addr::ANY).0
+}
+
+fn non_zero(bytes: &[u8]) -> Option<Ipv4Addr> {
+    let addr = Ipv4Addr(bytes.try_into().ok()?);
+    if addr == Ipv4Addr::ANY { None } else { Some(addr) }
+}
+
+impl DhcpMessage {
+    pub fn parse(data: &[u8]) -> Result<Self, &'static str> {
+        if data.len() < FIXED_LEN + 4 {
+            return Err("DHCP message too short");
+        }
+        if data[FIXED_LEN..FIXED_LEN + 4] != MAGIC_COOKIE {
+            return Err("Not a DHCP message");
+        }
+
+        let mut msg = DhcpMessage {
+            op: data[0],
+            xid: u32::from_be_bytes(data[4..8].try_into().unwrap()),
+            flags: u16::from_be_bytes(data[10..12].try_into().unwrap()),
+            ciaddr: non_zero(&data[12..16]),
+            yiaddr: non_zero(&data[16..20]),
+            chaddr: data[28..34].try_into().unwrap(),
+            ..Default::default()
+        };
+
+        let mut opts = &data[FIXED_LEN + 4..];
+        while let Some((&code, rest)) = opts.split_first() {
+            match code {
+                OPT_PAD => { opts = rest; continue; }
+                OPT_END => break,
+                _ => {}
+            }
+            let (&len, rest) = rest.split_first().ok_or("Truncated DHCP option")?;
+            let len = len as usize;
+            if rest.len() < len {
+                return Err("Truncated DHCP option");
+            }
+            let value = &rest[..len];
+            let secs = || value.try_into().ok().map(u32::from_be_bytes);
+            match code {
+                OPT_MSG_TYPE if len == 1 => msg.msg_type = value[0],
+                OPT_SUBNET_MASK => msg.subnet_mask = non_zero(value),
+                OPT_ROUTER => msg.router = value.get(..4).and_then(non_zero),
+                OPT_DNS => msg.dns = value.as_chunks::<4>().0.iter().filter_map(|addr| non_zero(addr)).collect(),
+                OPT_SERVER_ID => msg.server_id = non_zero(value),
+                OPT_REQUESTED_IP => msg.requested_ip = non_zero(value),
+                OPT_LEASE_TIME => msg.lease_secs = secs(),
+                OPT_RENEWAL_TIME => msg.renewal_secs = secs(),
+                OPT_REBINDING_TIME => msg.rebinding_secs = secs(),
+                _ => {} // Unknown options are skipped
+            }
+            opts = &rest[len..];
+        }
+        Ok(msg)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = alloc::vec![0u8; FIXED_LEN];
+        bytes[0] = self.op;
+        bytes[1] = 1; // htype: Ethernet
+        bytes[2] = 6; // hlen
+        bytes[4..8].copy_from_slice(&self.xid.to_be_bytes());
+        bytes[10..12].copy_from_slice(&self.flags.to_be_bytes());
+        bytes[12..16].copy_from_slice(&addr_or_zero(self.ciaddr));
+        bytes[16..20].copy_from_slice(&addr_or_zero(self.yiaddr));
+        bytes[28..34].copy_from_slice(&self.chaddr);
+        bytes.extend_from_slice(&MAGIC_COOKIE);
+
+        bytes.extend_from_slice(&[OPT_MSG_TYPE, 1, self.msg_type]);
+        let mut addr_opt = |code: u8, addrs: &[Ipv4Addr]| {
+            if !addrs.is_empty() {
+                bytes.push(code);
+                bytes.push((addrs.len() * 4) as u8);
+                for addr in addrs {
+                    bytes.extend_from_slice(&addr.0);
+                }
+            }
+        };
+        addr_opt(OPT_REQUESTED_IP, self.requested_ip.as_slice());
+        addr_opt(OPT_SERVER_ID, self.server_id.as_slice());
+        addr_opt(OPT_SUBNET_MASK, self.subnet_mask.as_slice());
+        addr_opt(OPT_ROUTER, self.router.as_slice());
+        addr_opt(OPT_DNS, &self.dns);
+        for (code, secs) in [
+            (OPT_LEASE_TIME, self.lease_secs),
+            (OPT_RENEWAL_TIME, self.renewal_secs),
+            (OPT_REBINDING_TIME, self.rebinding_secs),
+        ] {
+            if let Some(secs) = secs {
+                bytes.extend_from_slice(&[code, 4]);
+                bytes.extend_from_slice(&secs.to_be_bytes());
+            }
+        }
+        if self.op == BOOTREQUEST {
+            bytes.extend_from_slice(&[OPT_PARAM_LIST, 3, OPT_SUBNET_MASK, OPT_ROUTER, OPT_DNS]);
+        }
+        bytes.push(OPT_END);
+
+        // Some servers drop BOOTP messages shorter than 300 bytes
+        if bytes.len() < 300 {
+            bytes.resize(300, OPT_PAD);
+        }
+        bytes
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// CLIENT STATE MACHINE
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// An acknowledged address assignment
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Lease {
+    pub ip: Ipv4Addr,
+    pub netmask: Ipv4Addr,
+    pub gateway: Ipv4Addr,
+    pub dns: Vec<Ipv4Addr>,
+    pub server: Ipv4Addr,
+    /// Uptime (ms) at which the ACK arrived
+    pub acquired_ms: u64,
+    pub lease_ms: u64,
+    pub renew_ms: u64,
+    pub rebind_ms: u64,
+}
+
+impl Lease {
+    fn from_ack(ack: &DhcpMessage, server: Ipv4Addr, now: u64) -> Option<Self> {
+        let lease_secs = ack.lease_secs.unwrap_or(u32::MAX) as u64;
+        Some(Self {
+            ip: ack.yiaddr?,
+            netmask: ack.subnet_mask.unwrap_or(Ipv4Addr([255, 255, 255, 0])),
+            gateway: ack.router.unwrap_or(Ipv4Addr::ANY),
+            dns: ack.dns.clone(),
+            server: ack.server_id.unwrap_or(server),
+            acquired_ms: now,
+            lease_ms: lease_secs * 1000,
+            // Defaults from RFC 2131 4.4.5: T1 = 0.5, T2 = 0.875 of the lease
+            renew_ms: ack.renewal_secs.map_or(lease_secs * 500, |s| s as u64 * 1000),
+            rebind_ms: ack.rebinding_secs.map_or(lease_secs * 875, |s| s as u64 * 1000),
+        })
+    }
+
+    fn elapsed(&self, now: u64) -> u64 {
+        now.saturating_sub(self.acquired_ms)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DhcpState {
+    Selecting,
+    Requesting,
+    Bound,
+    Renewing,
+    Rebinding,
+    /// Gave up on DHCP and applied the config.txt settings
+    Static,
+}
+
+/// What the client asks its caller to do
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DhcpEvent {
+    /// Transmit from port 68 to `dst`:67
+    Send { dst: Ipv4Addr, msg: DhcpMessage },
+    /// Apply a new or renewed lease
+    Bound(Lease),
+    /// The address is no longer valid
+    Expired,
+    /// No server answered
+    GaveUp,
+}
+
+pub struct DhcpClient {
+    state: DhcpState,
+    mac: MacAddress,
+    xid: u32,
+    attempts: u32,
+    next_send_ms: u64,
+    /// OFFER being requested (Requesting)
+    offer: Option<(Ipv4Addr, Ipv4Addr)>,
+    lease: Option<Lease>,
+}
+
+impl DhcpClient {
+    pub fn new(mac: MacAddress, xid: u32) -> Self {
+        Self {
+            state: DhcpState::Selecting,
+            mac,
+            xid,
+            attempts: 0,
+            next_send_ms: 0,
+            offer: None,
+            lease: None,
+        }
+    }
+
+    pub fn state(&self) -> DhcpState {
+        self.state
+    }
+
+    pub fn lease(&self) -> Option<&Lease> {
+        self.lease.as_ref()
+    }
+
+    fn request_msg(&self, msg_type: u8) -> DhcpMessage {
+        DhcpMessage {
+            op: BOOTREQUEST,
+            xid: self.xid,
+            flags: FLAG_BROADCAST,
+            chaddr: self.mac.0,
+            msg_type,
+            ..Default::default()
+        }
+    }
+
+    /// (Re)enter SELECTING and broadcast a DISCOVER
+    pub fn start(&mut self, now: u64) -> Vec<DhcpEvent> {
+        self.state = DhcpState::Selecting;
+        self.xid = self.xid.wrapping_add(1);
+        self.offer = None;
+        self.attempts = 1;
+        self.next_send_ms = now + RETRY_MS;
+        alloc::vec![DhcpEvent::Send { dst: Ipv4Addr::BROADCAST, msg: self.request_msg(DISCOVER) }]
+    }
+
+    /// REQUEST for the current state: selecting an offer, renewing or rebinding
+    fn send_request(&self) -> DhcpEvent {
+        let mut msg = self.request_msg(REQUEST);
+        let mut dst = Ipv4Addr::BROADCAST;
+        match (self.state, &self.lease) {
+            (DhcpState::Requesting, _) => {
+                if let Some((ip, server)) = self.offer {
+                    msg.requested_ip = Some(ip);
+                    msg.server_id = Some(server);
+                }
+            }
+            (DhcpState::Renewing, Some(lease)) => {
+                msg.ciaddr = Some(lease.ip);
+                msg.flags = 0;
+                dst = lease.server;
+            }
+            (_, Some(lease)) => {
+                msg.ciaddr = Some(lease.ip);
+                msg.flags = 0;
+            }
+            _ => {}
+        }
+        DhcpEvent::Send { dst, msg }
+    }
+
+    /// Feed a message received on port 68
+    pub fn on_message(&mut self, msg: &DhcpMessage, now: u64) -> Vec<DhcpEvent> {
+        if msg.op != BOOTREPLY || msg.xid != self.xid || msg.chaddr != self.mac.0 {
+            return Vec::new();
+        }
+
+        match (self.state, msg.msg_type) {
+            (DhcpState::Selecting, OFFER) => {
+                let (Some(ip), Some(server)) = (msg.yiaddr, msg.server_id) else {
+                    return Vec::new();
+                };
+                self.offer = Some((ip, server));
+                self.state = DhcpState::Requesting;
+                self.attempts = 1;
+                self.next_send_ms = now + RETRY_MS;
+                alloc::vec![self.send_request()]
+            }
+            (DhcpState::Requesting | DhcpState::Renewing | DhcpState::Rebinding, ACK) => {
+                let server = self.offer.map(|(_, s)| s)
+                    .or(self.lease.as_ref().map(|l| l.server))
+                    .unwrap_or(Ipv4Addr::ANY);
+                let Some(lease) = Lease::from_ack(msg, server, now) else {
+                    return Vec::new();
+                };
+                self.state = DhcpState::Bound;
+                self.lease = Some(lease.clone());
+                alloc::vec![DhcpEvent::Bound(lease)]
+            }
+            (DhcpState::Requesting | DhcpState::Renewing | DhcpState::Rebinding, NAK) => {
+                let had_lease = self.lease.take().is_some();
+                let mut events = if had_lease { alloc::vec![DhcpEvent::Expired] } else { Vec::new() };
+                events.extend(self.start(now));
+                events
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// Timers: retransmissions, T1/T2 and lease expiry
+    pub fn on_tick(&mut self, now: u64) -> Vec<DhcpEvent> {
+        match self.state {
+            DhcpState::Selecting | DhcpState::Requesting => {
+                if now < self.next_send_ms {
+                    return Vec::new();
+                }
+                if self.attempts >= MAX_ATTEMPTS {
+                    // Back off and start over later; the caller may fall back
+                    self.state = DhcpState::Selecting;
+                    self.xid = self.xid.wrapping_add(1);
+                    self.offer = None;
+                    self.attempts = 0;
+                    self.next_send_ms = now + RESTART_MS;
+                    return alloc::vec![DhcpEvent::GaveUp];
+                }
+                self.next_send_ms = now + (RETRY_MS << self.attempts);
+                self.attempts += 1;
+                if self.state == DhcpState::Selecting {
+                    alloc::vec![DhcpEvent::Send { dst: Ipv4Addr::BROADCAST, msg: self.request_msg(DISCOVER) }]
+                } else {
+                    alloc::vec![self.send_request()]
+                }
+            }
+            DhcpState::Bound | DhcpState::Renewing | DhcpState::Rebinding => {
+                let Some(lease) = &self.lease else { return Vec::new() };
+                let elapsed = lease.elapsed(now);
+                if elapsed >= lease.lease_ms {
+                    self.lease = None;
+                    let mut events = alloc::vec![DhcpEvent::Expired];
+                    events.extend(self.start(now));
+                    return events;
+                }
+
+                let (phase, deadline) = if elapsed >= lease.rebind_ms {
+                    (DhcpState::Rebinding, lease.lease_ms)
+                } else if elapsed >= lease.renew_ms {
+                    (DhcpState::Renewing, lease.rebind_ms)
+                } else {
+                    return Vec::new();
+                };
+                if phase != self.state {
+                    self.state = phase;
+                } else if now < self.next_send_ms {
+                    return Vec::new();
+                }
+                // Retry after half the time left in this phase, at least a minute
+                let remaining = deadline.saturating_sub(elapsed);
+                self.next_send_ms = now + core::cmp::max(remaining / 2, MIN_RENEW_RETRY_MS);
+                alloc::vec![self.send_request()]
+            }
+            DhcpState::Static => Vec::new(),
+        }
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// STATIC FALLBACK
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Parse the `net_*` keys of a config.txt; `None` without `net_ip`
+pub fn parse_static_config(text: &str) -> Option<NetConfig> {
+    let mut cfg = NetConfig::new();
+    let mut have_ip = false;
+    for line in text.lines() {
+        let line = line.trim();
+        if line.starts_with('#') {
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else { continue };
+        match key.trim() {
+            "net_ip" => {
+                cfg.ip_addr = Ipv4Addr::parse(value)?;
+                have_ip = true;
+            }
+            "net_netmask" => cfg.netmask = Ipv4Addr::parse(value)?,
+            "net_gateway" => cfg.gateway = Ipv4Addr::parse(value)?,
+            "net_dns" => {
+                for (slot, server) in cfg.dns_servers.iter_mut().zip(value.split(',')) {
+                    *slot = Ipv4Addr::parse(server)?;
+                }
+            }
+            _ => {}
+        }
+    }
+    if have_ip { Some(cfg) } else { None }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// GLOBAL CLIENT
+// ═══════════════════════════════════════════════════════════════════════════════
+
+static CLIENT: SpinLock<Option<DhcpClient>> = SpinLock::new(None);
+
+/// Settings from config.txt, applied if DHCP gets no answer
+static STATIC_CONFIG: SpinLock<Option<NetConfig>> = SpinLock::new(None);
+
+/// Begin address acquisition on the default interface
+pub fn start() {
+    if udp::register_listener(CLIENT_PORT).is_err() {
+        crate::kprintln!("[DHCP] Port {} busy", CLIENT_PORT);
+        return;
+    }
+    let mac = crate::net::config().mac_addr;
+    let mut client = DhcpClient::new(mac, crate::drivers::rng::next_u64() as u32);
+    let events = client.start(crate::drivers::timer::uptime_ms());
+    *CLIENT.lock() = Some(client);
+    crate::kprintln!("[DHCP] Discovering...");
+    perform(events);
+}
+
+/// Remember the static fallback settings found in a config.txt
+pub fn set_static_config(text: &str) {
+    if let Some(cfg) = parse_static_config(text) {
+        crate::kprintln!("[DHCP] Static fallback {} via {}", cfg.ip_addr, cfg.gateway);
+        *STATIC_CONFIG.lock() = Some(cfg);
+    }
+}
+
+/// Drive the client: handle replies and timers (called from the timer tick)
+pub fn tick() {
+    let now = crate::drivers::timer::uptime_ms();
+    let mut events = Vec::new();
+    {
+        let mut guard = CLIENT.lock();
+        let Some(client) = guard.as_mut() else { return };
+        while let Some(datagram) = udp::recv_from(CLIENT_PORT) {
+            if let Ok(msg) = DhcpMessage::parse(&datagram.payload) {
+                events.extend(client.on_message(&msg, now));
+            }
+        }
+        events.extend(client.on_tick(now));
+
+        // Fall back once; DHCP stops for good
+        if events.contains(&DhcpEvent::GaveUp) && STATIC_CONFIG.lock().is_some() {
+            client.state = DhcpState::Static;
+        }
+    }
+    perform(events);
+}
+
+/// Carry out the client's requests (no locks held)
+fn perform(events: Vec<DhcpEvent>) {
+    for event in events {
+        match event {
+            DhcpEvent::Send { dst, msg } => {
+                let _ = udp::send_to(CLIENT_PORT, dst.into(), SERVER_PORT, &msg.to_bytes());
+            }
+            DhcpEvent::Bound(lease) => {
+                let mut cfg = crate::net::config();
+                let changed = cfg.ip_addr != lease.ip;
+                cfg.ip_addr = lease.ip;
+                cfg.netmask = lease.netmask;
+                cfg.gateway = lease.gateway;
+                cfg.dns_servers = [Ipv4Addr::ANY; MAX_DNS_SERVERS];
+                for (slot, server) in cfg.dns_servers.iter_mut().zip(lease.dns.iter()) {
+                    *slot = *server;
+                }
+                crate::net::set_config(cfg);
+                if changed {
+                    crate::kprintln!("[DHCP] Bound {} mask {} gw {} ({}s lease)",
+                        lease.ip, lease.netmask, lease.gateway, lease.lease_ms / 1000);
+                    let _ = crate::net::arp::announce();
+                }
+            }
+            DhcpEvent::Expired => {
+                crate::kprintln!("[DHCP] Lease lost");
+                let mut cfg = crate::net::config();
+                cfg.ip_addr = Ipv4Addr::ANY;
+                cfg.gateway = Ipv4Addr::ANY;
+                crate::net::set_config(cfg);
+            }
+            DhcpEvent::GaveUp => {
+                let fallback = STATIC_CONFIG.lock().clone();
+                match fallback {
+                    Some(mut cfg) => {
+                        crate::kprintln!("[DHCP] No server, using static {}", cfg.ip_addr);
+                        cfg.mac_addr = crate::net::config().mac_addr;
+                        crate::net::set_config(cfg);
+                        let _ = crate::net::arp::announce();
+                    }
+                    None => crate::kprintln!("[DHCP] No server, retrying later"),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    const MAC: MacAddress = MacAddress([0x52, 0x54, 0x00, 0x12, 0x34, 0x56]);
+    const SERVER: Ipv4Addr = Ipv4Addr([10, 0, 2, 2]);
+    const OFFERED: Ipv4Addr = Ipv4Addr([10, 0, 2, 15]);
+
+    /// A reply shaped like QEMU's user-mode (slirp) DHCP server sends
+    fn slirp_reply(xid: u32, msg_type: u8) -> DhcpMessage {
+        DhcpMessage {
+            op: BOOTREPLY,
+            xid,
+            chaddr: MAC.0,
+            yiaddr: Some(OFFERED),
+            msg_type,
+            subnet_mask: Some(Ipv4Addr([255, 255, 255, 0])),
+            router: Some(SERVER),
+            dns: vec![Ipv4Addr([10, 0, 2, 3])],
+            server_id: Some(SERVER),
+            lease_secs: Some(86400),
+            ..Default::default()
+        }
+    }
+
+    fn sent(events: &[DhcpEvent]) -> &DhcpMessage {
+        match events {
+            [DhcpEvent::Send { msg, .. }] => msg,
+            other => panic!("expected one send, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_message_round_trip() {
+        let msg = slirp_reply(0xdeadbeef, ACK);
+        let bytes = msg.to_bytes();
+        assert_eq!(bytes.len(), 300);
+        assert_eq!(DhcpMessage::parse(&bytes).unwrap(), msg);
+    }
+
+    #[test]
+    fn test_full_exchange() {
+        let mut client = DhcpClient::new(MAC, 41);
+        let discover = sent(&client.start(0)).clone();
+        assert_eq!(discover.msg_type, DISCOVER);
+        assert_eq!(discover.flags, FLAG_BROADCAST);
+
+        let request = sent(&client.on_message(&slirp_reply(discover.xid, OFFER), 10)).clone();
+        assert_eq!(request.msg_type, REQUEST);
+        assert_eq!(request.requested_ip, Some(OFFERED));
+        assert_eq!(request.server_id, Some(SERVER));
+
+        let events = client.on_message(&slirp_reply(discover.xid, ACK), 20);
+        let DhcpEvent::Bound(lease) = &events[0] else { panic!("{:?}", events) };
+        assert_eq!(lease.ip, OFFERED);
+        assert_eq!(lease.gateway, SERVER);
+        assert_eq!(lease.dns, vec![Ipv4Addr([10, 0, 2, 3])]);
+        assert_eq!(lease.renew_ms, 43_200_000);
+        assert_eq!(client.state(), DhcpState::Bound);
+    }
+
+    #[test]
+    fn test_ignores_foreign_xid() {
+        let mut client = DhcpClient::new(MAC, 7);
+        let xid = sent(&client.start(0)).xid;
+        assert!(client.on_message(&slirp_reply(xid + 1, OFFER), 1).is_empty());
+        assert_eq!(client.state(), DhcpState::Selecting);
+    }
+
+    #[test]
+    fn test_renew_rebind_expire() {
+        let mut client = DhcpClient::new(MAC, 1);
+        let xid = sent(&client.start(0)).xid;
+        client.on_message(&slirp_reply(xid, OFFER), 0);
+        client.on_message(&slirp_reply(xid, ACK), 0);
+        assert!(client.on_tick(1000).is_empty());
+
+        // T1: unicast renewal to the server
+        let events = client.on_tick(43_200_000);
+        assert_eq!(client.state(), DhcpState::Renewing);
+        let DhcpEvent::Send { dst, msg } = &events[0] else { panic!() };
+        assert_eq!(*dst, SERVER);
+        assert_eq!(msg.ciaddr, Some(OFFERED));
+
+        // T2: broadcast
+        let events = client.on_tick(75_600_000);
+        assert_eq!(client.state(), DhcpState::Rebinding);
+        assert!(matches!(&events[0], DhcpEvent::Send { dst, .. } if *dst == Ipv4Addr::BROADCAST));
+
+        // Expiry: address dropped, discovery restarts
+        let events = client.on_tick(86_400_000);
+        assert_eq!(events[0], DhcpEvent::Expired);
+        assert_eq!(client.state(), DhcpState::Selecting);
+    }
+
+    #[test]
+    fn test_gives_up_after_retries() {
+        let mut client = DhcpClient::new(MAC, 1);
+        client.start(0);
+        let mut now = 0;
+        for _ in 1..MAX_ATTEMPTS {
+            now += 60_000;
+            assert_eq!(sent(&client.on_tick(now)).msg_type, DISCOVER);
+        }
+        now += 60_000;
+        assert_eq!(client.on_tick(now), vec![DhcpEvent::GaveUp]);
+    }
+
+    #[test]
+    fn test_static_config() {
+        let text = "arm_64bit=1\n# net_ip=1.1.1.1\nnet_ip=192.168.1.50\nnet_gateway=192.168.1.1\nnet_dns=192.168.1.1, 1.1.1.1\n";
+        let cfg = parse_static_config(text).unwrap();
+        assert_eq!(cfg.ip_addr, Ipv4Addr([192, 168, 1, 50]));
+        assert_eq!(cfg.netmask, Ipv4Addr([255, 255, 255, 0]));
+        assert_eq!(cfg.gateway, Ipv4Addr([192, 168, 1, 1]));
+        assert_eq!(cfg.dns_servers, [Ipv4Addr([192, 168, 1, 1]), Ipv4Addr([1, 1, 1, 1])]);
+        assert!(parse_static_config("arm_64bit=1\n").is_none());
+        assert!(parse_static_config("net_ip=300.1.1.1\n").is_none());
+    }
+}
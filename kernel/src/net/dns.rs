@@ -0,0 +1,754 @@
+//! DNS Stub Resolver (RFC 1035)
+//!
+//! Resolves host names through the name servers in `NetConfig` (learned
+//! from DHCP or `config.txt`). Queries ask for recursion, time out after
+//! `TIMEOUT_MS` and move on to the next server; answers are cached for
+//! their TTL, and failures for a short while so callers don't hammer a
+//! dead server.
+//!
+//! Each query gets a random ID and its own random source port, and a reply
+//! must match both (plus the server and question) to be taken, so an
+//! off-path attacker has to guess 32 bits rather than 16.
+//!
+//! Like the DHCP client, `Resolver` is pure (datagrams and time in,
+//! events out). `tick` performs the I/O; `resolve` blocks the caller
+//! until the answer or a failure is cached.
+
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::convert::TryInto;
+use core::fmt::{self, Write};
+use crate::kernel::sync::{SpinLock, EWOULDBLOCK, POLL_WAIT};
+use crate::net::ip::{IpAddr, Ipv4Addr};
+use crate::net::udp;
+
+pub const DNS_PORT: u16 = 53;
+
+const HEADER_LEN: usize = 12;
+const CLASS_IN: u16 = 1;
+/// Recursion desired
+const FLAG_RD: u16 = 0x0100;
+const FLAG_QR: u16 = 0x8000;
+
+const RCODE_OK: u8 = 0;
+const RCODE_NXDOMAIN: u8 = 3;
+
+/// Longest name on the wire (RFC 1035 §2.3.4)
+const MAX_NAME_LEN: usize = 253;
+const MAX_LABEL_LEN: usize = 63;
+/// Compression pointers followed before giving up on a name
+const MAX_POINTER_HOPS: usize = 16;
+
+/// Wait per attempt before asking the next server
+const TIMEOUT_MS: u64 = 1_000;
+/// Attempts per query, rotating through the servers
+const MAX_ATTEMPTS: u8 = 4;
+/// Upper bound on a cached answer, whatever the server says
+const MAX_TTL_S: u32 = 86_400;
+/// How long "no such host" is remembered
+const NEGATIVE_TTL_MS: u64 = 30_000;
+/// How long a timeout is remembered
+const FAILURE_TTL_MS: u64 = 5_000;
+
+const MAX_CACHE_ENTRIES: usize = 64;
+const MAX_QUERIES: usize = 16;
+
+/// Source ports come from the dynamic range (RFC 6335 §6)
+const PORT_BASE: u16 = 49152;
+/// Random ports tried before giving up on a query
+const PORT_ATTEMPTS: usize = 8;
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// MESSAGES
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Query types we ask for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[repr(u16)]
+pub enum RecordType {
+    A = 1,
+    Aaaa = 28,
+}
+
+/// One address record from an answer section
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Answer {
+    A(Ipv4Addr),
+    Aaaa([u8; 16]),
+}
+
+impl fmt::Display for Answer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Answer::A(ip) => write!(f, "{}", ip),
+            Answer::Aaaa(bytes) => {
+                for (i, pair) in bytes.chunks(2).enumerate() {
+                    if i > 0 {
+                        f.write_str(":")?;
+                    }
+                    write!(f, "{:x}", u16::from_be_bytes([pair[0], pair[1]]))?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Lowercase a host name and check it can be encoded
+pub fn normalize_name(name: &str) -> Result<String, &'static str> {
+    let name = name.trim().trim_end_matches('.');
+    if name.is_empty() || name.len() > MAX_NAME_LEN {
+        return Err("Invalid host name");
+    }
+    for label in name.split('.') {
+        let valid_chars = label.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_');
+        if label.is_empty() || label.len() > MAX_LABEL_LEN || !valid_chars {
+            return Err("Invalid host name");
+        }
+    }
+    Ok(name.to_ascii_lowercase())
+}
+
+/// Encode a standard recursive query for one name
+pub fn build_query(id: u16, name: &str, qtype: RecordType) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(HEADER_LEN + name.len() + 6);
+    bytes.extend_from_slice(&id.to_be_bytes());
+    bytes.extend_from_slice(&FLAG_RD.to_be_bytes());
+    bytes.extend_from_slice(&1u16.to_be_bytes()); // QDCOUNT
+    bytes.extend_from_slice(&[0; 6]);            // AN/NS/AR counts
+
+    for label in name.split('.') {
+        bytes.push(label.len() as u8);
+        bytes.extend_from_slice(label.as_bytes());
+    }
+    bytes.push(0);
+    bytes.extend_from_slice(&(qtype as u16).to_be_bytes());
+    bytes.extend_from_slice(&CLASS_IN.to_be_bytes());
+    bytes
+}
+
+fn read_u16(data: &[u8], pos: usize) -> Result<u16, &'static str> {
+    data.get(pos..pos + 2)
+        .map(|b| u16::from_be_bytes(b.try_into().unwrap()))
+        .ok_or("DNS message truncated")
+}
+
+/// Decode a (possibly compressed) name; returns it and the offset after it
+fn read_name(data: &[u8], mut pos: usize) -> Result<(String, usize), &'static str> {
+    let mut name = String::new();
+    let mut end = None;
+    let mut hops = 0;
+
+    loop {
+        let len = *data.get(pos).ok_or("DNS message truncated")? as usize;
+        if len & 0xC0 == 0xC0 {
+            // Pointer: the rest of the name lives elsewhere
+            let target = (read_u16(data, pos)? & 0x3FFF) as usize;
+            end.get_or_insert(pos + 2);
+            hops += 1;
+            if hops > MAX_POINTER_HOPS {
+                return Err("DNS name pointer loop");
+            }
+            pos = target;
+        } else if len == 0 {
+            return Ok((name, end.unwrap_or(pos + 1)));
+        } else {
+            let label = data.get(pos + 1..pos + 1 + len).ok_or("DNS message truncated")?;
+            if !name.is_empty() {
+                name.push('.');
+            }
+            name.extend(label.iter().map(|b| b.to_ascii_lowercase() as char));
+            if name.len() > MAX_NAME_LEN {
+                return Err("DNS name too long");
+            }
+            pos += 1 + len;
+        }
+    }
+}
+
+/// The parts of a response the resolver needs
+#[derive(Debug, Clone)]
+pub struct DnsResponse {
+    pub id: u16,
+    pub rcode: u8,
+    /// First question: (name, type)
+    pub question: Option<(String, u16)>,
+    /// Address records with their TTL in seconds
+    pub answers: Vec<(Answer, u32)>,
+}
+
+impl DnsResponse {
+    pub fn parse(data: &[u8]) -> Result<Self, &'static str> {
+        if data.len() < HEADER_LEN {
+            return Err("DNS message too short");
+        }
+        let id = read_u16(data, 0)?;
+        let flags = read_u16(data, 2)?;
+        if flags & FLAG_QR == 0 {
+            return Err("Not a DNS response");
+        }
+        let qdcount = read_u16(data, 4)?;
+        let ancount = read_u16(data, 6)?;
+
+        let mut pos = HEADER_LEN;
+        let mut question = None;
+        for _ in 0..qdcount {
+            let (name, next) = read_name(data, pos)?;
+            let qtype = read_u16(data, next)?;
+            question.get_or_insert((name, qtype));
+            pos = next + 4;
+        }
+
+        // CNAMEs and other records are skipped; only addresses matter
+        let mut answers = Vec::new();
+        for _ in 0..ancount {
+            let (_, next) = read_name(data, pos)?;
+            let rtype = read_u16(data, next)?;
+            let class = read_u16(data, next + 2)?;
+            let ttl = data.get(next + 4..next + 8)
+                .map(|b| u32::from_be_bytes(b.try_into().unwrap()))
+                .ok_or("DNS message truncated")?;
+            let rdlen = read_u16(data, next + 8)? as usize;
+            let rdata = data.get(next + 10..next + 10 + rdlen).ok_or("DNS message truncated")?;
+            pos = next + 10 + rdlen;
+
+            if class != CLASS_IN {
+                continue;
+            }
+            let answer = match (rtype, rdlen) {
+                (t, 4) if t == RecordType::A as u16 => Answer::A(Ipv4Addr(rdata.try_into().unwrap())),
+                (t, 16) if t == RecordType::Aaaa as u16 => Answer::Aaaa(rdata.try_into().unwrap()),
+                _ => continue,
+            };
+            answers.push((answer, ttl));
+        }
+
+        Ok(Self { id, rcode: (flags & 0x000F) as u8, question, answers })
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// CACHE
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Outcome of a lookup: addresses, or why there are none
+pub type LookupResult = Result<Vec<Answer>, &'static str>;
+
+struct CacheEntry {
+    result: LookupResult,
+    expires_ms: u64,
+}
+
+/// Answers (and failures) keyed by name and type, dropped at expiry
+pub struct DnsCache {
+    entries: BTreeMap<(String, RecordType), CacheEntry>,
+}
+
+impl DnsCache {
+    pub const fn new() -> Self {
+        Self { entries: BTreeMap::new() }
+    }
+
+    /// Unexpired result for a name
+    pub fn get(&self, name: &str, qtype: RecordType, now: u64) -> Option<LookupResult> {
+        self.entries.get(&(String::from(name), qtype))
+            .filter(|e| now < e.expires_ms)
+            .map(|e| e.result.clone())
+    }
+
+    /// Remember a result for `ttl_ms`, evicting the entry closest to expiry if full
+    pub fn insert(&mut self, name: &str, qtype: RecordType, result: LookupResult, ttl_ms: u64, now: u64) {
+        self.expire(now);
+        let key = (String::from(name), qtype);
+        if self.entries.len() >= MAX_CACHE_ENTRIES && !self.entries.contains_key(&key) {
+            let victim = self.entries.iter()
+                .min_by_key(|(_, e)| e.expires_ms)
+                .map(|(k, _)| k.clone());
+            if let Some(victim) = victim {
+                self.entries.remove(&victim);
+            }
+        }
+        self.entries.insert(key, CacheEntry { result, expires_ms: now + ttl_ms });
+    }
+
+    /// Drop expired entries
+    pub fn expire(&mut self, now: u64) {
+        self.entries.retain(|_, e| now < e.expires_ms);
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+impl Default for DnsCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// RESOLVER
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// What the resolver wants done
+#[derive(Debug, Clone, PartialEq)]
+pub enum DnsEvent {
+    /// Send `packet` from local `port` to `server` port 53
+    Send { server: Ipv4Addr, port: u16, packet: Vec<u8> },
+    /// A query finished; the result is now cached and `port` is free
+    Resolved { name: String, port: u16, result: LookupResult },
+}
+
+/// Result of asking the resolver for a name
+#[derive(Debug, Clone, PartialEq)]
+pub enum Lookup {
+    Done(LookupResult),
+    /// A new query must be sent
+    Send(DnsEvent),
+    /// A query for this name is already in flight
+    Pending,
+}
+
+/// An outstanding query
+struct Query {
+    id: u16,
+    /// Local port the query is sent from and the reply must reach
+    port: u16,
+    name: String,
+    qtype: RecordType,
+    /// Index into the server list of the server last asked
+    server_idx: usize,
+    server: Ipv4Addr,
+    attempts: u8,
+    deadline_ms: u64,
+}
+
+pub struct Resolver {
+    cache: DnsCache,
+    queries: Vec<Query>,
+}
+
+impl Resolver {
+    pub const fn new() -> Self {
+        Self { cache: DnsCache::new(), queries: Vec::new() }
+    }
+
+    pub fn cache(&self) -> &DnsCache {
+        &self.cache
+    }
+
+    /// Local ports of the queries in flight
+    pub fn ports(&self) -> Vec<u16> {
+        self.queries.iter().map(|q| q.port).collect()
+    }
+
+    /// Answer from the cache or start a query (`name` already normalized)
+    ///
+    /// Only a new query calls `open`, for its random ID and a bound random
+    /// source port.
+    pub fn lookup(
+        &mut self,
+        name: &str,
+        qtype: RecordType,
+        servers: &[Ipv4Addr],
+        now: u64,
+        open: impl FnOnce() -> Result<(u16, u16), &'static str>,
+    ) -> Lookup {
+        if let Some(result) = self.cache.get(name, qtype, now) {
+            return Lookup::Done(result);
+        }
+        if self.queries.iter().any(|q| q.name == name && q.qtype == qtype) {
+            return Lookup::Pending;
+        }
+        if servers.is_empty() {
+            return Lookup::Done(Err("No name servers"));
+        }
+        if self.queries.len() >= MAX_QUERIES {
+            return Lookup::Done(Err("Too many queries"));
+        }
+
+        let (id, port) = match open() {
+            Ok(opened) => opened,
+            Err(e) => return Lookup::Done(Err(e)),
+        };
+        let query = Query {
+            id,
+            port,
+            name: String::from(name),
+            qtype,
+            server_idx: 0,
+            server: servers[0],
+            attempts: 1,
+            deadline_ms: now + TIMEOUT_MS,
+        };
+        let event = DnsEvent::Send { server: query.server, port, packet: build_query(id, name, qtype) };
+        self.queries.push(query);
+        Lookup::Send(event)
+    }
+
+    /// Handle a datagram from port 53 that arrived on local `port`
+    pub fn on_reply(
+        &mut self,
+        data: &[u8],
+        from: Ipv4Addr,
+        port: u16,
+        servers: &[Ipv4Addr],
+        now: u64,
+    ) -> Vec<DnsEvent> {
+        let Ok(resp) = DnsResponse::parse(data) else { return Vec::new() };
+
+        // Must answer our question, from the server we asked, to the port
+        // we asked from
+        let found = self.queries.iter().position(|q| {
+            q.id == resp.id && q.port == port && q.server == from
+                && resp.question.as_ref().map_or(false, |(name, t)| *name == q.name && *t == q.qtype as u16)
+        });
+        let Some(i) = found else { return Vec::new() };
+
+        match resp.rcode {
+            RCODE_OK => {
+                let qtype = self.queries[i].qtype;
+                let matching = |a: &Answer| matches!((a, qtype), (Answer::A(_), RecordType::A) | (Answer::Aaaa(_), RecordType::Aaaa));
+                let addrs: Vec<Answer> = resp.answers.iter().map(|(a, _)| *a).filter(matching).collect();
+                if addrs.is_empty() {
+                    return self.finish(i, Err("No address records"), NEGATIVE_TTL_MS, now);
+                }
+                let ttl = resp.answers.iter()
+                    .filter(|(a, _)| matching(a))
+                    .map(|(_, ttl)| (*ttl).min(MAX_TTL_S))
+                    .min()
+                    .unwrap_or(0);
+                self.finish(i, Ok(addrs), ttl as u64 * 1000, now)
+            }
+            RCODE_NXDOMAIN => self.finish(i, Err("Host not found"), NEGATIVE_TTL_MS, now),
+            // SERVFAIL, REFUSED...: this server can't help, try the next
+            _ => self.retry(i, servers, now),
+        }
+    }
+
+    /// Expire the cache and retry queries that timed out
+    pub fn on_tick(&mut self, servers: &[Ipv4Addr], now: u64) -> Vec<DnsEvent> {
+        self.cache.expire(now);
+        let mut events = Vec::new();
+        for i in (0..self.queries.len()).rev() {
+            if now >= self.queries[i].deadline_ms {
+                events.extend(self.retry(i, servers, now));
+            }
+        }
+        events
+    }
+
+    /// Ask the next server, or give up after `MAX_ATTEMPTS`
+    fn retry(&mut self, i: usize, servers: &[Ipv4Addr], now: u64) -> Vec<DnsEvent> {
+        let query = &mut self.queries[i];
+        if query.attempts >= MAX_ATTEMPTS || servers.is_empty() {
+            return self.finish(i, Err("Timed out"), FAILURE_TTL_MS, now);
+        }
+        query.attempts += 1;
+        query.server_idx = (query.server_idx + 1) % servers.len();
+        query.server = servers[query.server_idx];
+        query.deadline_ms = now + TIMEOUT_MS;
+        let packet = build_query(query.id, &query.name, query.qtype);
+        alloc::vec![DnsEvent::Send { server: query.server, port: query.port, packet }]
+    }
+
+    /// Cache a query's result and retire it
+    fn finish(&mut self, i: usize, result: LookupResult, ttl_ms: u64, now: u64) -> Vec<DnsEvent> {
+        let query = self.queries.swap_remove(i);
+        self.cache.insert(&query.name, query.qtype, result.clone(), ttl_ms, now);
+        alloc::vec![DnsEvent::Resolved { name: query.name, port: query.port, result }]
+    }
+}
+
+/// One-line description of a result, for logs and the shell
+pub fn describe(result: &LookupResult) -> String {
+    let mut out = String::new();
+    match result {
+        Ok(addrs) => {
+            for (i, addr) in addrs.iter().enumerate() {
+                let sep = if i > 0 { ", " } else { "" };
+                let _ = write!(out, "{}{}", sep, addr);
+            }
+        }
+        Err(e) => out.push_str(e),
+    }
+    out
+}
+
+/// Names that never go to a server
+fn literal(name: &str, qtype: RecordType) -> Option<Answer> {
+    match qtype {
+        RecordType::A if name.eq_ignore_ascii_case("localhost") => Some(Answer::A(Ipv4Addr::LOOPBACK)),
+        RecordType::A => Ipv4Addr::parse(name).map(Answer::A),
+        RecordType::Aaaa => None,
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// GLOBAL RESOLVER
+// ═══════════════════════════════════════════════════════════════════════════════
+
+static RESOLVER: SpinLock<Option<Resolver>> = SpinLock::new(None);
+
+/// Start the resolver; ports are bound per query
+pub fn start() {
+    *RESOLVER.lock() = Some(Resolver::new());
+}
+
+/// Bind a random free port in the dynamic range
+fn bind_random_port() -> Result<u16, &'static str> {
+    for _ in 0..PORT_ATTEMPTS {
+        let port = PORT_BASE + (crate::drivers::rng::next_u64() % u64::from(u16::MAX - PORT_BASE + 1)) as u16;
+        if udp::register_listener(port).is_ok() {
+            return Ok(port);
+        }
+    }
+    Err("No free port")
+}
+
+/// Configured name servers, in order
+fn servers() -> Vec<Ipv4Addr> {
+    crate::net::config().dns_servers.iter()
+        .copied()
+        .filter(|s| *s != Ipv4Addr::ANY)
+        .collect()
+}
+
+/// Handle replies and timeouts (called from the timer tick and by waiters)
+pub fn tick() {
+    let servers = servers();
+    let now = crate::drivers::timer::uptime_ms();
+    let mut events = Vec::new();
+    {
+        let mut guard = RESOLVER.lock();
+        let Some(resolver) = guard.as_mut() else { return };
+        for port in resolver.ports() {
+            while let Some(datagram) = udp::recv_from(port) {
+                // Name servers are IPv4 (from DHCP or config.txt)
+                if let (DNS_PORT, IpAddr::V4(from)) = (datagram.src_port, datagram.src_addr) {
+                    events.extend(resolver.on_reply(&datagram.payload, from, port, &servers, now));
+                }
+            }
+        }
+        events.extend(resolver.on_tick(&servers, now));
+    }
+    perform(events);
+}
+
+/// Carry out the resolver's requests (no locks held)
+fn perform(events: Vec<DnsEvent>) {
+    for event in events {
+        match event {
+            DnsEvent::Send { server, port, packet } => {
+                let _ = udp::send_to(port, server.into(), DNS_PORT, &packet);
+            }
+            DnsEvent::Resolved { name, port, result } => {
+                let _ = udp::unregister_listener(port);
+                crate::kprintln!("[DNS] {}: {}", name, describe(&result));
+            }
+        }
+    }
+}
+
+/// Look a name up without blocking; `None` while the query is in flight
+pub fn lookup(name: &str, qtype: RecordType) -> Option<LookupResult> {
+    if let Some(answer) = literal(name.trim(), qtype) {
+        return Some(Ok(alloc::vec![answer]));
+    }
+    let name = match normalize_name(name) {
+        Ok(name) => name,
+        Err(e) => return Some(Err(e)),
+    };
+
+    tick();
+    let servers = servers();
+    let now = crate::drivers::timer::uptime_ms();
+    let open = || Ok((crate::drivers::rng::next_u64() as u16, bind_random_port()?));
+    let lookup = match RESOLVER.lock().as_mut() {
+        Some(resolver) => resolver.lookup(&name, qtype, &servers, now, open),
+        None => return Some(Err("Resolver not started")),
+    };
+    match lookup {
+        Lookup::Done(result) => Some(result),
+        Lookup::Send(event) => {
+            perform(alloc::vec![event]);
+            None
+        }
+        Lookup::Pending => None,
+    }
+}
+
+/// Look a name up, blocking until an answer or failure
+pub fn resolve(name: &str, qtype: RecordType) -> LookupResult {
+    // tick() wakes POLL_WAIT every 10ms, so timeouts are seen promptly
+    match POLL_WAIT.wait_until(|| lookup(name, qtype)) {
+        Ok(result) => result,
+        // No agent to block (kernel context): spin, the tick still runs
+        Err(EWOULDBLOCK) => loop {
+            if let Some(result) = lookup(name, qtype) {
+                return result;
+            }
+            core::hint::spin_loop();
+        },
+        Err(e) => Err(e), // EINTR
+    }
+}
+
+/// IPv4 addresses of a host
+pub fn resolve_ipv4(name: &str) -> Result<Vec<Ipv4Addr>, &'static str> {
+    let answers = resolve(name, RecordType::A)?;
+    Ok(answers.into_iter()
+        .filter_map(|a| match a {
+            Answer::A(ip) => Some(ip),
+            Answer::Aaaa(_) => None,
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SERVERS: [Ipv4Addr; 2] = [Ipv4Addr([10, 0, 2, 3]), Ipv4Addr([1, 1, 1, 1])];
+    const PORT: u16 = 50_000;
+
+    /// ID and source port for a new query
+    fn open() -> Result<(u16, u16), &'static str> {
+        Ok((7, PORT))
+    }
+
+    /// A response to `query` with the given rcode and A records (name compressed)
+    fn response(query: &[u8], rcode: u8, answers: &[([u8; 4], u32)]) -> Vec<u8> {
+        let mut bytes = query.to_vec();
+        bytes[2] = 0x81; // QR | RD
+        bytes[3] = 0x80 | rcode;
+        bytes[6..8].copy_from_slice(&(answers.len() as u16).to_be_bytes());
+        for (ip, ttl) in answers {
+            bytes.extend_from_slice(&[0xC0, HEADER_LEN as u8]); // → question name
+            bytes.extend_from_slice(&1u16.to_be_bytes());
+            bytes.extend_from_slice(&CLASS_IN.to_be_bytes());
+            bytes.extend_from_slice(&ttl.to_be_bytes());
+            bytes.extend_from_slice(&4u16.to_be_bytes());
+            bytes.extend_from_slice(ip);
+        }
+        bytes
+    }
+
+    fn sent(lookup: Lookup) -> (Ipv4Addr, Vec<u8>) {
+        match lookup {
+            Lookup::Send(DnsEvent::Send { server, packet, .. }) => (server, packet),
+            other => panic!("expected a query, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_build_query() {
+        let q = build_query(0x1234, "example.com", RecordType::A);
+        assert_eq!(&q[..4], &[0x12, 0x34, 0x01, 0x00]);
+        assert_eq!(&q[12..25], b"\x07example\x03com\x00");
+        assert_eq!(&q[25..], &[0, 1, 0, 1]);
+    }
+
+    #[test]
+    fn test_normalize_name() {
+        assert_eq!(normalize_name("WWW.Example.COM.").unwrap(), "www.example.com");
+        assert!(normalize_name("").is_err());
+        assert!(normalize_name("bad..name").is_err());
+        assert!(normalize_name("spa ce.com").is_err());
+    }
+
+    #[test]
+    fn test_answer_cached_for_ttl() {
+        let mut r = Resolver::new();
+        let (server, query) = sent(r.lookup("example.com", RecordType::A, &SERVERS, 0, open));
+        assert_eq!(server, SERVERS[0]);
+        assert_eq!(r.lookup("example.com", RecordType::A, &SERVERS, 10, open), Lookup::Pending);
+
+        let reply = response(&query, RCODE_OK, &[([93, 184, 216, 34], 300), ([93, 184, 216, 35], 60)]);
+        let events = r.on_reply(&reply, server, PORT, &SERVERS, 20);
+        let expected: LookupResult = Ok(alloc::vec![
+            Answer::A(Ipv4Addr([93, 184, 216, 34])),
+            Answer::A(Ipv4Addr([93, 184, 216, 35])),
+        ]);
+        assert_eq!(events, alloc::vec![DnsEvent::Resolved { name: "example.com".into(), port: PORT, result: expected.clone() }]);
+
+        // Shortest TTL wins, then the name is asked again
+        assert_eq!(r.lookup("example.com", RecordType::A, &SERVERS, 60_019, open), Lookup::Done(expected));
+        assert!(matches!(r.lookup("example.com", RecordType::A, &SERVERS, 60_020, open), Lookup::Send(_)));
+    }
+
+    #[test]
+    fn test_spoofed_reply_ignored() {
+        let mut r = Resolver::new();
+        let (server, query) = sent(r.lookup("example.com", RecordType::A, &SERVERS, 0, open));
+        let reply = response(&query, RCODE_OK, &[([6, 6, 6, 6], 300)]);
+
+        // Wrong server, wrong port, then wrong id
+        assert!(r.on_reply(&reply, Ipv4Addr([6, 6, 6, 6]), PORT, &SERVERS, 1).is_empty());
+        assert!(r.on_reply(&reply, server, PORT + 1, &SERVERS, 1).is_empty());
+        let mut forged = reply.clone();
+        forged[1] ^= 0xFF;
+        assert!(r.on_reply(&forged, server, PORT, &SERVERS, 1).is_empty());
+        assert_eq!(r.lookup("example.com", RecordType::A, &SERVERS, 2, open), Lookup::Pending);
+    }
+
+    #[test]
+    fn test_timeout_rotates_servers_then_fails() {
+        let mut r = Resolver::new();
+        sent(r.lookup("example.com", RecordType::A, &SERVERS, 0, open));
+
+        let mut asked = alloc::vec![SERVERS[0]];
+        let mut now = 0;
+        for _ in 1..MAX_ATTEMPTS {
+            now += TIMEOUT_MS;
+            match r.on_tick(&SERVERS, now).as_slice() {
+                [DnsEvent::Send { server, .. }] => asked.push(*server),
+                other => panic!("expected a retry, got {:?}", other),
+            }
+        }
+        assert_eq!(asked, [SERVERS[0], SERVERS[1], SERVERS[0], SERVERS[1]]);
+
+        now += TIMEOUT_MS;
+        let events = r.on_tick(&SERVERS, now);
+        assert_eq!(events, alloc::vec![DnsEvent::Resolved { name: "example.com".into(), port: PORT, result: Err("Timed out") }]);
+        assert_eq!(r.lookup("example.com", RecordType::A, &SERVERS, now + 1, open), Lookup::Done(Err("Timed out")));
+        assert!(matches!(r.lookup("example.com", RecordType::A, &SERVERS, now + FAILURE_TTL_MS, open), Lookup::Send(_)));
+    }
+
+    #[test]
+    fn test_nxdomain_and_servfail() {
+        let mut r = Resolver::new();
+        let (server, query) = sent(r.lookup("nowhere.test", RecordType::A, &SERVERS, 0, open));
+
+        // SERVFAIL moves straight on to the next server
+        let events = r.on_reply(&response(&query, 2, &[]), server, PORT, &SERVERS, 5);
+        let next = match events.as_slice() {
+            [DnsEvent::Send { server, .. }] => *server,
+            other => panic!("expected a retry, got {:?}", other),
+        };
+        assert_eq!(next, SERVERS[1]);
+
+        r.on_reply(&response(&query, RCODE_NXDOMAIN, &[]), next, PORT, &SERVERS, 10);
+        assert_eq!(r.lookup("nowhere.test", RecordType::A, &SERVERS, 20, open), Lookup::Done(Err("Host not found")));
+        assert_eq!(r.cache().len(), 1);
+    }
+
+    #[test]
+    fn test_literals_and_no_servers() {
+        assert_eq!(literal("10.0.0.1", RecordType::A), Some(Answer::A(Ipv4Addr([10, 0, 0, 1]))));
+        assert_eq!(literal("LocalHost", RecordType::A), Some(Answer::A(Ipv4Addr::LOOPBACK)));
+        assert_eq!(literal("example.com", RecordType::A), None);
+
+        let mut r = Resolver::new();
+        assert_eq!(r.lookup("example.com", RecordType::A, &[], 0, open), Lookup::Done(Err("No name servers")));
+        let busy = || Err("No free port");
+        assert_eq!(r.lookup("example.com", RecordType::A, &SERVERS, 0, busy), Lookup::Done(Err("No free port")));
+        assert!(r.ports().is_empty());
+    }
+}
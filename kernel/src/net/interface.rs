@@ -1,24 +1,51 @@
+//! Network Interfaces
+//!
+//! Every NIC implements `NetworkInterface` and is registered in
+//! `INTERFACES`. Outgoing frames go to the interface picked by the routing
+//! table (or loopback for local destinations); `poll` drains every
+//! interface and hands the frames to the protocol layers by EtherType.
+//! Both directions pass through `capture::tap` while a capture runs.
+
+use alloc::boxed::Box;
 use alloc::vec::Vec;
 use alloc::collections::VecDeque;
-use crate::net::arp::ArpPacket;
+use crate::kernel::sync::SpinLock;
+use crate::net::arp::{ArpOperation, ArpPacket};
+use crate::net::capture;
+use crate::net::ethernet::{EtherType, MacAddress};
+
+/// Largest Ethernet frame handled (without FCS)
+pub const MAX_FRAME_SIZE: usize = 1518;
+/// Smallest frame: the Ethernet header alone
+const MIN_FRAME_SIZE: usize = 14;
+/// Frames taken from one interface per `poll` call
+const RX_BUDGET: usize = 32;
 
 /// Network Interface Trait
-/// 
+///
 /// Abstraction for hardware or software network devices.
 pub trait NetworkInterface {
     /// Send a raw packet (Ethernet frame)
     fn send(&mut self, packet: &[u8]) -> Result<(), &'static str>;
-    
+
     /// Receive a raw packet (Ethernet frame)
     /// Returns None if no packet is available.
     fn receive(&mut self) -> Option<Vec<u8>>;
-    
+
     /// Get the MAC address of the interface
     fn mac_address(&self) -> [u8; 6];
+
+    /// Short name, e.g. "eth0"
+    fn name(&self) -> &'static str;
+
+    /// Largest IP packet the link carries
+    fn mtu(&self) -> usize {
+        1500
+    }
 }
 
 /// Loopback Interface
-/// 
+///
 /// A software interface that echoes back everything sent to it.
 pub struct LoopbackInterface {
     queue: VecDeque<Vec<u8>>,
@@ -52,47 +79,336 @@ impl NetworkInterface for LoopbackInterface {
     fn mac_address(&self) -> [u8; 6] {
         [0, 0, 0, 0, 0, 0]
     }
+
+    fn name(&self) -> &'static str {
+        "lo"
+    }
+
+    fn mtu(&self) -> usize {
+        // Still Ethernet-framed, so bounded by MAX_FRAME_SIZE
+        MAX_FRAME_SIZE - MIN_FRAME_SIZE
+    }
 }
 
 // ═══════════════════════════════════════════════════════════════════════════════
-// GLOBAL SEND FUNCTIONS (Hardware driver stubs)
+// HARDWARE INTERFACES
 // ═══════════════════════════════════════════════════════════════════════════════
 
-/// Send an Ethernet frame
-/// 
-/// In a full implementation, this would use the hardware Ethernet driver.
-/// For now, this is a stub that successfully "sends" without hardware.
-pub fn send_frame(frame: &[u8]) -> Result<(), &'static str> {
-    // Validate frame size
-    if frame.len() < 14 {
+/// VirtIO-Net device (QEMU `virt`)
+pub struct VirtioNetInterface;
+
+impl NetworkInterface for VirtioNetInterface {
+    fn send(&mut self, packet: &[u8]) -> Result<(), &'static str> {
+        crate::drivers::virtio::send_frame(packet)
+    }
+
+    fn receive(&mut self) -> Option<Vec<u8>> {
+        receive_with(crate::drivers::virtio::recv_frame)
+    }
+
+    fn mac_address(&self) -> [u8; 6] {
+        crate::drivers::virtio::get_mac()
+    }
+
+    fn name(&self) -> &'static str {
+        "eth0"
+    }
+}
+
+/// RP1 Gigabit Ethernet MAC (Raspberry Pi 5)
+pub struct Rp1EthernetInterface;
+
+impl NetworkInterface for Rp1EthernetInterface {
+    fn send(&mut self, packet: &[u8]) -> Result<(), &'static str> {
+        crate::drivers::ethernet::ETHERNET.lock().send_frame(packet)
+    }
+
+    fn receive(&mut self) -> Option<Vec<u8>> {
+        receive_with(|buf| crate::drivers::ethernet::ETHERNET.lock().recv_frame(buf))
+    }
+
+    fn mac_address(&self) -> [u8; 6] {
+        crate::drivers::ethernet::ETHERNET.lock().get_mac_address().0
+    }
+
+    fn name(&self) -> &'static str {
+        "eth0"
+    }
+}
+
+/// Adapt a driver's non-blocking `recv_frame(buf)` to `receive()`
+fn receive_with(recv: impl FnOnce(&mut [u8]) -> Result<usize, &'static str>) -> Option<Vec<u8>> {
+    let mut buf = [0u8; MAX_FRAME_SIZE];
+    match recv(&mut buf) {
+        Ok(len) if len >= MIN_FRAME_SIZE => Some(buf[..len].to_vec()),
+        _ => None,
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// REGISTRY
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Index of an interface in the registry
+pub type InterfaceId = usize;
+
+/// All network interfaces known to the kernel
+pub struct InterfaceRegistry {
+    interfaces: Vec<Box<dyn NetworkInterface + Send>>,
+    /// Interface used for non-local traffic
+    default: Option<InterfaceId>,
+    /// Interface used for 127.0.0.0/8, ::1 and our own addresses
+    loopback: Option<InterfaceId>,
+}
+
+impl InterfaceRegistry {
+    pub const fn new() -> Self {
+        Self {
+            interfaces: Vec::new(),
+            default: None,
+            loopback: None,
+        }
+    }
+
+    /// Add an interface; the first non-loopback one becomes the default
+    pub fn register(&mut self, iface: Box<dyn NetworkInterface + Send>) -> InterfaceId {
+        let id = self.interfaces.len();
+        let is_loopback = iface.name() == "lo";
+        crate::kprintln!("[NET] Registered interface {} (id {})", iface.name(), id);
+        self.interfaces.push(iface);
+        if is_loopback {
+            self.loopback.get_or_insert(id);
+        } else {
+            self.default.get_or_insert(id);
+        }
+        id
+    }
+
+    pub fn get(&mut self, id: InterfaceId) -> Option<&mut (dyn NetworkInterface + Send + 'static)> {
+        self.interfaces.get_mut(id).map(|iface| &mut **iface)
+    }
+
+    pub fn default_id(&self) -> Option<InterfaceId> {
+        self.default
+    }
+
+    pub fn loopback_id(&self) -> Option<InterfaceId> {
+        self.loopback
+    }
+
+    pub fn len(&self) -> usize {
+        self.interfaces.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.interfaces.is_empty()
+    }
+}
+
+impl Default for InterfaceRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Global interface registry
+pub static INTERFACES: SpinLock<InterfaceRegistry> = SpinLock::new(InterfaceRegistry::new());
+
+/// Register an interface
+pub fn register(iface: Box<dyn NetworkInterface + Send>) -> InterfaceId {
+    INTERFACES.lock().register(iface)
+}
+
+/// Interface used for non-local traffic
+pub fn default_id() -> Option<InterfaceId> {
+    INTERFACES.lock().default_id()
+}
+
+/// Interface used for 127.0.0.0/8 and our own address
+pub fn loopback_id() -> Option<InterfaceId> {
+    INTERFACES.lock().loopback_id()
+}
+
+/// MAC address of an interface
+pub fn mac_address(id: InterfaceId) -> Option<MacAddress> {
+    INTERFACES.lock().get(id).map(|iface| MacAddress(iface.mac_address()))
+}
+
+/// MAC address of the default interface
+pub fn default_mac() -> Option<MacAddress> {
+    mac_address(default_id()?)
+}
+
+/// Largest IP packet an interface carries
+pub fn mtu(id: InterfaceId) -> Option<usize> {
+    INTERFACES.lock().get(id).map(|iface| iface.mtu())
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// TRANSMIT
+// ═══════════════════════════════════════════════════════════════════════════════
+
+fn check_frame(frame: &[u8]) -> Result<(), &'static str> {
+    if frame.len() < MIN_FRAME_SIZE {
         return Err("Frame too short");
     }
-    if frame.len() > 1518 {
+    if frame.len() > MAX_FRAME_SIZE {
         return Err("Frame too large");
     }
-    
-    // Note: Hardware Ethernet driver integration is scheduled for a future sprint.
-    // Currently using loopback/stub for network stack verification.
-    // For now, stub that succeeds without hardware
-    
     Ok(())
 }
 
-/// Send an ARP reply packet
-pub fn send_arp_reply(reply: &ArpPacket) -> Result<(), &'static str> {
-    let cfg = crate::net::config();
-    
+/// Send an Ethernet frame on a specific interface
+pub fn send_frame_on(id: InterfaceId, frame: &[u8]) -> Result<(), &'static str> {
+    check_frame(frame)?;
+    capture::tap(frame);
+    INTERFACES.lock().get(id).ok_or("No such interface")?.send(frame)
+}
+
+/// Send an Ethernet frame on the default interface
+pub fn send_frame(frame: &[u8]) -> Result<(), &'static str> {
+    check_frame(frame)?;
+    capture::tap(frame);
+    let mut registry = INTERFACES.lock();
+    let id = registry.default_id().ok_or("No network interface")?;
+    registry.get(id).ok_or("No network interface")?.send(frame)
+}
+
+/// Send a frame back to ourselves
+///
+/// Captured here only: the same frame coming back out of `lo` is not
+/// tapped again.
+pub fn send_loopback(frame: &[u8]) -> Result<(), &'static str> {
+    check_frame(frame)?;
+    capture::tap(frame);
+    let mut registry = INTERFACES.lock();
+    let id = registry.loopback_id().ok_or("No loopback interface")?;
+    registry.get(id).ok_or("No loopback interface")?.send(frame)
+}
+
+/// Send an ARP packet on `id`: requests are broadcast, replies go to the target
+pub fn send_arp(id: InterfaceId, packet: &ArpPacket) -> Result<(), &'static str> {
+    let dst = match packet.operation {
+        ArpOperation::Request => MacAddress::BROADCAST,
+        _ => packet.target_hw_addr,
+    };
+
     // Build Ethernet frame for ARP
     let mut frame = [0u8; 42]; // 14 (eth) + 28 (arp)
-    
+
     // Ethernet header
-    frame[0..6].copy_from_slice(&reply.target_hw_addr.0);  // Dst MAC
-    frame[6..12].copy_from_slice(&cfg.mac_addr.0);         // Src MAC
+    frame[0..6].copy_from_slice(&dst.0);                   // Dst MAC
+    frame[6..12].copy_from_slice(&packet.sender_hw_addr.0); // Src MAC
     frame[12..14].copy_from_slice(&0x0806u16.to_be_bytes()); // EtherType: ARP
-    
+
     // ARP payload
-    let arp_bytes = reply.to_bytes();
+    let arp_bytes = packet.to_bytes();
     frame[14..42].copy_from_slice(&arp_bytes[..28]);
-    
-    send_frame(&frame)
+
+    send_frame_on(id, &frame)
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// RECEIVE
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Is this frame addressed to `our_mac` (or to everyone)?
+fn accepts(frame: &[u8], our_mac: [u8; 6]) -> bool {
+    let dst = &frame[0..6];
+    // Group bit covers broadcast and multicast; loopback has no address
+    dst == our_mac || dst[0] & 1 != 0 || our_mac == [0; 6]
+}
+
+/// Hand one frame received on `id` to the protocol layer for its EtherType
+pub fn handle_frame(id: InterfaceId, frame: &[u8]) -> Result<(), &'static str> {
+    if frame.len() < MIN_FRAME_SIZE {
+        return Err("Frame too short");
+    }
+    let payload = &frame[MIN_FRAME_SIZE..];
+    match EtherType::from(u16::from_be_bytes([frame[12], frame[13]])) {
+        EtherType::IPv4 => crate::net::ipv4::handle_packet(payload),
+        EtherType::ARP => crate::net::arp::handle_packet(id, payload),
+        EtherType::IPv6 => crate::net::ipv6::handle_packet(payload),
+        _ => Ok(()), // Other protocols are ignored
+    }
+}
+
+/// RX pump: drain every interface and dispatch the frames
+///
+/// Called from the timer tick. The registry lock is released before a
+/// frame is handled, since handlers transmit replies. Returns the number
+/// of frames processed.
+pub fn poll() -> usize {
+    let count = INTERFACES.lock().len();
+    let mut handled = 0;
+    for id in 0..count {
+        for _ in 0..RX_BUDGET {
+            let received = {
+                let mut registry = INTERFACES.lock();
+                registry.get(id).and_then(|iface| {
+                    let mac = iface.mac_address();
+                    let loopback = iface.name() == "lo";
+                    iface.receive().map(|frame| (frame, mac, loopback))
+                })
+            };
+            let Some((frame, mac, loopback)) = received else { break };
+            if !loopback {
+                capture::tap(&frame);
+            }
+            if accepts(&frame, mac) {
+                // Malformed packets are dropped
+                let _ = handle_frame(id, &frame);
+                handled += 1;
+            }
+        }
+    }
+    handled
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// INITIALIZATION
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Register loopback and whichever NIC came up
+pub fn init() {
+    register(Box::new(LoopbackInterface::new()));
+
+    if crate::drivers::virtio::is_present() {
+        register(Box::new(VirtioNetInterface));
+    } else if crate::drivers::ethernet::is_present() {
+        register(Box::new(Rp1EthernetInterface));
+    } else {
+        crate::kprintln!("[NET] No network hardware, loopback only");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registry_default_and_loopback() {
+        let mut registry = InterfaceRegistry::new();
+        assert_eq!(registry.register(Box::new(LoopbackInterface::new())), 0);
+        assert_eq!(registry.default_id(), None);
+        assert_eq!(registry.loopback_id(), Some(0));
+
+        let frame = [0u8; 60];
+        registry.get(0).unwrap().send(&frame).unwrap();
+        assert_eq!(registry.get(0).unwrap().receive().unwrap().len(), 60);
+        assert!(registry.get(0).unwrap().receive().is_none());
+    }
+
+    #[test]
+    fn test_mac_filter() {
+        let ours = [0x52, 0x54, 0, 0x12, 0x34, 0x56];
+        let mut frame = [0u8; 14];
+        frame[..6].copy_from_slice(&ours);
+        assert!(accepts(&frame, ours));
+        frame[..6].copy_from_slice(&[0xFF; 6]);
+        assert!(accepts(&frame, ours));
+        frame[..6].copy_from_slice(&[0x02, 0, 0, 0, 0, 1]);
+        assert!(!accepts(&frame, ours));
+        assert!(accepts(&frame, [0; 6]));
+    }
 }
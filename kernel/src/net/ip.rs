@@ -13,6 +13,295 @@ impl Ipv4Addr {
     pub fn new(a: u8, b: u8, c: u8, d: u8) -> Self {
         Self([a, b, c, d])
     }
+
+    /// 127.0.0.0/8
+    pub fn is_loopback(&self) -> bool {
+        self.0[0] == 127
+    }
+
+    /// Parse dotted-quad notation ("10.0.2.15")
+    pub fn parse(s: &str) -> Option<Self> {
+        let mut octets = [0u8; 4];
+        let mut parts = s.trim().split('.');
+        for octet in octets.iter_mut() {
+            *octet = parts.next()?.parse().ok()?;
+        }
+        if parts.next().is_some() {
+            return None;
+        }
+        Some(Self(octets))
+    }
+}
+
+impl core::fmt::Display for Ipv4Addr {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let [a, b, c, d] = self.0;
+        write!(f, "{}.{}.{}.{}", a, b, c, d)
+    }
+}
+
+/// IPv6 Address
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Ipv6Addr(pub [u8; 16]);
+
+impl Ipv6Addr {
+    pub const UNSPECIFIED: Self = Self([0; 16]);
+    pub const LOOPBACK: Self = Self([0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1]);
+    /// ff02::1
+    pub const ALL_NODES: Self = Self([0xff, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1]);
+    /// ff02::2
+    pub const ALL_ROUTERS: Self = Self([0xff, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2]);
+
+    /// Build from eight 16-bit groups
+    pub fn new(segments: [u16; 8]) -> Self {
+        let mut bytes = [0u8; 16];
+        for (chunk, seg) in bytes.as_chunks_mut::<2>().0.iter_mut().zip(segments) {
+            *chunk = seg.to_be_bytes();
+        }
+        Self(bytes)
+    }
+
+    pub fn segments(&self) -> [u16; 8] {
+        let mut segments = [0u16; 8];
+        for (seg, chunk) in segments.iter_mut().zip(self.0.as_chunks::<2>().0) {
+            *seg = u16::from_be_bytes(*chunk);
+        }
+        segments
+    }
+
+    pub fn is_unspecified(&self) -> bool {
+        *self == Self::UNSPECIFIED
+    }
+
+    pub fn is_loopback(&self) -> bool {
+        *self == Self::LOOPBACK
+    }
+
+    /// ff00::/8
+    pub fn is_multicast(&self) -> bool {
+        self.0[0] == 0xff
+    }
+
+    /// fe80::/10
+    pub fn is_link_local(&self) -> bool {
+        self.0[0] == 0xfe && self.0[1] & 0xc0 == 0x80
+    }
+
+    /// Solicited-node multicast group (ff02::1:ffXX:XXXX, RFC 4291 §2.7.1)
+    pub fn solicited_node(&self) -> Self {
+        let mut group = [0xff, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0xff, 0, 0, 0];
+        group[13..].copy_from_slice(&self.0[13..]);
+        Self(group)
+    }
+
+    /// IPv4-mapped form (::ffff:a.b.c.d)
+    pub fn from_ipv4_mapped(ip: Ipv4Addr) -> Self {
+        let mut bytes = [0u8; 16];
+        bytes[10] = 0xff;
+        bytes[11] = 0xff;
+        bytes[12..].copy_from_slice(&ip.0);
+        Self(bytes)
+    }
+
+    /// The IPv4 address inside an IPv4-mapped address
+    pub fn to_ipv4_mapped(&self) -> Option<Ipv4Addr> {
+        if self.0[..10] == [0; 10] && self.0[10..12] == [0xff, 0xff] {
+            Some(Ipv4Addr([self.0[12], self.0[13], self.0[14], self.0[15]]))
+        } else {
+            None
+        }
+    }
+
+    /// Same first `prefix_len` bits?
+    pub fn matches_prefix(&self, prefix: &Ipv6Addr, prefix_len: u8) -> bool {
+        let bits = prefix_len.min(128) as usize;
+        let (bytes, rest) = (bits / 8, bits % 8);
+        if self.0[..bytes] != prefix.0[..bytes] {
+            return false;
+        }
+        rest == 0 || (self.0[bytes] ^ prefix.0[bytes]) >> (8 - rest) == 0
+    }
+
+    /// Parse RFC 4291 text form ("fe80::1", "2001:db8::10", "::ffff:10.0.2.15")
+    pub fn parse(s: &str) -> Option<Self> {
+        let s = s.trim();
+        let (head, tail) = match s.find("::") {
+            Some(idx) => (&s[..idx], Some(&s[idx + 2..])),
+            None => (s, None),
+        };
+
+        let mut front = Vec::new();
+        let mut back = Vec::new();
+        parse_groups(head, &mut front)?;
+        if let Some(tail) = tail {
+            parse_groups(tail, &mut back)?;
+        }
+
+        let total = front.len() + back.len();
+        let segments = match tail {
+            None if total == 8 => front,
+            Some(_) if total < 8 => {
+                front.resize(8 - back.len(), 0);
+                front.extend_from_slice(&back);
+                front
+            }
+            _ => return None,
+        };
+        let mut out = [0u16; 8];
+        out.copy_from_slice(&segments);
+        Some(Self::new(out))
+    }
+}
+
+/// Colon-separated hex groups; a trailing dotted quad counts as two
+fn parse_groups(s: &str, out: &mut Vec<u16>) -> Option<()> {
+    if s.is_empty() {
+        return Some(());
+    }
+    let mut parts = s.split(':').peekable();
+    while let Some(part) = parts.next() {
+        if parts.peek().is_none() && part.contains('.') {
+            let v4 = Ipv4Addr::parse(part)?;
+            out.push(u16::from_be_bytes([v4.0[0], v4.0[1]]));
+            out.push(u16::from_be_bytes([v4.0[2], v4.0[3]]));
+        } else if part.is_empty() || part.len() > 4 {
+            return None;
+        } else {
+            out.push(u16::from_str_radix(part, 16).ok()?);
+        }
+    }
+    Some(())
+}
+
+impl core::fmt::Display for Ipv6Addr {
+    /// RFC 5952: lowercase, longest run of two or more zero groups as "::"
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let segments = self.segments();
+        if let Some(v4) = self.to_ipv4_mapped() {
+            return write!(f, "::ffff:{}", v4);
+        }
+
+        let mut best = (0, 0); // (start, len)
+        let mut run = (0, 0);
+        for (i, &seg) in segments.iter().enumerate() {
+            if seg == 0 {
+                if run.1 == 0 {
+                    run.0 = i;
+                }
+                run.1 += 1;
+                if run.1 > best.1 {
+                    best = run;
+                }
+            } else {
+                run.1 = 0;
+            }
+        }
+
+        let write_groups = |f: &mut core::fmt::Formatter<'_>, groups: &[u16]| {
+            for (i, seg) in groups.iter().enumerate() {
+                if i > 0 {
+                    write!(f, ":")?;
+                }
+                write!(f, "{:x}", seg)?;
+            }
+            Ok(())
+        };
+        if best.1 < 2 {
+            return write_groups(f, &segments);
+        }
+        write_groups(f, &segments[..best.0])?;
+        write!(f, "::")?;
+        write_groups(f, &segments[best.0 + best.1..])
+    }
+}
+
+/// An address of either family
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IpAddr {
+    V4(Ipv4Addr),
+    V6(Ipv6Addr),
+}
+
+impl IpAddr {
+    pub fn is_ipv6(&self) -> bool {
+        matches!(self, IpAddr::V6(_))
+    }
+
+    pub fn is_unspecified(&self) -> bool {
+        match self {
+            IpAddr::V4(ip) => *ip == Ipv4Addr::ANY,
+            IpAddr::V6(ip) => ip.is_unspecified(),
+        }
+    }
+
+    pub fn is_loopback(&self) -> bool {
+        match self {
+            IpAddr::V4(ip) => ip.is_loopback(),
+            IpAddr::V6(ip) => ip.is_loopback(),
+        }
+    }
+
+    /// As IPv6, mapping IPv4 into ::ffff:0:0/96
+    pub fn to_ipv6(&self) -> Ipv6Addr {
+        match self {
+            IpAddr::V4(ip) => Ipv6Addr::from_ipv4_mapped(*ip),
+            IpAddr::V6(ip) => *ip,
+        }
+    }
+
+    /// Collapse an IPv4-mapped IPv6 address to plain IPv4
+    pub fn unmapped(&self) -> Self {
+        match self {
+            IpAddr::V6(ip) => ip.to_ipv4_mapped().map_or(*self, IpAddr::V4),
+            _ => *self,
+        }
+    }
+
+    /// Unspecified address of the same family
+    pub fn unspecified(&self) -> Self {
+        match self {
+            IpAddr::V4(_) => IpAddr::V4(Ipv4Addr::ANY),
+            IpAddr::V6(_) => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+        }
+    }
+
+    /// Would a socket bound to `self` take traffic for `dst`?
+    ///
+    /// The IPv6 wildcard is dual-stack and covers IPv4 too.
+    pub fn covers(&self, dst: IpAddr) -> bool {
+        match (self, dst) {
+            _ if *self == dst => true,
+            (IpAddr::V6(ip), _) if ip.is_unspecified() => true,
+            (IpAddr::V4(ip), IpAddr::V4(_)) => *ip == Ipv4Addr::ANY,
+            _ => false,
+        }
+    }
+
+    /// Parse either notation
+    pub fn parse(s: &str) -> Option<Self> {
+        Ipv4Addr::parse(s).map(IpAddr::V4).or_else(|| Ipv6Addr::parse(s).map(IpAddr::V6))
+    }
+}
+
+impl From<Ipv4Addr> for IpAddr {
+    fn from(ip: Ipv4Addr) -> Self {
+        IpAddr::V4(ip)
+    }
+}
+
+impl From<Ipv6Addr> for IpAddr {
+    fn from(ip: Ipv6Addr) -> Self {
+        IpAddr::V6(ip)
+    }
+}
+
+impl core::fmt::Display for IpAddr {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            IpAddr::V4(ip) => core::fmt::Display::fmt(ip, f),
+            IpAddr::V6(ip) => core::fmt::Display::fmt(ip, f),
+        }
+    }
 }
 
 /// IPv4 Protocol
@@ -1,12 +1,41 @@
 //! IPv4 (Internet Protocol version 4)
 //!
-//! Handles IP packet routing and forwarding.
+//! Handles IP packet routing and forwarding. Outgoing datagrams follow
+//! the routing table and are fragmented to the route's MTU; incoming
+//! fragments are held until the whole datagram is in (or time runs out).
 
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicU16, Ordering};
+use crate::kernel::sync::SpinLock;
 use super::{config, checksum};
 use super::ip::Ipv4Addr;
+use super::ethernet::MacAddress;
+use super::interface;
+use super::route;
 use super::arp;
 use super::icmp;
 
+const HEADER_LEN: usize = 20;
+/// Largest payload the 16-bit total length allows
+pub const MAX_PAYLOAD: usize = 65535 - HEADER_LEN;
+
+/// More Fragments flag and offset mask of the flags/fragment field
+const FLAG_MF: u16 = 0x2000;
+const OFFSET_MASK: u16 = 0x1FFF;
+const DEFAULT_TTL: u8 = 64;
+
+/// Time allowed for all fragments of a datagram to arrive
+const REASSEMBLY_TIMEOUT_MS: u64 = 30_000;
+/// Datagrams reassembled at once; the oldest is dropped for a new one
+const MAX_REASSEMBLIES: usize = 8;
+
+/// Identification for the next outgoing datagram
+static NEXT_ID: AtomicU16 = AtomicU16::new(1);
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// RECEIVE
+// ═══════════════════════════════════════════════════════════════════════════════
+
 /// Handle incoming IPv4 packet
 pub fn handle_packet(data: &[u8]) -> Result<(), &'static str> {
     if data.len() < 20 {
@@ -22,18 +51,20 @@ pub fn handle_packet(data: &[u8]) -> Result<(), &'static str> {
         return Err("Not IPv4");
     }
 
-    if data.len() < header_len {
+    if data.len() < header_len || header_len < HEADER_LEN {
         return Err("IP header truncated");
     }
 
     let total_len = u16::from_be_bytes([data[2], data[3]]) as usize;
+    let id = u16::from_be_bytes([data[4], data[5]]);
+    let flags_offset = u16::from_be_bytes([data[6], data[7]]);
     let protocol = data[9];
     let src_ip = Ipv4Addr([data[12], data[13], data[14], data[15]]);
     let dst_ip = Ipv4Addr([data[16], data[17], data[18], data[19]]);
 
-    // Check if packet is for us
+    // Check if packet is for us (anything goes until DHCP assigns an address)
     let our_ip = config().ip_addr;
-    if dst_ip != our_ip && dst_ip != Ipv4Addr::BROADCAST {
+    if our_ip != Ipv4Addr::ANY && dst_ip != our_ip && dst_ip != Ipv4Addr::BROADCAST && !dst_ip.is_loopback() {
         return Ok(());  // Not for us, ignore
     }
 
@@ -56,60 +87,341 @@ pub fn handle_packet(data: &[u8]) -> Result<(), &'static str> {
 
     let payload = &data[header_len..total_len];
 
-    // Dispatch based on protocol
+    // Fragments wait for the rest of their datagram
+    let more = flags_offset & FLAG_MF != 0;
+    let offset = (flags_offset & OFFSET_MASK) as usize * 8;
+    if more || offset != 0 {
+        let key = FragmentKey { src: src_ip, dst: dst_ip, protocol, id };
+        let now = crate::drivers::timer::uptime_ms();
+        let whole = REASSEMBLY.lock().insert(key, offset, more, payload, now);
+        return match whole {
+            Some(datagram) => deliver(protocol, &datagram, src_ip, dst_ip),
+            None => Ok(()),
+        };
+    }
+
+    deliver(protocol, payload, src_ip, dst_ip)
+}
+
+/// Dispatch based on protocol
+fn deliver(protocol: u8, payload: &[u8], src_ip: Ipv4Addr, dst_ip: Ipv4Addr) -> Result<(), &'static str> {
     match protocol {
         1 => icmp::handle_packet(payload, src_ip),  // ICMP
-        17 => super::udp::handle_packet(payload, src_ip),  // UDP
-        6 => super::tcp::handle_packet(payload, src_ip, dst_ip),  // TCP
+        17 => super::udp::handle_packet(payload, src_ip.into()),  // UDP
+        6 => super::tcp::handle_packet(payload, src_ip.into(), dst_ip.into()),  // TCP
         _ => Ok(()),  // Unknown protocol
     }
 }
 
-/// Send an IPv4 packet
-pub fn send_packet(dst_ip: Ipv4Addr, protocol: u8, payload: &[u8]) -> Result<(), &'static str> {
-    if payload.len() > 1480 {  // Max payload (1500 MTU - 20 IP header)
+// ═══════════════════════════════════════════════════════════════════════════════
+// REASSEMBLY (RFC 791 §3.2, RFC 815)
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Fragments belong together when all four fields match
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FragmentKey {
+    pub src: Ipv4Addr,
+    pub dst: Ipv4Addr,
+    pub protocol: u8,
+    pub id: u16,
+}
+
+/// A datagram being put back together
+struct Partial {
+    key: FragmentKey,
+    data: Vec<u8>,
+    /// Received byte ranges, sorted and merged
+    ranges: Vec<(usize, usize)>,
+    /// Known once the last fragment (MF clear) arrives
+    total_len: Option<usize>,
+    deadline_ms: u64,
+}
+
+impl Partial {
+    fn is_complete(&self) -> bool {
+        self.total_len.is_some_and(|total| self.ranges == [(0, total)])
+    }
+
+    fn add_range(&mut self, start: usize, end: usize) {
+        self.ranges.push((start, end));
+        self.ranges.sort_unstable();
+        let mut merged: Vec<(usize, usize)> = Vec::with_capacity(self.ranges.len());
+        for &(s, e) in &self.ranges {
+            match merged.last_mut() {
+                Some(last) if s <= last.1 => last.1 = last.1.max(e),
+                _ => merged.push((s, e)),
+            }
+        }
+        self.ranges = merged;
+    }
+}
+
+/// Buffers for datagrams whose fragments are still arriving
+pub struct Reassembler {
+    partials: Vec<Partial>,
+}
+
+impl Reassembler {
+    pub const fn new() -> Self {
+        Self { partials: Vec::new() }
+    }
+
+    /// Add a fragment; returns the whole payload once every byte is in
+    ///
+    /// `offset` is in bytes. Overlapping data simply overwrites what was
+    /// there; fragments that contradict the known length are dropped.
+    pub fn insert(&mut self, key: FragmentKey, offset: usize, more: bool, data: &[u8], now: u64) -> Option<Vec<u8>> {
+        let end = offset + data.len();
+        // Only the last fragment may have a length that isn't a multiple of 8
+        if end > MAX_PAYLOAD || (more && data.len() % 8 != 0) {
+            return None;
+        }
+
+        let idx = match self.partials.iter().position(|p| p.key == key) {
+            Some(idx) => idx,
+            None => {
+                if self.partials.len() >= MAX_REASSEMBLIES {
+                    let oldest = self.partials.iter().enumerate()
+                        .min_by_key(|(_, p)| p.deadline_ms)
+                        .map(|(i, _)| i)?;
+                    self.partials.swap_remove(oldest);
+                }
+                self.partials.push(Partial {
+                    key,
+                    data: Vec::new(),
+                    ranges: Vec::new(),
+                    total_len: None,
+                    deadline_ms: now + REASSEMBLY_TIMEOUT_MS,
+                });
+                self.partials.len() - 1
+            }
+        };
+
+        let partial = &mut self.partials[idx];
+        let beyond_end = partial.total_len.is_some_and(|total| end > total || (!more && end != total));
+        if beyond_end || (!more && partial.ranges.last().is_some_and(|&(_, e)| e > end)) {
+            return None; // Inconsistent with what we already have
+        }
+        if !more {
+            partial.total_len = Some(end);
+        }
+        if partial.data.len() < end {
+            partial.data.resize(end, 0);
+        }
+        partial.data[offset..end].copy_from_slice(data);
+        partial.add_range(offset, end);
+
+        if !partial.is_complete() {
+            return None;
+        }
+        let mut done = self.partials.swap_remove(idx);
+        done.data.truncate(done.total_len.unwrap_or(0));
+        Some(done.data)
+    }
+
+    /// Drop datagrams whose fragments took too long; returns how many
+    pub fn expire(&mut self, now: u64) -> usize {
+        let before = self.partials.len();
+        self.partials.retain(|p| now < p.deadline_ms);
+        before - self.partials.len()
+    }
+
+    pub fn len(&self) -> usize {
+        self.partials.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.partials.is_empty()
+    }
+}
+
+impl Default for Reassembler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+static REASSEMBLY: SpinLock<Reassembler> = SpinLock::new(Reassembler::new());
+
+/// Discard stale reassembly buffers (called from the timer tick)
+pub fn tick() {
+    let dropped = REASSEMBLY.lock().expire(crate::drivers::timer::uptime_ms());
+    if dropped > 0 {
+        crate::kprintln!("[IPv4] Reassembly timed out, dropped {} datagram(s)", dropped);
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// TRANSMIT
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Split `payload` into IPv4 packets no larger than `mtu`
+///
+/// Every fragment but the last carries a multiple of 8 bytes, as the
+/// offset field counts 8-byte units.
+pub fn build_packets(src_ip: Ipv4Addr, dst_ip: Ipv4Addr, protocol: u8, id: u16, payload: &[u8], mtu: usize)
+    -> Result<Vec<Vec<u8>>, &'static str>
+{
+    if payload.len() > MAX_PAYLOAD {
         return Err("Payload too large");
     }
+    if mtu < route::MIN_MTU {
+        return Err("MTU too small");
+    }
+    let chunk = (mtu - HEADER_LEN) / 8 * 8;
+
+    let mut packets = Vec::new();
+    let mut offset = 0;
+    loop {
+        let end = (offset + chunk).min(payload.len());
+        let more = end < payload.len();
+        let total_len = HEADER_LEN + end - offset;
+
+        let mut packet = Vec::with_capacity(total_len);
+        packet.push(0x45);  // Version 4, IHL 5 (20 bytes)
+        packet.push(0);     // DSCP/ECN
+        packet.extend_from_slice(&(total_len as u16).to_be_bytes());  // Total Length
+        packet.extend_from_slice(&id.to_be_bytes());                  // Identification
+        let flags_offset = (offset / 8) as u16 | if more { FLAG_MF } else { 0 };
+        packet.extend_from_slice(&flags_offset.to_be_bytes());        // Flags/Fragment Offset
+        packet.push(DEFAULT_TTL);
+        packet.push(protocol);
+        packet.extend_from_slice(&[0, 0]);  // Checksum (calculated below)
+        packet.extend_from_slice(&src_ip.0);
+        packet.extend_from_slice(&dst_ip.0);
+        let ip_checksum = checksum(&packet[..HEADER_LEN]);
+        packet[10..12].copy_from_slice(&ip_checksum.to_be_bytes());
+
+        packet.extend_from_slice(&payload[offset..end]);
+        packets.push(packet);
 
+        offset = end;
+        if !more {
+            return Ok(packets);
+        }
+    }
+}
+
+/// Ethernet frames for a datagram (destination MAC filled in later)
+fn frames(src_mac: MacAddress, src_ip: Ipv4Addr, dst_ip: Ipv4Addr, protocol: u8, payload: &[u8], mtu: usize)
+    -> Result<Vec<Vec<u8>>, &'static str>
+{
+    let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    let packets = build_packets(src_ip, dst_ip, protocol, id, payload, mtu)?;
+    Ok(packets.into_iter().map(|packet| {
+        let mut frame = Vec::with_capacity(14 + packet.len());
+        frame.extend_from_slice(&MacAddress::ZERO.0);
+        frame.extend_from_slice(&src_mac.0);
+        frame.extend_from_slice(&0x0800u16.to_be_bytes());  // EtherType: IPv4
+        frame.extend_from_slice(&packet);
+        frame
+    }).collect())
+}
+
+/// Send an IPv4 packet
+pub fn send_packet(dst_ip: Ipv4Addr, protocol: u8, payload: &[u8]) -> Result<(), &'static str> {
     let cfg = config();
+    let src_ip = if dst_ip.is_loopback() { dst_ip } else { cfg.ip_addr };
+
+    // Traffic to ourselves never touches the wire
+    if dst_ip.is_loopback() || dst_ip == cfg.ip_addr {
+        let iface = interface::loopback_id().ok_or("No loopback interface")?;
+        let mtu = interface::mtu(iface).ok_or("No loopback interface")?;
+        for frame in frames(MacAddress::ZERO, src_ip, dst_ip, protocol, payload, mtu)? {
+            interface::send_loopback(&frame)?;
+        }
+        return Ok(());
+    }
 
-    // Resolve destination MAC address
-    let dst_mac = if cfg.is_local(dst_ip) {
-        // Local network - ARP for target
-        arp::resolve(dst_ip).ok_or("ARP resolution failed")?
-    } else {
-        // Remote network - ARP for gateway
-        arp::resolve(cfg.gateway).ok_or("Gateway ARP failed")?
-    };
-
-    // Build IP packet
-    let total_len = 20 + payload.len();
-    let mut packet = [0u8; 1518];  // Max Ethernet frame
-
-    // Ethernet header
-    packet[0..6].copy_from_slice(&dst_mac.0);
-    packet[6..12].copy_from_slice(&cfg.mac_addr.0);
-    packet[12..14].copy_from_slice(&0x0800u16.to_be_bytes());  // EtherType: IPv4
-
-    // IP header
-    packet[14] = 0x45;  // Version 4, IHL 5 (20 bytes)
-    packet[15] = 0;     // DSCP/ECN
-    packet[16..18].copy_from_slice(&(total_len as u16).to_be_bytes());  // Total Length
-    packet[18..20].copy_from_slice(&0u16.to_be_bytes());  // Identification
-    packet[20..22].copy_from_slice(&0u16.to_be_bytes());  // Flags/Fragment Offset
-    packet[22] = 64;    // TTL
-    packet[23] = protocol;
-    packet[24..26].copy_from_slice(&0u16.to_be_bytes());  // Checksum (calculated later)
-    packet[26..30].copy_from_slice(&cfg.ip_addr.0);  // Source IP
-    packet[30..34].copy_from_slice(&dst_ip.0);       // Dest IP
-
-    // Calculate IP checksum
-    let ip_checksum = checksum(&packet[14..34]);
-    packet[24..26].copy_from_slice(&ip_checksum.to_be_bytes());
-
-    // Copy payload
-    packet[34..34 + payload.len()].copy_from_slice(payload);
-
-    // Send Ethernet frame
-    super::interface::send_frame(&packet[..14 + total_len])
+    // Limited broadcast stays on the default link (DHCP before we have routes)
+    if dst_ip == Ipv4Addr::BROADCAST {
+        let iface = interface::default_id().ok_or("No network interface")?;
+        let mtu = interface::mtu(iface).ok_or("No network interface")?;
+        let src_mac = interface::mac_address(iface).ok_or("No network interface")?;
+        for mut frame in frames(src_mac, src_ip, dst_ip, protocol, payload, mtu)? {
+            frame[0..6].copy_from_slice(&MacAddress::BROADCAST.0);
+            interface::send_frame_on(iface, &frame)?;
+        }
+        return Ok(());
+    }
+
+    // Everything else: ARP for the route's next hop (gateway or target)
+    let hop = route::lookup(dst_ip)?;
+    let src_mac = interface::mac_address(hop.iface).ok_or("No such interface")?;
+    for frame in frames(src_mac, src_ip, dst_ip, protocol, payload, hop.mtu)? {
+        arp::send_to(hop.iface, hop.addr, frame)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SRC: Ipv4Addr = Ipv4Addr([10, 0, 2, 15]);
+    const DST: Ipv4Addr = Ipv4Addr([10, 0, 2, 2]);
+
+    fn key(id: u16) -> FragmentKey {
+        FragmentKey { src: DST, dst: SRC, protocol: 17, id }
+    }
+
+    fn flags_offset(packet: &[u8]) -> (bool, usize) {
+        let field = u16::from_be_bytes([packet[6], packet[7]]);
+        (field & FLAG_MF != 0, (field & OFFSET_MASK) as usize * 8)
+    }
+
+    #[test]
+    fn test_fragmentation_fits_mtu() {
+        let payload: Vec<u8> = (0..3000u32).map(|i| i as u8).collect();
+        let packets = build_packets(SRC, DST, 17, 42, &payload, 1500).unwrap();
+        assert_eq!(packets.len(), 3);
+        assert!(packets.iter().all(|p| p.len() <= 1500 && u16::from_be_bytes([p[4], p[5]]) == 42));
+        assert_eq!(flags_offset(&packets[0]), (true, 0));
+        assert_eq!(flags_offset(&packets[1]), (true, 1480));
+        assert_eq!(flags_offset(&packets[2]), (false, 2960));
+        assert_eq!(checksum(&packets[1][..HEADER_LEN]), 0);
+
+        // Unfragmented: one packet, no flags
+        let packets = build_packets(SRC, DST, 17, 43, &payload[..100], 1500).unwrap();
+        assert_eq!(packets.len(), 1);
+        assert_eq!(flags_offset(&packets[0]), (false, 0));
+        assert!(build_packets(SRC, DST, 17, 44, &payload, 60).is_err());
+    }
+
+    #[test]
+    fn test_reassembly_out_of_order() {
+        let payload: Vec<u8> = (0..2000u32).map(|i| (i * 7) as u8).collect();
+        let packets = build_packets(DST, SRC, 17, 9, &payload, 576).unwrap();
+        assert_eq!(packets.len(), 4);
+
+        let mut r = Reassembler::new();
+        let mut result = None;
+        for packet in packets.iter().rev().chain(packets.iter().take(1)) {
+            let (more, offset) = flags_offset(packet);
+            assert!(result.is_none());
+            result = r.insert(key(9), offset, more, &packet[HEADER_LEN..], 0);
+            if result.is_some() {
+                break;
+            }
+        }
+        assert_eq!(result.unwrap(), payload);
+        assert!(r.is_empty());
+    }
+
+    #[test]
+    fn test_reassembly_timeout_and_limits() {
+        let mut r = Reassembler::new();
+        assert!(r.insert(key(1), 0, true, &[0u8; 16], 0).is_none());
+        // Non-final fragments must be a multiple of 8 bytes
+        assert!(r.insert(key(1), 16, true, &[0u8; 5], 0).is_none());
+        assert_eq!(r.expire(REASSEMBLY_TIMEOUT_MS - 1), 0);
+        assert_eq!(r.expire(REASSEMBLY_TIMEOUT_MS), 1);
+
+        // A full table evicts the oldest datagram
+        for id in 0..=MAX_REASSEMBLIES as u16 {
+            r.insert(key(id), 0, true, &[0u8; 8], id as u64);
+        }
+        assert_eq!(r.len(), MAX_REASSEMBLIES);
+        assert!(r.partials.iter().all(|p| p.key.id != 0));
+    }
 }
@@ -0,0 +1,1018 @@
+//! IPv6 (Internet Protocol version 6)
+//!
+//! Stateless autoconfiguration on the default interface: a link-local
+//! address derived from the MAC (EUI-64), checked with Duplicate Address
+//! Detection, then global addresses for the prefixes routers advertise
+//! (RFC 4862). Neighbor Discovery (RFC 4861) takes the place of ARP, and
+//! ICMPv6 answers echo requests.
+//!
+//! Not handled yet: fragmentation (oversized packets are refused and
+//! incoming fragments dropped) and MLD reports, so switches doing MLD
+//! snooping must flood solicited-node multicast.
+
+use alloc::vec::Vec;
+use core::convert::TryInto;
+use crate::kernel::sync::SpinLock;
+use super::ip::{IpAddr, Ipv6Addr};
+use super::ethernet::MacAddress;
+use super::interface::{self, InterfaceId};
+
+const HEADER_LEN: usize = 40;
+/// Every IPv6 link carries packets this large (RFC 8200 §5)
+pub const MIN_MTU: usize = 1280;
+const DEFAULT_HOP_LIMIT: u8 = 64;
+/// NDP messages are only valid with this hop limit (RFC 4861 §6.1)
+const NDP_HOP_LIMIT: u8 = 255;
+
+/// Next Header values
+pub const NEXT_HEADER_TCP: u8 = 6;
+pub const NEXT_HEADER_UDP: u8 = 17;
+pub const NEXT_HEADER_ICMPV6: u8 = 58;
+const NEXT_HEADER_HOP_BY_HOP: u8 = 0;
+const NEXT_HEADER_ROUTING: u8 = 43;
+const NEXT_HEADER_FRAGMENT: u8 = 44;
+const NEXT_HEADER_DEST_OPTS: u8 = 60;
+
+/// ICMPv6 message types
+const ICMP_ECHO_REQUEST: u8 = 128;
+const ICMP_ECHO_REPLY: u8 = 129;
+const ICMP_ROUTER_SOLICIT: u8 = 133;
+const ICMP_ROUTER_ADVERT: u8 = 134;
+const ICMP_NEIGHBOR_SOLICIT: u8 = 135;
+const ICMP_NEIGHBOR_ADVERT: u8 = 136;
+
+/// NDP option types
+const OPT_SOURCE_LL: u8 = 1;
+const OPT_TARGET_LL: u8 = 2;
+const OPT_PREFIX_INFO: u8 = 3;
+const OPT_MTU: u8 = 5;
+
+/// Neighbor Advertisement flags
+const NA_SOLICITED: u8 = 0x40;
+const NA_OVERRIDE: u8 = 0x20;
+/// Prefix Information flags
+const PREFIX_AUTONOMOUS: u8 = 0x40;
+
+/// Protocol constants (RFC 4861 §10)
+const RETRANS_TIMER_MS: u64 = 1000;
+const MAX_MULTICAST_SOLICIT: u8 = 3;
+const MAX_RTR_SOLICITATIONS: u8 = 3;
+const RTR_SOLICITATION_INTERVAL_MS: u64 = 4000;
+/// A prefix's valid lifetime can only be cut below this by a fresh RA
+/// if it is already shorter (RFC 4862 §5.5.3 e)
+const TWO_HOURS_MS: u64 = 2 * 60 * 60 * 1000;
+/// Lifetime value meaning "forever"
+const INFINITE_LIFETIME: u32 = u32::MAX;
+
+/// Neighbor cache size and how long an entry is trusted
+const MAX_NEIGHBORS: usize = 16;
+const NEIGHBOR_TIMEOUT_MS: u64 = 10 * 60 * 1000;
+/// Destinations awaiting resolution, and frames held for each
+const MAX_PENDING: usize = 8;
+const MAX_QUEUED_FRAMES: usize = 16;
+/// Addresses per interface (link-local plus SLAAC)
+const MAX_ADDRESSES: usize = 4;
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// HEADER
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Fixed IPv6 header (RFC 8200 §3)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Ipv6Header {
+    pub payload_len: u16,
+    pub next_header: u8,
+    pub hop_limit: u8,
+    pub src: Ipv6Addr,
+    pub dst: Ipv6Addr,
+}
+
+impl Ipv6Header {
+    pub fn parse(data: &[u8]) -> Result<Self, &'static str> {
+        if data.len() < HEADER_LEN {
+            return Err("Packet too short for IPv6 header");
+        }
+        if data[0] >> 4 != 6 {
+            return Err("Not an IPv6 packet");
+        }
+        Ok(Self {
+            payload_len: u16::from_be_bytes([data[4], data[5]]),
+            next_header: data[6],
+            hop_limit: data[7],
+            src: Ipv6Addr(data[8..24].try_into().unwrap()),
+            dst: Ipv6Addr(data[24..40].try_into().unwrap()),
+        })
+    }
+
+    pub fn to_bytes(&self) -> [u8; HEADER_LEN] {
+        let mut bytes = [0u8; HEADER_LEN];
+        bytes[0] = 0x60; // Version 6, traffic class and flow label zero
+        bytes[4..6].copy_from_slice(&self.payload_len.to_be_bytes());
+        bytes[6] = self.next_header;
+        bytes[7] = self.hop_limit;
+        bytes[8..24].copy_from_slice(&self.src.0);
+        bytes[24..40].copy_from_slice(&self.dst.0);
+        bytes
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// ADDRESSING
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Interface identifier from a MAC (modified EUI-64, RFC 4291 appendix A)
+pub fn interface_id(mac: MacAddress) -> [u8; 8] {
+    let m = mac.0;
+    [m[0] ^ 0x02, m[1], m[2], 0xff, 0xfe, m[3], m[4], m[5]]
+}
+
+/// Address formed from a /64 prefix and our interface identifier
+pub fn slaac_address(prefix: Ipv6Addr, mac: MacAddress) -> Ipv6Addr {
+    let mut addr = prefix.0;
+    addr[8..].copy_from_slice(&interface_id(mac));
+    Ipv6Addr(addr)
+}
+
+/// fe80::/64 address for a MAC
+pub fn link_local(mac: MacAddress) -> Ipv6Addr {
+    slaac_address(Ipv6Addr([0xfe, 0x80, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]), mac)
+}
+
+/// Ethernet group address for a multicast destination (RFC 2464 §7)
+pub fn multicast_mac(group: Ipv6Addr) -> MacAddress {
+    let g = group.0;
+    MacAddress([0x33, 0x33, g[12], g[13], g[14], g[15]])
+}
+
+/// One address assigned to the interface
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AddrInfo {
+    pub addr: Ipv6Addr,
+    pub prefix_len: u8,
+    /// Set while Duplicate Address Detection runs; not usable until then
+    pub dad_deadline_ms: Option<u64>,
+    /// `None` never expires (link-local, infinite lifetime)
+    pub valid_until_ms: Option<u64>,
+}
+
+impl AddrInfo {
+    pub fn is_tentative(&self) -> bool {
+        self.dad_deadline_ms.is_some()
+    }
+}
+
+/// Default router learned from Router Advertisements
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Router {
+    pub addr: Ipv6Addr,
+    pub expires_ms: u64,
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// ICMPv6 MESSAGES
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// ICMPv6 header with a zero checksum (filled in on transmit)
+fn icmp_message(icmp_type: u8, body: &[u8]) -> Vec<u8> {
+    let mut message = Vec::with_capacity(4 + body.len());
+    message.extend_from_slice(&[icmp_type, 0, 0, 0]);
+    message.extend_from_slice(body);
+    message
+}
+
+/// Source/Target Link-Layer Address option
+fn ll_option(opt_type: u8, mac: MacAddress) -> [u8; 8] {
+    let mut opt = [opt_type, 1, 0, 0, 0, 0, 0, 0];
+    opt[2..].copy_from_slice(&mac.0);
+    opt
+}
+
+fn neighbor_solicitation(target: Ipv6Addr, source_ll: Option<MacAddress>) -> Vec<u8> {
+    let mut body = Vec::with_capacity(28);
+    body.extend_from_slice(&[0; 4]); // Reserved
+    body.extend_from_slice(&target.0);
+    if let Some(mac) = source_ll {
+        body.extend_from_slice(&ll_option(OPT_SOURCE_LL, mac));
+    }
+    icmp_message(ICMP_NEIGHBOR_SOLICIT, &body)
+}
+
+fn neighbor_advertisement(target: Ipv6Addr, flags: u8, mac: MacAddress) -> Vec<u8> {
+    let mut body = Vec::with_capacity(28);
+    body.extend_from_slice(&[flags, 0, 0, 0]);
+    body.extend_from_slice(&target.0);
+    body.extend_from_slice(&ll_option(OPT_TARGET_LL, mac));
+    icmp_message(ICMP_NEIGHBOR_ADVERT, &body)
+}
+
+fn router_solicitation(mac: MacAddress) -> Vec<u8> {
+    let mut body = Vec::with_capacity(12);
+    body.extend_from_slice(&[0; 4]); // Reserved
+    body.extend_from_slice(&ll_option(OPT_SOURCE_LL, mac));
+    icmp_message(ICMP_ROUTER_SOLICIT, &body)
+}
+
+/// Walk NDP options (type, whole option including type/length)
+fn options(mut data: &[u8]) -> impl Iterator<Item = (u8, &[u8])> {
+    core::iter::from_fn(move || {
+        if data.len() < 8 {
+            return None;
+        }
+        let len = data[1] as usize * 8;
+        if len == 0 || len > data.len() {
+            return None; // Malformed: stop parsing (RFC 4861 §4.6)
+        }
+        let (opt, rest) = data.split_at(len);
+        data = rest;
+        Some((opt[0], opt))
+    })
+}
+
+/// Link-layer address from a Source/Target LL option
+fn ll_address(opt: &[u8]) -> MacAddress {
+    MacAddress(opt[2..8].try_into().unwrap())
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// NEIGHBOR CACHE
+// ═══════════════════════════════════════════════════════════════════════════════
+
+#[derive(Debug, Clone, Copy)]
+struct Neighbor {
+    ip: Ipv6Addr,
+    mac: MacAddress,
+    learned_ms: u64,
+    last_used_ms: u64,
+}
+
+/// Frames waiting for a neighbor's link-layer address
+struct Pending {
+    ip: Ipv6Addr,
+    frames: Vec<Vec<u8>>,
+    solicitations_sent: u8,
+    next_retry_ms: u64,
+}
+
+/// Neighbor cache plus the frames held until resolution
+struct NeighborTable {
+    entries: Vec<Neighbor>,
+    pending: Vec<Pending>,
+}
+
+/// What to do with a frame after `submit`
+#[derive(Debug, PartialEq, Eq)]
+enum Resolution {
+    /// Destination MAC filled in: send now
+    Send(Vec<u8>),
+    /// Frame held; solicit this neighbor
+    Solicit(Ipv6Addr),
+    /// Frame held behind an outstanding solicitation
+    Queued,
+}
+
+impl NeighborTable {
+    const fn new() -> Self {
+        Self { entries: Vec::new(), pending: Vec::new() }
+    }
+
+    fn lookup(&mut self, ip: Ipv6Addr, now: u64) -> Option<MacAddress> {
+        let idx = self.entries.iter().position(|n| n.ip == ip)?;
+        if now.saturating_sub(self.entries[idx].learned_ms) >= NEIGHBOR_TIMEOUT_MS {
+            self.entries.swap_remove(idx); // Stale: resolve again
+            return None;
+        }
+        self.entries[idx].last_used_ms = now;
+        Some(self.entries[idx].mac)
+    }
+
+    fn submit(&mut self, next_hop: Ipv6Addr, mut frame: Vec<u8>, now: u64) -> Result<Resolution, &'static str> {
+        if let Some(mac) = self.lookup(next_hop, now) {
+            frame[0..6].copy_from_slice(&mac.0);
+            return Ok(Resolution::Send(frame));
+        }
+        if let Some(pending) = self.pending.iter_mut().find(|p| p.ip == next_hop) {
+            if pending.frames.len() >= MAX_QUEUED_FRAMES {
+                pending.frames.remove(0);
+            }
+            pending.frames.push(frame);
+            return Ok(Resolution::Queued);
+        }
+        if self.pending.len() >= MAX_PENDING {
+            return Err("Neighbor queue full");
+        }
+        self.pending.push(Pending {
+            ip: next_hop,
+            frames: alloc::vec![frame],
+            solicitations_sent: 1,
+            next_retry_ms: now + RETRANS_TIMER_MS,
+        });
+        Ok(Resolution::Solicit(next_hop))
+    }
+
+    /// Record a mapping; returns the frames it releases, already addressed
+    fn learn(&mut self, ip: Ipv6Addr, mac: MacAddress, now: u64) -> Vec<Vec<u8>> {
+        if let Some(entry) = self.entries.iter_mut().find(|n| n.ip == ip) {
+            entry.mac = mac;
+            entry.learned_ms = now;
+        } else {
+            if self.entries.len() >= MAX_NEIGHBORS {
+                let lru = self.entries.iter().enumerate()
+                    .min_by_key(|(_, n)| n.last_used_ms)
+                    .map_or(0, |(idx, _)| idx);
+                self.entries.swap_remove(lru);
+            }
+            self.entries.push(Neighbor { ip, mac, learned_ms: now, last_used_ms: now });
+        }
+
+        let Some(idx) = self.pending.iter().position(|p| p.ip == ip) else {
+            return Vec::new();
+        };
+        let mut frames = self.pending.swap_remove(idx).frames;
+        for frame in frames.iter_mut() {
+            frame[0..6].copy_from_slice(&mac.0);
+        }
+        frames
+    }
+
+    /// Neighbors to solicit again; silent ones are dropped with their frames
+    fn tick(&mut self, now: u64) -> Vec<Ipv6Addr> {
+        self.entries.retain(|n| now.saturating_sub(n.learned_ms) < NEIGHBOR_TIMEOUT_MS);
+
+        let mut retry = Vec::new();
+        self.pending.retain_mut(|p| {
+            if now < p.next_retry_ms {
+                return true;
+            }
+            if p.solicitations_sent >= MAX_MULTICAST_SOLICIT {
+                crate::kprintln!("[IPv6] {} unreachable, dropped {} frame(s)", p.ip, p.frames.len());
+                return false;
+            }
+            p.solicitations_sent += 1;
+            p.next_retry_ms = now + RETRANS_TIMER_MS;
+            retry.push(p.ip);
+            true
+        });
+        retry
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// INTERFACE STATE
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// An ICMPv6 message the state machine wants sent (hop limit 255)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NdpMessage {
+    pub src: Ipv6Addr,
+    pub dst: Ipv6Addr,
+    /// ICMPv6 message with a zero checksum
+    pub message: Vec<u8>,
+}
+
+/// Everything the stack knows about the IPv6 interface
+///
+/// Pure state machine: methods take the current time and return the
+/// messages to transmit, so the caller sends them with no lock held.
+pub struct Ipv6State {
+    pub iface: InterfaceId,
+    pub mac: MacAddress,
+    /// Link-local first, then SLAAC addresses
+    addrs: Vec<AddrInfo>,
+    pub router: Option<Router>,
+    /// Link MTU, possibly lowered by a Router Advertisement
+    pub mtu: usize,
+    pub hop_limit: u8,
+    solicitations_sent: u8,
+    next_solicit_ms: Option<u64>,
+    neighbors: NeighborTable,
+}
+
+impl Ipv6State {
+    pub fn new(iface: InterfaceId, mac: MacAddress, mtu: usize) -> Self {
+        Self {
+            iface,
+            mac,
+            addrs: Vec::new(),
+            router: None,
+            mtu,
+            hop_limit: DEFAULT_HOP_LIMIT,
+            solicitations_sent: 0,
+            next_solicit_ms: None,
+            neighbors: NeighborTable::new(),
+        }
+    }
+
+    /// Begin autoconfiguration with the link-local address
+    pub fn start(&mut self, now: u64) -> Vec<NdpMessage> {
+        self.add_tentative(link_local(self.mac), 64, None, now).into_iter().collect()
+    }
+
+    pub fn addresses(&self) -> &[AddrInfo] {
+        &self.addrs
+    }
+
+    /// Is `ip` one of our addresses (tentative ones excluded)?
+    pub fn owns(&self, ip: Ipv6Addr) -> bool {
+        self.addrs.iter().any(|a| a.addr == ip && !a.is_tentative())
+    }
+
+    /// Should a packet for `dst` be accepted?
+    pub fn accepts(&self, dst: Ipv6Addr) -> bool {
+        dst == Ipv6Addr::ALL_NODES
+            || self.addrs.iter().any(|a| a.addr == dst || a.addr.solicited_node() == dst)
+    }
+
+    /// Source address for traffic to `dst` (RFC 6724, simplified)
+    ///
+    /// Link-scope destinations get the link-local address; others prefer a
+    /// global address in the same prefix, then any global one.
+    pub fn source_for(&self, dst: Ipv6Addr) -> Option<Ipv6Addr> {
+        let usable = || self.addrs.iter().filter(|a| !a.is_tentative());
+        let link_scope = dst.is_link_local() || (dst.is_multicast() && dst.0[1] & 0x0f <= 2);
+        let global = || usable().filter(|a| !a.addr.is_link_local());
+        let pick = if link_scope {
+            None
+        } else {
+            global().find(|a| dst.matches_prefix(&a.addr, a.prefix_len)).or_else(|| global().next())
+        };
+        pick.or_else(|| usable().find(|a| a.addr.is_link_local())).map(|a| a.addr)
+    }
+
+    /// Neighbor to deliver `dst` to: itself if on-link, else the router
+    pub fn next_hop(&self, dst: Ipv6Addr) -> Result<Ipv6Addr, &'static str> {
+        let on_link = dst.is_link_local()
+            || self.addrs.iter().any(|a| !a.addr.is_link_local() && dst.matches_prefix(&a.addr, a.prefix_len));
+        if on_link {
+            return Ok(dst);
+        }
+        self.router.map(|r| r.addr).ok_or("No route to host")
+    }
+
+    /// Add an address and probe for duplicates (one NS from ::)
+    fn add_tentative(&mut self, addr: Ipv6Addr, prefix_len: u8, valid_until_ms: Option<u64>, now: u64) -> Option<NdpMessage> {
+        if self.addrs.len() >= MAX_ADDRESSES {
+            return None;
+        }
+        self.addrs.push(AddrInfo {
+            addr,
+            prefix_len,
+            dad_deadline_ms: Some(now + RETRANS_TIMER_MS),
+            valid_until_ms,
+        });
+        Some(NdpMessage {
+            src: Ipv6Addr::UNSPECIFIED,
+            dst: addr.solicited_node(),
+            message: neighbor_solicitation(addr, None),
+        })
+    }
+
+    /// Another node uses (or is claiming) `addr`: give it up
+    fn duplicate(&mut self, addr: Ipv6Addr) {
+        let before = self.addrs.len();
+        self.addrs.retain(|a| !(a.addr == addr && a.is_tentative()));
+        if self.addrs.len() != before {
+            crate::kprintln!("[IPv6] Duplicate address {}, not using it", addr);
+        }
+    }
+
+    /// Neighbor Solicitation for address resolution
+    fn solicitation(&self, target: Ipv6Addr) -> Option<NdpMessage> {
+        Some(NdpMessage {
+            src: self.source_for(target)?,
+            dst: target.solicited_node(),
+            message: neighbor_solicitation(target, Some(self.mac)),
+        })
+    }
+
+    /// Hold `frame` (destination MAC unset) until `next_hop` resolves
+    fn submit(&mut self, next_hop: Ipv6Addr, frame: Vec<u8>, now: u64) -> Result<(Option<Vec<u8>>, Option<NdpMessage>), &'static str> {
+        Ok(match self.neighbors.submit(next_hop, frame, now)? {
+            Resolution::Send(frame) => (Some(frame), None),
+            Resolution::Solicit(target) => (None, self.solicitation(target)),
+            Resolution::Queued => (None, None),
+        })
+    }
+
+    /// Timers: DAD completion, lifetimes, router and neighbor solicitations
+    pub fn on_tick(&mut self, now: u64) -> Vec<NdpMessage> {
+        let mut out = Vec::new();
+
+        for addr in self.addrs.iter_mut() {
+            if addr.dad_deadline_ms.is_some_and(|deadline| now >= deadline) {
+                addr.dad_deadline_ms = None;
+                crate::kprintln!("[IPv6] {}/{} ready", addr.addr, addr.prefix_len);
+                if addr.addr.is_link_local() {
+                    // Link-local usable: ask routers for prefixes right away
+                    self.next_solicit_ms = Some(now);
+                }
+            }
+        }
+        self.addrs.retain(|a| a.valid_until_ms.is_none_or(|until| now < until));
+        if self.router.is_some_and(|r| now >= r.expires_ms) {
+            crate::kprintln!("[IPv6] Default router expired");
+            self.router = None;
+        }
+
+        if self.router.is_none()
+            && self.solicitations_sent < MAX_RTR_SOLICITATIONS
+            && self.next_solicit_ms.is_some_and(|at| now >= at)
+        {
+            if let Some(src) = self.source_for(Ipv6Addr::ALL_ROUTERS) {
+                self.solicitations_sent += 1;
+                self.next_solicit_ms = Some(now + RTR_SOLICITATION_INTERVAL_MS);
+                out.push(NdpMessage {
+                    src,
+                    dst: Ipv6Addr::ALL_ROUTERS,
+                    message: router_solicitation(self.mac),
+                });
+            }
+        }
+
+        for target in self.neighbors.tick(now) {
+            out.extend(self.solicitation(target));
+        }
+        out
+    }
+
+    /// Handle an NDP message; returns replies and frames it released
+    pub fn on_ndp(&mut self, src: Ipv6Addr, hop_limit: u8, message: &[u8], now: u64)
+        -> (Vec<NdpMessage>, Vec<Vec<u8>>)
+    {
+        let mut replies = Vec::new();
+        let mut released = Vec::new();
+        if hop_limit != NDP_HOP_LIMIT || message.len() < 8 || message[1] != 0 {
+            return (replies, released); // Off-link or malformed (RFC 4861 §7.1)
+        }
+
+        match message[0] {
+            ICMP_NEIGHBOR_SOLICIT if message.len() >= 24 => {
+                let target = Ipv6Addr(message[8..24].try_into().unwrap());
+                if src.is_unspecified() {
+                    // Someone else running DAD for this address
+                    if self.addrs.iter().any(|a| a.addr == target && a.is_tentative()) {
+                        self.duplicate(target);
+                    } else if self.owns(target) {
+                        replies.push(NdpMessage {
+                            src: target,
+                            dst: Ipv6Addr::ALL_NODES,
+                            message: neighbor_advertisement(target, NA_OVERRIDE, self.mac),
+                        });
+                    }
+                    return (replies, released);
+                }
+                for (opt_type, opt) in options(&message[24..]) {
+                    if opt_type == OPT_SOURCE_LL {
+                        released.extend(self.neighbors.learn(src, ll_address(opt), now));
+                    }
+                }
+                if self.owns(target) {
+                    replies.push(NdpMessage {
+                        src: target,
+                        dst: src,
+                        message: neighbor_advertisement(target, NA_SOLICITED | NA_OVERRIDE, self.mac),
+                    });
+                }
+            }
+            ICMP_NEIGHBOR_ADVERT if message.len() >= 24 => {
+                let target = Ipv6Addr(message[8..24].try_into().unwrap());
+                if self.addrs.iter().any(|a| a.addr == target) {
+                    self.duplicate(target);
+                    return (replies, released);
+                }
+                for (opt_type, opt) in options(&message[24..]) {
+                    if opt_type == OPT_TARGET_LL {
+                        released.extend(self.neighbors.learn(target, ll_address(opt), now));
+                    }
+                }
+            }
+            ICMP_ROUTER_ADVERT if message.len() >= 16 && src.is_link_local() => {
+                released.extend(self.on_router_advert(src, message, now, &mut replies));
+            }
+            _ => {} // Router Solicitations are for routers
+        }
+        (replies, released)
+    }
+
+    fn on_router_advert(&mut self, src: Ipv6Addr, message: &[u8], now: u64, replies: &mut Vec<NdpMessage>) -> Vec<Vec<u8>> {
+        let mut released = Vec::new();
+        if message[4] != 0 {
+            self.hop_limit = message[4];
+        }
+        let lifetime_s = u16::from_be_bytes([message[6], message[7]]) as u64;
+        if lifetime_s == 0 {
+            if self.router.is_some_and(|r| r.addr == src) {
+                self.router = None;
+            }
+        } else {
+            if self.router.is_none_or(|r| r.addr != src) {
+                crate::kprintln!("[IPv6] Default router {}", src);
+            }
+            self.router = Some(Router { addr: src, expires_ms: now + lifetime_s * 1000 });
+        }
+
+        for (opt_type, opt) in options(&message[16..]) {
+            match opt_type {
+                OPT_SOURCE_LL => released.extend(self.neighbors.learn(src, ll_address(opt), now)),
+                OPT_MTU => {
+                    let mtu = u32::from_be_bytes(opt[4..8].try_into().unwrap()) as usize;
+                    if (MIN_MTU..=self.mtu).contains(&mtu) {
+                        self.mtu = mtu;
+                    }
+                }
+                OPT_PREFIX_INFO if opt.len() >= 32 => {
+                    let prefix_len = opt[2];
+                    let valid = u32::from_be_bytes(opt[4..8].try_into().unwrap());
+                    let prefix = Ipv6Addr(opt[16..32].try_into().unwrap());
+                    // SLAAC needs a /64 that isn't link-local (RFC 4862 §5.5.3)
+                    if opt[3] & PREFIX_AUTONOMOUS == 0 || prefix_len != 64 || prefix.is_link_local() {
+                        continue;
+                    }
+                    let addr = slaac_address(prefix, self.mac);
+                    let valid_until = (valid != INFINITE_LIFETIME).then(|| now + valid as u64 * 1000);
+                    if let Some(existing) = self.addrs.iter_mut().find(|a| a.addr == addr) {
+                        existing.valid_until_ms = refresh_lifetime(existing.valid_until_ms, valid_until, now);
+                    } else if valid != 0 {
+                        replies.extend(self.add_tentative(addr, prefix_len, valid_until, now));
+                    }
+                }
+                _ => {}
+            }
+        }
+        released
+    }
+}
+
+/// New valid lifetime for a known prefix (RFC 4862 §5.5.3 e)
+///
+/// Advertisements may extend a lifetime freely but only shorten it to two
+/// hours, so a forged RA cannot take the address away at once.
+fn refresh_lifetime(current: Option<u64>, advertised: Option<u64>, now: u64) -> Option<u64> {
+    let (Some(current), Some(advertised)) = (current, advertised) else {
+        return advertised;
+    };
+    let remaining = current.saturating_sub(now);
+    if advertised > now + TWO_HOURS_MS || advertised > current {
+        Some(advertised)
+    } else if remaining > TWO_HOURS_MS {
+        Some(now + TWO_HOURS_MS)
+    } else {
+        Some(current)
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// GLOBAL STATE
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Interface state (`None` until `start`, or with no NIC)
+static STATE: SpinLock<Option<Ipv6State>> = SpinLock::new(None);
+
+fn now_ms() -> u64 {
+    crate::drivers::timer::uptime_ms()
+}
+
+/// Start autoconfiguration on the default interface
+pub fn start() {
+    let Some(iface) = interface::default_id() else { return };
+    let (Some(mac), Some(mtu)) = (interface::mac_address(iface), interface::mtu(iface)) else { return };
+    let mut state = Ipv6State::new(iface, mac, mtu);
+    let messages = state.start(now_ms());
+    *STATE.lock() = Some(state);
+    send_ndp(messages);
+}
+
+/// Periodic maintenance (called from the timer tick)
+pub fn tick() {
+    let messages = match STATE.lock().as_mut() {
+        Some(state) => state.on_tick(now_ms()),
+        None => return,
+    };
+    send_ndp(messages);
+}
+
+/// Addresses assigned to the interface
+pub fn addresses() -> Vec<AddrInfo> {
+    STATE.lock().as_ref().map_or_else(Vec::new, |s| s.addresses().to_vec())
+}
+
+/// Current default router
+pub fn router() -> Option<Router> {
+    STATE.lock().as_ref().and_then(|s| s.router)
+}
+
+/// Source address for traffic to `dst`
+pub fn source_address(dst: Ipv6Addr) -> Option<Ipv6Addr> {
+    if dst.is_loopback() {
+        return Some(Ipv6Addr::LOOPBACK);
+    }
+    STATE.lock().as_ref()?.source_for(dst)
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// RECEIVE
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Handle an incoming IPv6 packet
+pub fn handle_packet(data: &[u8]) -> Result<(), &'static str> {
+    let header = Ipv6Header::parse(data)?;
+    let end = HEADER_LEN + header.payload_len as usize;
+    if data.len() < end {
+        return Err("IPv6 packet length mismatch");
+    }
+
+    let for_us = header.dst.is_loopback()
+        || STATE.lock().as_ref().is_some_and(|s| s.accepts(header.dst));
+    if !for_us {
+        return Ok(()); // Not for us, ignore
+    }
+
+    // Step over the extension headers we don't act on
+    let mut next_header = header.next_header;
+    let mut payload = &data[HEADER_LEN..end];
+    loop {
+        match next_header {
+            NEXT_HEADER_HOP_BY_HOP | NEXT_HEADER_ROUTING | NEXT_HEADER_DEST_OPTS => {
+                if payload.len() < 8 {
+                    return Err("IPv6 extension header truncated");
+                }
+                let len = (payload[1] as usize + 1) * 8;
+                if payload.len() < len {
+                    return Err("IPv6 extension header truncated");
+                }
+                next_header = payload[0];
+                payload = &payload[len..];
+            }
+            NEXT_HEADER_FRAGMENT => return Err("IPv6 fragments not supported"),
+            _ => break,
+        }
+    }
+
+    deliver(next_header, payload, &header)
+}
+
+/// Dispatch based on the upper-layer protocol
+fn deliver(next_header: u8, payload: &[u8], header: &Ipv6Header) -> Result<(), &'static str> {
+    let (src, dst) = (IpAddr::V6(header.src), IpAddr::V6(header.dst));
+    match next_header {
+        NEXT_HEADER_ICMPV6 => handle_icmp(payload, header),
+        NEXT_HEADER_UDP => super::udp::handle_packet(payload, src),
+        NEXT_HEADER_TCP => super::tcp::handle_packet(payload, src, dst),
+        _ => Ok(()), // Unknown protocol
+    }
+}
+
+fn handle_icmp(message: &[u8], header: &Ipv6Header) -> Result<(), &'static str> {
+    if message.len() < 4 {
+        return Err("ICMPv6 message too short");
+    }
+    let (src, dst) = (IpAddr::V6(header.src), IpAddr::V6(header.dst));
+    if super::pseudo_checksum(src, dst, NEXT_HEADER_ICMPV6, message) != 0 {
+        return Err("ICMPv6 checksum mismatch");
+    }
+
+    match message[0] {
+        ICMP_ECHO_REQUEST => {
+            // Same identifier, sequence and data back to the sender
+            let mut reply = message.to_vec();
+            reply[0] = ICMP_ECHO_REPLY;
+            reply[2] = 0;
+            reply[3] = 0;
+            let from = if header.dst.is_multicast() {
+                source_address(header.src).ok_or("No IPv6 address")?
+            } else {
+                header.dst
+            };
+            transmit(from, header.src, NEXT_HEADER_ICMPV6, DEFAULT_HOP_LIMIT, reply)
+        }
+        ICMP_ECHO_REPLY => Ok(()), // Nothing sends requests yet
+        ICMP_ROUTER_SOLICIT..=ICMP_NEIGHBOR_ADVERT => {
+            let (iface, (replies, released)) = match STATE.lock().as_mut() {
+                Some(state) => (state.iface, state.on_ndp(header.src, header.hop_limit, message, now_ms())),
+                None => return Ok(()),
+            };
+            for frame in released {
+                let _ = interface::send_frame_on(iface, &frame);
+            }
+            send_ndp(replies);
+            Ok(())
+        }
+        _ => Ok(()),
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// TRANSMIT
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Send an IPv6 packet from the best source address for `dst`
+pub fn send_packet(dst: Ipv6Addr, next_header: u8, payload: &[u8]) -> Result<(), &'static str> {
+    let src = source_address(dst).ok_or("No IPv6 address")?;
+    let hop_limit = STATE.lock().as_ref().map_or(DEFAULT_HOP_LIMIT, |s| s.hop_limit);
+    transmit(src, dst, next_header, hop_limit, payload.to_vec())
+}
+
+/// Send NDP messages (checksummed here, hop limit 255); errors are dropped
+fn send_ndp(messages: Vec<NdpMessage>) {
+    for msg in messages {
+        let _ = transmit(msg.src, msg.dst, NEXT_HEADER_ICMPV6, NDP_HOP_LIMIT, msg.message);
+    }
+}
+
+/// Frame a packet; ICMPv6 checksums are filled in here
+fn transmit(src: Ipv6Addr, dst: Ipv6Addr, next_header: u8, hop_limit: u8, mut payload: Vec<u8>)
+    -> Result<(), &'static str>
+{
+    if next_header == NEXT_HEADER_ICMPV6 {
+        let sum = super::pseudo_checksum(IpAddr::V6(src), IpAddr::V6(dst), NEXT_HEADER_ICMPV6, &payload);
+        payload[2..4].copy_from_slice(&sum.to_be_bytes());
+    }
+    let header = Ipv6Header {
+        payload_len: u16::try_from(payload.len()).map_err(|_| "Payload too large")?,
+        next_header,
+        hop_limit,
+        src,
+        dst,
+    };
+
+    let local = dst.is_loopback() || STATE.lock().as_ref().is_some_and(|s| s.owns(dst));
+    let (iface, src_mac, mtu) = if local {
+        let iface = interface::loopback_id().ok_or("No loopback interface")?;
+        (iface, MacAddress::ZERO, interface::mtu(iface).ok_or("No loopback interface")?)
+    } else {
+        let state = STATE.lock();
+        let state = state.as_ref().ok_or("No IPv6 interface")?;
+        (state.iface, state.mac, state.mtu)
+    };
+    if HEADER_LEN + payload.len() > mtu {
+        return Err("Packet too big"); // No fragmentation yet
+    }
+
+    let mut frame = Vec::with_capacity(14 + HEADER_LEN + payload.len());
+    frame.extend_from_slice(&MacAddress::ZERO.0);
+    frame.extend_from_slice(&src_mac.0);
+    frame.extend_from_slice(&0x86DDu16.to_be_bytes()); // EtherType: IPv6
+    frame.extend_from_slice(&header.to_bytes());
+    frame.extend_from_slice(&payload);
+
+    // Our own addresses never touch the wire
+    if local {
+        return interface::send_loopback(&frame);
+    }
+    if dst.is_multicast() {
+        frame[0..6].copy_from_slice(&multicast_mac(dst).0);
+        return interface::send_frame_on(iface, &frame);
+    }
+
+    // Unicast: Neighbor Discovery for the next hop (router or target)
+    let (ready, solicit) = {
+        let mut state = STATE.lock();
+        let state = state.as_mut().ok_or("No IPv6 interface")?;
+        let hop = state.next_hop(dst)?;
+        state.submit(hop, frame, now_ms())?
+    };
+    send_ndp(solicit.into_iter().collect());
+    match ready {
+        Some(frame) => interface::send_frame_on(iface, &frame),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+    use crate::net::ip::Ipv4Addr;
+    use crate::net::pseudo_checksum;
+
+    const MAC: MacAddress = MacAddress([0x52, 0x54, 0x00, 0x12, 0x34, 0x56]);
+    const ROUTER_MAC: MacAddress = MacAddress([0x52, 0x54, 0x00, 0xaa, 0xbb, 0xcc]);
+
+    fn addr(s: &str) -> Ipv6Addr {
+        Ipv6Addr::parse(s).unwrap()
+    }
+
+    /// Router Advertisement (30 minute router lifetime) for 2001:db8:1::/64
+    fn router_advert(valid_s: u32) -> Vec<u8> {
+        let mut body = vec![64, 0, 0x07, 0x08, 0, 0, 0, 0, 0, 0, 0, 0];
+        body.extend_from_slice(&ll_option(OPT_SOURCE_LL, ROUTER_MAC));
+        let mut prefix = vec![OPT_PREFIX_INFO, 4, 64, 0x80 | PREFIX_AUTONOMOUS];
+        prefix.extend_from_slice(&valid_s.to_be_bytes());
+        prefix.extend_from_slice(&valid_s.to_be_bytes());
+        prefix.extend_from_slice(&[0; 4]);
+        prefix.extend_from_slice(&addr("2001:db8:1::").0);
+        body.extend_from_slice(&prefix);
+        icmp_message(ICMP_ROUTER_ADVERT, &body)
+    }
+
+    #[test]
+    fn test_address_text_and_derivation() {
+        assert_eq!(alloc::format!("{}", addr("2001:DB8:0:0:1:0:0:1")), "2001:db8::1:0:0:1");
+        assert_eq!(alloc::format!("{}", Ipv6Addr::LOOPBACK), "::1");
+        assert_eq!(alloc::format!("{}", Ipv6Addr::UNSPECIFIED), "::");
+        assert_eq!(addr("::ffff:10.0.2.15").to_ipv4_mapped(), Ipv4Addr::parse("10.0.2.15"));
+        assert!(Ipv6Addr::parse("1::2::3").is_none());
+        assert!(Ipv6Addr::parse("1:2:3:4:5:6:7:8:9").is_none());
+
+        let ll = link_local(MAC);
+        assert_eq!(ll, addr("fe80::5054:ff:fe12:3456"));
+        assert!(ll.is_link_local());
+        assert_eq!(ll.solicited_node(), addr("ff02::1:ff12:3456"));
+        assert_eq!(multicast_mac(ll.solicited_node()).0, [0x33, 0x33, 0xff, 0x12, 0x34, 0x56]);
+        assert!(addr("2001:db8:1::5").matches_prefix(&addr("2001:db8:1::"), 64));
+        assert!(!addr("2001:db8:2::5").matches_prefix(&addr("2001:db8:1::"), 64));
+    }
+
+    #[test]
+    fn test_dad_then_router_solicitation() {
+        let mut state = Ipv6State::new(1, MAC, 1500);
+        let probe = state.start(0);
+        assert_eq!(probe.len(), 1);
+        assert_eq!(probe[0].src, Ipv6Addr::UNSPECIFIED);
+        assert_eq!(probe[0].dst, link_local(MAC).solicited_node());
+        assert!(state.source_for(Ipv6Addr::ALL_ROUTERS).is_none());
+
+        // No objection within RetransTimer: address usable, RS goes out
+        let out = state.on_tick(RETRANS_TIMER_MS);
+        assert!(state.owns(link_local(MAC)));
+        assert_eq!(out.len(), 1);
+        assert_eq!((out[0].dst, out[0].message[0]), (Ipv6Addr::ALL_ROUTERS, ICMP_ROUTER_SOLICIT));
+    }
+
+    #[test]
+    fn test_duplicate_address_abandoned() {
+        let mut state = Ipv6State::new(1, MAC, 1500);
+        state.start(0);
+        let na = neighbor_advertisement(link_local(MAC), NA_OVERRIDE, ROUTER_MAC);
+        state.on_ndp(addr("fe80::1"), NDP_HOP_LIMIT, &na, 10);
+        assert!(state.addresses().is_empty());
+    }
+
+    #[test]
+    fn test_slaac_from_router_advert() {
+        let mut state = Ipv6State::new(1, MAC, 1500);
+        state.start(0);
+        state.on_tick(RETRANS_TIMER_MS);
+        let router = addr("fe80::1");
+
+        // Wrong hop limit: forwarded from off-link, ignored
+        state.on_ndp(router, 64, &router_advert(1800), 2000);
+        assert!(state.router.is_none());
+
+        let (probes, _) = state.on_ndp(router, NDP_HOP_LIMIT, &router_advert(1800), 2000);
+        let global = addr("2001:db8:1:0:5054:ff:fe12:3456");
+        assert_eq!(state.router.map(|r| r.addr), Some(router));
+        assert_eq!(probes.len(), 1);
+        assert_eq!(probes[0].dst, global.solicited_node());
+        assert_eq!(state.source_for(addr("2001:db8:9::1")), Some(link_local(MAC)));
+
+        state.on_tick(2000 + RETRANS_TIMER_MS);
+        assert_eq!(state.source_for(addr("2001:db8:9::1")), Some(global));
+        assert_eq!(state.source_for(addr("fe80::99")), Some(link_local(MAC)));
+        assert_eq!(state.next_hop(addr("2001:db8:1::99")), Ok(addr("2001:db8:1::99")));
+        assert_eq!(state.next_hop(addr("2600::1")), Ok(router));
+
+        // The RA's source link-layer option filled the neighbor cache
+        assert_eq!(state.neighbors.lookup(router, 3000), Some(ROUTER_MAC));
+
+        // Lifetime runs out: address goes
+        state.on_tick(2000 + 1800 * 1000);
+        assert!(!state.owns(global));
+    }
+
+    #[test]
+    fn test_neighbor_resolution() {
+        let mut state = Ipv6State::new(1, MAC, 1500);
+        state.start(0);
+        state.on_tick(RETRANS_TIMER_MS);
+        let peer = addr("fe80::2");
+        let peer_mac = MacAddress([0x52, 0x54, 0x00, 0, 0, 2]);
+
+        let (ready, solicit) = state.submit(peer, vec![0u8; 80], 2000).unwrap();
+        assert!(ready.is_none());
+        let solicit = solicit.unwrap();
+        assert_eq!((solicit.src, solicit.dst), (link_local(MAC), peer.solicited_node()));
+        assert_eq!(state.submit(peer, vec![0u8; 80], 2001).unwrap(), (None, None));
+
+        // Solicited advertisement releases both frames
+        let na = neighbor_advertisement(peer, NA_SOLICITED | NA_OVERRIDE, peer_mac);
+        let (_, released) = state.on_ndp(peer, NDP_HOP_LIMIT, &na, 2100);
+        assert_eq!(released.len(), 2);
+        assert!(released.iter().all(|f| f[0..6] == peer_mac.0));
+
+        // We answer solicitations for our own address
+        let ns = neighbor_solicitation(link_local(MAC), Some(peer_mac));
+        let (replies, _) = state.on_ndp(peer, NDP_HOP_LIMIT, &ns, 2200);
+        assert_eq!(replies.len(), 1);
+        assert_eq!((replies[0].dst, replies[0].message[0]), (peer, ICMP_NEIGHBOR_ADVERT));
+    }
+
+    #[test]
+    fn test_icmp_checksum() {
+        let src = IpAddr::V6(addr("fe80::1"));
+        let dst = IpAddr::V6(addr("fe80::2"));
+        let mut msg = icmp_message(ICMP_ECHO_REQUEST, &[0, 1, 0, 7, b'h', b'i']);
+        let sum = pseudo_checksum(src, dst, NEXT_HEADER_ICMPV6, &msg);
+        msg[2..4].copy_from_slice(&sum.to_be_bytes());
+        assert_eq!(pseudo_checksum(src, dst, NEXT_HEADER_ICMPV6, &msg), 0);
+    }
+}
@@ -6,18 +6,24 @@ pub mod interface;
 pub mod ethernet;
 pub mod ip;
 pub mod ipv4;
+pub mod ipv6;
+pub mod route;
 pub mod arp;
 pub mod icmp;
 pub mod udp;
 pub mod tcp;
 pub mod socket;
-pub use socket::SocketFile;
+pub mod dhcp;
+pub mod dns;
+pub mod capture;
+pub use socket::{SocketFile, TcpSocket};
 
 // Re-export key types for convenience
-pub use ip::Ipv4Addr;
+pub use ip::{IpAddr, Ipv4Addr, Ipv6Addr};
 pub use ethernet::MacAddress;
 pub use tcp::{TcpConnection, TcpState, TcpSegment, TCB_TABLE, tcp_tick};
 
+use alloc::vec::Vec;
 use crate::kernel::sync::SpinLock;
 
 // ═══════════════════════════════════════════════════════════════════════════════
@@ -31,8 +37,13 @@ pub struct NetConfig {
     pub netmask: Ipv4Addr,
     pub gateway: Ipv4Addr,
     pub mac_addr: MacAddress,
+    /// Name servers; `Ipv4Addr::ANY` marks an unused slot
+    pub dns_servers: [Ipv4Addr; MAX_DNS_SERVERS],
 }
 
+/// Name servers remembered from DHCP or `config.txt`
+pub const MAX_DNS_SERVERS: usize = 2;
+
 impl NetConfig {
     pub const fn new() -> Self {
         Self {
@@ -40,6 +51,7 @@ impl NetConfig {
             netmask: Ipv4Addr([255, 255, 255, 0]),
             gateway: Ipv4Addr([0, 0, 0, 0]),
             mac_addr: MacAddress([0, 0, 0, 0, 0, 0]),
+            dns_servers: [Ipv4Addr([0, 0, 0, 0]); MAX_DNS_SERVERS],
         }
     }
 }
@@ -71,11 +83,30 @@ pub fn config() -> NetConfig {
     NET_CONFIG.lock().clone()
 }
 
-/// Set network configuration
+/// Set network configuration (and the routes derived from it)
 pub fn set_config(cfg: NetConfig) {
+    route::apply_config(&cfg);
     *NET_CONFIG.lock() = cfg;
 }
 
+/// Register interfaces and start address configuration
+pub fn init() {
+    interface::init();
+    dns::start();
+
+    let mut cfg = config();
+    match interface::default_mac() {
+        Some(mac) => cfg.mac_addr = mac,
+        None => return, // Loopback only: nothing to configure
+    }
+    crate::kprintln!("[NET] mac {:02x?}", cfg.mac_addr.0);
+    set_config(cfg);
+
+    // Addresses arrive asynchronously (or from config.txt on failure)
+    dhcp::start();
+    ipv6::start();
+}
+
 // ═══════════════════════════════════════════════════════════════════════════════
 // CHECKSUM (RFC 1071)
 // ═══════════════════════════════════════════════════════════════════════════════
@@ -107,12 +138,53 @@ pub fn checksum(data: &[u8]) -> u16 {
     !(sum as u16)
 }
 
+/// Checksum over an upper-layer packet and the IP pseudo-header
+///
+/// IPv4 (RFC 793): source, destination, zero, protocol, 16-bit length.
+/// IPv6 (RFC 8200 §8.1): source, destination, 32-bit length, three zero
+/// bytes, next header. Run over a packet with its checksum field zeroed
+/// to compute it, or over a received packet to get 0 when it is intact.
+pub fn pseudo_checksum(src: IpAddr, dst: IpAddr, protocol: u8, data: &[u8]) -> u16 {
+    let mut pseudo = Vec::with_capacity(40 + data.len());
+    match (src, dst) {
+        (IpAddr::V4(src), IpAddr::V4(dst)) => {
+            pseudo.extend_from_slice(&src.0);
+            pseudo.extend_from_slice(&dst.0);
+            pseudo.push(0);
+            pseudo.push(protocol);
+            pseudo.extend_from_slice(&(data.len() as u16).to_be_bytes());
+        }
+        (src, dst) => {
+            pseudo.extend_from_slice(&src.to_ipv6().0);
+            pseudo.extend_from_slice(&dst.to_ipv6().0);
+            pseudo.extend_from_slice(&(data.len() as u32).to_be_bytes());
+            pseudo.extend_from_slice(&[0, 0, 0, protocol]);
+        }
+    }
+    pseudo.extend_from_slice(data);
+    checksum(&pseudo)
+}
+
 // ═══════════════════════════════════════════════════════════════════════════════
-// IPv4 FORWARDING
+// DUAL-STACK TRANSMIT
 // ═══════════════════════════════════════════════════════════════════════════════
 
-/// Send an IPv4 packet (stub until full driver integration)
-/// This is called by tcp.rs for sending TCP segments
-pub fn send_ip_packet(dst_ip: Ipv4Addr, protocol: u8, payload: &[u8]) -> Result<(), &'static str> {
-    ipv4::send_packet(dst_ip, protocol, payload)
+/// Send a transport packet over whichever IP version `dst` uses
+///
+/// Called by tcp.rs and udp.rs; the protocol number doubles as the IPv6
+/// next header.
+pub fn send_ip_packet(dst_ip: IpAddr, protocol: u8, payload: &[u8]) -> Result<(), &'static str> {
+    match dst_ip {
+        IpAddr::V4(dst) => ipv4::send_packet(dst, protocol, payload),
+        IpAddr::V6(dst) => ipv6::send_packet(dst, protocol, payload),
+    }
+}
+
+/// Our address for traffic to `dst` (unspecified if we have none yet)
+pub fn source_address(dst: IpAddr) -> IpAddr {
+    match dst {
+        IpAddr::V4(ip) if ip.is_loopback() => dst,
+        IpAddr::V4(_) => IpAddr::V4(config().ip_addr),
+        IpAddr::V6(ip) => IpAddr::V6(ipv6::source_address(ip).unwrap_or(Ipv6Addr::UNSPECIFIED)),
+    }
 }
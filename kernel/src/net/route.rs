@@ -0,0 +1,261 @@
+//! IPv4 Routing Table
+//!
+//! Longest-prefix match over routes that each name an outgoing interface,
+//! an optional gateway and an optional MTU. The connected subnet and the
+//! default route are derived from `NetConfig` whenever it changes;
+//! routes added with `add` (e.g. for a second NIC) are left alone.
+
+use alloc::vec::Vec;
+use crate::kernel::sync::SpinLock;
+use crate::net::interface::{self, InterfaceId};
+use crate::net::ip::Ipv4Addr;
+use crate::net::NetConfig;
+
+/// Routes the table holds at most
+const MAX_ROUTES: usize = 32;
+/// Smallest MTU every IPv4 link must carry (RFC 791)
+pub const MIN_MTU: usize = 68;
+
+/// One routing table entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Route {
+    pub dest: Ipv4Addr,
+    pub prefix_len: u8,
+    /// `None` for directly attached networks
+    pub gateway: Option<Ipv4Addr>,
+    pub iface: InterfaceId,
+    /// Overrides the interface MTU when smaller
+    pub mtu: Option<usize>,
+    /// Derived from `NetConfig` (replaced on every config change)
+    pub from_config: bool,
+}
+
+impl Route {
+    /// Does `ip` fall inside this route's prefix?
+    pub fn contains(&self, ip: Ipv4Addr) -> bool {
+        network(ip, self.prefix_len) == self.dest
+    }
+}
+
+/// Where to send a packet for a destination
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NextHop {
+    pub iface: InterfaceId,
+    /// Address to resolve with ARP: the gateway, or the destination itself
+    pub addr: Ipv4Addr,
+    pub mtu: usize,
+}
+
+/// Netmask for a prefix length
+fn prefix_mask(prefix_len: u8) -> u32 {
+    match prefix_len {
+        0 => 0,
+        n => u32::MAX << (32 - n.min(32) as u32),
+    }
+}
+
+/// `ip` with the host bits cleared
+fn network(ip: Ipv4Addr, prefix_len: u8) -> Ipv4Addr {
+    Ipv4Addr((u32::from_be_bytes(ip.0) & prefix_mask(prefix_len)).to_be_bytes())
+}
+
+/// Prefix length of a netmask (counts leading ones)
+pub fn prefix_len(netmask: Ipv4Addr) -> u8 {
+    u32::from_be_bytes(netmask.0).leading_ones() as u8
+}
+
+pub struct RoutingTable {
+    routes: Vec<Route>,
+}
+
+impl RoutingTable {
+    pub const fn new() -> Self {
+        Self { routes: Vec::new() }
+    }
+
+    /// Add a route, replacing one for the same prefix
+    pub fn add(&mut self, mut route: Route) -> Result<(), &'static str> {
+        if route.prefix_len > 32 {
+            return Err("Invalid prefix length");
+        }
+        if route.mtu.is_some_and(|mtu| mtu < MIN_MTU) {
+            return Err("MTU too small");
+        }
+        // Keep only the network part of the destination
+        route.dest = network(route.dest, route.prefix_len);
+
+        if let Some(existing) = self.routes.iter_mut()
+            .find(|r| r.dest == route.dest && r.prefix_len == route.prefix_len)
+        {
+            *existing = route;
+            return Ok(());
+        }
+        if self.routes.len() >= MAX_ROUTES {
+            return Err("Routing table full");
+        }
+        self.routes.push(route);
+        Ok(())
+    }
+
+    /// Remove the route for a prefix
+    pub fn remove(&mut self, dest: Ipv4Addr, prefix_len: u8) -> Result<(), &'static str> {
+        let dest = network(dest, prefix_len);
+        let before = self.routes.len();
+        self.routes.retain(|r| !(r.dest == dest && r.prefix_len == prefix_len));
+        if self.routes.len() == before {
+            return Err("No such route");
+        }
+        Ok(())
+    }
+
+    /// Most specific route covering `dst`
+    pub fn lookup(&self, dst: Ipv4Addr) -> Option<&Route> {
+        self.routes.iter()
+            .filter(|r| r.contains(dst))
+            .max_by_key(|r| r.prefix_len)
+    }
+
+    /// Replace the config-derived routes: connected subnet and default gateway
+    pub fn apply_config(&mut self, cfg: &NetConfig, iface: InterfaceId) {
+        self.routes.retain(|r| !r.from_config);
+        if cfg.ip_addr == Ipv4Addr::ANY {
+            return; // No address yet: only broadcast goes out
+        }
+
+        let connected = Route {
+            dest: cfg.ip_addr,
+            prefix_len: prefix_len(cfg.netmask),
+            gateway: None,
+            iface,
+            mtu: None,
+            from_config: true,
+        };
+        let _ = self.add(connected);
+
+        if cfg.gateway != Ipv4Addr::ANY {
+            let _ = self.add(Route {
+                dest: Ipv4Addr::ANY,
+                prefix_len: 0,
+                gateway: Some(cfg.gateway),
+                ..connected
+            });
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Route> {
+        self.routes.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.routes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.routes.is_empty()
+    }
+}
+
+impl Default for RoutingTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Global routing table
+pub static ROUTES: SpinLock<RoutingTable> = SpinLock::new(RoutingTable::new());
+
+/// Add a route
+pub fn add(route: Route) -> Result<(), &'static str> {
+    ROUTES.lock().add(route)
+}
+
+/// Remove the route for a prefix
+pub fn remove(dest: Ipv4Addr, prefix_len: u8) -> Result<(), &'static str> {
+    ROUTES.lock().remove(dest, prefix_len)
+}
+
+/// Re-derive the connected and default routes (called by `set_config`)
+pub fn apply_config(cfg: &NetConfig) {
+    if let Some(iface) = interface::default_id() {
+        ROUTES.lock().apply_config(cfg, iface);
+    }
+}
+
+/// Pick the interface, next hop and MTU for `dst`
+pub fn lookup(dst: Ipv4Addr) -> Result<NextHop, &'static str> {
+    let route = *ROUTES.lock().lookup(dst).ok_or("No route to host")?;
+    let link_mtu = interface::mtu(route.iface).ok_or("No such interface")?;
+    Ok(NextHop {
+        iface: route.iface,
+        addr: route.gateway.unwrap_or(dst),
+        mtu: route.mtu.map_or(link_mtu, |mtu| mtu.min(link_mtu)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cfg(ip: [u8; 4], mask: [u8; 4], gw: [u8; 4]) -> NetConfig {
+        NetConfig {
+            ip_addr: Ipv4Addr(ip),
+            netmask: Ipv4Addr(mask),
+            gateway: Ipv4Addr(gw),
+            ..NetConfig::new()
+        }
+    }
+
+    #[test]
+    fn test_longest_prefix_wins() {
+        let mut table = RoutingTable::new();
+        table.apply_config(&cfg([10, 0, 2, 15], [255, 255, 255, 0], [10, 0, 2, 2]), 1);
+        table.add(Route {
+            dest: Ipv4Addr([192, 168, 7, 99]), // host bits are dropped
+            prefix_len: 24,
+            gateway: None,
+            iface: 2,
+            mtu: Some(1280),
+            from_config: false,
+        }).unwrap();
+
+        let route = table.lookup(Ipv4Addr([10, 0, 2, 40])).unwrap();
+        assert_eq!((route.iface, route.gateway), (1, None));
+        let route = table.lookup(Ipv4Addr([192, 168, 7, 1])).unwrap();
+        assert_eq!((route.iface, route.mtu), (2, Some(1280)));
+        assert_eq!(route.dest, Ipv4Addr([192, 168, 7, 0]));
+        let route = table.lookup(Ipv4Addr([8, 8, 8, 8])).unwrap();
+        assert_eq!((route.prefix_len, route.gateway), (0, Some(Ipv4Addr([10, 0, 2, 2]))));
+    }
+
+    #[test]
+    fn test_config_routes_replaced() {
+        let mut table = RoutingTable::new();
+        table.add(Route {
+            dest: Ipv4Addr([172, 16, 0, 0]),
+            prefix_len: 12,
+            gateway: Some(Ipv4Addr([10, 0, 2, 254])),
+            iface: 1,
+            mtu: None,
+            from_config: false,
+        }).unwrap();
+        table.apply_config(&cfg([10, 0, 2, 15], [255, 255, 255, 0], [10, 0, 2, 2]), 1);
+        assert_eq!(table.len(), 3);
+
+        // Lease lost: config routes go, manual ones stay
+        table.apply_config(&cfg([0; 4], [255, 255, 255, 0], [0; 4]), 1);
+        assert_eq!(table.len(), 1);
+        assert!(table.lookup(Ipv4Addr([8, 8, 8, 8])).is_none());
+        assert!(table.lookup(Ipv4Addr([172, 20, 1, 1])).is_some());
+
+        assert!(table.remove(Ipv4Addr([172, 16, 0, 0]), 12).is_ok());
+        assert!(table.is_empty());
+    }
+
+    #[test]
+    fn test_prefix_helpers() {
+        assert_eq!(prefix_len(Ipv4Addr([255, 255, 255, 0])), 24);
+        assert_eq!(prefix_len(Ipv4Addr([0, 0, 0, 0])), 0);
+        assert_eq!(prefix_mask(32), u32::MAX);
+        assert_eq!(prefix_mask(0), 0);
+    }
+}
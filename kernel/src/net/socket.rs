@@ -1,49 +1,272 @@
 //! Socket Filesystem Interface
-//! 
+//!
 //! Bridges the VFS `FileOps` trait with the Network Stack.
+//!
+//! - `SocketFile`: a UDP socket bound to a local port
+//! - `TcpSocket`: a TCP socket backed by the `TCB_TABLE`
+//!
+//! Both come in `AF_INET` and `AF_INET6` flavours. As on Linux, IPv6
+//! sockets are dual-stack and see IPv4 peers as IPv4-mapped addresses.
 
 use alloc::vec::Vec;
 use alloc::sync::Arc;
 use core::any::Any;
-use crate::fs::vfs::FileStat;
+use core::sync::atomic::{AtomicU16, Ordering};
+use crate::fs::vfs::{FileStat, POLLIN, POLLOUT, POLLHUP};
 use crate::fs::{FileOps, SeekFrom};
-use crate::net::udp;
+use crate::net::ip::{IpAddr, Ipv4Addr, Ipv6Addr};
+use crate::net::tcp::{self, TcpState, TCB_TABLE};
+use crate::net::udp::{self, UdpMessage};
+use crate::kernel::sync::{WaitQueue, EWOULDBLOCK};
 use crate::kprintln;
 
+// ═══════════════════════════════════════════════════════════════════════════════
+// BSD SOCKET CONSTANTS
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Address family: IPv4
+pub const AF_INET: u16 = 2;
+/// Address family: IPv6
+pub const AF_INET6: u16 = 10;
+
+/// Socket type: connection-oriented byte stream (TCP)
+pub const SOCK_STREAM: u64 = 1;
+/// Socket type: datagrams (UDP)
+pub const SOCK_DGRAM: u64 = 2;
+/// Type flag: create the socket in non-blocking mode (same bit as O_NONBLOCK)
+pub const SOCK_NONBLOCK: u64 = crate::fs::vfs::O_NONBLOCK as u64;
+
+/// shutdown() modes
+pub const SHUT_RD: u64 = 0;
+pub const SHUT_WR: u64 = 1;
+pub const SHUT_RDWR: u64 = 2;
+
+/// setsockopt() level and options
+pub use intent_abi::{SOL_SOCKET, SO_KEEPALIVE};
+
+/// First port handed out for implicit binds (IANA dynamic range)
+const EPHEMERAL_PORT_START: u16 = 49152;
+
+static NEXT_EPHEMERAL_PORT: AtomicU16 = AtomicU16::new(EPHEMERAL_PORT_START);
+
+/// Allocate an ephemeral local port
+pub fn ephemeral_port() -> u16 {
+    let port = NEXT_EPHEMERAL_PORT.fetch_add(1, Ordering::Relaxed);
+    if port == u16::MAX {
+        NEXT_EPHEMERAL_PORT.store(EPHEMERAL_PORT_START, Ordering::Relaxed);
+    }
+    port
+}
+
+/// IPv4 socket address (layout of `struct sockaddr_in`)
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct SockAddrIn {
+    pub family: u16,
+    /// Port in network byte order
+    pub port: u16,
+    pub addr: [u8; 4],
+    pub zero: [u8; 8],
+}
+
+impl SockAddrIn {
+    pub fn new(addr: Ipv4Addr, port: u16) -> Self {
+        Self {
+            family: AF_INET,
+            port: port.to_be(),
+            addr: addr.0,
+            zero: [0; 8],
+        }
+    }
+
+    /// Host-order port
+    pub fn port(&self) -> u16 {
+        u16::from_be(self.port)
+    }
+
+    pub fn ip(&self) -> Ipv4Addr {
+        Ipv4Addr(self.addr)
+    }
+}
+
+/// IPv6 socket address (layout of `struct sockaddr_in6`)
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct SockAddrIn6 {
+    pub family: u16,
+    /// Port in network byte order
+    pub port: u16,
+    pub flowinfo: u32,
+    pub addr: [u8; 16],
+    pub scope_id: u32,
+}
+
+impl SockAddrIn6 {
+    pub fn new(addr: Ipv6Addr, port: u16) -> Self {
+        Self {
+            family: AF_INET6,
+            port: port.to_be(),
+            flowinfo: 0,
+            addr: addr.0,
+            scope_id: 0,
+        }
+    }
+
+    /// Host-order port
+    pub fn port(&self) -> u16 {
+        u16::from_be(self.port)
+    }
+
+    pub fn ip(&self) -> Ipv6Addr {
+        Ipv6Addr(self.addr)
+    }
+}
+
+/// A socket address of either family
+#[derive(Debug, Clone, Copy)]
+pub enum SockAddr {
+    V4(SockAddrIn),
+    V6(SockAddrIn6),
+}
+
+impl SockAddr {
+    /// `addr` as a socket of `family` reports it
+    ///
+    /// `AF_INET6` maps IPv4 addresses; `AF_INET` cannot hold IPv6 ones.
+    pub fn new(family: u16, addr: IpAddr, port: u16) -> Option<Self> {
+        match (family, addr) {
+            (AF_INET, IpAddr::V4(ip)) => Some(SockAddr::V4(SockAddrIn::new(ip, port))),
+            (AF_INET6, ip) => Some(SockAddr::V6(SockAddrIn6::new(ip.to_ipv6(), port))),
+            _ => None,
+        }
+    }
+
+    /// Size of the C structure for `family`
+    pub fn size_of(family: u16) -> Option<usize> {
+        match family {
+            AF_INET => Some(core::mem::size_of::<SockAddrIn>()),
+            AF_INET6 => Some(core::mem::size_of::<SockAddrIn6>()),
+            _ => None,
+        }
+    }
+
+    /// The address, with IPv4-mapped IPv6 collapsed to IPv4
+    pub fn ip(&self) -> IpAddr {
+        match self {
+            SockAddr::V4(sa) => IpAddr::V4(sa.ip()),
+            SockAddr::V6(sa) => IpAddr::V6(sa.ip()).unmapped(),
+        }
+    }
+
+    /// Host-order port
+    pub fn port(&self) -> u16 {
+        match self {
+            SockAddr::V4(sa) => sa.port(),
+            SockAddr::V6(sa) => sa.port(),
+        }
+    }
+}
+
+/// Next datagram on `port` that a socket of `family` can report
+///
+/// `AF_INET` sockets skip IPv6 senders, which they have no address for.
+pub fn take_datagram(port: u16, family: u16) -> Option<UdpMessage> {
+    while let Some(msg) = udp::recv_from(port) {
+        if family == AF_INET6 || !msg.src_addr.is_ipv6() {
+            return Some(msg);
+        }
+    }
+    None
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// UDP SOCKET
+// ═══════════════════════════════════════════════════════════════════════════════
+
 /// A File representing a bound UDP socket
 pub struct SocketFile {
+    /// Local port (0 while unbound)
     pub port: u16,
+    /// `AF_INET` or `AF_INET6`
+    pub family: u16,
+    /// Default destination set by connect()
+    pub peer: Option<(IpAddr, u16)>,
+    /// O_NONBLOCK: fail with EWOULDBLOCK instead of sleeping
+    pub nonblocking: bool,
 }
 
 impl SocketFile {
     pub fn new(port: u16) -> Self {
-        Self { port }
+        Self { port, family: AF_INET, peer: None, nonblocking: false }
+    }
+
+    /// Bind to a local port and start queueing datagrams for it
+    pub fn bind(&mut self, port: u16) -> Result<(), &'static str> {
+        if self.port != 0 {
+            return Err("Socket already bound");
+        }
+        udp::register_listener(port)?;
+        self.port = port;
+        Ok(())
+    }
+
+    /// Take the next datagram, sleeping on `udp::RX_WAIT` unless non-blocking
+    ///
+    /// Returns `Ok(None)` only when there is no current agent to block.
+    pub fn recv_msg(&mut self) -> Result<Option<UdpMessage>, &'static str> {
+        if self.port == 0 {
+            return Err("Socket not bound");
+        }
+        let (port, family) = (self.port, self.family);
+        if self.nonblocking {
+            return take_datagram(port, family).map(Some).ok_or(EWOULDBLOCK);
+        }
+        match udp::RX_WAIT.wait_until(|| take_datagram(port, family)) {
+            Ok(msg) => Ok(Some(msg)),
+            Err(EWOULDBLOCK) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Set the default destination for write()/send()
+    pub fn connect(&mut self, addr: IpAddr, port: u16) -> Result<(), &'static str> {
+        if self.port == 0 {
+            self.bind(ephemeral_port())?;
+        }
+        self.peer = Some((addr, port));
+        Ok(())
     }
 }
 
 impl FileOps for SocketFile {
-    fn read(&mut self, _buf: &mut [u8]) -> Result<usize, &'static str> {
-        // Standard read() pulls payload only? 
-        // Or should we return error and force recvfrom?
-        // Ideally `read` should return just data for connected sockets.
-        // For connectionless, it returns data from the first packet in queue.
-        
-        // This is a blocking read in a real OS, but here we are non-blocking or just check queue.
-        // Let's implement non-blocking read for now.
-        
-        if let Some(msg) = udp::recv_from(self.port) {
-            let len = core::cmp::min(_buf.len(), msg.payload.len());
-            _buf[..len].copy_from_slice(&msg.payload[..len]);
-            Ok(len)
-        } else {
-            Ok(0) // No data (EAGAIN in unix)
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, &'static str> {
+        // read() returns just the payload of the next datagram
+        match self.recv_msg()? {
+            Some(msg) => {
+                let len = core::cmp::min(buf.len(), msg.payload.len());
+                buf[..len].copy_from_slice(&msg.payload[..len]);
+                Ok(len)
+            }
+            None => Ok(0), // No agent to block (kernel context)
         }
     }
 
-    fn write(&mut self, _buf: &[u8]) -> Result<usize, &'static str> {
-        // write() on UDP socket requires connect() first, which we don't have.
-        // So this should probably error or default to broadcast?
-        Err("Use sendto instead")
+    fn read_queue(&self) -> Option<&'static WaitQueue> {
+        if self.nonblocking { None } else { Some(&udp::RX_WAIT) }
+    }
+
+    fn try_read(&mut self, buf: &mut [u8]) -> Result<usize, &'static str> {
+        let nonblocking = core::mem::replace(&mut self.nonblocking, true);
+        let res = self.read(buf);
+        self.nonblocking = nonblocking;
+        res
+    }
+
+    fn write(&mut self, buf: &[u8]) -> Result<usize, &'static str> {
+        // write() on UDP socket requires connect() first
+        let (addr, port) = self.peer.ok_or("Use sendto instead")?;
+        udp::send_to(self.port, addr, port, buf)?;
+        Ok(buf.len())
     }
 
     fn seek(&mut self, _pos: SeekFrom) -> Result<u64, &'static str> {
@@ -63,7 +286,19 @@ impl FileOps for SocketFile {
             inode: 0, // TODO
         })
     }
-    
+
+    fn set_nonblocking(&mut self, nonblocking: bool) {
+        self.nonblocking = nonblocking;
+    }
+
+    fn poll_ready(&self) -> u16 {
+        if self.port != 0 && udp::has_packets(self.port) {
+            POLLIN | POLLOUT
+        } else {
+            POLLOUT
+        }
+    }
+
     fn as_any(&mut self) -> &mut dyn Any {
         self
     }
@@ -72,6 +307,410 @@ impl FileOps for SocketFile {
 impl Drop for SocketFile {
     fn drop(&mut self) {
         // Ensure we unregister if dropped without close
-        let _ = udp::unregister_listener(self.port);
+        if self.port != 0 {
+            let _ = udp::unregister_listener(self.port);
+        }
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// TCP SOCKET
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Lifecycle of a TCP socket descriptor
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TcpSocketState {
+    /// Fresh from socket()
+    Unbound,
+    /// Local address assigned by bind()
+    Bound,
+    /// Accepting connections
+    Listening,
+    /// Attached to a connection in the TCB table
+    Connected,
+    /// Connection released
+    Closed,
+}
+
+/// A File representing a TCP socket
+///
+/// The socket only stores the connection 4-tuple; all protocol state lives
+/// in the global `TCB_TABLE`, so the file lock is never held while waiting.
+pub struct TcpSocket {
+    pub state: TcpSocketState,
+    /// `AF_INET` or `AF_INET6`
+    pub family: u16,
+    pub local_addr: IpAddr,
+    pub local_port: u16,
+    pub remote_addr: IpAddr,
+    pub remote_port: u16,
+    /// O_NONBLOCK: fail with EWOULDBLOCK instead of sleeping
+    pub nonblocking: bool,
+    /// SO_KEEPALIVE: probe idle connections (accepted ones inherit it)
+    pub keepalive: bool,
+    read_shutdown: bool,
+    write_shutdown: bool,
+}
+
+impl TcpSocket {
+    pub fn new() -> Self {
+        Self {
+            state: TcpSocketState::Unbound,
+            family: AF_INET,
+            local_addr: IpAddr::V4(Ipv4Addr::ANY),
+            local_port: 0,
+            remote_addr: IpAddr::V4(Ipv4Addr::ANY),
+            remote_port: 0,
+            nonblocking: false,
+            keepalive: false,
+            read_shutdown: false,
+            write_shutdown: false,
+        }
+    }
+
+    /// Wrap a connection already present in the TCB table (from accept)
+    fn from_connection(family: u16, local_addr: IpAddr, local_port: u16, remote_addr: IpAddr, remote_port: u16) -> Self {
+        Self {
+            state: TcpSocketState::Connected,
+            family,
+            local_addr,
+            local_port,
+            remote_addr,
+            remote_port,
+            nonblocking: false,
+            keepalive: false,
+            read_shutdown: false,
+            write_shutdown: false,
+        }
+    }
+
+    /// Assign a local address
+    pub fn bind(&mut self, addr: IpAddr, port: u16) -> Result<(), &'static str> {
+        if self.state != TcpSocketState::Unbound {
+            return Err("Socket already bound");
+        }
+        if TCB_TABLE.lock().find_listener(port).is_some() {
+            return Err("Address in use");
+        }
+        self.local_addr = addr;
+        self.local_port = port;
+        self.state = TcpSocketState::Bound;
+        Ok(())
+    }
+
+    /// Start accepting connections, queueing at most `backlog` of them
+    pub fn listen(&mut self, backlog: usize) -> Result<(), &'static str> {
+        match self.state {
+            TcpSocketState::Unbound => self.bind(self.wildcard(), ephemeral_port())?,
+            TcpSocketState::Bound => {}
+            _ => return Err("Socket cannot listen"),
+        }
+        tcp::listen(self.local_addr, self.local_port, backlog)?;
+        self.state = TcpSocketState::Listening;
+        // Connections accepted from here inherit SO_KEEPALIVE
+        self.set_keepalive(self.keepalive);
+        Ok(())
+    }
+
+    /// Claim an established connection, if one is waiting (non-blocking)
+    pub fn try_accept(&mut self) -> Result<Option<TcpSocket>, &'static str> {
+        if self.state != TcpSocketState::Listening {
+            return Err("Socket not listening");
+        }
+        Ok(TCB_TABLE.lock().accept(self.local_port).map(|(la, lp, ra, rp)| {
+            let mut sock = TcpSocket::from_connection(self.family, la, lp, ra, rp);
+            sock.keepalive = self.keepalive;
+            sock
+        }))
+    }
+
+    /// Send SYN to a peer. The handshake completes asynchronously.
+    pub fn connect(&mut self, addr: IpAddr, port: u16) -> Result<(), &'static str> {
+        match self.state {
+            TcpSocketState::Unbound => self.local_port = ephemeral_port(),
+            TcpSocketState::Bound => {}
+            _ => return Err("Socket already connected"),
+        }
+        // A wildcard (or other-family) bind takes our address toward the peer
+        if self.local_addr.is_unspecified() || self.local_addr.is_ipv6() != addr.is_ipv6() {
+            self.local_addr = crate::net::source_address(addr);
+        }
+        tcp::connect(self.local_addr, self.local_port, addr, port, self.keepalive)?;
+        self.remote_addr = addr;
+        self.remote_port = port;
+        self.state = TcpSocketState::Connected;
+        Ok(())
+    }
+
+    /// Current state of the underlying connection (None once it is gone)
+    pub fn connection_state(&self) -> Option<TcpState> {
+        if self.state != TcpSocketState::Connected {
+            return None;
+        }
+        let mut table = TCB_TABLE.lock();
+        let idx = table.find(self.local_addr, self.local_port, self.remote_addr, self.remote_port)?;
+        table.get_mut(idx).map(|c| c.state)
+    }
+
+    /// Queue data on the connection
+    pub fn send(&mut self, buf: &[u8]) -> Result<usize, &'static str> {
+        if self.state != TcpSocketState::Connected {
+            return Err("Socket not connected");
+        }
+        if self.write_shutdown {
+            return Err("Broken pipe");
+        }
+        let mut table = TCB_TABLE.lock();
+        let idx = table.find(self.local_addr, self.local_port, self.remote_addr, self.remote_port)
+            .ok_or("Connection reset")?;
+        let conn = table.get_mut(idx).ok_or("Connection reset")?;
+        match conn.state {
+            TcpState::Established | TcpState::CloseWait => {}
+            TcpState::SynSent | TcpState::SynReceived => return Err("Connection not established"),
+            _ => return Err("Broken pipe"),
+        }
+        let n = conn.send_data(buf);
+        // Unsent segments stay on the retransmit queue, so a failed
+        // transmission here is retried by tcp_tick.
+        let _ = conn.flush_send_buffer();
+        Ok(n)
+    }
+
+    /// Take received data (non-blocking)
+    ///
+    /// Returns `Ok(None)` when no data is queued yet, `Ok(Some(0))` at end of stream.
+    pub fn recv(&mut self, buf: &mut [u8]) -> Result<Option<usize>, &'static str> {
+        if self.state != TcpSocketState::Connected {
+            return Err("Socket not connected");
+        }
+        if self.read_shutdown {
+            return Ok(Some(0));
+        }
+        let mut table = TCB_TABLE.lock();
+        let idx = match table.find(self.local_addr, self.local_port, self.remote_addr, self.remote_port) {
+            Some(idx) => idx,
+            None => return Ok(Some(0)),
+        };
+        let conn = table.get_mut(idx).ok_or("Connection reset")?;
+
+        if !conn.recv_buffer.is_empty() {
+            let space = conn.recv_space();
+            let mut n = 0;
+            while n < buf.len() {
+                match conn.recv_buffer.pop_front() {
+                    Some(byte) => {
+                        buf[n] = byte;
+                        n += 1;
+                    }
+                    None => break,
+                }
+            }
+            conn.window_drained(space);
+            return Ok(Some(n));
+        }
+
+        match conn.state {
+            TcpState::SynSent | TcpState::SynReceived | TcpState::Established
+            | TcpState::FinWait1 | TcpState::FinWait2 => Ok(None),
+            // Peer sent FIN (or connection is gone): end of stream
+            _ => Ok(Some(0)),
+        }
+    }
+
+    /// Shut down one or both directions
+    pub fn shutdown(&mut self, how: u64) -> Result<(), &'static str> {
+        if how > SHUT_RDWR {
+            return Err("Invalid shutdown mode");
+        }
+        if self.state != TcpSocketState::Connected {
+            return Err("Socket not connected");
+        }
+        if how == SHUT_RD || how == SHUT_RDWR {
+            self.read_shutdown = true;
+        }
+        if (how == SHUT_WR || how == SHUT_RDWR) && !self.write_shutdown {
+            self.write_shutdown = true;
+            let mut table = TCB_TABLE.lock();
+            if let Some(idx) = table.find(self.local_addr, self.local_port, self.remote_addr, self.remote_port) {
+                if let Some(conn) = table.get_mut(idx) {
+                    conn.close()?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Turn keepalive probes on or off (SO_KEEPALIVE)
+    pub fn set_keepalive(&mut self, on: bool) {
+        self.keepalive = on;
+        let mut table = TCB_TABLE.lock();
+        let idx = match self.state {
+            TcpSocketState::Listening => table.find_listener(self.local_port),
+            TcpSocketState::Connected => {
+                table.find(self.local_addr, self.local_port, self.remote_addr, self.remote_port)
+            }
+            _ => None,
+        };
+        if let Some(conn) = idx.and_then(|idx| table.get_mut(idx)) {
+            conn.keepalive = on;
+        }
+    }
+
+    /// Set a socket option
+    pub fn setsockopt(&mut self, level: u64, name: u64, value: u64) -> Result<(), &'static str> {
+        match (level, name) {
+            (SOL_SOCKET, SO_KEEPALIVE) => {
+                self.set_keepalive(value != 0);
+                Ok(())
+            }
+            _ => Err("Unknown socket option"),
+        }
+    }
+
+    /// Address of the connected peer, in this socket's family
+    pub fn peer_addr(&self) -> Option<SockAddr> {
+        SockAddr::new(self.family, self.remote_addr, self.remote_port)
+    }
+
+    /// Wildcard address of this socket's family (IPv6's is dual-stack)
+    fn wildcard(&self) -> IpAddr {
+        if self.family == AF_INET6 {
+            IpAddr::V6(Ipv6Addr::UNSPECIFIED)
+        } else {
+            IpAddr::V4(Ipv4Addr::ANY)
+        }
+    }
+}
+
+impl Default for TcpSocket {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FileOps for TcpSocket {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, &'static str> {
+        if self.nonblocking {
+            return self.recv(buf)?.ok_or(EWOULDBLOCK);
+        }
+        // Sleep until data, FIN or reset; 0 if there is no agent to block
+        match tcp::SOCKET_WAIT.wait_until(|| self.recv(buf).transpose()) {
+            Ok(res) => res,
+            Err(EWOULDBLOCK) => Ok(0),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn read_queue(&self) -> Option<&'static WaitQueue> {
+        if self.nonblocking { None } else { Some(&tcp::SOCKET_WAIT) }
+    }
+
+    fn try_read(&mut self, buf: &mut [u8]) -> Result<usize, &'static str> {
+        self.recv(buf)?.ok_or(EWOULDBLOCK)
+    }
+
+    fn write(&mut self, buf: &[u8]) -> Result<usize, &'static str> {
+        self.send(buf)
+    }
+
+    fn seek(&mut self, _pos: SeekFrom) -> Result<u64, &'static str> {
+        Err("Cannot seek on socket")
+    }
+
+    fn close(&mut self) -> Result<(), &'static str> {
+        self.release();
+        Ok(())
+    }
+
+    fn stat(&self) -> Result<FileStat, &'static str> {
+        Ok(FileStat {
+            size: 0,
+            mode: 0xC000, // S_IFSOCK
+            inode: 0,
+        })
+    }
+
+    fn set_nonblocking(&mut self, nonblocking: bool) {
+        self.nonblocking = nonblocking;
+    }
+
+    fn poll_ready(&self) -> u16 {
+        match self.state {
+            TcpSocketState::Listening => {
+                if TCB_TABLE.lock().has_pending_accept(self.local_port) { POLLIN } else { 0 }
+            }
+            TcpSocketState::Connected => {
+                let mut table = TCB_TABLE.lock();
+                let conn = table.find(self.local_addr, self.local_port, self.remote_addr, self.remote_port)
+                    .and_then(|idx| table.get_mut(idx));
+                let conn = match conn {
+                    Some(conn) => conn,
+                    None => return POLLIN | POLLHUP, // Reset or fully closed
+                };
+                let mut ready = 0;
+                if !conn.recv_buffer.is_empty() || self.read_shutdown {
+                    ready |= POLLIN;
+                }
+                match conn.state {
+                    TcpState::SynSent | TcpState::SynReceived => {}
+                    TcpState::Established => {
+                        if !self.write_shutdown { ready |= POLLOUT; }
+                    }
+                    TcpState::CloseWait => {
+                        // Peer sent FIN: reads hit EOF, we may still write
+                        ready |= POLLIN;
+                        if !self.write_shutdown { ready |= POLLOUT; }
+                    }
+                    TcpState::Closed => ready |= POLLIN | POLLHUP,
+                    _ => ready |= POLLIN,
+                }
+                ready
+            }
+            TcpSocketState::Closed => POLLHUP,
+            TcpSocketState::Unbound | TcpSocketState::Bound => POLLOUT | POLLHUP,
+        }
+    }
+
+    fn as_any(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+impl TcpSocket {
+    /// Detach from the TCB table: stop listening or send FIN
+    ///
+    /// A closing connection stays in the table, orphaned, until the
+    /// close completes and `tcp_tick` frees it.
+    fn release(&mut self) {
+        let mut table = TCB_TABLE.lock();
+        match self.state {
+            TcpSocketState::Listening => table.close_listener(self.local_port),
+            TcpSocketState::Connected => {
+                if let Some(idx) = table.find(self.local_addr, self.local_port, self.remote_addr, self.remote_port) {
+                    let closed = match table.get_mut(idx) {
+                        Some(conn) => {
+                            if !self.write_shutdown {
+                                let _ = conn.close();
+                            }
+                            conn.orphaned = true;
+                            conn.state == TcpState::Closed
+                        }
+                        None => false,
+                    };
+                    if closed {
+                        table.remove(idx);
+                    }
+                }
+            }
+            _ => {}
+        }
+        self.state = TcpSocketState::Closed;
+    }
+}
+
+impl Drop for TcpSocket {
+    fn drop(&mut self) {
+        // Last descriptor gone: behave like close()
+        self.release();
     }
 }
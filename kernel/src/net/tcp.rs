@@ -4,15 +4,18 @@
 //! - Connection tracking (TCB table)
 //! - Retransmission with RTT-based RTO (Jacobson/Karels)
 //! - Congestion control (RFC 5681: Slow Start, Congestion Avoidance, Fast Recovery)
+//! - Options: MSS, window scaling and timestamps (RFC 7323), SACK (RFC 2018)
+//! - Lifecycle: SYN backlog, half-close, TIME-WAIT reaping, keepalive,
+//!   RST/SYN/ACK validation with challenge ACKs (RFC 5961)
 
 use alloc::collections::VecDeque;
 use alloc::vec::Vec;
 use core::convert::TryInto;
 
-use crate::kernel::sync::SpinLock;
+use crate::kernel::sync::{SpinLock, WaitQueue};
 use crate::drivers::timer;
-use crate::net::ip::Ipv4Addr;
-use crate::net::ipv4;
+use crate::net::ip::IpAddr;
+use crate::net::send_ip_packet;
 
 // ═══════════════════════════════════════════════════════════════════════════════
 // CONSTANTS
@@ -21,6 +24,13 @@ use crate::net::ipv4;
 /// Maximum Segment Size (typical for Ethernet)
 const MSS: u32 = 1460;
 
+/// IPv6 headers are 20 bytes longer, so segments shrink to fit the same MTU
+const MSS_V6: u32 = 1440;
+
+/// MSS assumed when the peer's SYN carries none (RFC 9293 §3.7.1)
+const DEFAULT_MSS: u32 = 536;
+const DEFAULT_MSS_V6: u32 = 1220;
+
 /// Initial congestion window (3 * MSS per RFC 5681)
 const INITIAL_CWND: u32 = 4380;
 
@@ -39,14 +49,48 @@ const INITIAL_RTO_US: u64 = 1_000_000;
 /// Maximum connections in TCB table
 const MAX_CONNECTIONS: usize = 64;
 
-/// Maximum segments in retransmit queue per connection
+/// Maximum data segments in flight per connection; sending stops until
+/// an ACK frees a slot
 const MAX_RETRANSMIT_QUEUE: usize = 16;
 
 /// Duplicate ACK threshold for fast retransmit
 const DUP_ACK_THRESHOLD: u8 = 3;
 
-/// Default receive window size
-const DEFAULT_RECV_WINDOW: u32 = 65535;
+/// Retransmissions of a segment before the connection is aborted (R2, RFC 1122 §4.2.3.5)
+const MAX_RETRIES: u8 = 8;
+
+/// Retransmissions of a SYN or SYN-ACK before the handshake is abandoned
+const MAX_SYN_RETRIES: u8 = 5;
+
+/// Upper bound on a listener's backlog (`listen()` clamps to it)
+pub const SOMAXCONN: usize = 128;
+
+/// Maximum Segment Lifetime; TIME-WAIT lasts twice this
+const MSL_US: u64 = 30_000_000;
+
+/// How long an orphaned connection may sit in FIN-WAIT-2 waiting for the peer's FIN
+const FIN_WAIT2_TIMEOUT_US: u64 = 60_000_000;
+
+/// Keepalive: idle time before the first probe, time between probes, and
+/// unanswered probes before the connection is dropped (RFC 1122 §4.2.3.6)
+const KEEPALIVE_IDLE_US: u64 = 7_200_000_000;
+const KEEPALIVE_INTERVAL_US: u64 = 75_000_000;
+const KEEPALIVE_PROBES: u8 = 9;
+
+/// Challenge ACKs sent per second across all connections (RFC 5961 §7)
+const CHALLENGE_ACK_LIMIT: u32 = 100;
+
+/// Default receive window size (needs window scaling beyond 64 KiB)
+const DEFAULT_RECV_WINDOW: u32 = 256 * 1024;
+
+/// Shift we offer for our receive window (256 KiB >> 3 fits in 16 bits)
+const RECV_WINDOW_SCALE: u8 = 3;
+
+/// Largest shift RFC 7323 allows
+const MAX_WINDOW_SCALE: u8 = 14;
+
+/// Out-of-order segments held for reassembly (and reported via SACK)
+const MAX_OUT_OF_ORDER: usize = 32;
 
 // ═══════════════════════════════════════════════════════════════════════════════
 // TCP FLAGS
@@ -149,6 +193,9 @@ impl TcpSegment {
         let data_offset = data_offset_res >> 4;
         let header_len = (data_offset as usize) * 4;
         
+        if header_len < 20 {
+            return Err("TCP data offset below minimum header");
+        }
         if data.len() < header_len {
             return Err("Packet shorter than TCP header length");
         }
@@ -205,7 +252,7 @@ impl TcpSegment {
     }
     
     /// Serialize segment with computed checksum
-    pub fn to_bytes_with_checksum(&self, src_ip: Ipv4Addr, dst_ip: Ipv4Addr) -> Vec<u8> {
+    pub fn to_bytes_with_checksum(&self, src_ip: IpAddr, dst_ip: IpAddr) -> Vec<u8> {
         // First serialize with zero checksum
         let mut bytes = self.to_bytes();
         
@@ -220,53 +267,143 @@ impl TcpSegment {
     }
 }
 
+// ═══════════════════════════════════════════════════════════════════════════════
+// TCP OPTIONS (RFC 9293 §3.2, RFC 7323, RFC 2018)
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Option kinds
+const OPT_END: u8 = 0;
+const OPT_NOP: u8 = 1;
+const OPT_MSS: u8 = 2;
+const OPT_WINDOW_SCALE: u8 = 3;
+const OPT_SACK_PERMITTED: u8 = 4;
+const OPT_SACK: u8 = 5;
+const OPT_TIMESTAMP: u8 = 8;
+
+/// Bytes the timestamp option takes in every segment once negotiated
+const TIMESTAMP_OVERHEAD: usize = 12;
+
+/// Options of one segment
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TcpOptions {
+    /// Largest segment the sender will accept (SYN only)
+    pub mss: Option<u16>,
+    /// Shift applied to the sender's window field (SYN only)
+    pub window_scale: Option<u8>,
+    /// Sender understands SACK blocks (SYN only)
+    pub sack_permitted: bool,
+    /// Received ranges above the cumulative ACK, `[start, end)`
+    pub sack_blocks: Vec<(u32, u32)>,
+    /// (TSval, TSecr)
+    pub timestamp: Option<(u32, u32)>,
+}
+
+impl TcpOptions {
+    /// Parse the options area of a header; malformed trailing options are ignored
+    pub fn parse(data: &[u8]) -> Self {
+        let mut opts = Self::default();
+        let mut i = 0;
+        while i < data.len() {
+            match data[i] {
+                OPT_END => break,
+                OPT_NOP => {
+                    i += 1;
+                    continue;
+                }
+                _ => {}
+            }
+            let Some(&len) = data.get(i + 1) else { break };
+            let len = len as usize;
+            if len < 2 || i + len > data.len() {
+                break;
+            }
+            let body = &data[i + 2..i + len];
+            match (data[i], body.len()) {
+                (OPT_MSS, 2) => opts.mss = Some(u16::from_be_bytes([body[0], body[1]])),
+                (OPT_WINDOW_SCALE, 1) => opts.window_scale = Some(body[0].min(MAX_WINDOW_SCALE)),
+                (OPT_SACK_PERMITTED, 0) => opts.sack_permitted = true,
+                (OPT_SACK, n) if n % 8 == 0 => {
+                    opts.sack_blocks = body.as_chunks::<8>().0.iter()
+                        .map(|b| (u32::from_be_bytes(b[0..4].try_into().unwrap()),
+                                  u32::from_be_bytes(b[4..8].try_into().unwrap())))
+                        .collect();
+                }
+                (OPT_TIMESTAMP, 8) => {
+                    opts.timestamp = Some((u32::from_be_bytes(body[0..4].try_into().unwrap()),
+                                           u32::from_be_bytes(body[4..8].try_into().unwrap())));
+                }
+                _ => {}  // Unknown option: skip
+            }
+            i += len;
+        }
+        opts
+    }
+
+    /// Serialize, NOP-padded so the header stays a multiple of 4 bytes
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        if let Some(mss) = self.mss {
+            bytes.extend_from_slice(&[OPT_MSS, 4]);
+            bytes.extend_from_slice(&mss.to_be_bytes());
+        }
+        // SACK-permitted shares its word with the timestamp when both are sent
+        match (self.sack_permitted, self.timestamp.is_some()) {
+            (true, true) => bytes.extend_from_slice(&[OPT_SACK_PERMITTED, 2]),
+            (true, false) => bytes.extend_from_slice(&[OPT_NOP, OPT_NOP, OPT_SACK_PERMITTED, 2]),
+            (false, true) => bytes.extend_from_slice(&[OPT_NOP, OPT_NOP]),
+            (false, false) => {}
+        }
+        if let Some((val, ecr)) = self.timestamp {
+            bytes.extend_from_slice(&[OPT_TIMESTAMP, 10]);
+            bytes.extend_from_slice(&val.to_be_bytes());
+            bytes.extend_from_slice(&ecr.to_be_bytes());
+        }
+        if let Some(shift) = self.window_scale {
+            bytes.extend_from_slice(&[OPT_NOP, OPT_WINDOW_SCALE, 3, shift]);
+        }
+        if !self.sack_blocks.is_empty() {
+            bytes.extend_from_slice(&[OPT_NOP, OPT_NOP, OPT_SACK, 2 + 8 * self.sack_blocks.len() as u8]);
+            for &(start, end) in &self.sack_blocks {
+                bytes.extend_from_slice(&start.to_be_bytes());
+                bytes.extend_from_slice(&end.to_be_bytes());
+            }
+        }
+        while bytes.len() % 4 != 0 {
+            bytes.push(OPT_NOP);
+        }
+        bytes
+    }
+}
+
+/// Timestamp clock: milliseconds, wrapping (RFC 7323 §5.4)
+fn timestamp_now() -> u32 {
+    timer::uptime_ms() as u32
+}
+
 // ═══════════════════════════════════════════════════════════════════════════════
 // TCP CHECKSUM (RFC 793)
 // ═══════════════════════════════════════════════════════════════════════════════
 
-/// Compute TCP checksum with pseudo-header (RFC 793)
+/// Compute TCP checksum with pseudo-header (RFC 793, RFC 8200 §8.1)
 ///
 /// The TCP checksum is computed over:
-/// - 12-byte pseudo-header: src IP (4), dst IP (4), zero (1), protocol (1), TCP length (2)
+/// - the IPv4 or IPv6 pseudo-header: addresses, protocol, TCP length
 /// - TCP header (with checksum field set to 0)
 /// - TCP payload
-pub fn tcp_checksum(src_ip: Ipv4Addr, dst_ip: Ipv4Addr, tcp_segment: &[u8]) -> u16 {
-    let tcp_len = tcp_segment.len();
-    
-    // Build pseudo-header (12 bytes)
-    let mut pseudo = Vec::with_capacity(12 + tcp_len);
-    pseudo.extend_from_slice(&src_ip.0);           // Source IP (4 bytes)
-    pseudo.extend_from_slice(&dst_ip.0);           // Destination IP (4 bytes)
-    pseudo.push(0);                                 // Reserved (1 byte)
-    pseudo.push(6);                                 // Protocol = TCP (1 byte)
-    pseudo.extend_from_slice(&(tcp_len as u16).to_be_bytes()); // TCP Length (2 bytes)
-    
+pub fn tcp_checksum(src_ip: IpAddr, dst_ip: IpAddr, tcp_segment: &[u8]) -> u16 {
     // Copy TCP segment (with checksum field zeroed)
     let mut tcp_copy = tcp_segment.to_vec();
     if tcp_copy.len() >= 18 {
         tcp_copy[16] = 0;  // Zero checksum field
         tcp_copy[17] = 0;
     }
-    pseudo.extend_from_slice(&tcp_copy);
-    
-    // Use RFC 1071 checksum
-    crate::net::checksum(&pseudo)
+    crate::net::pseudo_checksum(src_ip, dst_ip, 6, &tcp_copy)
 }
 
 /// Verify TCP checksum
-pub fn verify_tcp_checksum(src_ip: Ipv4Addr, dst_ip: Ipv4Addr, tcp_segment: &[u8]) -> bool {
+pub fn verify_tcp_checksum(src_ip: IpAddr, dst_ip: IpAddr, tcp_segment: &[u8]) -> bool {
     // Computing checksum over segment with existing checksum should yield 0 or 0xFFFF
-    let tcp_len = tcp_segment.len();
-    
-    let mut pseudo = Vec::with_capacity(12 + tcp_len);
-    pseudo.extend_from_slice(&src_ip.0);
-    pseudo.extend_from_slice(&dst_ip.0);
-    pseudo.push(0);
-    pseudo.push(6);
-    pseudo.extend_from_slice(&(tcp_len as u16).to_be_bytes());
-    pseudo.extend_from_slice(tcp_segment);
-    
-    let result = crate::net::checksum(&pseudo);
+    let result = crate::net::pseudo_checksum(src_ip, dst_ip, 6, tcp_segment);
     result == 0 || result == 0xFFFF
 }
 
@@ -275,19 +412,37 @@ pub fn verify_tcp_checksum(src_ip: Ipv4Addr, dst_ip: Ipv4Addr, tcp_segment: &[u8
 // ═══════════════════════════════════════════════════════════════════════════════
 
 /// Entry in the retransmission queue
+///
+/// Only the payload is kept: the header is rebuilt on retransmission so it
+/// carries the current ACK, window and timestamp.
 #[derive(Debug, Clone)]
 struct RetransmitEntry {
     /// Starting sequence number of this segment
     seq_num: u32,
-    /// Segment data (including TCP header)
-    data: Vec<u8>,
+    /// Segment payload
+    payload: Vec<u8>,
+    /// SYN and/or FIN, which occupy a sequence number each
+    control: u16,
     /// Time when segment was sent (microseconds since boot)
     send_time: u64,
     /// Number of times this segment has been retransmitted
     retransmit_count: u8,
+    /// The peer reported holding this segment in a SACK block
+    sacked: bool,
+}
+
+impl RetransmitEntry {
+    /// Sequence number just past this segment
+    fn end(&self) -> u32 {
+        let control = (self.control & (TcpFlags::SYN | TcpFlags::FIN)).count_ones();
+        self.seq_num.wrapping_add(self.payload.len() as u32 + control)
+    }
 }
 
 /// Queue of unacknowledged segments awaiting retransmission
+///
+/// Doubles as the SACK scoreboard (RFC 6675): segments the peer reported
+/// via SACK are skipped when filling holes.
 #[derive(Debug, Clone)]
 struct RetransmitQueue {
     entries: VecDeque<RetransmitEntry>,
@@ -301,27 +456,56 @@ impl RetransmitQueue {
     }
     
     /// Add a segment to the retransmit queue
-    fn push(&mut self, seq_num: u32, data: Vec<u8>, send_time: u64) {
-        if self.entries.len() >= MAX_RETRANSMIT_QUEUE {
-            // Drop oldest if queue is full
-            self.entries.pop_front();
-        }
+    fn push(&mut self, seq_num: u32, payload: Vec<u8>, send_time: u64) {
+        self.push_entry(seq_num, payload, 0, send_time);
+    }
+    
+    /// Add a SYN or FIN to the retransmit queue
+    fn push_control(&mut self, seq_num: u32, control: u16, send_time: u64) {
+        self.push_entry(seq_num, Vec::new(), control, send_time);
+    }
+    
+    fn push_entry(&mut self, seq_num: u32, payload: Vec<u8>, control: u16, send_time: u64) {
         self.entries.push_back(RetransmitEntry {
             seq_num,
-            data,
+            payload,
+            control,
             send_time,
             retransmit_count: 0,
+            sacked: false,
         });
     }
     
-    /// Remove all segments that have been acknowledged (seq < ack_num)
+    /// Remove all segments that have been acknowledged (end <= ack_num)
     fn ack_up_to(&mut self, ack_num: u32) {
         self.entries.retain(|entry| {
             // Keep entries whose data extends beyond the ack
-            seq_after(entry.seq_num + entry.data.len() as u32, ack_num)
+            seq_after(entry.end(), ack_num)
         });
     }
     
+    /// Mark segments lying entirely inside a SACK block
+    fn sack(&mut self, start: u32, end: u32) {
+        for entry in &mut self.entries {
+            if !seq_after(start, entry.seq_num) && !seq_after(entry.end(), end) {
+                entry.sacked = true;
+            }
+        }
+    }
+    
+    /// Forget SACK information (after a timeout the peer may have discarded it)
+    fn clear_sacks(&mut self) {
+        for entry in &mut self.entries {
+            entry.sacked = false;
+        }
+    }
+    
+    /// First hole at or after `from` with SACKed data above it
+    fn next_hole(&self, from: u32) -> Option<usize> {
+        let idx = self.entries.iter().position(|e| !e.sacked && !seq_after(from, e.seq_num))?;
+        self.entries.iter().skip(idx + 1).any(|e| e.sacked).then_some(idx)
+    }
+    
     /// Get the first unacked segment for potential retransmission
     fn front(&self) -> Option<&RetransmitEntry> {
         self.entries.front()
@@ -332,9 +516,17 @@ impl RetransmitQueue {
         self.entries.is_empty()
     }
     
-    /// Mark the front entry as retransmitted
-    fn mark_retransmitted(&mut self, now: u64) {
-        if let Some(entry) = self.entries.front_mut() {
+    /// No room for another data segment
+    ///
+    /// Entries are never dropped unacknowledged, so the sender must wait.
+    /// A SYN or FIN may still be queued past the limit.
+    fn is_full(&self) -> bool {
+        self.entries.len() >= MAX_RETRANSMIT_QUEUE
+    }
+    
+    /// Mark an entry as retransmitted
+    fn mark_retransmitted(&mut self, idx: usize, now: u64) {
+        if let Some(entry) = self.entries.get_mut(idx) {
             entry.send_time = now;
             entry.retransmit_count += 1;
         }
@@ -351,15 +543,23 @@ pub struct TcpConnection {
     // ─────────────────────────────────────────────────────────────────────────
     // Connection Identity
     // ─────────────────────────────────────────────────────────────────────────
-    pub local_addr: Ipv4Addr,
+    pub local_addr: IpAddr,
     pub local_port: u16,
-    pub remote_addr: Ipv4Addr,
+    pub remote_addr: IpAddr,
     pub remote_port: u16,
     
     // ─────────────────────────────────────────────────────────────────────────
     // State Machine
     // ─────────────────────────────────────────────────────────────────────────
     pub state: TcpState,
+    /// When `state` was entered (microseconds since boot; drives TIME-WAIT)
+    state_since: u64,
+    /// Set once a socket has claimed this connection via accept()
+    pub accepted: bool,
+    /// Set once the owning socket is closed; nobody will read from it again
+    pub orphaned: bool,
+    /// Listener only: connections allowed to wait for accept()
+    pub backlog: usize,
     
     // ─────────────────────────────────────────────────────────────────────────
     // Sequence Numbers (RFC 793)
@@ -370,6 +570,8 @@ pub struct TcpConnection {
     pub send_next: u32,
     /// SND.WND - Send Window: peer's advertised receive window
     pub send_window: u32,
+    /// Largest window the peer has advertised (bounds acceptable ACKs, RFC 5961 §5)
+    max_send_window: u32,
     /// RCV.NXT - Receive Next: next expected sequence number
     pub recv_next: u32,
     /// RCV.WND - Receive Window: our advertised receive window
@@ -378,6 +580,8 @@ pub struct TcpConnection {
     pub iss: u32,
     /// Initial Receive Sequence (from peer)
     pub irs: u32,
+    /// Sequence number of our FIN, once it has been sent
+    fin_seq: Option<u32>,
     
     // ─────────────────────────────────────────────────────────────────────────
     // RTT Estimation (Jacobson/Karels)
@@ -393,6 +597,28 @@ pub struct TcpConnection {
     /// Time when RTT measurement started
     rtt_time: u64,
     
+    // ─────────────────────────────────────────────────────────────────────────
+    // Negotiated Options (RFC 7323, RFC 2018)
+    // ─────────────────────────────────────────────────────────────────────────
+    /// Largest payload the peer accepts (its MSS option, before our options)
+    pub mss: u32,
+    /// Window scaling in use (offered until the handshake settles it)
+    pub wscale_ok: bool,
+    /// Shift applied to the peer's window field
+    snd_wscale: u8,
+    /// Shift applied to our window field
+    rcv_wscale: u8,
+    /// SACK in use
+    pub sack_ok: bool,
+    /// Timestamps in use
+    pub ts_ok: bool,
+    /// TS.Recent: timestamp to echo back (and the PAWS floor)
+    ts_recent: u32,
+    /// Last ACK number we sent (decides when TS.Recent is updated)
+    last_ack_sent: u32,
+    /// End of the last retransmitted segment (holes below it were resent)
+    high_rxt: u32,
+    
     // ─────────────────────────────────────────────────────────────────────────
     // Congestion Control (RFC 5681)
     // ─────────────────────────────────────────────────────────────────────────
@@ -415,6 +641,16 @@ pub struct TcpConnection {
     /// Queue of unacknowledged segments
     retransmit_queue: RetransmitQueue,
     
+    // ─────────────────────────────────────────────────────────────────────────
+    // Keepalive (RFC 1122 §4.2.3.6)
+    // ─────────────────────────────────────────────────────────────────────────
+    /// Probe the peer after a long idle period (SO_KEEPALIVE)
+    pub keepalive: bool,
+    /// Time the last segment arrived
+    last_recv: u64,
+    /// Probes sent since then
+    keepalive_probes: u8,
+    
     // ─────────────────────────────────────────────────────────────────────────
     // Buffers
     // ─────────────────────────────────────────────────────────────────────────
@@ -422,32 +658,53 @@ pub struct TcpConnection {
     pub recv_buffer: VecDeque<u8>,
     /// Send buffer (data to send)
     pub send_buffer: VecDeque<u8>,
+    /// Segments received beyond a gap, sorted by sequence number
+    out_of_order: Vec<(u32, Vec<u8>)>,
+    /// Start of the most recently queued out-of-order segment (first SACK block)
+    last_out_of_order: u32,
 }
 
 impl TcpConnection {
     /// Create a new TCP connection
-    pub fn new(local_addr: Ipv4Addr, local_port: u16, remote_addr: Ipv4Addr, remote_port: u16) -> Self {
+    pub fn new(local_addr: IpAddr, local_port: u16, remote_addr: IpAddr, remote_port: u16) -> Self {
         // Generate initial sequence number (simplified - should use secure random)
         let iss = (timer::uptime_us() as u32).wrapping_mul(12345);
         
+        let now = timer::uptime_us();
+        
         Self {
             local_addr,
             local_port,
             remote_addr,
             remote_port,
             state: TcpState::Closed,
+            state_since: now,
+            accepted: false,
+            orphaned: false,
+            backlog: 0,
             send_unacked: iss,
             send_next: iss,
             send_window: 0,
+            max_send_window: 0,
             recv_next: 0,
             recv_window: DEFAULT_RECV_WINDOW,
             iss,
             irs: 0,
+            fin_seq: None,
             srtt: 0,
             rttvar: 0,
             rto: INITIAL_RTO_US,
             rtt_seq: None,
             rtt_time: 0,
+            mss: if remote_addr.is_ipv6() { DEFAULT_MSS_V6 } else { DEFAULT_MSS },
+            wscale_ok: true,
+            snd_wscale: 0,
+            rcv_wscale: RECV_WINDOW_SCALE,
+            sack_ok: true,
+            ts_ok: true,
+            ts_recent: 0,
+            last_ack_sent: 0,
+            high_rxt: iss,
             cwnd: INITIAL_CWND,
             ssthresh: INITIAL_SSTHRESH,
             congestion_state: CongestionState::SlowStart,
@@ -455,11 +712,22 @@ impl TcpConnection {
             last_ack: 0,
             recover: 0,
             retransmit_queue: RetransmitQueue::new(),
+            keepalive: false,
+            last_recv: now,
+            keepalive_probes: 0,
             recv_buffer: VecDeque::new(),
             send_buffer: VecDeque::new(),
+            out_of_order: Vec::new(),
+            last_out_of_order: 0,
         }
     }
     
+    /// Move to `state`, restarting the state timer
+    fn set_state(&mut self, state: TcpState) {
+        self.state = state;
+        self.state_since = timer::uptime_us();
+    }
+    
     /// Update RTT estimate using Jacobson/Karels algorithm
     fn update_rtt(&mut self, measured_rtt: u64) {
         if self.srtt == 0 {
@@ -487,22 +755,45 @@ impl TcpConnection {
     }
     
     /// Process an incoming ACK
-    pub fn process_ack(&mut self, ack_num: u32, window: u32) {
+    ///
+    /// `window` is already scaled. SACK blocks update the retransmit
+    /// scoreboard, and an echoed timestamp gives an RTT sample for every
+    /// ACK of new data (RTTM, RFC 7323 §4).
+    pub fn process_ack(&mut self, ack_num: u32, window: u32, options: &TcpOptions) {
         // Update send window
         self.send_window = window;
+        self.max_send_window = self.max_send_window.max(window);
+        
+        if self.sack_ok {
+            for &(start, end) in &options.sack_blocks {
+                self.retransmit_queue.sack(start, end);
+            }
+        }
         
         // Check if this is a new ACK (acknowledges new data)
         if seq_after(ack_num, self.send_unacked) {
             // New data acknowledged
             
-            // Update RTT if we were timing this segment
-            if let Some(rtt_seq) = self.rtt_seq {
-                if seq_after(ack_num, rtt_seq) || ack_num == rtt_seq {
-                    let now = timer::uptime_us();
-                    let measured_rtt = now.saturating_sub(self.rtt_time);
-                    self.update_rtt(measured_rtt);
+            // Update RTT from the echoed timestamp, or the segment we were timing
+            match options.timestamp {
+                Some((_, echo)) if self.ts_ok && echo != 0 => {
+                    let rtt_ms = timestamp_now().wrapping_sub(echo);
+                    if (rtt_ms as i32) >= 0 {
+                        // At least one tick of clock granularity
+                        self.update_rtt(rtt_ms.max(1) as u64 * 1000);
+                    }
                     self.rtt_seq = None;
                 }
+                _ => {
+                    if let Some(rtt_seq) = self.rtt_seq {
+                        if seq_after(ack_num, rtt_seq) || ack_num == rtt_seq {
+                            let now = timer::uptime_us();
+                            let measured_rtt = now.saturating_sub(self.rtt_time);
+                            self.update_rtt(measured_rtt);
+                            self.rtt_seq = None;
+                        }
+                    }
+                }
             }
             
             // Update congestion window based on state
@@ -519,17 +810,20 @@ impl TcpConnection {
             self.dup_ack_count = 0;
             self.last_ack = ack_num;
             
-            // Exit fast recovery if all data is acknowledged
-            if self.congestion_state == CongestionState::FastRecovery 
-                && (seq_after(ack_num, self.recover) || ack_num == self.recover) 
-            {
-                // Full ACK - exit fast recovery
-                self.congestion_state = CongestionState::CongestionAvoidance;
-                self.cwnd = self.ssthresh;
+            if self.congestion_state == CongestionState::FastRecovery {
+                if seq_after(ack_num, self.recover) || ack_num == self.recover {
+                    // Full ACK - exit fast recovery
+                    self.congestion_state = CongestionState::CongestionAvoidance;
+                    self.cwnd = self.ssthresh;
+                } else {
+                    // Partial ACK: the segment now at the front was lost too (RFC 6582)
+                    self.retransmit(0);
+                }
             }
-        } else if ack_num == self.send_unacked && ack_num == self.last_ack {
-            // Duplicate ACK
-            self.dup_ack_count += 1;
+        } else if ack_num == self.send_unacked && ack_num == self.last_ack
+                  && !self.retransmit_queue.is_empty() {
+            // Duplicate ACK (only meaningful while data is outstanding)
+            self.dup_ack_count = self.dup_ack_count.saturating_add(1);
             
             if self.dup_ack_count == DUP_ACK_THRESHOLD {
                 // Fast Retransmit & Fast Recovery (RFC 5681)
@@ -537,17 +831,25 @@ impl TcpConnection {
             } else if self.dup_ack_count > DUP_ACK_THRESHOLD 
                       && self.congestion_state == CongestionState::FastRecovery {
                 // Inflate cwnd during fast recovery
-                self.cwnd += MSS;
+                self.cwnd += self.segment_size() as u32;
+                
+                // With SACK, each further duplicate can fill the next hole (RFC 6675)
+                if self.sack_ok {
+                    if let Some(idx) = self.retransmit_queue.next_hole(self.high_rxt) {
+                        self.retransmit(idx);
+                    }
+                }
             }
         }
     }
     
     /// Update congestion window on new ACK
     fn update_cwnd_on_ack(&mut self, bytes_acked: u32) {
+        let smss = self.segment_size() as u32;
         match self.congestion_state {
             CongestionState::SlowStart => {
-                // Exponential growth: cwnd += min(bytes_acked, MSS)
-                self.cwnd += bytes_acked.min(MSS);
+                // Exponential growth: cwnd += min(bytes_acked, SMSS)
+                self.cwnd += bytes_acked.min(smss);
                 
                 // Transition to congestion avoidance if threshold reached
                 if self.cwnd >= self.ssthresh {
@@ -555,9 +857,9 @@ impl TcpConnection {
                 }
             }
             CongestionState::CongestionAvoidance => {
-                // Linear growth: cwnd += MSS * MSS / cwnd (approximately 1 MSS per RTT)
-                if self.cwnd > 0 {
-                    self.cwnd += (MSS * MSS) / self.cwnd;
+                // Linear growth: cwnd += SMSS * SMSS / cwnd (approximately 1 SMSS per RTT)
+                if let Some(increase) = (smss * smss).checked_div(self.cwnd) {
+                    self.cwnd += increase;
                 }
             }
             CongestionState::FastRecovery => {
@@ -569,12 +871,14 @@ impl TcpConnection {
     
     /// Enter fast recovery after 3 duplicate ACKs
     fn enter_fast_recovery(&mut self) {
-        // ssthresh = max(FlightSize / 2, 2*MSS)
+        let smss = self.segment_size() as u32;
+        
+        // ssthresh = max(FlightSize / 2, 2*SMSS)
         let flight_size = self.send_next.wrapping_sub(self.send_unacked);
-        self.ssthresh = (flight_size / 2).max(2 * MSS);
+        self.ssthresh = (flight_size / 2).max(2 * smss);
         
-        // cwnd = ssthresh + 3*MSS (for the 3 dup ACKs)
-        self.cwnd = self.ssthresh + 3 * MSS;
+        // cwnd = ssthresh + 3*SMSS (for the 3 dup ACKs)
+        self.cwnd = self.ssthresh + 3 * smss;
         
         // Record recovery point
         self.recover = self.send_next;
@@ -583,17 +887,19 @@ impl TcpConnection {
         self.congestion_state = CongestionState::FastRecovery;
         
         // Retransmit the first unacked segment
-        self.retransmit_first();
+        self.retransmit(0);
     }
     
     /// Handle RTO timeout
     pub fn handle_timeout(&mut self) {
-        // ssthresh = max(FlightSize / 2, 2*MSS)
+        let smss = self.segment_size() as u32;
+        
+        // ssthresh = max(FlightSize / 2, 2*SMSS)
         let flight_size = self.send_next.wrapping_sub(self.send_unacked);
-        self.ssthresh = (flight_size / 2).max(2 * MSS);
+        self.ssthresh = (flight_size / 2).max(2 * smss);
         
-        // cwnd = 1 MSS (back to slow start)
-        self.cwnd = MSS;
+        // cwnd = 1 SMSS (back to slow start)
+        self.cwnd = smss;
         
         // Return to slow start
         self.congestion_state = CongestionState::SlowStart;
@@ -604,28 +910,44 @@ impl TcpConnection {
         // Reset duplicate ACK counter
         self.dup_ack_count = 0;
         
+        // The receiver may have dropped what it SACKed (RFC 2018 §8)
+        self.retransmit_queue.clear_sacks();
+        
         // Retransmit first unacked segment
-        self.retransmit_first();
+        self.retransmit(0);
     }
     
-    /// Retransmit the first unacknowledged segment
-    fn retransmit_first(&mut self) {
-        if let Some(entry) = self.retransmit_queue.front() {
-            let data = entry.data.clone();
-            let now = timer::uptime_us();
-            
-            // Send via IP layer
-            let _ = ipv4::send_packet(self.remote_addr, 6, &data);
-            
-            // Update retransmit entry
-            self.retransmit_queue.mark_retransmitted(now);
-            
-            // Invalidate RTT measurement (Karn's algorithm)
-            self.rtt_seq = None;
+    /// Retransmit a queued segment under a fresh header
+    fn retransmit(&mut self, idx: usize) {
+        let Some(entry) = self.retransmit_queue.entries.get(idx) else { return };
+        let seq = entry.seq_num;
+        let payload = entry.payload.clone();
+        let mut flags = entry.control;
+        self.high_rxt = entry.end();
+        
+        // Only the opening SYN goes out without an ACK
+        if self.state != TcpState::SynSent {
+            flags |= TcpFlags::ACK;
         }
+        if !payload.is_empty() {
+            flags |= TcpFlags::PSH;
+        }
+        
+        // Send via IP layer
+        let _ = self.transmit(seq, flags, payload);
+        
+        // Update retransmit entry
+        self.retransmit_queue.mark_retransmitted(idx, timer::uptime_us());
+        
+        // Invalidate RTT measurement (Karn's algorithm)
+        self.rtt_seq = None;
     }
     
     /// Check for retransmission timeout
+    ///
+    /// A segment that keeps timing out means the peer is gone: the
+    /// connection is aborted after `MAX_RETRIES` (`MAX_SYN_RETRIES` for
+    /// the handshake).
     pub fn check_retransmit(&mut self, now: u64) {
         if self.retransmit_queue.is_empty() {
             return;
@@ -634,11 +956,62 @@ impl TcpConnection {
         if let Some(entry) = self.retransmit_queue.front() {
             let elapsed = now.saturating_sub(entry.send_time);
             if elapsed >= self.rto {
-                self.handle_timeout();
+                let limit = if entry.control & TcpFlags::SYN != 0 { MAX_SYN_RETRIES } else { MAX_RETRIES };
+                if entry.retransmit_count >= limit {
+                    self.abort();
+                } else {
+                    self.handle_timeout();
+                }
             }
         }
     }
     
+    /// Send a keepalive probe if the connection has been idle long enough
+    ///
+    /// The probe is an ACK for an old sequence number, which makes a live
+    /// peer answer with an ACK; unanswered probes abort the connection.
+    pub fn check_keepalive(&mut self, now: u64) {
+        if !self.keepalive
+            || !matches!(self.state, TcpState::Established | TcpState::CloseWait)
+            || !self.retransmit_queue.is_empty()
+        {
+            return;
+        }
+        let due = KEEPALIVE_IDLE_US + self.keepalive_probes as u64 * KEEPALIVE_INTERVAL_US;
+        if now.saturating_sub(self.last_recv) < due {
+            return;
+        }
+        if self.keepalive_probes >= KEEPALIVE_PROBES {
+            self.abort();
+            return;
+        }
+        self.keepalive_probes += 1;
+        let _ = self.transmit(self.send_unacked.wrapping_sub(1), TcpFlags::ACK, Vec::new());
+    }
+    
+    /// Has this connection outlived its purpose (ready to free)?
+    pub fn expired(&self, now: u64) -> bool {
+        let age = now.saturating_sub(self.state_since);
+        match self.state {
+            TcpState::Closed => true,
+            TcpState::TimeWait => age >= 2 * MSL_US,
+            // The peer may never send its FIN, and nobody would notice
+            TcpState::FinWait2 => self.orphaned && age >= FIN_WAIT2_TIMEOUT_US,
+            _ => false,
+        }
+    }
+    
+    /// Tear the connection down at once, telling the peer with a RST
+    pub fn abort(&mut self) {
+        if !matches!(self.state, TcpState::Closed | TcpState::Listen | TcpState::SynSent) {
+            let _ = send_rst(self.local_addr, self.local_port, self.remote_addr, self.remote_port,
+                             self.send_next, self.recv_next);
+        }
+        self.retransmit_queue.entries.clear();
+        self.send_buffer.clear();
+        self.set_state(TcpState::Closed);
+    }
+    
     /// Queue data for sending
     pub fn send_data(&mut self, data: &[u8]) -> usize {
         for &byte in data {
@@ -647,9 +1020,12 @@ impl TcpConnection {
         data.len()
     }
     
-    /// Send queued data (respecting window)
+    /// Send queued data (respecting window), then our FIN once closed
     pub fn flush_send_buffer(&mut self) -> Result<(), &'static str> {
-        if self.state != TcpState::Established {
+        // CLOSE-WAIT may still send: only the peer has finished. After
+        // close() the data queued before it still goes out ahead of the FIN.
+        let closing = matches!(self.state, TcpState::FinWait1 | TcpState::Closing | TcpState::LastAck);
+        if self.state != TcpState::Established && self.state != TcpState::CloseWait && !closing {
             return Err("Connection not established");
         }
         
@@ -660,12 +1036,12 @@ impl TcpConnection {
             let flight_size = self.send_next.wrapping_sub(self.send_unacked);
             let window = self.cwnd.min(self.send_window);
             
-            if flight_size >= window {
-                break; // Window full
+            if flight_size >= window || self.retransmit_queue.is_full() {
+                break; // Window or retransmit queue full
             }
             
             let available = window - flight_size;
-            let send_size = (available as usize).min(MSS as usize).min(self.send_buffer.len());
+            let send_size = (available as usize).min(self.segment_size()).min(self.send_buffer.len());
             
             if send_size == 0 {
                 break;
@@ -679,46 +1055,285 @@ impl TcpConnection {
                 }
             }
             
-            // Build TCP segment with proper checksum
-            let segment = TcpSegment {
-                src_port: self.local_port,
-                dst_port: self.remote_port,
-                sequence_num: self.send_next,
-                ack_num: self.recv_next,
-                data_offset: 5,
-                flags: TcpFlags::new(TcpFlags::ACK | TcpFlags::PSH),
-                window_size: self.recv_window as u16,
-                checksum: 0, // Will be computed below
-                urgent_pointer: 0,
-                options: Vec::new(),
-                payload: payload.clone(),
-            };
-            
-            // Compute checksum with pseudo-header (RFC 793)
-            let segment_bytes = segment.to_bytes_with_checksum(self.local_addr, self.remote_addr);
-            
             // Start RTT measurement if not already timing
+            let seq = self.send_next;
             if self.rtt_seq.is_none() {
-                self.rtt_seq = Some(self.send_next);
+                self.rtt_seq = Some(seq);
                 self.rtt_time = now;
             }
             
             // Add to retransmit queue
-            self.retransmit_queue.push(self.send_next, segment_bytes.clone(), now);
+            self.retransmit_queue.push(seq, payload.clone(), now);
             
             // Update SND.NXT
-            self.send_next = self.send_next.wrapping_add(payload.len() as u32);
+            self.send_next = seq.wrapping_add(payload.len() as u32);
             
             // Send via IP layer
-            ipv4::send_packet(self.remote_addr, 6, &segment_bytes)?;
+            self.transmit(seq, TcpFlags::ACK | TcpFlags::PSH, payload)?;
+        }
+        
+        if closing && self.fin_seq.is_none() && self.send_buffer.is_empty() {
+            self.fin_seq = Some(self.send_next);
+            self.send_control(TcpFlags::FIN)?;
         }
         
         Ok(())
     }
     
+    /// Close our sending side (RFC 793 CLOSE call)
+    ///
+    /// Moves to FIN-WAIT-1 or LAST-ACK; the FIN follows any queued data.
+    /// Receiving continues until the peer's FIN (half-close).
+    pub fn close(&mut self) -> Result<(), &'static str> {
+        match self.state {
+            TcpState::Established | TcpState::CloseWait => {
+                self.set_state(if self.state == TcpState::Established {
+                    TcpState::FinWait1
+                } else {
+                    TcpState::LastAck
+                });
+                self.flush_send_buffer()
+            }
+            TcpState::SynReceived => {
+                // Nothing was sent yet, so FIN right away (RFC 9293 §3.10.4)
+                self.set_state(TcpState::FinWait1);
+                self.flush_send_buffer()
+            }
+            TcpState::Listen | TcpState::SynSent | TcpState::Closed => {
+                self.set_state(TcpState::Closed);
+                Ok(())
+            }
+            _ => Err("Connection closing"),
+        }
+    }
+    
+    /// Has the peer acknowledged our FIN?
+    fn fin_acked(&self) -> bool {
+        self.fin_seq.is_some_and(|fin| seq_after(self.send_unacked, fin))
+    }
+    
+    /// Receive window still open: buffer space the application has not used
+    pub fn recv_space(&self) -> u32 {
+        self.recv_window.saturating_sub(self.recv_buffer.len() as u32)
+    }
+    
+    /// Called after the application drained the receive buffer
+    ///
+    /// If the window we last advertised was below one segment, the peer may
+    /// be waiting on it, so announce the reopened window right away.
+    pub fn window_drained(&mut self, space_before: u32) {
+        if space_before < self.local_mss() && self.recv_space() >= self.local_mss() {
+            let _ = send_ack(self);
+        }
+    }
+    
+    /// Is `seq` inside our receive window (RFC 5961 §3.2)?
+    fn in_window(&self, seq: u32) -> bool {
+        !seq_after(self.recv_next, seq)
+            && seq_after(self.recv_next.wrapping_add(self.recv_window.max(1)), seq)
+    }
+    
+    /// Could accept() hand this connection out?
+    fn is_acceptable(&self, local_port: u16) -> bool {
+        self.local_port == local_port
+            && self.remote_port != 0
+            && !self.accepted
+            && !matches!(self.state, TcpState::Listen | TcpState::SynReceived | TcpState::Closed)
+    }
+    
+    /// Is this a child of the listener on `local_port` that accept() has not claimed yet?
+    fn is_pending_child(&self, local_port: u16) -> bool {
+        self.local_port == local_port
+            && self.remote_port != 0
+            && !self.accepted
+            && self.state != TcpState::Closed
+    }
+    
+    /// MSS our side of the path allows (advertised in our SYN)
+    fn local_mss(&self) -> u32 {
+        if self.remote_addr.is_ipv6() { MSS_V6 } else { MSS }
+    }
+    
+    /// Largest payload per segment: the smaller MSS, less the options sent with it
+    fn segment_size(&self) -> usize {
+        let overhead = if self.ts_ok { TIMESTAMP_OVERHEAD } else { 0 };
+        (self.mss.min(self.local_mss()) as usize).saturating_sub(overhead).max(1)
+    }
+    
+    /// Settle the options from the peer's SYN or SYN-ACK
+    ///
+    /// An option stays on only if both sides sent it (RFC 7323 §1.3, RFC 2018 §2).
+    fn negotiate(&mut self, peer: &TcpOptions) {
+        let default_mss = if self.remote_addr.is_ipv6() { DEFAULT_MSS_V6 } else { DEFAULT_MSS };
+        self.mss = peer.mss.map_or(default_mss, u32::from);
+        match peer.window_scale {
+            Some(shift) if self.wscale_ok => self.snd_wscale = shift,
+            _ => {
+                self.wscale_ok = false;
+                self.snd_wscale = 0;
+                self.rcv_wscale = 0;
+                self.recv_window = self.recv_window.min(u16::MAX as u32);
+            }
+        }
+        self.sack_ok &= peer.sack_permitted;
+        match peer.timestamp {
+            Some((val, _)) if self.ts_ok => self.ts_recent = val,
+            _ => self.ts_ok = false,
+        }
+    }
+    
+    /// Build a segment carrying this connection's options
+    ///
+    /// SYNs offer what is still enabled; later segments carry the timestamp
+    /// and, on pure ACKs, SACK blocks for data held beyond a gap.
+    fn build_segment(&mut self, sequence_num: u32, flags: u16, payload: Vec<u8>) -> TcpSegment {
+        let syn = flags & TcpFlags::SYN != 0;
+        let ack = flags & TcpFlags::ACK != 0;
+        
+        let mut options = TcpOptions::default();
+        if syn {
+            options.mss = Some(self.local_mss() as u16);
+            options.window_scale = self.wscale_ok.then_some(self.rcv_wscale);
+            options.sack_permitted = self.sack_ok;
+        } else if self.sack_ok && payload.is_empty() {
+            options.sack_blocks = self.sack_blocks();
+        }
+        if self.ts_ok {
+            options.timestamp = Some((timestamp_now(), if ack { self.ts_recent } else { 0 }));
+        }
+        if ack {
+            self.last_ack_sent = self.recv_next;
+        }
+        
+        // The window in a SYN is never scaled (RFC 7323 §2.2)
+        let space = self.recv_space();
+        let window = if syn { space } else { space >> self.rcv_wscale };
+        let options = options.to_bytes();
+        TcpSegment {
+            src_port: self.local_port,
+            dst_port: self.remote_port,
+            sequence_num,
+            ack_num: if ack { self.recv_next } else { 0 },
+            data_offset: (5 + options.len() / 4) as u8,
+            flags: TcpFlags::new(flags),
+            window_size: window.min(u16::MAX as u32) as u16,
+            checksum: 0,
+            urgent_pointer: 0,
+            options,
+            payload,
+        }
+    }
+    
+    /// Build, checksum and send a segment
+    fn transmit(&mut self, sequence_num: u32, flags: u16, payload: Vec<u8>) -> Result<(), &'static str> {
+        let segment = self.build_segment(sequence_num, flags, payload);
+        send_ip_packet(self.remote_addr, 6, &segment.to_bytes_with_checksum(self.local_addr, self.remote_addr))
+    }
+    
+    /// Send a SYN or FIN at SND.NXT
+    ///
+    /// Both consume a sequence number, so they are queued for
+    /// retransmission like data.
+    fn send_control(&mut self, control: u16) -> Result<(), &'static str> {
+        let seq = self.send_next;
+        self.retransmit_queue.push_control(seq, control, timer::uptime_us());
+        self.send_next = seq.wrapping_add(1);
+        let flags = if self.state == TcpState::SynSent { control } else { control | TcpFlags::ACK };
+        self.transmit(seq, flags, Vec::new())
+    }
+    
+    /// PAWS check and TS.Recent update (RFC 7323 §5.3)
+    ///
+    /// Returns false for an old duplicate, which must be dropped.
+    fn check_timestamp(&mut self, segment: &TcpSegment, options: &TcpOptions) -> bool {
+        let Some((val, _)) = options.timestamp.filter(|_| self.ts_ok) else {
+            return true;
+        };
+        if seq_after(self.ts_recent, val) && !segment.flags.contains(TcpFlags::RST) {
+            return false;
+        }
+        if !seq_after(segment.sequence_num, self.last_ack_sent) {
+            self.ts_recent = val;
+        }
+        true
+    }
+    
+    /// Take payload arriving at `seq`
+    ///
+    /// In-order data goes to the receive buffer along with any held
+    /// segments it now reaches; data beyond a gap is held (within the
+    /// window) until the gap fills. Bytes past the advertised window are
+    /// dropped; the peer sends them again once it opens.
+    fn receive_data(&mut self, seq: u32, payload: &[u8]) {
+        // Trim bytes we already have
+        let (mut seq, mut payload) = (seq, payload);
+        if seq_after(self.recv_next, seq) {
+            let dup = self.recv_next.wrapping_sub(seq) as usize;
+            if dup >= payload.len() {
+                return;
+            }
+            payload = &payload[dup..];
+            seq = self.recv_next;
+        }
+        
+        if seq != self.recv_next {
+            let reach = seq.wrapping_sub(self.recv_next) as usize + payload.len();
+            if reach > self.recv_space() as usize
+                || self.out_of_order.len() >= MAX_OUT_OF_ORDER
+                || self.out_of_order.iter().any(|(s, _)| *s == seq)
+            {
+                return;
+            }
+            let pos = self.out_of_order.iter()
+                .position(|(s, _)| seq_after(*s, seq))
+                .unwrap_or(self.out_of_order.len());
+            self.out_of_order.insert(pos, (seq, payload.to_vec()));
+            self.last_out_of_order = seq;
+            return;
+        }
+        
+        let payload = &payload[..payload.len().min(self.recv_space() as usize)];
+        self.recv_buffer.extend(payload.iter().copied());
+        self.recv_next = self.recv_next.wrapping_add(payload.len() as u32);
+        
+        // The gap may be closed now
+        while self.out_of_order.first().is_some_and(|(s, _)| !seq_after(*s, self.recv_next)) {
+            let (start, data) = self.out_of_order.remove(0);
+            let skip = self.recv_next.wrapping_sub(start) as usize;
+            let end = data.len().min(skip + self.recv_space() as usize);
+            if skip < end {
+                self.recv_buffer.extend(data[skip..end].iter().copied());
+                self.recv_next = self.recv_next.wrapping_add((end - skip) as u32);
+            }
+        }
+    }
+    
+    /// SACK blocks for held data, the latest arrival's block first (RFC 2018 §4)
+    fn sack_blocks(&self) -> Vec<(u32, u32)> {
+        let mut blocks: Vec<(u32, u32)> = Vec::new();
+        for (start, data) in &self.out_of_order {
+            let end = start.wrapping_add(data.len() as u32);
+            match blocks.last_mut() {
+                Some(last) if !seq_after(*start, last.1) => {
+                    if seq_after(end, last.1) {
+                        last.1 = end;
+                    }
+                }
+                _ => blocks.push((*start, end)),
+            }
+        }
+        let recent = self.last_out_of_order;
+        if let Some(i) = blocks.iter().position(|&(s, e)| !seq_after(s, recent) && seq_after(e, recent)) {
+            blocks[..=i].rotate_right(1);
+        }
+        // 40 option bytes hold four blocks, three next to a timestamp
+        blocks.truncate(if self.ts_ok { 3 } else { 4 });
+        blocks
+    }
+    
     /// Matches a 4-tuple (used for connection lookup)
-    pub fn matches(&self, local_addr: Ipv4Addr, local_port: u16, 
-                   remote_addr: Ipv4Addr, remote_port: u16) -> bool {
+    pub fn matches(&self, local_addr: IpAddr, local_port: u16, 
+                   remote_addr: IpAddr, remote_port: u16) -> bool {
         self.local_addr == local_addr 
             && self.local_port == local_port
             && self.remote_addr == remote_addr
@@ -733,6 +1348,9 @@ impl TcpConnection {
 /// Global TCP Connection Table
 pub static TCB_TABLE: SpinLock<TcpConnectionTable> = SpinLock::new(TcpConnectionTable::new());
 
+/// Agents blocked in connect/accept/recv; woken whenever the table changes
+pub static SOCKET_WAIT: WaitQueue = WaitQueue::new();
+
 /// Table of active TCP connections
 pub struct TcpConnectionTable {
     connections: Vec<TcpConnection>,
@@ -745,9 +1363,14 @@ impl TcpConnectionTable {
         }
     }
     
+    /// Iterate over all connections (for introspection)
+    pub fn iter(&self) -> impl Iterator<Item = &TcpConnection> {
+        self.connections.iter()
+    }
+
     /// Find a connection by 4-tuple
-    pub fn find(&self, local_addr: Ipv4Addr, local_port: u16,
-                remote_addr: Ipv4Addr, remote_port: u16) -> Option<usize> {
+    pub fn find(&self, local_addr: IpAddr, local_port: u16,
+                remote_addr: IpAddr, remote_port: u16) -> Option<usize> {
         self.connections.iter().position(|c| {
             c.matches(local_addr, local_port, remote_addr, remote_port)
         })
@@ -760,9 +1383,41 @@ impl TcpConnectionTable {
         })
     }
     
+    /// Claim an established, not yet accepted connection on a listening port
+    ///
+    /// Returns the 4-tuple (local addr, local port, remote addr, remote port).
+    /// The table keeps insertion order, so the oldest connection is handed out first.
+    pub fn accept(&mut self, local_port: u16) -> Option<(IpAddr, u16, IpAddr, u16)> {
+        let conn = self.connections.iter_mut().find(|c| c.is_acceptable(local_port))?;
+        conn.accepted = true;
+        Some((conn.local_addr, conn.local_port, conn.remote_addr, conn.remote_port))
+    }
+    
+    /// Is an established connection waiting to be accepted on this port?
+    pub fn has_pending_accept(&self, local_port: u16) -> bool {
+        self.connections.iter().any(|c| c.is_acceptable(local_port))
+    }
+    
+    /// Connections in the listener's backlog: half-open or waiting for accept()
+    pub fn pending_children(&self, local_port: u16) -> usize {
+        self.connections.iter().filter(|c| c.is_pending_child(local_port)).count()
+    }
+    
+    /// Stop listening on a port, resetting connections nobody accepted
+    pub fn close_listener(&mut self, local_port: u16) {
+        for conn in &mut self.connections {
+            if conn.is_pending_child(local_port) {
+                conn.abort();
+            }
+        }
+        self.connections.retain(|c| {
+            !(c.state == TcpState::Listen && c.local_port == local_port) && c.state != TcpState::Closed
+        });
+    }
+    
     /// Add a new connection
     pub fn add(&mut self, conn: TcpConnection) -> Result<usize, &'static str> {
-        if self.connections.len() >= MAX_CONNECTIONS {
+        if self.connections.len() >= MAX_CONNECTIONS && self.reap() == 0 {
             return Err("Connection table full");
         }
         self.connections.push(conn);
@@ -781,13 +1436,24 @@ impl TcpConnectionTable {
         }
     }
     
-    /// Check all connections for retransmission timeouts
+    /// Check all connections for retransmission and keepalive timeouts
     pub fn check_retransmits(&mut self) {
         let now = timer::uptime_us();
         for conn in &mut self.connections {
             conn.check_retransmit(now);
+            conn.check_keepalive(now);
         }
     }
+    
+    /// Free closed connections and those whose TIME-WAIT (2MSL) has passed
+    ///
+    /// A socket still holding a freed connection sees it as reset/EOF.
+    pub fn reap(&mut self) -> usize {
+        let now = timer::uptime_us();
+        let before = self.connections.len();
+        self.connections.retain(|c| !c.expired(now));
+        before - self.connections.len()
+    }
 }
 
 impl Default for TcpConnectionTable {
@@ -800,131 +1466,226 @@ impl Default for TcpConnectionTable {
 // PACKET HANDLER
 // ═══════════════════════════════════════════════════════════════════════════════
 
-/// Handle incoming TCP packet (called from IPv4 handler)
-pub fn handle_packet(data: &[u8], src_ip: Ipv4Addr, dst_ip: Ipv4Addr) -> Result<(), &'static str> {
+/// Handle incoming TCP packet (called from the IPv4 and IPv6 handlers)
+pub fn handle_packet(data: &[u8], src_ip: IpAddr, dst_ip: IpAddr) -> Result<(), &'static str> {
+    if !verify_tcp_checksum(src_ip, dst_ip, data) {
+        return Err("Bad TCP checksum");
+    }
     let segment = TcpSegment::parse(data)?;
+    let result = handle_segment(&segment, src_ip, dst_ip);
+    
+    // Any segment can make a socket readable, accept()able or closed
+    SOCKET_WAIT.wake_all();
+    result
+}
+
+/// Demultiplex a parsed segment to its connection or listener
+fn handle_segment(segment: &TcpSegment, src_ip: IpAddr, dst_ip: IpAddr) -> Result<(), &'static str> {
     
+    let options = TcpOptions::parse(&segment.options);
     let mut table = TCB_TABLE.lock();
+    let is_syn = segment.flags.contains(TcpFlags::SYN) && !segment.flags.contains(TcpFlags::ACK);
     
     // Look for existing connection
     if let Some(idx) = table.find(dst_ip, segment.dst_port, src_ip, segment.src_port) {
         let conn = table.get_mut(idx).unwrap();
-        process_segment(conn, &segment)?;
-    } else {
-        // Check for listening socket
-        if let Some(_idx) = table.find_listener(segment.dst_port) {
-            // Handle incoming SYN
-            if segment.flags.contains(TcpFlags::SYN) && !segment.flags.contains(TcpFlags::ACK) {
-                // Create new connection for this client
-                let mut conn = TcpConnection::new(dst_ip, segment.dst_port, src_ip, segment.src_port);
-                conn.state = TcpState::SynReceived;
-                conn.irs = segment.sequence_num;
-                conn.recv_next = segment.sequence_num.wrapping_add(1);
-                
-                // Send SYN-ACK
-                send_syn_ack(&conn)?;
-                
-                // Add to table
-                table.add(conn)?;
-            }
-        } else if segment.flags.contains(TcpFlags::RST) {
-            // Ignore RST for non-existent connections
-            return Ok(());
-        } else {
-            // Send RST for unexpected packets
-            send_rst(dst_ip, segment.dst_port, src_ip, segment.src_port, 
-                    segment.ack_num, segment.sequence_num.wrapping_add(1))?;
+        
+        // A new SYN above the old sequence space may reopen a TIME-WAIT
+        // connection (RFC 9293 §3.6.1); anything else goes to the TCB
+        if !(is_syn && conn.state == TcpState::TimeWait && seq_after(segment.sequence_num, conn.recv_next)) {
+            return process_segment(conn, segment, &options);
         }
+        table.remove(idx);
+    }
+    
+    // Check for listening socket (an IPv4 listener ignores IPv6 peers)
+    let listener = table.find_listener(segment.dst_port)
+        .filter(|&idx| table.connections[idx].local_addr.covers(dst_ip));
+    if let Some(idx) = listener {
+        // Handle incoming SYN, unless the backlog is full: dropping it makes
+        // the client retry later instead of failing outright
+        let (backlog, keepalive) = (table.connections[idx].backlog, table.connections[idx].keepalive);
+        if is_syn && table.pending_children(segment.dst_port) < backlog {
+            // Create new connection for this client
+            let mut conn = TcpConnection::new(dst_ip, segment.dst_port, src_ip, segment.src_port);
+            conn.set_state(TcpState::SynReceived);
+            conn.keepalive = keepalive;
+            conn.irs = segment.sequence_num;
+            conn.recv_next = segment.sequence_num.wrapping_add(1);
+            conn.send_window = segment.window_size as u32;
+            conn.negotiate(&options);
+            
+            // Send SYN-ACK (answering only the options the client offered)
+            conn.send_control(TcpFlags::SYN)?;
+            
+            // Add to table
+            table.add(conn)?;
+        }
+    } else if segment.flags.contains(TcpFlags::RST) {
+        // Ignore RST for non-existent connections
+        return Ok(());
+    } else if segment.flags.contains(TcpFlags::ACK) {
+        // Send RST for unexpected packets (RFC 9293 §3.10.7.1)
+        send_rst(dst_ip, segment.dst_port, src_ip, segment.src_port, segment.ack_num, 0)?;
+    } else {
+        let len = segment.payload.len() as u32
+            + segment.flags.contains(TcpFlags::SYN) as u32
+            + segment.flags.contains(TcpFlags::FIN) as u32;
+        send_rst(dst_ip, segment.dst_port, src_ip, segment.src_port,
+                 0, segment.sequence_num.wrapping_add(len))?;
     }
     
     Ok(())
 }
 
 /// Process a TCP segment for an existing connection
-fn process_segment(conn: &mut TcpConnection, segment: &TcpSegment) -> Result<(), &'static str> {
-    // Handle RST
+///
+/// Follows the segment-arrival checks of RFC 9293 §3.10.7.4, with the
+/// RST, SYN and ACK validation of RFC 5961 against blind injection.
+fn process_segment(conn: &mut TcpConnection, segment: &TcpSegment, options: &TcpOptions) -> Result<(), &'static str> {
+    // Any segment shows the peer is alive
+    conn.last_recv = timer::uptime_us();
+    conn.keepalive_probes = 0;
+    
+    if conn.state == TcpState::SynSent {
+        return process_syn_sent(conn, segment, options);
+    }
+    
+    // RST: only an exact match resets; an in-window guess gets a challenge ACK
     if segment.flags.contains(TcpFlags::RST) {
-        conn.state = TcpState::Closed;
+        if segment.sequence_num == conn.recv_next {
+            conn.set_state(TcpState::Closed);
+        } else if conn.in_window(segment.sequence_num) {
+            send_challenge_ack(conn)?;
+        }
         return Ok(());
     }
     
-    match conn.state {
-        TcpState::SynSent => {
-            // Expecting SYN-ACK
-            if segment.flags.contains(TcpFlags::SYN) && segment.flags.contains(TcpFlags::ACK) {
-                conn.irs = segment.sequence_num;
-                conn.recv_next = segment.sequence_num.wrapping_add(1);
-                conn.send_unacked = segment.ack_num;
-                conn.send_window = segment.window_size as u32;
-                conn.state = TcpState::Established;
-                
-                // Send ACK to complete handshake
-                send_ack(conn)?;
-            }
-        }
-        TcpState::SynReceived => {
-            // Expecting ACK
-            if segment.flags.contains(TcpFlags::ACK) && segment.ack_num == conn.send_next {
-                conn.state = TcpState::Established;
-            }
+    // PAWS: an old duplicate is answered with an ACK and dropped
+    if !conn.check_timestamp(segment, options) {
+        return send_ack(conn);
+    }
+    
+    // SYN on a synchronized connection: challenge ACK (RFC 5961 §4)
+    if segment.flags.contains(TcpFlags::SYN) {
+        if conn.state == TcpState::SynReceived && segment.sequence_num == conn.irs {
+            // Our SYN-ACK was lost: send it again
+            conn.retransmit(0);
+            return Ok(());
         }
-        TcpState::Established => {
-            // Process ACK
-            if segment.flags.contains(TcpFlags::ACK) {
-                conn.process_ack(segment.ack_num, segment.window_size as u32);
-            }
-            
-            // Process data
-            if !segment.payload.is_empty() {
-                if segment.sequence_num == conn.recv_next {
-                    // In-order data
-                    for byte in &segment.payload {
-                        conn.recv_buffer.push_back(*byte);
-                    }
-                    conn.recv_next = conn.recv_next.wrapping_add(segment.payload.len() as u32);
-                    
-                    // Send ACK
-                    send_ack(conn)?;
-                }
-                // Out-of-order data: send duplicate ACK (will trigger fast retransmit at sender)
-                else {
-                    send_ack(conn)?;
-                }
-            }
-            
-            // Handle FIN
-            if segment.flags.contains(TcpFlags::FIN) {
-                conn.recv_next = conn.recv_next.wrapping_add(1);
-                conn.state = TcpState::CloseWait;
-                send_ack(conn)?;
-            }
+        return send_challenge_ack(conn);
+    }
+    
+    if !segment.flags.contains(TcpFlags::ACK) {
+        return Ok(());
+    }
+    
+    // ACK must lie in [SND.UNA - MAX.SND.WND, SND.NXT] (RFC 5961 §5)
+    let ack = segment.ack_num;
+    if seq_after(ack, conn.send_next) {
+        return send_challenge_ack(conn);
+    }
+    if seq_after(conn.send_unacked.wrapping_sub(conn.max_send_window), ack) {
+        return send_challenge_ack(conn);
+    }
+    
+    // Window fields after the SYN are scaled (RFC 7323 §2.3)
+    let window = (segment.window_size as u32) << conn.snd_wscale;
+    
+    if conn.state == TcpState::SynReceived {
+        // Expecting the ACK of our SYN-ACK
+        if !seq_after(ack, conn.send_unacked) {
+            return Ok(());
         }
-        TcpState::FinWait1 => {
-            if segment.flags.contains(TcpFlags::ACK) {
-                if segment.flags.contains(TcpFlags::FIN) {
-                    conn.recv_next = conn.recv_next.wrapping_add(1);
-                    conn.state = TcpState::TimeWait;
-                    send_ack(conn)?;
-                } else {
-                    conn.state = TcpState::FinWait2;
-                }
+        conn.send_unacked = ack;
+        conn.last_ack = ack;
+        conn.retransmit_queue.ack_up_to(ack);
+        conn.send_window = window;
+        conn.max_send_window = window;
+        conn.set_state(TcpState::Established);
+    } else {
+        conn.process_ack(ack, window, options);
+    }
+    
+    // Our FIN being acknowledged advances the close
+    if conn.fin_acked() {
+        match conn.state {
+            TcpState::FinWait1 => conn.set_state(TcpState::FinWait2),
+            TcpState::Closing => conn.set_state(TcpState::TimeWait),
+            TcpState::LastAck => {
+                conn.set_state(TcpState::Closed);
+                return Ok(());
             }
+            _ => {}
         }
-        TcpState::FinWait2 => {
-            if segment.flags.contains(TcpFlags::FIN) {
-                conn.recv_next = conn.recv_next.wrapping_add(1);
-                conn.state = TcpState::TimeWait;
-                send_ack(conn)?;
-            }
+    }
+    
+    // Process data (our side may have closed already: half-close)
+    let receiving = matches!(conn.state, TcpState::Established | TcpState::FinWait1 | TcpState::FinWait2);
+    if receiving && !segment.payload.is_empty() {
+        // Out-of-order data is held; the ACK is then a duplicate carrying
+        // SACK blocks (will trigger fast retransmit at sender)
+        conn.receive_data(segment.sequence_num, &segment.payload);
+        send_ack(conn)?;
+    }
+    
+    // Handle FIN (only once everything before it has arrived)
+    let fin_seq = segment.sequence_num.wrapping_add(segment.payload.len() as u32);
+    if segment.flags.contains(TcpFlags::FIN) && receiving && fin_seq == conn.recv_next {
+        conn.recv_next = conn.recv_next.wrapping_add(1);
+        conn.set_state(match conn.state {
+            TcpState::Established => TcpState::CloseWait,
+            TcpState::FinWait1 => TcpState::Closing,
+            _ => TcpState::TimeWait,
+        });
+        send_ack(conn)?;
+    } else if segment.flags.contains(TcpFlags::FIN) && conn.state == TcpState::TimeWait {
+        // Our last ACK was lost: repeat it and restart the 2MSL timer
+        conn.set_state(TcpState::TimeWait);
+        send_ack(conn)?;
+    }
+    
+    // The window may have opened for queued data (or a pending FIN);
+    // states with nothing left to send just decline
+    let _ = conn.flush_send_buffer();
+    
+    Ok(())
+}
+
+/// Handle the reply to our SYN (RFC 9293 §3.10.7.3)
+fn process_syn_sent(conn: &mut TcpConnection, segment: &TcpSegment, options: &TcpOptions) -> Result<(), &'static str> {
+    let has_ack = segment.flags.contains(TcpFlags::ACK);
+    if has_ack && segment.ack_num != conn.send_next {
+        // Not an answer to our SYN
+        if !segment.flags.contains(TcpFlags::RST) {
+            send_rst(conn.local_addr, conn.local_port, conn.remote_addr, conn.remote_port,
+                     segment.ack_num, 0)?;
         }
-        TcpState::LastAck => {
-            if segment.flags.contains(TcpFlags::ACK) {
-                conn.state = TcpState::Closed;
-            }
+        return Ok(());
+    }
+    if segment.flags.contains(TcpFlags::RST) {
+        // Connection refused
+        if has_ack {
+            conn.set_state(TcpState::Closed);
         }
-        _ => {}
+        return Ok(());
     }
     
+    // Expecting SYN-ACK
+    if segment.flags.contains(TcpFlags::SYN) && has_ack {
+        conn.negotiate(options);
+        conn.irs = segment.sequence_num;
+        conn.recv_next = segment.sequence_num.wrapping_add(1);
+        conn.send_unacked = segment.ack_num;
+        conn.last_ack = segment.ack_num;
+        conn.retransmit_queue.ack_up_to(segment.ack_num);
+        conn.send_window = segment.window_size as u32;
+        conn.max_send_window = conn.send_window;
+        conn.set_state(TcpState::Established);
+        
+        // Send ACK to complete handshake
+        send_ack(conn)?;
+    }
     Ok(())
 }
 
@@ -938,46 +1699,35 @@ fn seq_after(a: u32, b: u32) -> bool {
     ((a.wrapping_sub(b)) as i32) > 0
 }
 
-/// Send a SYN-ACK segment
-fn send_syn_ack(conn: &TcpConnection) -> Result<(), &'static str> {
-    let segment = TcpSegment {
-        src_port: conn.local_port,
-        dst_port: conn.remote_port,
-        sequence_num: conn.iss,
-        ack_num: conn.recv_next,
-        data_offset: 5,
-        flags: TcpFlags::new(TcpFlags::SYN | TcpFlags::ACK),
-        window_size: conn.recv_window as u16,
-        checksum: 0,
-        urgent_pointer: 0,
-        options: Vec::new(),
-        payload: Vec::new(),
-    };
-    
-    ipv4::send_packet(conn.remote_addr, 6, &segment.to_bytes())
+/// Send an ACK segment
+fn send_ack(conn: &mut TcpConnection) -> Result<(), &'static str> {
+    conn.transmit(conn.send_next, TcpFlags::ACK, Vec::new())
 }
 
-/// Send an ACK segment
-fn send_ack(conn: &TcpConnection) -> Result<(), &'static str> {
-    let segment = TcpSegment {
-        src_port: conn.local_port,
-        dst_port: conn.remote_port,
-        sequence_num: conn.send_next,
-        ack_num: conn.recv_next,
-        data_offset: 5,
-        flags: TcpFlags::new(TcpFlags::ACK),
-        window_size: conn.recv_window as u16,
-        checksum: 0,
-        urgent_pointer: 0,
-        options: Vec::new(),
-        payload: Vec::new(),
-    };
-    
-    ipv4::send_packet(conn.remote_addr, 6, &segment.to_bytes())
+/// Challenge ACKs sent in the current one-second window: (window start, count)
+static CHALLENGE_ACKS: SpinLock<(u64, u32)> = SpinLock::new((0, 0));
+
+/// Send a challenge ACK (RFC 5961 §3.2), rate-limited across all connections
+///
+/// A genuine peer answers with a RST carrying the exact sequence number;
+/// an off-path attacker never sees the ACK.
+fn send_challenge_ack(conn: &mut TcpConnection) -> Result<(), &'static str> {
+    let now = timer::uptime_us();
+    {
+        let mut budget = CHALLENGE_ACKS.lock();
+        if now.saturating_sub(budget.0) >= 1_000_000 {
+            *budget = (now, 0);
+        }
+        if budget.1 >= CHALLENGE_ACK_LIMIT {
+            return Ok(());
+        }
+        budget.1 += 1;
+    }
+    send_ack(conn)
 }
 
 /// Send a RST segment
-fn send_rst(_src_addr: Ipv4Addr, src_port: u16, dst_addr: Ipv4Addr, dst_port: u16,
+fn send_rst(src_addr: IpAddr, src_port: u16, dst_addr: IpAddr, dst_port: u16,
            seq: u32, ack: u32) -> Result<(), &'static str> {
     let segment = TcpSegment {
         src_port,
@@ -993,17 +1743,22 @@ fn send_rst(_src_addr: Ipv4Addr, src_port: u16, dst_addr: Ipv4Addr, dst_port: u1
         payload: Vec::new(),
     };
     
-    ipv4::send_packet(dst_addr, 6, &segment.to_bytes())
+    send_ip_packet(dst_addr, 6, &segment.to_bytes_with_checksum(src_addr, dst_addr))
 }
 
 // ═══════════════════════════════════════════════════════════════════════════════
 // TIMER TICK (Called from scheduler)
 // ═══════════════════════════════════════════════════════════════════════════════
 
-/// Periodic TCP tick - check for retransmission timeouts
+/// Periodic TCP tick - check for retransmission and keepalive timeouts,
+/// then free closed and expired TIME-WAIT connections
 /// Should be called from scheduler tick (e.g., every 100ms)
 pub fn tcp_tick() {
-    (*TCB_TABLE.lock()).check_retransmits();
+    let mut table = TCB_TABLE.lock();
+    table.check_retransmits();
+    table.reap();
+    drop(table);
+    SOCKET_WAIT.wake_all();
 }
 
 // ═══════════════════════════════════════════════════════════════════════════════
@@ -1011,35 +1766,25 @@ pub fn tcp_tick() {
 // ═══════════════════════════════════════════════════════════════════════════════
 
 /// Create a listening socket
-pub fn listen(local_addr: Ipv4Addr, local_port: u16) -> Result<usize, &'static str> {
-    let mut conn = TcpConnection::new(local_addr, local_port, Ipv4Addr::ANY, 0);
+///
+/// At most `backlog` connections (clamped to 1..=`SOMAXCONN`) may be
+/// half-open or waiting for accept(); further SYNs are dropped.
+pub fn listen(local_addr: IpAddr, local_port: u16, backlog: usize) -> Result<usize, &'static str> {
+    let mut conn = TcpConnection::new(local_addr, local_port, local_addr.unspecified(), 0);
     conn.state = TcpState::Listen;
+    conn.backlog = backlog.clamp(1, SOMAXCONN);
     (*TCB_TABLE.lock()).add(conn)
 }
 
 /// Initiate a connection
-pub fn connect(local_addr: Ipv4Addr, local_port: u16, 
-               remote_addr: Ipv4Addr, remote_port: u16) -> Result<usize, &'static str> {
+pub fn connect(local_addr: IpAddr, local_port: u16, 
+               remote_addr: IpAddr, remote_port: u16, keepalive: bool) -> Result<usize, &'static str> {
     let mut conn = TcpConnection::new(local_addr, local_port, remote_addr, remote_port);
     conn.state = TcpState::SynSent;
-    conn.send_next = conn.iss.wrapping_add(1);
-    
-    // Send SYN
-    let segment = TcpSegment {
-        src_port: local_port,
-        dst_port: remote_port,
-        sequence_num: conn.iss,
-        ack_num: 0,
-        data_offset: 5,
-        flags: TcpFlags::new(TcpFlags::SYN),
-        window_size: conn.recv_window as u16,
-        checksum: 0,
-        urgent_pointer: 0,
-        options: Vec::new(),
-        payload: Vec::new(),
-    };
+    conn.keepalive = keepalive;
     
-    ipv4::send_packet(remote_addr, 6, &segment.to_bytes())?;
+    // Send SYN offering every option; the SYN-ACK decides which stay on
+    conn.send_control(TcpFlags::SYN)?;
     
     (*TCB_TABLE.lock()).add(conn)
 }
@@ -1052,6 +1797,7 @@ pub fn connect(local_addr: Ipv4Addr, local_port: u16,
 mod tests {
     use super::*;
     use alloc::vec;
+    use crate::net::ip::{Ipv4Addr, Ipv6Addr};
     
     // ──────────────────────────────────────────────────────────────────────────
     // Test Helper: Create connection without hardware timer dependency
@@ -1059,23 +1805,38 @@ mod tests {
     
     fn test_connection() -> TcpConnection {
         TcpConnection {
-            local_addr: Ipv4Addr([127, 0, 0, 1]),
+            local_addr: IpAddr::V4(Ipv4Addr([127, 0, 0, 1])),
             local_port: 1234,
-            remote_addr: Ipv4Addr([127, 0, 0, 1]),
+            remote_addr: IpAddr::V4(Ipv4Addr([127, 0, 0, 1])),
             remote_port: 80,
             state: TcpState::Closed,
+            state_since: 0,
+            accepted: false,
+            orphaned: false,
+            backlog: 0,
             send_unacked: 1000,
             send_next: 1000,
             send_window: 65535,
+            max_send_window: 65535,
             recv_next: 0,
             recv_window: DEFAULT_RECV_WINDOW,
             iss: 1000,
             irs: 0,
+            fin_seq: None,
             srtt: 0,
             rttvar: 0,
             rto: INITIAL_RTO_US,
             rtt_seq: None,
             rtt_time: 0,
+            mss: MSS,
+            wscale_ok: false,
+            snd_wscale: 0,
+            rcv_wscale: 0,
+            sack_ok: false,
+            ts_ok: false,
+            ts_recent: 0,
+            last_ack_sent: 0,
+            high_rxt: 1000,
             cwnd: INITIAL_CWND,
             ssthresh: INITIAL_SSTHRESH,
             congestion_state: CongestionState::SlowStart,
@@ -1083,8 +1844,13 @@ mod tests {
             last_ack: 0,
             recover: 0,
             retransmit_queue: RetransmitQueue::new(),
+            keepalive: false,
+            last_recv: 0,
+            keepalive_probes: 0,
             recv_buffer: VecDeque::new(),
             send_buffer: VecDeque::new(),
+            out_of_order: Vec::new(),
+            last_out_of_order: 0,
         }
     }
     
@@ -1141,6 +1907,18 @@ mod tests {
         assert!(TcpSegment::parse(&data).is_err());
     }
     
+    #[test]
+    fn test_segment_parse_rejects_short_data_offset() {
+        // Data offsets 0-4 claim a header shorter than the fixed 20 bytes
+        let mut data = [0u8; 40];
+        for offset in 0..5u8 {
+            data[12] = offset << 4;
+            assert!(TcpSegment::parse(&data).is_err());
+        }
+        data[12] = 5 << 4;
+        assert!(TcpSegment::parse(&data).is_ok());
+    }
+    
     #[test]
     fn test_segment_roundtrip() {
         let original = TcpSegment {
@@ -1167,14 +1945,175 @@ mod tests {
         assert_eq!(parsed.payload, original.payload);
     }
     
+    // ──────────────────────────────────────────────────────────────────────────
+    // TCP OPTIONS TESTS
+    // ──────────────────────────────────────────────────────────────────────────
+    
+    #[test]
+    fn test_options_roundtrip() {
+        let opts = TcpOptions {
+            mss: Some(1460),
+            window_scale: Some(7),
+            sack_permitted: true,
+            sack_blocks: Vec::new(),
+            timestamp: Some((0x01020304, 0x05060708)),
+        };
+        
+        let bytes = opts.to_bytes();
+        assert_eq!(bytes.len() % 4, 0);
+        assert!(bytes.len() <= 40);
+        assert_eq!(TcpOptions::parse(&bytes), opts);
+    }
+    
+    #[test]
+    fn test_options_parse_skips_unknown_and_truncated() {
+        let data = [
+            OPT_NOP,
+            30, 4, 0xAA, 0xBB,          // Unknown kind 30: skipped
+            OPT_MSS, 4, 0x05, 0xB4,     // MSS 1460
+            OPT_WINDOW_SCALE, 3, 20,    // Shift above 14 is clamped
+            OPT_TIMESTAMP, 10, 0, 0,    // Runs past the end: ignored
+        ];
+        
+        let opts = TcpOptions::parse(&data);
+        assert_eq!(opts.mss, Some(1460));
+        assert_eq!(opts.window_scale, Some(MAX_WINDOW_SCALE));
+        assert_eq!(opts.timestamp, None);
+    }
+    
+    #[test]
+    fn test_negotiate_keeps_only_shared_options() {
+        let mut conn = test_connection();
+        conn.wscale_ok = true;
+        conn.rcv_wscale = RECV_WINDOW_SCALE;
+        conn.sack_ok = true;
+        conn.ts_ok = true;
+        
+        // Peer offers an MSS and SACK only
+        let peer = TcpOptions { mss: Some(1200), sack_permitted: true, ..Default::default() };
+        conn.negotiate(&peer);
+        
+        assert_eq!(conn.mss, 1200);
+        assert!(conn.sack_ok);
+        assert!(!conn.ts_ok);
+        assert!(!conn.wscale_ok);
+        assert_eq!(conn.rcv_wscale, 0);
+        assert_eq!(conn.recv_window, u16::MAX as u32);
+        
+        // No MSS option means the RFC default
+        conn.negotiate(&TcpOptions::default());
+        assert_eq!(conn.mss, DEFAULT_MSS);
+    }
+    
+    #[test]
+    fn test_segment_size_leaves_room_for_timestamp() {
+        let mut conn = test_connection();
+        conn.ts_ok = true;
+        assert_eq!(conn.segment_size(), MSS as usize - TIMESTAMP_OVERHEAD);
+        
+        // A smaller peer MSS wins
+        conn.mss = 536;
+        assert_eq!(conn.segment_size(), 536 - TIMESTAMP_OVERHEAD);
+    }
+    
+    // ──────────────────────────────────────────────────────────────────────────
+    // REASSEMBLY / SACK TESTS
+    // ──────────────────────────────────────────────────────────────────────────
+    
+    #[test]
+    fn test_out_of_order_reassembly() {
+        let mut conn = test_connection();
+        conn.recv_next = 100;
+        
+        // Two segments arrive beyond a 10-byte hole
+        conn.receive_data(120, &[3; 10]);
+        conn.receive_data(110, &[2; 10]);
+        assert!(conn.recv_buffer.is_empty());
+        assert_eq!(conn.sack_blocks(), vec![(110, 130)]);
+        
+        // Filling the hole delivers everything in order
+        conn.receive_data(100, &[1; 10]);
+        assert_eq!(conn.recv_next, 130);
+        assert_eq!(conn.recv_buffer.len(), 30);
+        assert!(conn.out_of_order.is_empty());
+        assert!(conn.sack_blocks().is_empty());
+    }
+    
+    #[test]
+    fn test_receive_window_limits_data() {
+        let mut conn = test_connection();
+        conn.recv_next = 100;
+        conn.recv_window = 50;
+        conn.rcv_wscale = 0;
+        
+        // Only what fits in the window is taken, and the window shrinks
+        conn.receive_data(100, &[1; 40]);
+        assert_eq!(conn.recv_space(), 10);
+        assert_eq!(conn.build_segment(0, TcpFlags::ACK, Vec::new()).window_size, 10);
+        conn.receive_data(140, &[2; 20]);
+        assert_eq!(conn.recv_buffer.len(), 50);
+        assert_eq!(conn.recv_next, 150);
+        assert_eq!(conn.build_segment(0, TcpFlags::ACK, Vec::new()).window_size, 0);
+        
+        // Held data must fit too
+        conn.recv_buffer.clear();
+        conn.receive_data(190, &[3; 20]);
+        assert!(conn.out_of_order.is_empty());
+    }
+    
+    #[test]
+    fn test_retransmit_queue_never_drops() {
+        let mut queue = RetransmitQueue::new();
+        for i in 0..MAX_RETRANSMIT_QUEUE as u32 {
+            queue.push(i * 10, vec![0; 10], 0);
+        }
+        assert!(queue.is_full());
+        queue.push_control(MAX_RETRANSMIT_QUEUE as u32 * 10, TcpFlags::FIN, 0);
+        assert_eq!(queue.front().unwrap().seq_num, 0);
+        queue.ack_up_to(10);
+        assert!(!queue.is_full());
+    }
+    
+    #[test]
+    fn test_sack_blocks_latest_first() {
+        let mut conn = test_connection();
+        conn.recv_next = 0;
+        
+        conn.receive_data(100, &[0; 10]);
+        conn.receive_data(300, &[0; 10]);
+        conn.receive_data(200, &[0; 10]);
+        
+        assert_eq!(conn.sack_blocks(), vec![(200, 210), (100, 110), (300, 310)]);
+    }
+    
+    #[test]
+    fn test_retransmit_queue_sack_scoreboard() {
+        let mut queue = RetransmitQueue::new();
+        queue.push(1000, vec![0; 100], 0);
+        queue.push(1100, vec![0; 100], 0);
+        queue.push(1200, vec![0; 100], 0);
+        
+        // Nothing SACKed: no hole to fill
+        assert_eq!(queue.next_hole(1000), None);
+        
+        // Peer holds the last segment, so the first two are holes
+        queue.sack(1200, 1300);
+        assert_eq!(queue.next_hole(1000), Some(0));
+        assert_eq!(queue.next_hole(1100), Some(1));
+        
+        // A timeout forgets what was SACKed
+        queue.clear_sacks();
+        assert_eq!(queue.next_hole(1000), None);
+    }
+    
     // ──────────────────────────────────────────────────────────────────────────
     // TCP CHECKSUM TESTS
     // ──────────────────────────────────────────────────────────────────────────
     
     #[test]
     fn test_tcp_checksum_basic() {
-        let src_ip = Ipv4Addr([192, 168, 1, 1]);
-        let dst_ip = Ipv4Addr([192, 168, 1, 2]);
+        let src_ip = IpAddr::V4(Ipv4Addr([192, 168, 1, 1]));
+        let dst_ip = IpAddr::V4(Ipv4Addr([192, 168, 1, 2]));
         
         let segment = TcpSegment {
             src_port: 1234,
@@ -1199,8 +2138,8 @@ mod tests {
     
     #[test]
     fn test_tcp_checksum_verify() {
-        let src_ip = Ipv4Addr([10, 0, 0, 1]);
-        let dst_ip = Ipv4Addr([10, 0, 0, 2]);
+        let src_ip = IpAddr::V4(Ipv4Addr([10, 0, 0, 1]));
+        let dst_ip = IpAddr::V4(Ipv4Addr([10, 0, 0, 2]));
         
         let segment = TcpSegment {
             src_port: 5000,
@@ -1223,6 +2162,38 @@ mod tests {
         assert!(verify_tcp_checksum(src_ip, dst_ip, &bytes));
     }
     
+    #[test]
+    fn test_tcp_checksum_ipv6() {
+        let src_ip = IpAddr::V6(Ipv6Addr::parse("2001:db8::1").unwrap());
+        let dst_ip = IpAddr::V6(Ipv6Addr::parse("2001:db8::2").unwrap());
+        
+        let segment = TcpSegment {
+            src_port: 5000,
+            dst_port: 443,
+            sequence_num: 1000,
+            ack_num: 0,
+            data_offset: 5,
+            flags: TcpFlags::new(TcpFlags::SYN),
+            window_size: 16384,
+            checksum: 0,
+            urgent_pointer: 0,
+            options: Vec::new(),
+            payload: Vec::new(),
+        };
+        let bytes = segment.to_bytes_with_checksum(src_ip, dst_ip);
+        assert!(verify_tcp_checksum(src_ip, dst_ip, &bytes));
+        
+        // The IPv6 pseudo-header differs, so a checksum made for IPv4 fails
+        let v4 = IpAddr::V4(Ipv4Addr([10, 0, 0, 1]));
+        assert!(!verify_tcp_checksum(v4, dst_ip, &bytes));
+        
+        // IPv6 segments are smaller so they fit the same MTU
+        let mut conn = test_connection();
+        assert_eq!(conn.segment_size(), MSS as usize);
+        conn.remote_addr = dst_ip;
+        assert_eq!(conn.segment_size(), MSS_V6 as usize);
+    }
+    
     // ──────────────────────────────────────────────────────────────────────────
     // RTT ESTIMATION TESTS (Jacobson/Karels)
     // ──────────────────────────────────────────────────────────────────────────
@@ -1345,14 +2316,14 @@ mod tests {
         
         // Test connection 4-tuple matching
         assert!(conn.matches(
-            Ipv4Addr([127, 0, 0, 1]), 1234,
-            Ipv4Addr([127, 0, 0, 1]), 80
+            IpAddr::V4(Ipv4Addr([127, 0, 0, 1])), 1234,
+            IpAddr::V4(Ipv4Addr([127, 0, 0, 1])), 80
         ));
         
         // Different ports should not match
         assert!(!conn.matches(
-            Ipv4Addr([127, 0, 0, 1]), 5001,
-            Ipv4Addr([127, 0, 0, 1]), 80
+            IpAddr::V4(Ipv4Addr([127, 0, 0, 1])), 5001,
+            IpAddr::V4(Ipv4Addr([127, 0, 0, 1])), 80
         ));
     }
     
@@ -1397,4 +2368,135 @@ mod tests {
         assert!(seq_after(10, 0xFFFFFF00_u32));
         assert!(!seq_after(0xFFFFFF00_u32, 10));
     }
+    
+    // ──────────────────────────────────────────────────────────────────────────
+    // ACCEPT / CLOSE TESTS
+    // ──────────────────────────────────────────────────────────────────────────
+    
+    #[test]
+    fn test_accept_claims_established_once() {
+        let mut table = TcpConnectionTable::new();
+        
+        let mut listener = test_connection();
+        listener.local_port = 80;
+        listener.remote_port = 0;
+        listener.state = TcpState::Listen;
+        table.add(listener).unwrap();
+        
+        let mut child = test_connection();
+        child.local_port = 80;
+        child.state = TcpState::SynReceived;
+        table.add(child).unwrap();
+        
+        // Handshake not finished yet
+        assert!(table.accept(80).is_none());
+        
+        table.get_mut(1).unwrap().state = TcpState::Established;
+        let tuple = table.accept(80).unwrap();
+        assert_eq!(tuple.1, 80);
+        assert_eq!(tuple.3, 80);
+        
+        // Already handed out
+        assert!(table.accept(80).is_none());
+    }
+    
+    #[test]
+    fn test_close_listener() {
+        let mut conn = test_connection();
+        conn.state = TcpState::Listen;
+        assert!(conn.close().is_ok());
+        assert_eq!(conn.state, TcpState::Closed);
+    }
+    
+    #[test]
+    fn test_backlog_counts_unaccepted_children() {
+        let mut table = TcpConnectionTable::new();
+        
+        let mut listener = test_connection();
+        listener.local_port = 80;
+        listener.remote_port = 0;
+        listener.state = TcpState::Listen;
+        table.add(listener).unwrap();
+        
+        for (port, state) in [(5000, TcpState::SynReceived), (5001, TcpState::Established)] {
+            let mut child = test_connection();
+            child.local_port = 80;
+            child.remote_port = port;
+            child.state = state;
+            table.add(child).unwrap();
+        }
+        assert_eq!(table.pending_children(80), 2);
+        
+        // Accepting frees a backlog slot
+        assert_eq!(table.accept(80).unwrap().3, 5001);
+        assert_eq!(table.pending_children(80), 1);
+    }
+    
+    // ──────────────────────────────────────────────────────────────────────────
+    // LIFECYCLE TESTS
+    // ──────────────────────────────────────────────────────────────────────────
+    
+    #[test]
+    fn test_time_wait_expires_after_2msl() {
+        let mut conn = test_connection();
+        conn.state = TcpState::TimeWait;
+        conn.state_since = 1_000;
+        
+        assert!(!conn.expired(1_000 + 2 * MSL_US - 1));
+        assert!(conn.expired(1_000 + 2 * MSL_US));
+        
+        conn.state = TcpState::Closed;
+        assert!(conn.expired(1_000));
+    }
+    
+    #[test]
+    fn test_fin_wait2_times_out_only_when_orphaned() {
+        let mut conn = test_connection();
+        conn.state = TcpState::FinWait2;
+        
+        // A socket can still read half-closed data: wait for the peer
+        assert!(!conn.expired(FIN_WAIT2_TIMEOUT_US));
+        
+        conn.orphaned = true;
+        assert!(conn.expired(FIN_WAIT2_TIMEOUT_US));
+    }
+    
+    #[test]
+    fn test_fin_occupies_a_sequence_number() {
+        let mut queue = RetransmitQueue::new();
+        queue.push(1000, vec![0; 10], 0);
+        queue.push_control(1010, TcpFlags::FIN, 0);
+        
+        // Acking the data leaves the FIN outstanding
+        queue.ack_up_to(1010);
+        assert!(!queue.is_empty());
+        
+        queue.ack_up_to(1011);
+        assert!(queue.is_empty());
+    }
+    
+    #[test]
+    fn test_fin_acked() {
+        let mut conn = test_connection();
+        assert!(!conn.fin_acked());
+        
+        conn.fin_seq = Some(1000);
+        conn.send_next = 1001;
+        assert!(!conn.fin_acked());
+        
+        conn.send_unacked = 1001;
+        assert!(conn.fin_acked());
+    }
+    
+    #[test]
+    fn test_rst_window_check() {
+        let mut conn = test_connection();
+        conn.recv_next = 5000;
+        conn.recv_window = 1000;
+        
+        assert!(conn.in_window(5000));
+        assert!(conn.in_window(5999));
+        assert!(!conn.in_window(6000));
+        assert!(!conn.in_window(4999));
+    }
 }
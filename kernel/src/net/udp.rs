@@ -1,8 +1,8 @@
 use alloc::vec::Vec;
 use alloc::collections::BTreeMap;
 use core::convert::TryInto;
-use crate::net::ip::Ipv4Addr;
-use crate::kernel::sync::SpinLock;
+use crate::net::ip::IpAddr;
+use crate::kernel::sync::{SpinLock, WaitQueue};
 
 /// UDP Packet
 #[derive(Debug, Clone)]
@@ -57,7 +57,7 @@ impl UdpPacket {
 /// UDP packet with source address
 #[derive(Debug, Clone)]
 pub struct UdpMessage {
-    pub src_addr: Ipv4Addr,
+    pub src_addr: IpAddr,
     pub src_port: u16,
     pub payload: Vec<u8>,
 }
@@ -101,6 +101,9 @@ impl UdpListener {
 /// Global UDP Listener Registry
 static UDP_LISTENERS: SpinLock<BTreeMap<u16, UdpListener>> = SpinLock::new(BTreeMap::new());
 
+/// Agents blocked waiting for a datagram on any port
+pub static RX_WAIT: WaitQueue = WaitQueue::new();
+
 /// Register a UDP listener on a specific port
 pub fn register_listener(port: u16) -> Result<(), &'static str> {
     let mut listeners = UDP_LISTENERS.lock();
@@ -124,6 +127,11 @@ pub fn unregister_listener(port: u16) -> Result<(), &'static str> {
     }
 }
 
+/// Does a registered port have queued packets?
+pub fn has_packets(port: u16) -> bool {
+    UDP_LISTENERS.lock().get(&port).map(|l| l.has_packets()).unwrap_or(false)
+}
+
 /// Receive a packet from a registered port (non-blocking)
 pub fn recv_from(port: u16) -> Option<UdpMessage> {
     let mut listeners = UDP_LISTENERS.lock();
@@ -135,8 +143,37 @@ pub fn recv_from(port: u16) -> Option<UdpMessage> {
     }
 }
 
+/// Send a UDP datagram to either address family
+///
+/// The checksum covers the pseudo-header of the outgoing IP version
+/// (mandatory for IPv6). Datagrams larger than the route MTU are
+/// fragmented by IPv4; IPv6 refuses them.
+pub fn send_to(src_port: u16, dst_addr: IpAddr, dst_port: u16, payload: &[u8]) -> Result<(), &'static str> {
+    if payload.len() > crate::net::ipv4::MAX_PAYLOAD - 8 {  // 8 UDP header
+        return Err("Datagram too large");
+    }
+    
+    let packet = UdpPacket {
+        src_port,
+        dst_port,
+        length: (8 + payload.len()) as u16,
+        checksum: 0,
+        payload: payload.to_vec(),
+    };
+    let mut bytes = packet.to_bytes();
+    
+    let src_addr = crate::net::source_address(dst_addr);
+    let checksum = match crate::net::pseudo_checksum(src_addr, dst_addr, 17, &bytes) {
+        0 => 0xFFFF,  // Zero means "no checksum" on the wire
+        sum => sum,
+    };
+    bytes[6..8].copy_from_slice(&checksum.to_be_bytes());
+    
+    crate::net::send_ip_packet(dst_addr, 17, &bytes)
+}
+
 /// Handle incoming UDP packet
-pub fn handle_packet(data: &[u8], src_ip: Ipv4Addr) -> Result<(), &'static str> {
+pub fn handle_packet(data: &[u8], src_ip: IpAddr) -> Result<(), &'static str> {
     let packet = UdpPacket::parse(data)?;
     
     // Dispatch to registered listener
@@ -149,6 +186,10 @@ pub fn handle_packet(data: &[u8], src_ip: Ipv4Addr) -> Result<(), &'static str>
         };
         
         listener.enqueue(msg)?;
+        drop(listeners);
+        
+        // Wake blocked readers (called from the NIC RX path)
+        RX_WAIT.wake_all();
     } else {
         // No listener registered for this port - silently drop
         // In production, could send ICMP Port Unreachable
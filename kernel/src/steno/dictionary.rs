@@ -587,6 +587,10 @@ pub mod concepts {
     // File Operations (REDEFINED)
     pub const LIST_FILES: ConceptID = ConceptID(0x0008_0005);
     pub const READ_FILE: ConceptID = ConceptID(0x0008_0006);
+
+    // Network (0x000B_xxxx)
+    pub const RESOLVE_HOST: ConceptID = ConceptID(0x000B_0001);
+    pub const CAPTURE_TRAFFIC: ConceptID = ConceptID(0x000B_0002);
 }
 
 impl Default for StrokeSequence {
@@ -25,7 +25,8 @@
 //! Key: #  S- T- K- P- W- H- R- A- O- *  -E -U -F -R -P -B -L -G -T -S -D -Z
 //! ```
 
-use crate::kernel::sync::SpinLock;
+use crate::kernel::sync::{SpinLock, WaitQueue};
+use alloc::collections::VecDeque;
 use crate::intent::{Intent, ConceptID, IntentData};
 use crate::apps::APP_MANAGER;
 
@@ -55,10 +56,44 @@ pub fn init() {
 
 /// Process a stroke and return intent (if matched)
 pub fn process_stroke(stroke: Stroke) -> Option<Intent> {
+    tap_raw_stroke(stroke);
     let mut engine = STENO_ENGINE.lock();
     engine.process(stroke)
 }
 
+// ═══════════════════════════════════════════════════════════════════════════════
+// RAW STROKE STREAM (/dev/steno)
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Strokes kept for readers of `/dev/steno` (oldest dropped when full)
+const RAW_STROKE_CAPACITY: usize = 256;
+
+static RAW_STROKES: SpinLock<VecDeque<Stroke>> = SpinLock::new(VecDeque::new());
+
+/// Readers of `/dev/steno` waiting for a stroke
+pub static STROKE_WAIT: WaitQueue = WaitQueue::new();
+
+fn tap_raw_stroke(stroke: Stroke) {
+    {
+        let mut queue = RAW_STROKES.lock();
+        if queue.len() == RAW_STROKE_CAPACITY {
+            queue.pop_front();
+        }
+        queue.push_back(stroke);
+    }
+    STROKE_WAIT.wake_all();
+}
+
+/// Take the oldest unread raw stroke
+pub fn read_raw_stroke() -> Option<Stroke> {
+    RAW_STROKES.lock().pop_front()
+}
+
+/// Are raw strokes waiting to be read?
+pub fn has_raw_strokes() -> bool {
+    !RAW_STROKES.lock().is_empty()
+}
+
 /// Process stroke from raw bits (from hardware)
 pub fn process_raw(raw: u32) -> Option<Intent> {
     process_stroke(Stroke::from_raw(raw))
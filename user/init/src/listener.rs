@@ -4,10 +4,7 @@
 use core::panic::PanicInfo;
 use core::ffi::CStr;
 
-// Syscall numbers
-const SYS_PRINT: u64 = 2;
-const SYS_BIND_UDP: u64 = 23;
-const SYS_RECVFROM_FD: u64 = 24;
+use intent_abi::{SYS_PRINT, SYS_BIND_UDP, SYS_RECVFROM_FD};
 
 #[no_mangle]
 pub extern "C" fn _start() -> ! {
@@ -29,9 +26,8 @@ pub extern "C" fn _start() -> ! {
     let mut src_addr = [0u8; 8];
 
     loop {
-        // 2. Receive Packet (Blocking/Polling)
-        // Since we implemented non-blocking receive in kernel, we might need to retry.
-        // But let's assume we busy loop or sleep.
+        // 2. Receive Packet (Blocking)
+        // The kernel parks us on the UDP wait queue until a datagram arrives.
         
         let res = unsafe { 
             syscall4(SYS_RECVFROM_FD, fd, buf.as_mut_ptr() as u64, buf.len() as u64, src_addr.as_mut_ptr() as u64) 
@@ -56,9 +52,6 @@ pub extern "C" fn _start() -> ! {
                 print("Content: [Binary]\n");
             }
         }
-        
-        // Sleep a bit to avoid burning CPU
-        // syscall1(3, 100); // Sleep 100ms
     }
 }
 
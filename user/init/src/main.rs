@@ -4,11 +4,7 @@
 use core::panic::PanicInfo;
 use core::arch::asm;
 
-const SYS_EXIT: u64 = 0;
-const SYS_YIELD: u64 = 1;
-const SYS_PRINT: u64 = 2;
-const SYS_READ: u64 = 6;
-const SYS_PARSE_INTENT: u64 = 22;
+use intent_abi::{SYS_EXEC, SYS_EXIT, SYS_FORK, SYS_PRINT, SYS_READ, SYS_PARSE_INTENT, SYS_WAIT};
 
 #[no_mangle]
 pub extern "C" fn _start() -> ! {
@@ -81,13 +77,13 @@ pub extern "C" fn _start() -> ! {
              // Check if printable
              let has_printable = line.iter().any(|&b| b > 32 && b < 127);
              if has_printable {
-                 const SYS_PARSE_INTENT_ID: u64 = 22; 
                  let result = unsafe {
-                    syscall(SYS_PARSE_INTENT_ID, line.as_ptr() as u64, line.len() as u64, 0, 0)
+                    syscall(SYS_PARSE_INTENT, line.as_ptr() as u64, line.len() as u64, 0, 0)
                  };
                  
                  if result == 1 {
-                    print("Unknown command. Type 'help' for available commands.\n");
+                    // Not an intent: try it as a program, `cat file.txt`
+                    run_program(line);
                  } else if result == u64::MAX {
                     print("Error processing command.\n");
                  }
@@ -156,6 +152,63 @@ pub unsafe extern "C" fn memmove(dest: *mut u8, src: *const u8, n: usize) -> *mu
 // HELPERS
 // ══════════════════════════════════════════════════════════════════════════════
 
+/// Most words on a command line handed to a program
+const MAX_ARGS: usize = 8;
+
+/// Run `line` as a program in a child and wait for it
+///
+/// The first word names the program: a path if it contains '/', otherwise
+/// a file in /bin. The words go to it as argv.
+fn run_program(line: &[u8]) {
+    // NUL-terminated copies of the words, and their offsets
+    let mut words = [0u8; 160];
+    let mut starts = [0usize; MAX_ARGS];
+    let mut argc = 0;
+    let mut len = 0;
+    for word in line.split(|&b| b == b' ' || b == b'\t').filter(|w| !w.is_empty()) {
+        if argc == MAX_ARGS || len + word.len() + 1 > words.len() {
+            print("Too many arguments.\n");
+            return;
+        }
+        starts[argc] = len;
+        words[len..len + word.len()].copy_from_slice(word);
+        len += word.len() + 1;
+        argc += 1;
+    }
+    if argc == 0 {
+        return;
+    }
+
+    let mut argv = [0u64; MAX_ARGS + 1];
+    for i in 0..argc {
+        argv[i] = words[starts[i]..].as_ptr() as u64;
+    }
+
+    // Lines are at most 128 bytes, so "/bin/" + name + NUL always fits
+    let name = &words[..words.iter().position(|&b| b == 0).unwrap_or(0)];
+    let mut path = [0u8; 136];
+    let path_ptr = if name.contains(&b'/') {
+        argv[0]
+    } else {
+        path[..5].copy_from_slice(b"/bin/");
+        path[5..5 + name.len()].copy_from_slice(name);
+        path.as_ptr() as u64
+    };
+
+    let pid = unsafe { syscall(SYS_FORK, 0, 0, 0, 0) };
+    if pid == 0 {
+        // Child: only comes back from exec if there is no such program
+        unsafe { syscall(SYS_EXEC, path_ptr, argv.as_ptr() as u64, 0, 0); }
+        print("Unknown command. Type 'help' for available commands.\n");
+        unsafe { syscall(SYS_EXIT, 127, 0, 0, 0); }
+        loop { unsafe { asm!("wfi"); } }
+    } else if pid == u64::MAX {
+        print("Error processing command.\n");
+    } else {
+        unsafe { syscall(SYS_WAIT, pid, 0, 0, 0); }
+    }
+}
+
 fn print(s: &str) {
     unsafe { syscall(SYS_PRINT, s.as_ptr() as u64, s.len() as u64, 0, 0); }
 }
@@ -3,6 +3,7 @@
 
 use core::arch::asm;
 use core::panic::PanicInfo;
+use intent_abi::{SYS_ANNOUNCE, SYS_IPC_RECV, SYS_IPC_SEND, SYS_PRINT, IPC_MSG_SIZE};
 
 #[no_mangle]
 pub extern "C" fn _start() -> ! {
@@ -13,55 +14,44 @@ pub extern "C" fn _start() -> ! {
 }
 
 // Syscall wrappers
-unsafe fn sys_ipc_recv(buf: &mut [u8; 64]) -> u64 {
-    let mut sender: u64;
+unsafe fn sys_ipc_recv(buf: &mut [u8; IPC_MSG_SIZE]) -> u64 {
+    let sender: u64;
     asm!(
-        "mov x8, #27", // SYS_IPC_RECV
-        "mov x0, {0}",
         "svc #0",
-        "mov {1}, x0",
-        in(reg) buf.as_mut_ptr(),
-        out(reg) sender,
+        in("x8") SYS_IPC_RECV,
+        inlateout("x0") buf.as_mut_ptr() as u64 => sender,
     );
     sender
 }
 
-unsafe fn sys_ipc_send(pid: u64, msg: &[u8; 64]) -> u64 {
-    let mut res: u64;
+#[allow(dead_code)]
+unsafe fn sys_ipc_send(pid: u64, msg: &[u8; IPC_MSG_SIZE]) -> u64 {
+    let res: u64;
     asm!(
-        "mov x8, #26", // SYS_IPC_SEND
-        "mov x0, {0}",
-        "mov x1, {1}",
         "svc #0",
-        "mov {2}, x0",
-        in(reg) pid,
-        in(reg) msg.as_ptr(),
-        out(reg) res,
+        in("x8") SYS_IPC_SEND,
+        inlateout("x0") pid => res,
+        in("x1") msg.as_ptr(),
     );
     res
 }
 
 unsafe fn sys_announce(concept_id: u64) -> u64 {
-    let mut res: u64;
+    let res: u64;
     asm!(
-        "mov x8, #28", // SYS_ANNOUNCE
-        "mov x0, {0}",
         "svc #0",
-        "mov {1}, x0",
-        in(reg) concept_id,
-        out(reg) res,
+        in("x8") SYS_ANNOUNCE,
+        inlateout("x0") concept_id => res,
     );
     res
 }
 
 unsafe fn sys_print(s: &str) {
     asm!(
-        "mov x8, #2", // SYS_PRINT
-        "mov x0, {0}",
-        "mov x1, {1}",
         "svc #0",
-        in(reg) s.as_ptr(),
-        in(reg) s.len(),
+        in("x8") SYS_PRINT,
+        inlateout("x0") s.as_ptr() as u64 => _,
+        in("x1") s.len(),
     );
 }
 
@@ -80,7 +70,7 @@ fn main() {
         sys_print("[Counter] capabilities announced. Waiting for intents...\n");
         
         let mut count: i32 = 0;
-        let mut buf = [0u8; 64];
+        let mut buf = [0u8; IPC_MSG_SIZE];
 
         loop {
             let sender = sys_ipc_recv(&mut buf);
@@ -20,14 +20,14 @@ fn panic(_info: &PanicInfo) -> ! {
 }
 
 // Minimal Syscall Wrappers
-const SYS_EXIT: u64 = 0;
-const SYS_PRINT: u64 = 2;
+use intent_abi::{SYS_EXIT, SYS_PRINT};
 
 fn print(s: &str) {
     unsafe {
         core::arch::asm!(
-            "svc #2",
-            in("x0") s.as_ptr(),
+            "svc #0",
+            in("x8") SYS_PRINT,
+            inlateout("x0") s.as_ptr() as u64 => _,
             in("x1") s.len(),
         );
     }
@@ -37,7 +37,8 @@ fn exit(code: i32) -> ! {
     unsafe {
         core::arch::asm!(
             "svc #0",
-            in("x0") code,
+            in("x8") SYS_EXIT,
+            in("x0") code as u64,
         );
     }
     loop {}